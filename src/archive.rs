@@ -0,0 +1,189 @@
+// Copyright 2025 Niclas Hedam
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Several named payloads in one carrier
+//!
+//! An archive takes the place of a single envelope in the pipeline: it is encrypted
+//! and handed to the engine as one payload. Each entry is a complete envelope (see
+//! [`crate::envelope`]), so it keeps its own name, content type, compression and
+//! checksums; the archive only frames them:
+//!
+//! ```text
+//! [4 bytes: "LpAr"][1 byte: Version = 1][4 bytes: Entry Count]
+//! [8 bytes: Entry Length][Envelope] ... once per entry
+//! ```
+//!
+//! All integers are big-endian. A carrier with a single payload holds a bare
+//! envelope rather than a one-entry archive, so files stay readable by versions of
+//! Lupin that predate archives.
+
+use crate::envelope;
+use crate::error::{LupinError, Result};
+
+/// Marks an archive of enveloped entries
+pub const ARCHIVE_MAGIC: &[u8] = b"LpAr";
+
+/// Current version of the archive format
+pub const ARCHIVE_VERSION: u8 = 1;
+
+/// Magic, version and entry count
+const HEADER_LEN: usize = 4 + 1 + 4;
+
+/// Returns true if the data starts like an archive
+pub fn is_archive(data: &[u8]) -> bool {
+    data.starts_with(ARCHIVE_MAGIC)
+}
+
+/// Packs enveloped entries, in order, into an archive
+pub fn pack<E: AsRef<[u8]>>(entries: &[E]) -> Vec<u8> {
+    let len = entries.iter().map(|e| 8 + e.as_ref().len()).sum::<usize>();
+    let mut out = Vec::with_capacity(HEADER_LEN + len);
+    out.extend_from_slice(ARCHIVE_MAGIC);
+    out.push(ARCHIVE_VERSION);
+    out.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+    for entry in entries {
+        let entry = entry.as_ref();
+        out.extend_from_slice(&(entry.len() as u64).to_be_bytes());
+        out.extend_from_slice(entry);
+    }
+    out
+}
+
+/// Splits an archive into its enveloped entries, without unwrapping them
+///
+/// Fails with [`LupinError::PayloadTruncated`] if the data ends early and
+/// [`LupinError::UnsupportedEnvelope`] if it was written by a newer version.
+pub fn unpack(data: &[u8]) -> Result<Vec<&[u8]>> {
+    let truncated = |expected: usize| LupinError::PayloadTruncated {
+        expected,
+        actual: data.len(),
+    };
+    if data.len() < HEADER_LEN {
+        return Err(truncated(HEADER_LEN));
+    }
+    if !is_archive(data) {
+        return Err(LupinError::InvalidEnvelope {
+            reason: "missing archive magic".to_string(),
+        });
+    }
+    if data[4] != ARCHIVE_VERSION {
+        return Err(LupinError::UnsupportedEnvelope {
+            reason: format!("archive version {}", data[4]),
+        });
+    }
+
+    let count = u32::from_be_bytes(data[5..9].try_into().unwrap()) as usize;
+    let mut entries = Vec::new();
+    let mut pos = HEADER_LEN;
+    for _ in 0..count {
+        if data.len() - pos < 8 {
+            return Err(truncated(pos + 8));
+        }
+        let len = u64::from_be_bytes(data[pos..pos + 8].try_into().unwrap());
+        pos += 8;
+        let end = usize::try_from(len)
+            .ok()
+            .and_then(|len| pos.checked_add(len))
+            .unwrap_or(usize::MAX);
+        if end > data.len() {
+            return Err(truncated(end));
+        }
+
+        let entry = &data[pos..end];
+        if !envelope::is_envelope(entry) {
+            return Err(LupinError::InvalidEnvelope {
+                reason: format!("archive entry {} is not an envelope", entries.len()),
+            });
+        }
+        entries.push(entry);
+        pos = end;
+    }
+
+    if pos != data.len() {
+        return Err(LupinError::InvalidEnvelope {
+            reason: format!("{} unexpected bytes after the archive", data.len() - pos),
+        });
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compression::Compression;
+
+    fn entry(name: &str, payload: &[u8]) -> Vec<u8> {
+        envelope::wrap(payload, Some(name), None, Compression::None)
+            .unwrap()
+            .0
+    }
+
+    #[test]
+    fn test_pack_unpack_round_trip() {
+        // Arrange
+        let entries = vec![entry("a.txt", b"first"), entry("b.txt", b"second")];
+
+        // Act
+        let archive = pack(&entries);
+        let unpacked = unpack(&archive).unwrap();
+
+        // Assert
+        assert!(is_archive(&archive));
+        assert_eq!(unpacked, entries);
+        let second = envelope::unwrap(unpacked[1]).unwrap();
+        assert_eq!(second.filename.as_deref(), Some("b.txt"));
+        assert_eq!(second.payload, b"second");
+    }
+
+    #[test]
+    fn test_unpack_truncated() {
+        // Arrange
+        let archive = pack(&[entry("a.txt", b"first")]);
+
+        // Act
+        let result = unpack(&archive[..archive.len() - 1]);
+
+        // Assert
+        assert!(matches!(
+            result,
+            Err(LupinError::PayloadTruncated { expected, actual })
+                if expected == archive.len() && actual == archive.len() - 1
+        ));
+    }
+
+    #[test]
+    fn test_unpack_rejects_malformed() {
+        // Arrange
+        let archive = pack(&[entry("a.txt", b"first")]);
+        let mut newer = archive.clone();
+        newer[4] = ARCHIVE_VERSION + 1;
+        let mut trailing = archive.clone();
+        trailing.push(0);
+        let not_envelope = pack(&[b"bare payload".to_vec()]);
+
+        // Act & Assert
+        assert!(matches!(
+            unpack(&newer),
+            Err(LupinError::UnsupportedEnvelope { .. })
+        ));
+        assert!(matches!(
+            unpack(&trailing),
+            Err(LupinError::InvalidEnvelope { .. })
+        ));
+        assert!(matches!(
+            unpack(&not_envelope),
+            Err(LupinError::InvalidEnvelope { .. })
+        ));
+    }
+}
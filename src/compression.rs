@@ -0,0 +1,165 @@
+// Copyright 2025 Niclas Hedam
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Payload compression applied inside the envelope
+//!
+//! DEFLATE is always available; zstd needs the `zstd` cargo feature. Extracting a
+//! zstd payload without the feature fails with
+//! [`LupinError::CompressionUnavailable`] rather than returning compressed bytes.
+
+use crate::error::{LupinError, Result};
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use std::io::{Read, Write};
+
+/// Compression algorithm for the payload
+///
+/// Marked `#[non_exhaustive]` so algorithms can be added without breaking downstream
+/// `match` expressions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum Compression {
+    /// Store the payload as-is
+    None,
+    /// Raw DEFLATE (RFC 1951)
+    #[default]
+    Deflate,
+    /// Zstandard; requires the `zstd` cargo feature
+    Zstd,
+}
+
+impl Compression {
+    /// Returns a short lowercase name, as accepted by the CLI
+    pub fn name(&self) -> &'static str {
+        match self {
+            Compression::None => "none",
+            Compression::Deflate => "deflate",
+            Compression::Zstd => "zstd",
+        }
+    }
+}
+
+/// Compresses `data` with `algorithm`
+pub fn compress(data: &[u8], algorithm: Compression) -> Result<Vec<u8>> {
+    match algorithm {
+        Compression::None => Ok(data.to_vec()),
+        Compression::Deflate => {
+            let mut encoder = DeflateEncoder::new(Vec::new(), flate2::Compression::best());
+            encoder
+                .write_all(data)
+                .and_then(|_| encoder.finish())
+                .map_err(|e| LupinError::EmbedFailed { source: e })
+        }
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => {
+            zstd::bulk::compress(data, 19).map_err(|e| LupinError::EmbedFailed { source: e })
+        }
+        #[cfg(not(feature = "zstd"))]
+        Compression::Zstd => Err(LupinError::CompressionUnavailable { algorithm: "zstd" }),
+    }
+}
+
+/// Decompresses `data`, which must inflate to exactly `expected_len` bytes
+///
+/// The expected length comes from the carrier, so output is never allocated from it
+/// up front and decompression stops one byte past it; a crafted payload can't make
+/// extraction inflate without limit.
+pub fn decompress(data: &[u8], algorithm: Compression, expected_len: u64) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    let limit = expected_len.saturating_add(1);
+    let read = match algorithm {
+        Compression::None => return Ok(data.to_vec()),
+        Compression::Deflate => DeflateDecoder::new(data).take(limit).read_to_end(&mut out),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => zstd::stream::read::Decoder::new(data)
+            .and_then(|decoder| decoder.take(limit).read_to_end(&mut out)),
+        #[cfg(not(feature = "zstd"))]
+        Compression::Zstd => return Err(LupinError::CompressionUnavailable { algorithm: "zstd" }),
+    };
+
+    match read {
+        Ok(len) if len as u64 == expected_len => Ok(out),
+        Ok(len) => Err(LupinError::InvalidEnvelope {
+            reason: format!(
+                "payload inflated to {} bytes, expected {}",
+                len, expected_len
+            ),
+        }),
+        Err(e) => Err(LupinError::InvalidEnvelope {
+            reason: format!("payload failed to decompress: {}", e),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deflate_round_trip() {
+        // Arrange
+        let data = b"all work and no play makes jack a dull boy ".repeat(50);
+
+        // Act
+        let compressed = compress(&data, Compression::Deflate).unwrap();
+        let restored = decompress(&compressed, Compression::Deflate, data.len() as u64).unwrap();
+
+        // Assert
+        assert!(compressed.len() < data.len() / 10);
+        assert_eq!(restored, data);
+    }
+
+    #[test]
+    fn test_decompress_rejects_wrong_length() {
+        // Arrange
+        let data = vec![0u8; 4096];
+        let compressed = compress(&data, Compression::Deflate).unwrap();
+
+        // Act - a header that understates the size must not inflate past it
+        let short = decompress(&compressed, Compression::Deflate, 16);
+        let long = decompress(&compressed, Compression::Deflate, 8192);
+
+        // Assert
+        assert!(matches!(short, Err(LupinError::InvalidEnvelope { .. })));
+        assert!(matches!(long, Err(LupinError::InvalidEnvelope { .. })));
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_round_trip() {
+        // Arrange
+        let data = b"zstandard ".repeat(100);
+
+        // Act
+        let compressed = compress(&data, Compression::Zstd).unwrap();
+        let restored = decompress(&compressed, Compression::Zstd, data.len() as u64).unwrap();
+
+        // Assert
+        assert!(compressed.len() < data.len());
+        assert_eq!(restored, data);
+    }
+
+    #[cfg(not(feature = "zstd"))]
+    #[test]
+    fn test_zstd_unavailable() {
+        // Act
+        let result = compress(b"data", Compression::Zstd);
+
+        // Assert
+        assert!(matches!(
+            result,
+            Err(LupinError::CompressionUnavailable { algorithm: "zstd" })
+        ));
+    }
+}
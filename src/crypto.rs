@@ -0,0 +1,694 @@
+// Copyright 2025 Niclas Hedam
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Authenticated encryption of payloads before they reach an engine
+//!
+//! # Password Sealing
+//!
+//! The password is stretched with Argon2id (a memory-hard KDF) using a random 16-byte
+//! salt, and the payload is sealed with XChaCha20-Poly1305 under a random 24-byte
+//! nonce. Everything needed to reverse this except the password is stored in front of
+//! the ciphertext:
+//!
+//! ```text
+//! [4 bytes: "LpEn"][1 byte: Version][1 byte: Kind = 1 (password)]
+//! [4 bytes: Argon2 Memory KiB][4 bytes: Argon2 Iterations][4 bytes: Argon2 Lanes]
+//! [16 bytes: Salt][8 bytes: Key Check][24 bytes: Nonce][N + 16 bytes: Ciphertext + Tag]
+//! ```
+//!
+//! All integers are big-endian, and the whole header is bound to the ciphertext as
+//! associated data, so tampering with the KDF parameters is caught like any other
+//! change. The KDF yields 40 bytes: the first 32 are the cipher key and the last 8 are
+//! stored as the key check, which lets [`open_with_password`] tell a wrong password
+//! ([`LupinError::WrongPassword`]) apart from a payload that was damaged after
+//! embedding ([`LupinError::AuthenticationFailed`]).
+//!
+//! # Recipient Sealing
+//!
+//! For people who never share a passphrase, a payload can instead be sealed to one or
+//! more X25519 public keys ([`Recipient`]s). A random 32-byte file key encrypts the
+//! payload with XChaCha20-Poly1305, and a copy of the file key is wrapped for every
+//! recipient:
+//!
+//! ```text
+//! [4 bytes: "LpEn"][1 byte: Version][1 byte: Kind = 2 (recipients)][1 byte: Count]
+//! [32 bytes: Ephemeral Public Key][Count x 48 bytes: Wrapped File Key][24 bytes: Nonce]
+//! [N + 16 bytes: Ciphertext + Tag]
+//! ```
+//!
+//! Each wrapped file key is the file key sealed with ChaCha20-Poly1305 under
+//! `HKDF-SHA256(X25519(ephemeral, recipient), salt = ephemeral || recipient)`. The
+//! header doesn't say which recipient a stanza belongs to, so [`open_with_identities`]
+//! tries every [`Identity`] against every stanza. As with passwords, the whole header
+//! is authenticated as associated data.
+
+use crate::error::{LupinError, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Nonce, XChaCha20Poly1305, XNonce};
+use hkdf::Hkdf;
+use log::debug;
+use sha2::Sha256;
+use std::fmt;
+use std::str::FromStr;
+use x25519_dalek::{PublicKey, StaticSecret};
+use zeroize::Zeroizing;
+
+/// Marks a sealed payload
+pub const SEALED_MAGIC: &[u8] = b"LpEn";
+
+/// Current version of the sealed payload format
+const SEALED_VERSION: u8 = 1;
+
+/// Kind byte for payloads sealed with a password
+const KIND_PASSWORD: u8 = 1;
+
+/// Kind byte for payloads sealed to X25519 recipients
+const KIND_RECIPIENTS: u8 = 2;
+
+/// Argon2id memory cost in KiB (19 MiB, the OWASP recommended minimum)
+const KDF_MEMORY_KIB: u32 = 19 * 1024;
+
+/// Argon2id iteration count
+const KDF_ITERATIONS: u32 = 2;
+
+/// Argon2id degree of parallelism
+const KDF_LANES: u32 = 1;
+
+/// Upper bound on the memory cost accepted from a sealed header (1 GiB), so a
+/// crafted carrier can't make extraction allocate without limit
+const KDF_MAX_MEMORY_KIB: u32 = 1024 * 1024;
+
+/// Upper bound on the iteration count accepted from a sealed header
+const KDF_MAX_ITERATIONS: u32 = 64;
+
+const SALT_LEN: usize = 16;
+const CHECK_LEN: usize = 8;
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 24;
+
+/// Magic, version, kind, three KDF parameters, salt, key check and nonce
+const PASSWORD_HEADER_LEN: usize = 4 + 1 + 1 + 12 + SALT_LEN + CHECK_LEN + NONCE_LEN;
+
+/// Magic, version, kind, recipient count and ephemeral public key
+const RECIPIENTS_PREAMBLE_LEN: usize = 4 + 1 + 1 + 1 + 32;
+
+/// A file key sealed with ChaCha20-Poly1305 (key + tag)
+const WRAPPED_KEY_LEN: usize = KEY_LEN + 16;
+
+/// HKDF context string for deriving a recipient's key-wrapping key
+const WRAP_INFO: &[u8] = b"lupin x25519 file key v1";
+
+/// Text prefix of an encoded [`Recipient`]
+const RECIPIENT_PREFIX: &str = "lupin-pk-";
+
+/// Text prefix of an encoded [`Identity`]
+const IDENTITY_PREFIX: &str = "LUPIN-SK-";
+
+/// How a sealed payload was encrypted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SealedKind {
+    /// Sealed with [`seal_with_password`]
+    Password,
+    /// Sealed with [`seal_for_recipients`]
+    Recipients,
+}
+
+/// Returns true if the data starts like a sealed payload
+pub fn is_sealed(data: &[u8]) -> bool {
+    data.starts_with(SEALED_MAGIC)
+}
+
+/// Returns how a sealed payload was encrypted, or `None` if it isn't a sealed payload
+/// this version understands
+pub fn sealed_kind(data: &[u8]) -> Option<SealedKind> {
+    if !is_sealed(data) || data.get(4) != Some(&SEALED_VERSION) {
+        return None;
+    }
+    match data.get(5) {
+        Some(&KIND_PASSWORD) => Some(SealedKind::Password),
+        Some(&KIND_RECIPIENTS) => Some(SealedKind::Recipients),
+        _ => None,
+    }
+}
+
+/// An X25519 public key that payloads can be sealed to
+///
+/// Encoded as text as `lupin-pk-` followed by the unpadded URL-safe base64 key.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Recipient(PublicKey);
+
+impl Recipient {
+    /// Returns the raw 32-byte public key
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        self.0.as_bytes()
+    }
+}
+
+impl fmt::Display for Recipient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            RECIPIENT_PREFIX,
+            URL_SAFE_NO_PAD.encode(self.0.as_bytes())
+        )
+    }
+}
+
+impl fmt::Debug for Recipient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Recipient({})", self)
+    }
+}
+
+impl FromStr for Recipient {
+    type Err = LupinError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        decode_key(s, RECIPIENT_PREFIX, "public key").map(|bytes| Self(PublicKey::from(bytes)))
+    }
+}
+
+/// An X25519 private key that opens payloads sealed to its [`Recipient`]
+///
+/// Encoded as text as `LUPIN-SK-` followed by the unpadded URL-safe base64 key. The
+/// key is wiped from memory on drop and never shown by `Debug`.
+#[derive(Clone)]
+pub struct Identity(StaticSecret);
+
+impl Identity {
+    /// Generates a new random identity
+    pub fn generate() -> Self {
+        Self(StaticSecret::random_from_rng(OsRng))
+    }
+
+    /// Returns the public key that payloads for this identity are sealed to
+    pub fn recipient(&self) -> Recipient {
+        Recipient(PublicKey::from(&self.0))
+    }
+
+    /// Encodes the private key as text, for writing to an identity file
+    pub fn to_secret_string(&self) -> Zeroizing<String> {
+        Zeroizing::new(format!(
+            "{}{}",
+            IDENTITY_PREFIX,
+            URL_SAFE_NO_PAD.encode(self.0.as_bytes())
+        ))
+    }
+}
+
+impl fmt::Debug for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Identity(<redacted>, recipient: {})", self.recipient())
+    }
+}
+
+impl FromStr for Identity {
+    type Err = LupinError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        decode_key(s, IDENTITY_PREFIX, "identity").map(|bytes| Self(StaticSecret::from(bytes)))
+    }
+}
+
+/// Parses the identities in an identity file: one per line, ignoring blank lines and
+/// `#` comments
+pub fn parse_identities(text: &str) -> Result<Vec<Identity>> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(Identity::from_str)
+        .collect()
+}
+
+/// Decodes a key from its text form: `prefix` followed by the unpadded URL-safe
+/// base64 key
+pub(crate) fn decode_key(text: &str, prefix: &str, what: &str) -> Result<[u8; 32]> {
+    let invalid = |reason: String| LupinError::InvalidKey { reason };
+    let encoded = text
+        .trim()
+        .strip_prefix(prefix)
+        .ok_or_else(|| invalid(format!("{} must start with '{}'", what, prefix)))?;
+    let decoded = Zeroizing::new(
+        URL_SAFE_NO_PAD
+            .decode(encoded)
+            .map_err(|_| invalid(format!("{} is not valid base64", what)))?,
+    );
+    decoded
+        .as_slice()
+        .try_into()
+        .map_err(|_| invalid(format!("{} must be 32 bytes", what)))
+}
+
+/// Derives the key that wraps the file key for one recipient
+fn wrapping_key(
+    shared_secret: &[u8],
+    ephemeral: &PublicKey,
+    recipient: &PublicKey,
+) -> Zeroizing<[u8; KEY_LEN]> {
+    let mut salt = [0u8; 64];
+    salt[..32].copy_from_slice(ephemeral.as_bytes());
+    salt[32..].copy_from_slice(recipient.as_bytes());
+
+    let mut key = Zeroizing::new([0u8; KEY_LEN]);
+    Hkdf::<Sha256>::new(Some(&salt), shared_secret)
+        .expand(WRAP_INFO, key.as_mut())
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    key
+}
+
+/// Runs Argon2id over the password, returning the cipher key and the key check
+fn derive_key(
+    password: &[u8],
+    salt: &[u8],
+    memory_kib: u32,
+    iterations: u32,
+    lanes: u32,
+) -> Option<Zeroizing<[u8; KEY_LEN + CHECK_LEN]>> {
+    let params = Params::new(memory_kib, iterations, lanes, Some(KEY_LEN + CHECK_LEN)).ok()?;
+    let mut output = Zeroizing::new([0u8; KEY_LEN + CHECK_LEN]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(password, salt, output.as_mut())
+        .ok()?;
+    Some(output)
+}
+
+/// Encrypts the payload with a key derived from the password
+pub fn seal_with_password(plaintext: &[u8], password: &[u8]) -> Result<Vec<u8>> {
+    if password.is_empty() {
+        return Err(LupinError::EmptyPassword);
+    }
+
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce);
+
+    let derived = derive_key(password, &salt, KDF_MEMORY_KIB, KDF_ITERATIONS, KDF_LANES)
+        .ok_or_else(|| LupinError::EmbedFailed {
+            source: std::io::Error::new(
+                std::io::ErrorKind::Other,
+                "Argon2id key derivation failed",
+            ),
+        })?;
+    let (key, check) = derived.split_at(KEY_LEN);
+
+    let mut sealed = Vec::with_capacity(PASSWORD_HEADER_LEN + plaintext.len() + 16);
+    sealed.extend_from_slice(SEALED_MAGIC);
+    sealed.push(SEALED_VERSION);
+    sealed.push(KIND_PASSWORD);
+    sealed.extend_from_slice(&KDF_MEMORY_KIB.to_be_bytes());
+    sealed.extend_from_slice(&KDF_ITERATIONS.to_be_bytes());
+    sealed.extend_from_slice(&KDF_LANES.to_be_bytes());
+    sealed.extend_from_slice(&salt);
+    sealed.extend_from_slice(check);
+    sealed.extend_from_slice(&nonce);
+
+    let cipher = XChaCha20Poly1305::new_from_slice(key).expect("key is 32 bytes");
+    let ciphertext = cipher
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad: &sealed,
+            },
+        )
+        .map_err(|_| LupinError::EmbedFailed {
+            source: std::io::Error::new(std::io::ErrorKind::Other, "Payload encryption failed"),
+        })?;
+
+    debug!(
+        "Sealed {} byte payload with password ({} bytes sealed)",
+        plaintext.len(),
+        PASSWORD_HEADER_LEN + ciphertext.len()
+    );
+
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+/// Decrypts a payload produced by [`seal_with_password`]
+pub fn open_with_password(sealed: &[u8], password: &[u8]) -> Result<Vec<u8>> {
+    if sealed.len() < PASSWORD_HEADER_LEN || !is_sealed(sealed) {
+        return Err(LupinError::AuthenticationFailed);
+    }
+    if sealed[4] != SEALED_VERSION || sealed[5] != KIND_PASSWORD {
+        return Err(LupinError::AuthenticationFailed);
+    }
+
+    let (header, ciphertext) = sealed.split_at(PASSWORD_HEADER_LEN);
+    let read_u32 = |at: usize| {
+        u32::from_be_bytes([header[at], header[at + 1], header[at + 2], header[at + 3]])
+    };
+    let memory_kib = read_u32(6);
+    let iterations = read_u32(10);
+    let lanes = read_u32(14);
+    if memory_kib > KDF_MAX_MEMORY_KIB || iterations > KDF_MAX_ITERATIONS {
+        return Err(LupinError::AuthenticationFailed);
+    }
+
+    let salt = &header[18..18 + SALT_LEN];
+    let stored_check = &header[18 + SALT_LEN..18 + SALT_LEN + CHECK_LEN];
+    let nonce = &header[18 + SALT_LEN + CHECK_LEN..];
+
+    let derived = derive_key(password, salt, memory_kib, iterations, lanes)
+        .ok_or(LupinError::AuthenticationFailed)?;
+    let (key, check) = derived.split_at(KEY_LEN);
+    if check != stored_check {
+        return Err(LupinError::WrongPassword);
+    }
+
+    let cipher = XChaCha20Poly1305::new_from_slice(key).expect("key is 32 bytes");
+    cipher
+        .decrypt(
+            XNonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: header,
+            },
+        )
+        .map_err(|_| LupinError::AuthenticationFailed)
+}
+
+/// Encrypts the payload so that any one of the recipients can decrypt it
+pub fn seal_for_recipients(plaintext: &[u8], recipients: &[Recipient]) -> Result<Vec<u8>> {
+    let invalid_input = |message: &str| LupinError::EmbedFailed {
+        source: std::io::Error::new(std::io::ErrorKind::InvalidInput, message.to_string()),
+    };
+    if recipients.is_empty() {
+        return Err(invalid_input("at least one recipient is required"));
+    }
+    let count = u8::try_from(recipients.len())
+        .map_err(|_| invalid_input("at most 255 recipients are supported"))?;
+
+    let mut file_key = Zeroizing::new([0u8; KEY_LEN]);
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(file_key.as_mut());
+    OsRng.fill_bytes(&mut nonce);
+
+    // One ephemeral key serves every recipient; the HKDF salt binds each wrapping
+    // key to its recipient.
+    let ephemeral = StaticSecret::random_from_rng(OsRng);
+    let ephemeral_public = PublicKey::from(&ephemeral);
+
+    let mut sealed = Vec::with_capacity(
+        RECIPIENTS_PREAMBLE_LEN
+            + recipients.len() * WRAPPED_KEY_LEN
+            + NONCE_LEN
+            + plaintext.len()
+            + 16,
+    );
+    sealed.extend_from_slice(SEALED_MAGIC);
+    sealed.push(SEALED_VERSION);
+    sealed.push(KIND_RECIPIENTS);
+    sealed.push(count);
+    sealed.extend_from_slice(ephemeral_public.as_bytes());
+
+    for recipient in recipients {
+        let shared = ephemeral.diffie_hellman(&recipient.0);
+        if !shared.was_contributory() {
+            return Err(LupinError::InvalidKey {
+                reason: format!("{} is not a usable public key", recipient),
+            });
+        }
+        let key = wrapping_key(shared.as_bytes(), &ephemeral_public, &recipient.0);
+        // Every wrapping key is used exactly once, so a fixed nonce is safe
+        let wrapped = ChaCha20Poly1305::new_from_slice(key.as_ref())
+            .expect("key is 32 bytes")
+            .encrypt(&Nonce::default(), file_key.as_ref())
+            .map_err(|_| invalid_input("file key wrapping failed"))?;
+        sealed.extend_from_slice(&wrapped);
+    }
+    sealed.extend_from_slice(&nonce);
+
+    let ciphertext = XChaCha20Poly1305::new_from_slice(file_key.as_ref())
+        .expect("key is 32 bytes")
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad: &sealed,
+            },
+        )
+        .map_err(|_| LupinError::EmbedFailed {
+            source: std::io::Error::new(std::io::ErrorKind::Other, "Payload encryption failed"),
+        })?;
+
+    debug!(
+        "Sealed {} byte payload for {} recipient(s) ({} bytes sealed)",
+        plaintext.len(),
+        recipients.len(),
+        sealed.len() + ciphertext.len()
+    );
+
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+/// Decrypts a payload produced by [`seal_for_recipients`] with any matching identity
+pub fn open_with_identities(sealed: &[u8], identities: &[Identity]) -> Result<Vec<u8>> {
+    if sealed_kind(sealed) != Some(SealedKind::Recipients) || sealed.len() < RECIPIENTS_PREAMBLE_LEN
+    {
+        return Err(LupinError::AuthenticationFailed);
+    }
+
+    let count = sealed[6] as usize;
+    let header_len = RECIPIENTS_PREAMBLE_LEN + count * WRAPPED_KEY_LEN + NONCE_LEN;
+    if sealed.len() < header_len {
+        return Err(LupinError::AuthenticationFailed);
+    }
+    let (header, ciphertext) = sealed.split_at(header_len);
+
+    let mut ephemeral_bytes = [0u8; 32];
+    ephemeral_bytes.copy_from_slice(&header[7..RECIPIENTS_PREAMBLE_LEN]);
+    let ephemeral_public = PublicKey::from(ephemeral_bytes);
+    let stanzas =
+        header[RECIPIENTS_PREAMBLE_LEN..header_len - NONCE_LEN].chunks_exact(WRAPPED_KEY_LEN);
+    let nonce = &header[header_len - NONCE_LEN..];
+
+    let file_key = identities
+        .iter()
+        .find_map(|identity| {
+            let shared = identity.0.diffie_hellman(&ephemeral_public);
+            if !shared.was_contributory() {
+                return None;
+            }
+            let recipient = PublicKey::from(&identity.0);
+            let key = wrapping_key(shared.as_bytes(), &ephemeral_public, &recipient);
+            let cipher = ChaCha20Poly1305::new_from_slice(key.as_ref()).expect("key is 32 bytes");
+            stanzas
+                .clone()
+                .find_map(|stanza| cipher.decrypt(&Nonce::default(), stanza).ok())
+                .map(Zeroizing::new)
+        })
+        .ok_or(LupinError::NoMatchingIdentity)?;
+
+    XChaCha20Poly1305::new_from_slice(&file_key)
+        .map_err(|_| LupinError::AuthenticationFailed)?
+        .decrypt(
+            XNonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: header,
+            },
+        )
+        .map_err(|_| LupinError::AuthenticationFailed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open_round_trip() {
+        // Arrange
+        let plaintext = b"meet at the usual place";
+
+        // Act
+        let sealed = seal_with_password(plaintext, b"correct horse").unwrap();
+        let opened = open_with_password(&sealed, b"correct horse").unwrap();
+
+        // Assert
+        assert_eq!(opened, plaintext);
+        assert!(is_sealed(&sealed));
+        assert_eq!(sealed.len(), PASSWORD_HEADER_LEN + plaintext.len() + 16); // Header + ciphertext + tag
+        assert!(!sealed
+            .windows(plaintext.len())
+            .any(|window| window == plaintext)); // Plaintext must not leak
+    }
+
+    #[test]
+    fn test_seal_uses_fresh_salt_and_nonce() {
+        // Arrange & Act
+        let first = seal_with_password(b"payload", b"password").unwrap();
+        let second = seal_with_password(b"payload", b"password").unwrap();
+
+        // Assert
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn test_open_with_wrong_password() {
+        // Arrange
+        let sealed = seal_with_password(b"payload", b"password").unwrap();
+
+        // Act
+        let result = open_with_password(&sealed, b"passw0rd");
+
+        // Assert
+        assert!(matches!(result, Err(LupinError::WrongPassword)));
+    }
+
+    #[test]
+    fn test_open_tampered_payload() {
+        // Arrange - flip a ciphertext bit, then a KDF parameter bit
+        let sealed = seal_with_password(b"payload", b"password").unwrap();
+        let mut tampered_ciphertext = sealed.clone();
+        *tampered_ciphertext.last_mut().unwrap() ^= 1;
+        let mut tampered_header = sealed.clone();
+        tampered_header[PASSWORD_HEADER_LEN - 1] ^= 1; // Last nonce byte
+
+        // Act & Assert
+        for tampered in [tampered_ciphertext, tampered_header] {
+            let result = open_with_password(&tampered, b"password");
+            assert!(matches!(result, Err(LupinError::AuthenticationFailed)));
+        }
+    }
+
+    #[test]
+    fn test_open_truncated_payload() {
+        // Arrange
+        let sealed = seal_with_password(b"payload", b"password").unwrap();
+
+        // Act
+        let result = open_with_password(&sealed[..PASSWORD_HEADER_LEN - 1], b"password");
+
+        // Assert
+        assert!(matches!(result, Err(LupinError::AuthenticationFailed)));
+    }
+
+    #[test]
+    fn test_seal_rejects_empty_password() {
+        // Arrange & Act
+        let result = seal_with_password(b"payload", b"");
+
+        // Assert
+        assert!(matches!(result, Err(LupinError::EmptyPassword)));
+    }
+
+    #[test]
+    fn test_seal_for_recipients_round_trip() {
+        // Arrange - two recipients, each of whom can open the payload alone
+        let alice = Identity::generate();
+        let bob = Identity::generate();
+        let recipients = [alice.recipient(), bob.recipient()];
+
+        // Act
+        let sealed = seal_for_recipients(b"for both of you", &recipients).unwrap();
+
+        // Assert
+        assert_eq!(sealed_kind(&sealed), Some(SealedKind::Recipients));
+        for identity in [alice, bob] {
+            let opened = open_with_identities(&sealed, &[identity]).unwrap();
+            assert_eq!(opened, b"for both of you");
+        }
+    }
+
+    #[test]
+    fn test_open_with_identities_tries_every_identity() {
+        // Arrange
+        let recipient = Identity::generate();
+        let stranger = Identity::generate();
+        let sealed = seal_for_recipients(b"payload", &[recipient.recipient()]).unwrap();
+
+        // Act
+        let opened = open_with_identities(&sealed, &[stranger.clone(), recipient]).unwrap();
+        let refused = open_with_identities(&sealed, &[stranger]);
+
+        // Assert
+        assert_eq!(opened, b"payload");
+        assert!(matches!(refused, Err(LupinError::NoMatchingIdentity)));
+    }
+
+    #[test]
+    fn test_open_tampered_recipient_payload() {
+        // Arrange
+        let identity = Identity::generate();
+        let mut sealed = seal_for_recipients(b"payload", &[identity.recipient()]).unwrap();
+        *sealed.last_mut().unwrap() ^= 1;
+
+        // Act
+        let result = open_with_identities(&sealed, &[identity]);
+
+        // Assert
+        assert!(matches!(result, Err(LupinError::AuthenticationFailed)));
+    }
+
+    #[test]
+    fn test_key_text_round_trip() {
+        // Arrange
+        let identity = Identity::generate();
+        let recipient = identity.recipient();
+
+        // Act
+        let parsed_recipient: Recipient = recipient.to_string().parse().unwrap();
+        let parsed_identity: Identity = identity.to_secret_string().parse().unwrap();
+
+        // Assert
+        assert!(recipient.to_string().starts_with("lupin-pk-"));
+        assert_eq!(parsed_recipient, recipient);
+        assert_eq!(parsed_identity.recipient(), recipient);
+        assert!(!format!("{:?}", identity).contains(identity.to_secret_string().as_str()));
+    }
+
+    #[test]
+    fn test_parse_invalid_keys() {
+        // Arrange
+        let cases = [
+            "age1qyqszqgpqyqszqgpqyqszqgpqyqszqgp", // Wrong prefix
+            "lupin-pk-not*base64",                  // Not base64
+            "lupin-pk-AAAA",                        // Wrong length
+        ];
+
+        // Act & Assert
+        for case in cases {
+            assert!(matches!(
+                case.parse::<Recipient>(),
+                Err(LupinError::InvalidKey { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_parse_identities_skips_comments() {
+        // Arrange
+        let identity = Identity::generate();
+        let file = format!(
+            "# public key: {}\n\n{}\n",
+            identity.recipient(),
+            identity.to_secret_string().as_str()
+        );
+
+        // Act
+        let identities = parse_identities(&file).unwrap();
+
+        // Assert
+        assert_eq!(identities.len(), 1);
+        assert_eq!(identities[0].recipient(), identity.recipient());
+    }
+}
@@ -0,0 +1,1054 @@
+// Copyright 2025 Niclas Hedam
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! BMP steganography engine using the slack after the pixel array
+//!
+//! # How It Works
+//!
+//! A BMP file locates its pixel array (and any embedded colour profile) through
+//! offsets in its headers, so readers never look at bytes past the end of the image
+//! data. This engine stores the payload there and updates `bfSize`, the file size in
+//! the file header, to cover it.
+//!
+//! ## Storage Format
+//!
+//! The block goes right after the pixel array, or after the embedded colour profile
+//! if that comes later:
+//!
+//! ```text
+//! [6 bytes: "Lupin\0"][4 bytes: Big-endian Payload Length][N bytes: Raw Payload]
+//! ```
+//!
+//! Any slack the file already had follows the block, so removing it restores the
+//! file exactly.
+//!
+//! ## BMP Structure
+//!
+//! - **File header** (14 bytes): `BM`, the file size `bfSize` and the offset of the
+//!   pixel array `bfOffBits`, all little-endian
+//! - **DIB header**: width, height, bits per pixel and compression. `BITMAPCOREHEADER`
+//!   (12 bytes) and `BITMAPINFOHEADER` (40 bytes) and its V2-V5 extensions are
+//!   supported. A negative height marks a top-down image; otherwise the bottom row
+//!   is stored first
+//! - **Pixel array**: uncompressed rows are padded to a multiple of 4 bytes
+//!
+//! ## Stealth Mode
+//!
+//! [`EmbedMode::Stealth`] leaves the file size alone and hides the payload in the
+//! least-significant bits of the pixels instead. The pixels are uncompressed, so
+//! nothing needs decoding:
+//!
+//! 1. The payload is written, MSB first, into the LSB of the blue, green and red
+//!    byte of every pixel, row by row from the top of the image whichever order the
+//!    rows are stored in. The padding at the end of each row and the fourth byte of
+//!    a 32-bit pixel (alpha or unused) are never touched.
+//! 2. The headers and everything outside the pixel array are copied verbatim.
+//!
+//! The bit stream starts with a small header so extraction can detect the payload
+//! without being told the mode. Everything after the random nonce is
+//! whitened, so no fixed pattern shows in the pixel LSBs (see the
+//! [`crate::engines`] documentation):
+//!
+//! ```text
+//! [8 bytes: Nonce][4 bytes: "LpBm"][4 bytes: Big-endian Payload Length][N bytes: Raw Payload]
+//! ```
+//!
+//! Each colour byte changes by at most one intensity level, so the image is visually
+//! identical. Capacity is `width * height * 3 / 8` bytes minus the 16-byte header.
+//! Only uncompressed 24-bit images and 32-bit images with a byte per channel are
+//! supported; flipping the LSB of a palette index or a 16-bit pixel would visibly
+//! change it.
+
+use super::stealth;
+use crate::error::{LupinError, Result};
+use crate::{Capacity, EmbedMode, Probe, SteganographyEngine};
+use log::debug;
+use std::ops::Range;
+
+/// BMP steganography engine
+///
+/// In capacity mode, stores the payload in a signed block after the pixel array,
+/// which BMP readers never reach, and grows `bfSize` to match. In stealth mode, the
+/// payload is written into the least-significant bits of the pixels instead.
+///
+/// See the module documentation for details on how data is stored and limitations.
+pub struct BmpEngine;
+
+/// The header fields that locate and describe the image data
+#[derive(Debug, Clone, Copy)]
+struct ImageHeader {
+    /// Byte offset of the pixel array (`bfOffBits`)
+    offset: usize,
+    width: usize,
+    height: usize,
+    /// Whether the first stored row is the top of the image
+    top_down: bool,
+    bits_per_pixel: u16,
+    compression: u32,
+    /// Bytes per stored row, including padding
+    stride: usize,
+    /// Byte offset just past the pixel array and any embedded colour profile, where
+    /// the slack begins
+    content_end: usize,
+}
+
+/// The pixel array of an uncompressed 24- or 32-bit BMP, ready for LSB embedding
+struct DecodedImage {
+    header: ImageHeader,
+    /// Bytes per pixel (3 or 4)
+    bytes_per_pixel: usize,
+    /// Byte offsets of the blue, green and red channels within a pixel
+    channels: [usize; 3],
+    /// The whole file, modified in place
+    data: Vec<u8>,
+}
+
+impl DecodedImage {
+    /// Byte range of the pixel array in the file
+    fn pixel_range(&self) -> Range<usize> {
+        self.header.offset..self.header.offset + self.header.stride * self.header.height
+    }
+
+    /// Total number of payload bits the image can carry, including the header
+    fn capacity_bits(&self) -> usize {
+        self.header.width * self.header.height * self.channels.len()
+    }
+
+    /// Byte offset in the file of the byte holding carrier bit number `bit`
+    fn carrier_offset(&self, bit: usize) -> usize {
+        let pixel = bit / self.channels.len();
+        let channel = self.channels[bit % self.channels.len()];
+        let (row, column) = (pixel / self.header.width, pixel % self.header.width);
+
+        // Bits run from the top row down, whichever way the rows are stored
+        let stored_row = if self.header.top_down {
+            row
+        } else {
+            self.header.height - 1 - row
+        };
+        self.header.offset
+            + stored_row * self.header.stride
+            + column * self.bytes_per_pixel
+            + channel
+    }
+
+    /// Reads `len` bytes from the carrier bits, starting at byte `start` of the bit stream
+    fn read_bytes(&self, start: usize, len: usize) -> Vec<u8> {
+        (0..len)
+            .map(|i| {
+                (0..8).fold(0u8, |byte, b| {
+                    let offset = self.carrier_offset((start + i) * 8 + b);
+                    (byte << 1) | (self.data[offset] & 1)
+                })
+            })
+            .collect()
+    }
+
+    /// Writes `data` into the carrier bits, starting at byte 0 of the bit stream
+    fn write_bytes(&mut self, data: &[u8]) {
+        for (i, &byte) in data.iter().enumerate() {
+            for b in 0..8 {
+                let bit = (byte >> (7 - b)) & 1;
+                let offset = self.carrier_offset(i * 8 + b);
+                self.data[offset] = (self.data[offset] & !1) | bit;
+            }
+        }
+    }
+
+    /// Reads the stealth header, returning it if the magic matches
+    fn stealth_header(&self) -> Option<stealth::Header> {
+        if self.capacity_bits() < BmpEngine::STEALTH_HEADER_LEN * 8 {
+            return None;
+        }
+
+        stealth::open(
+            BmpEngine::STEALTH_MAGIC,
+            &self.read_bytes(0, BmpEngine::STEALTH_HEADER_LEN),
+        )
+    }
+}
+
+impl BmpEngine {
+    /// Creates a new BMP engine
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Length of the file header
+    const FILE_HEADER_LEN: usize = 14;
+
+    /// Length of `BITMAPCOREHEADER`, the OS/2 1.x DIB header
+    const CORE_HEADER_LEN: usize = 12;
+
+    /// Length of `BITMAPINFOHEADER`; the V2-V5 headers extend it
+    const INFO_HEADER_LEN: usize = 40;
+
+    /// Length of `BITMAPV5HEADER`, the first with an embedded colour profile
+    const V5_HEADER_LEN: usize = 124;
+
+    /// `bV5CSType` value of an embedded colour profile (`'MBED'`)
+    const PROFILE_EMBEDDED: u32 = 0x4D42_4544;
+
+    /// Uncompressed pixels
+    const BI_RGB: u32 = 0;
+
+    /// Uncompressed pixels with channel masks
+    const BI_BITFIELDS: u32 = 3;
+
+    /// Uncompressed pixels with channel masks including alpha
+    const BI_ALPHABITFIELDS: u32 = 6;
+
+    /// Signature at the start of a capacity-mode block.
+    ///
+    /// Other tools leave data after the pixel array too, so this is required to tell
+    /// our block apart from foreign slack.
+    const LUPIN_SIGNATURE: &'static [u8] = b"Lupin\0";
+
+    /// Capacity-mode block header size: signature + 4-byte big-endian payload length
+    const BLOCK_HEADER_LEN: usize = 10;
+
+    /// Signature at the start of a stealth-mode bit stream
+    const STEALTH_MAGIC: &'static [u8] = b"LpBm";
+
+    /// Stealth header size: nonce + signature + 4-byte big-endian payload length
+    const STEALTH_HEADER_LEN: usize = stealth::HEADER_LEN;
+
+    /// Reads a little-endian u16 from a slice
+    fn read_u16_le(data: &[u8]) -> u16 {
+        u16::from_le_bytes([data[0], data[1]])
+    }
+
+    /// Reads a little-endian u32 from a slice
+    fn read_u32_le(data: &[u8]) -> u32 {
+        u32::from_le_bytes([data[0], data[1], data[2], data[3]])
+    }
+
+    /// Parses the file and DIB headers and checks that the image data fits in the file
+    fn parse_header(data: &[u8]) -> Result<ImageHeader> {
+        let invalid = |reason: &str| LupinError::BmpInvalidFormat {
+            reason: reason.to_string(),
+        };
+
+        if data.len() < Self::FILE_HEADER_LEN + Self::CORE_HEADER_LEN || !data.starts_with(b"BM") {
+            return Err(invalid("File too short or missing BM signature"));
+        }
+        let offset = Self::read_u32_le(&data[10..14]) as usize;
+        let dib_size = Self::read_u32_le(&data[14..18]) as usize;
+        let dib = Self::FILE_HEADER_LEN;
+        if dib_size != Self::CORE_HEADER_LEN && dib_size < Self::INFO_HEADER_LEN {
+            return Err(LupinError::BmpInvalidFormat {
+                reason: format!("Unknown DIB header size {}", dib_size),
+            });
+        }
+        if data.len() < dib + dib_size {
+            return Err(invalid("DIB header runs past the end of the file"));
+        }
+
+        let (width, height, bits_per_pixel, compression) = if dib_size == Self::CORE_HEADER_LEN {
+            (
+                Self::read_u16_le(&data[18..20]) as i64,
+                Self::read_u16_le(&data[20..22]) as i64,
+                Self::read_u16_le(&data[24..26]),
+                Self::BI_RGB,
+            )
+        } else {
+            (
+                Self::read_u32_le(&data[18..22]) as i32 as i64,
+                Self::read_u32_le(&data[22..26]) as i32 as i64,
+                Self::read_u16_le(&data[28..30]),
+                Self::read_u32_le(&data[30..34]),
+            )
+        };
+
+        if width <= 0 || height == 0 {
+            return Err(invalid("Image has zero or negative width, or zero height"));
+        }
+        if !matches!(bits_per_pixel, 1 | 2 | 4 | 8 | 16 | 24 | 32) {
+            return Err(LupinError::BmpInvalidFormat {
+                reason: format!("Unsupported bit depth {}", bits_per_pixel),
+            });
+        }
+
+        // Only the INFOHEADER family has signed heights
+        let top_down = height < 0;
+        let (width, height) = (width as u64, height.unsigned_abs());
+        let stride = (width * bits_per_pixel as u64 + 31) / 32 * 4;
+        let pixel_len = match compression {
+            Self::BI_RGB | Self::BI_BITFIELDS | Self::BI_ALPHABITFIELDS => stride * height,
+            // Compressed pixels state their own size
+            _ => match Self::read_u32_le(&data[34..38]) {
+                0 => return Err(invalid("Compressed image without a pixel array size")),
+                size => size as u64,
+            },
+        };
+        let pixel_end = offset as u64 + pixel_len;
+
+        // A V5 header may place a colour profile after the pixels
+        let mut content_end = pixel_end;
+        if dib_size >= Self::V5_HEADER_LEN
+            && Self::read_u32_le(&data[dib + 56..dib + 60]) == Self::PROFILE_EMBEDDED
+        {
+            let profile_offset = Self::read_u32_le(&data[dib + 112..dib + 116]) as u64;
+            let profile_len = Self::read_u32_le(&data[dib + 116..dib + 120]) as u64;
+            content_end = content_end.max(dib as u64 + profile_offset + profile_len);
+        }
+
+        if offset < dib + dib_size || content_end > data.len() as u64 {
+            return Err(invalid(
+                "Image data runs past the end of the file; the file is truncated or malformed",
+            ));
+        }
+
+        Ok(ImageHeader {
+            offset,
+            width: width as usize,
+            height: height as usize,
+            top_down,
+            bits_per_pixel,
+            compression,
+            stride: stride as usize,
+            content_end: content_end as usize,
+        })
+    }
+
+    /// Finds the Lupin block in the slack, returning its byte range and whether it
+    /// was cut short by the end of the file
+    fn find_block(data: &[u8], header: &ImageHeader) -> Option<(Range<usize>, bool)> {
+        let slack = &data[header.content_end..];
+        if !slack.starts_with(Self::LUPIN_SIGNATURE) || slack.len() < Self::BLOCK_HEADER_LEN {
+            return None;
+        }
+
+        let len = u32::from_be_bytes([slack[6], slack[7], slack[8], slack[9]]) as usize;
+        let end = header.content_end + Self::BLOCK_HEADER_LEN + len;
+        if end > data.len() {
+            return Some((header.content_end..data.len(), false));
+        }
+        Some((header.content_end..end, true))
+    }
+
+    /// Rewrites `bfSize` when a block of `old_len` bytes becomes one of `new_len`.
+    ///
+    /// Only a `bfSize` that matched the file is updated, so one that was wrong (or
+    /// zero, as some writers leave it) is restored exactly on removal.
+    fn update_file_size(output: &mut [u8], old_len: usize) {
+        if Self::read_u32_le(&output[2..6]) as usize == old_len {
+            let size = output.len() as u32;
+            output[2..6].copy_from_slice(&size.to_le_bytes());
+        }
+    }
+
+    /// Finds the pixel array of an image stealth mode can write to
+    fn decode_image(data: &[u8]) -> Result<DecodedImage> {
+        let header = Self::parse_header(data)?;
+
+        let unsupported = |reason: &str| LupinError::StealthUnsupportedCarrier {
+            format: "BMP",
+            reason: reason.to_string(),
+        };
+
+        let channels = match (header.bits_per_pixel, header.compression) {
+            (24, Self::BI_RGB) => [0, 1, 2],
+            (32, Self::BI_RGB) => [0, 1, 2],
+            (32, Self::BI_BITFIELDS | Self::BI_ALPHABITFIELDS) => {
+                // The red, green and blue masks follow the INFOHEADER fields
+                let masks = data
+                    .get(54..66)
+                    .ok_or_else(|| unsupported("channel masks are missing"))?;
+                let mut channels = [0; 3];
+                for (channel, mask) in channels.iter_mut().rev().zip(masks.chunks(4)) {
+                    let mask = Self::read_u32_le(mask);
+                    if mask.count_ones() != 8 || mask.trailing_zeros() % 8 != 0 {
+                        return Err(unsupported("channel masks are not a byte each"));
+                    }
+                    *channel = mask.trailing_zeros() as usize / 8;
+                }
+                channels
+            }
+            (24 | 32, _) => return Err(unsupported("compressed images are not supported")),
+            _ => return Err(unsupported("only 24- and 32-bit images are supported")),
+        };
+
+        Ok(DecodedImage {
+            header,
+            bytes_per_pixel: header.bits_per_pixel as usize / 8,
+            channels,
+            data: data.to_vec(),
+        })
+    }
+
+    /// Checks whether the pixels already carry a stealth payload.
+    ///
+    /// Best effort: images stealth mode can't write to simply report `false`.
+    fn has_stealth_payload(data: &[u8]) -> bool {
+        Self::decode_image(data)
+            .map(|image| image.stealth_header().is_some())
+            .unwrap_or(false)
+    }
+
+    /// Hides the payload in the LSBs of the pixels
+    fn embed_stealth(&self, source_data: &[u8], payload: &[u8]) -> Result<Vec<u8>> {
+        let mut image = Self::decode_image(source_data)?;
+
+        if image.stealth_header().is_some() {
+            return Err(LupinError::EmbedCollision {
+                source: std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    "BMP pixel data already carries a Lupin payload",
+                ),
+            });
+        }
+
+        let stream = stealth::seal(Self::STEALTH_MAGIC, payload);
+
+        let capacity = image.capacity_bits() / 8;
+        if stream.len() > capacity {
+            return Err(LupinError::PayloadTooLarge {
+                capacity: capacity.saturating_sub(Self::STEALTH_HEADER_LEN),
+                requested: payload.len(),
+            });
+        }
+
+        debug!(
+            "BMP: Writing {} bytes into the LSBs of {} available carrier bytes",
+            stream.len(),
+            capacity
+        );
+
+        image.write_bytes(&stream);
+        Ok(image.data)
+    }
+
+    /// Finds a stealth payload's header and first bytes in the pixel LSBs
+    fn probe_stealth(source_data: &[u8]) -> Option<Probe> {
+        let image = Self::decode_image(source_data).ok()?;
+        let header = image.stealth_header()?;
+        let len = header.len;
+        let available = image.capacity_bits() / 8 - Self::STEALTH_HEADER_LEN;
+        let prefix = image.read_bytes(
+            Self::STEALTH_HEADER_LEN,
+            len.min(Probe::PREFIX_LEN).min(available),
+        );
+        Some(Probe::new(
+            EmbedMode::Stealth,
+            len,
+            vec![image.pixel_range()],
+            header.unmask(prefix, 0),
+        ))
+    }
+
+    /// Writes zero bits over a stealth payload and its header in the pixel LSBs
+    fn remove_stealth(source_data: &[u8]) -> Result<Vec<u8>> {
+        let mut image = Self::decode_image(source_data).map_err(|_| LupinError::BmpNoHiddenData)?;
+        let len = image
+            .stealth_header()
+            .ok_or(LupinError::BmpNoHiddenData)?
+            .len;
+
+        let wiped = (Self::STEALTH_HEADER_LEN + len).min(image.capacity_bits() / 8);
+        image.write_bytes(&vec![0; wiped]);
+
+        debug!("BMP: Cleared {} byte stealth payload from pixel data", len);
+
+        Ok(image.data)
+    }
+
+    /// Recovers a payload hidden in the LSBs of the pixels
+    fn extract_stealth(source_data: &[u8]) -> Result<Vec<u8>> {
+        // A file whose pixel format stealth mode never writes can't carry a
+        // stealth payload.
+        let image = Self::decode_image(source_data).map_err(|_| LupinError::BmpNoHiddenData)?;
+        let header = image.stealth_header().ok_or(LupinError::BmpNoHiddenData)?;
+        let len = header.len;
+
+        if Self::STEALTH_HEADER_LEN + len > image.capacity_bits() / 8 {
+            return Err(LupinError::BmpCorruptedData);
+        }
+
+        debug!("BMP: Found {} byte stealth payload in pixel data", len);
+
+        Ok(header.unmask(image.read_bytes(Self::STEALTH_HEADER_LEN, len), 0))
+    }
+}
+
+impl Default for BmpEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SteganographyEngine for BmpEngine {
+    fn magic_bytes(&self) -> &[u8] {
+        b"BM"
+    }
+
+    fn matches(&self, data: &[u8]) -> bool {
+        // `BM` alone is too weak to claim a file (a text file may start with it), so
+        // the file header has to add up as well
+        if data.len() < Self::FILE_HEADER_LEN + Self::CORE_HEADER_LEN || !data.starts_with(b"BM") {
+            return false;
+        }
+        let file_size = Self::read_u32_le(&data[2..6]) as usize;
+        let offset = Self::read_u32_le(&data[10..14]) as usize;
+        let dib_size = Self::read_u32_le(&data[14..18]) as usize;
+
+        // Some writers leave bfSize at zero
+        (file_size == 0 || file_size <= data.len())
+            && matches!(dib_size, 12 | 40 | 52 | 56 | 108 | 124)
+            && (Self::FILE_HEADER_LEN + dib_size..=data.len()).contains(&offset)
+    }
+
+    fn format_name(&self) -> &str {
+        "BMP"
+    }
+
+    fn format_ext(&self) -> &str {
+        ".bmp"
+    }
+
+    fn capacity(&self, source_data: &[u8], mode: EmbedMode) -> Result<Capacity> {
+        match mode {
+            // The block's 32-bit length field is the only limit
+            EmbedMode::Capacity => {
+                Self::parse_header(source_data)?;
+                Ok(Capacity::Unlimited)
+            }
+            EmbedMode::Stealth => {
+                let image = Self::decode_image(source_data)?;
+                let bytes = image.capacity_bits() / 8;
+                Ok(Capacity::Exact(
+                    bytes.saturating_sub(Self::STEALTH_HEADER_LEN),
+                ))
+            }
+        }
+    }
+
+    fn embed(&self, source_data: &[u8], payload: &[u8], mode: EmbedMode) -> Result<Vec<u8>> {
+        // Reject empty payloads so the embed contract is uniform across engines.
+        if payload.is_empty() {
+            return Err(LupinError::EmptyPayload);
+        }
+
+        // Refuse to embed into a BMP that already carries a Lupin block; a second
+        // one would hide the first.
+        let header = Self::parse_header(source_data)?;
+        if Self::find_block(source_data, &header).is_some() {
+            return Err(LupinError::EmbedCollision {
+                source: std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    "BMP already contains a Lupin block",
+                ),
+            });
+        }
+
+        // Exhaustive so a future EmbedMode variant is a compile error here rather than
+        // silently falling through to the capacity implementation below.
+        match mode {
+            EmbedMode::Capacity => {}
+            EmbedMode::Stealth => return self.embed_stealth(source_data, payload),
+        }
+
+        // A stealth payload would be shadowed by the block on extract, so treat it
+        // as a collision too.
+        if Self::has_stealth_payload(source_data) {
+            return Err(LupinError::EmbedCollision {
+                source: std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    "BMP pixel data already carries a Lupin payload",
+                ),
+            });
+        }
+
+        let len = u32::try_from(payload.len()).map_err(|_| LupinError::PayloadTooLarge {
+            capacity: u32::MAX as usize,
+            requested: payload.len(),
+        })?;
+
+        debug!(
+            "BMP: Inserting {} byte block at position {}",
+            Self::BLOCK_HEADER_LEN + payload.len(),
+            header.content_end
+        );
+
+        let mut output =
+            Vec::with_capacity(source_data.len() + Self::BLOCK_HEADER_LEN + payload.len());
+        output.extend_from_slice(&source_data[..header.content_end]);
+        output.extend_from_slice(Self::LUPIN_SIGNATURE);
+        output.extend_from_slice(&len.to_be_bytes());
+        output.extend_from_slice(payload);
+        output.extend_from_slice(&source_data[header.content_end..]);
+        Self::update_file_size(&mut output, source_data.len());
+
+        Ok(output)
+    }
+
+    fn probe(&self, source_data: &[u8]) -> Result<Option<Probe>> {
+        let header = Self::parse_header(source_data)?;
+        let Some((block, _)) = Self::find_block(source_data, &header) else {
+            return Ok(Self::probe_stealth(source_data));
+        };
+
+        let size = u32::from_be_bytes([
+            source_data[block.start + 6],
+            source_data[block.start + 7],
+            source_data[block.start + 8],
+            source_data[block.start + 9],
+        ]) as usize;
+        let payload = block.start + Self::BLOCK_HEADER_LEN;
+        let prefix = source_data[payload..block.end.min(payload + Probe::PREFIX_LEN)].to_vec();
+        Ok(Some(Probe::new(
+            EmbedMode::Capacity,
+            size,
+            vec![block],
+            prefix,
+        )))
+    }
+
+    fn remove(&self, source_data: &[u8]) -> Result<Vec<u8>> {
+        let header = Self::parse_header(source_data)?;
+        let Some((block, _)) = Self::find_block(source_data, &header) else {
+            return Self::remove_stealth(source_data);
+        };
+
+        debug!("BMP: Removing Lupin block at {}-{}", block.start, block.end);
+
+        let mut output = Vec::with_capacity(source_data.len() - block.len());
+        output.extend_from_slice(&source_data[..block.start]);
+        output.extend_from_slice(&source_data[block.end..]);
+        Self::update_file_size(&mut output, source_data.len());
+        Ok(output)
+    }
+
+    fn extract(&self, source_data: &[u8]) -> Result<Vec<u8>> {
+        // Read the payload from our block, falling back to the pixel LSBs when
+        // there is no block (stealth mode)
+        let header = Self::parse_header(source_data)?;
+        let Some((block, complete)) = Self::find_block(source_data, &header) else {
+            return Self::extract_stealth(source_data);
+        };
+
+        debug!("BMP: Found Lupin block at {}-{}", block.start, block.end);
+
+        // A block cut short is only returned if error correction can repair it
+        let payload = &source_data[block.start + Self::BLOCK_HEADER_LEN..block.end];
+        if !complete && !crate::fec::is_fec(payload) {
+            return Err(LupinError::BmpCorruptedData);
+        }
+        Ok(payload.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds an uncompressed BMP with a `BITMAPINFOHEADER` and pseudo-random pixels.
+    /// Row padding is left zero, and a 32-bit image's fourth bytes are `0xFF`.
+    fn create_bmp(width: i32, height: i32, bits_per_pixel: u16) -> Vec<u8> {
+        let stride = ((width as usize * bits_per_pixel as usize + 31) / 32) * 4;
+        let pixel_len = stride * height.unsigned_abs() as usize;
+        let offset = 14 + 40;
+
+        let mut bmp = b"BM".to_vec();
+        bmp.extend_from_slice(&((offset + pixel_len) as u32).to_le_bytes());
+        bmp.extend_from_slice(&[0; 4]);
+        bmp.extend_from_slice(&(offset as u32).to_le_bytes());
+        bmp.extend_from_slice(&40u32.to_le_bytes());
+        bmp.extend_from_slice(&width.to_le_bytes());
+        bmp.extend_from_slice(&height.to_le_bytes());
+        bmp.extend_from_slice(&1u16.to_le_bytes());
+        bmp.extend_from_slice(&bits_per_pixel.to_le_bytes());
+        bmp.extend_from_slice(&[0; 24]); // BI_RGB, sizes, resolution, palette counts
+
+        let row_len = width as usize * bits_per_pixel as usize / 8;
+        let mut seed = 0x1234_5678u32;
+        for _ in 0..height.unsigned_abs() {
+            for x in 0..stride {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                bmp.push(match x {
+                    _ if x >= row_len => 0,
+                    _ if bits_per_pixel == 32 && x % 4 == 3 => 0xFF,
+                    _ => (seed >> 16) as u8,
+                });
+            }
+        }
+        bmp
+    }
+
+    /// Stores the rows of a bottom-up BMP top-down instead, keeping the same image
+    fn flip_to_top_down(bmp: &[u8]) -> Vec<u8> {
+        let header = BmpEngine::parse_header(bmp).unwrap();
+        let mut flipped = bmp.to_vec();
+        let height = -(header.height as i32);
+        flipped[22..26].copy_from_slice(&height.to_le_bytes());
+        for row in 0..header.height {
+            let from = header.offset + row * header.stride;
+            let to = header.offset + (header.height - 1 - row) * header.stride;
+            flipped[to..to + header.stride].copy_from_slice(&bmp[from..from + header.stride]);
+        }
+        flipped
+    }
+
+    #[test]
+    fn test_bmp_magic_bytes() {
+        let engine = BmpEngine::new();
+        assert_eq!(engine.magic_bytes(), b"BM");
+    }
+
+    #[test]
+    fn test_bmp_format_info() {
+        let engine = BmpEngine::new();
+        assert_eq!(engine.format_name(), "BMP");
+        assert_eq!(engine.format_ext(), ".bmp");
+    }
+
+    #[test]
+    fn test_matches_validates_header() {
+        // Arrange
+        let engine = BmpEngine::new();
+        let bmp = create_bmp(4, 4, 24);
+        let text = b"BMW service log line 0\nBMW service log line 1\n";
+        let mut bad_dib = bmp.clone();
+        bad_dib[14..18].copy_from_slice(&41u32.to_le_bytes());
+        let mut bad_offset = bmp.clone();
+        bad_offset[10..14].copy_from_slice(&(bmp.len() as u32 + 1).to_le_bytes());
+
+        // Act & Assert
+        assert!(engine.matches(&bmp));
+        assert!(!engine.matches(text));
+        assert!(!engine.matches(&bad_dib));
+        assert!(!engine.matches(&bad_offset));
+        assert!(!engine.matches(&bmp[..bmp.len() - 1]));
+    }
+
+    #[test]
+    fn test_embed_and_extract() {
+        // Arrange
+        let engine = BmpEngine::new();
+        let bmp = create_bmp(5, 4, 24);
+        let payload = b"Secret message hidden in BMP!";
+
+        // Act
+        let embedded = engine.embed(&bmp, payload, EmbedMode::Capacity).unwrap();
+        let extracted = engine.extract(&embedded).unwrap();
+
+        // Assert - the pixels are untouched and bfSize covers the block
+        assert_eq!(extracted, payload);
+        assert_eq!(&embedded[6..bmp.len()], &bmp[6..]);
+        assert_eq!(
+            BmpEngine::read_u32_le(&embedded[2..6]) as usize,
+            embedded.len()
+        );
+    }
+
+    #[test]
+    fn test_existing_slack_kept() {
+        // Arrange - trailing bytes some writers leave, and a bfSize of zero
+        let engine = BmpEngine::new();
+        let mut bmp = create_bmp(4, 4, 24);
+        bmp.extend_from_slice(b"\0\0slack");
+        bmp[2..6].copy_from_slice(&[0; 4]);
+
+        // Act
+        let embedded = engine.embed(&bmp, b"payload", EmbedMode::Capacity).unwrap();
+        let restored = engine.remove(&embedded).unwrap();
+
+        // Assert - the block goes before the slack, and a wrong bfSize is left alone
+        assert_eq!(engine.extract(&embedded).unwrap(), b"payload");
+        assert!(embedded.ends_with(b"payload\0\0slack"));
+        assert_eq!(&embedded[2..6], &[0; 4]);
+        assert_eq!(restored, bmp);
+    }
+
+    #[test]
+    fn test_embed_collision() {
+        let engine = BmpEngine::new();
+        let embedded_once = engine
+            .embed(&create_bmp(4, 4, 24), b"First", EmbedMode::Capacity)
+            .unwrap();
+
+        let result = engine.embed(&embedded_once, b"Second", EmbedMode::Capacity);
+        assert!(matches!(result, Err(LupinError::EmbedCollision { .. })));
+    }
+
+    #[test]
+    fn test_extract_without_data() {
+        let engine = BmpEngine::new();
+        let mut bmp = create_bmp(4, 4, 24);
+        bmp.extend_from_slice(b"foreign slack");
+
+        let result = engine.extract(&bmp);
+        assert!(matches!(result, Err(LupinError::BmpNoHiddenData)));
+    }
+
+    #[test]
+    fn test_invalid_bmp() {
+        let engine = BmpEngine::new();
+        let bmp = create_bmp(4, 4, 24);
+        let mut bad_depth = bmp.clone();
+        bad_depth[28] = 7;
+
+        // Too short, cut off in the pixel array, and an impossible bit depth
+        for not_bmp in [&b"BM"[..], &bmp[..bmp.len() - 1], &bad_depth] {
+            let result = engine.embed(not_bmp, b"payload", EmbedMode::Capacity);
+            assert!(matches!(result, Err(LupinError::BmpInvalidFormat { .. })));
+        }
+    }
+
+    #[test]
+    fn test_empty_payload_rejected() {
+        let engine = BmpEngine::new();
+        assert!(matches!(
+            engine.embed(&create_bmp(4, 4, 24), b"", EmbedMode::Capacity),
+            Err(LupinError::EmptyPayload)
+        ));
+    }
+
+    #[test]
+    fn test_truncated_block() {
+        // Arrange
+        let engine = BmpEngine::new();
+        let bmp = create_bmp(4, 4, 24);
+        let embedded = engine
+            .embed(&bmp, &[0x42; 100], EmbedMode::Capacity)
+            .unwrap();
+        let truncated = &embedded[..embedded.len() - 10];
+
+        // Act
+        let result = engine.extract(truncated);
+        let probe = engine.probe(truncated).unwrap().unwrap();
+
+        // Assert - without error correction a cut-off payload is reported
+        assert!(matches!(result, Err(LupinError::BmpCorruptedData)));
+        assert_eq!(probe.size, 100);
+        assert_eq!(probe.ranges, vec![bmp.len()..truncated.len()]);
+    }
+
+    #[test]
+    fn test_stealth_round_trip() {
+        // Arrange
+        let engine = BmpEngine::new();
+        let payload = b"Hidden in the pixel LSBs";
+
+        for bits_per_pixel in [24, 32] {
+            let bmp = create_bmp(13, 9, bits_per_pixel);
+
+            // Act
+            let embedded = engine.embed(&bmp, payload, EmbedMode::Stealth).unwrap();
+            let extracted = engine.extract(&embedded).unwrap();
+
+            // Assert - same size, and only LSBs changed
+            assert_eq!(extracted, payload);
+            assert_eq!(embedded.len(), bmp.len());
+            assert!(embedded.iter().zip(&bmp).all(|(a, b)| a | 1 == b | 1));
+        }
+    }
+
+    #[test]
+    fn test_stealth_skips_padding_and_alpha() {
+        // Arrange - 5 RGB pixels take 15 bytes, padded to 16
+        let engine = BmpEngine::new();
+        let bmp = create_bmp(5, 16, 24);
+        let alpha = create_bmp(5, 16, 32);
+        let ones = vec![0xFF; 7];
+
+        // Act
+        let embedded = engine.embed(&bmp, &ones, EmbedMode::Stealth).unwrap();
+        let embedded_alpha = engine.embed(&alpha, &ones, EmbedMode::Stealth).unwrap();
+
+        // Assert
+        for row in 0..16 {
+            assert_eq!(embedded[54 + row * 16 + 15], 0);
+        }
+        for pixel in 0..80 {
+            assert_eq!(embedded_alpha[54 + pixel * 4 + 3], 0xFF);
+        }
+    }
+
+    #[test]
+    fn test_stealth_top_down() {
+        // Arrange - the same image stored bottom-up and top-down
+        let engine = BmpEngine::new();
+        let bottom_up = create_bmp(7, 12, 24);
+        let top_down = flip_to_top_down(&bottom_up);
+        let payload = b"orientation";
+
+        // Act
+        let embedded = engine
+            .embed(&bottom_up, payload, EmbedMode::Stealth)
+            .unwrap();
+        let embedded_top_down = engine
+            .embed(&top_down, payload, EmbedMode::Stealth)
+            .unwrap();
+
+        // Assert - the bits follow the picture, not the storage order, so flipping a
+        // carrier keeps its payload readable
+        assert_eq!(engine.extract(&embedded_top_down).unwrap(), payload);
+        assert_eq!(
+            engine.extract(&flip_to_top_down(&embedded)).unwrap(),
+            payload
+        );
+    }
+
+    #[test]
+    fn test_stealth_bitfields() {
+        // Arrange - 32-bit BI_BITFIELDS with the channels in RGBA byte order
+        let engine = BmpEngine::new();
+        let mut bmp = create_bmp(4, 4, 32);
+        bmp[30] = 3;
+        for (i, mask) in [0x0000_00FFu32, 0x0000_FF00, 0x00FF_0000]
+            .iter()
+            .enumerate()
+        {
+            bmp.splice(54 + i * 4..54 + i * 4, mask.to_le_bytes());
+        }
+        bmp[10] = 66;
+
+        // Act
+        let image = BmpEngine::decode_image(&bmp).unwrap();
+        let mut odd_masks = bmp.clone();
+        odd_masks[54..58].copy_from_slice(&0x0000_03FFu32.to_le_bytes());
+
+        // Assert
+        assert_eq!(image.channels, [2, 1, 0]);
+        assert!(matches!(
+            engine.embed(&odd_masks, b"payload", EmbedMode::Stealth),
+            Err(LupinError::StealthUnsupportedCarrier { format: "BMP", .. })
+        ));
+    }
+
+    #[test]
+    fn test_stealth_rejects_palette_images() {
+        // Arrange
+        let engine = BmpEngine::new();
+        let bmp = create_bmp(16, 16, 8);
+
+        // Act
+        let result = engine.embed(&bmp, b"payload", EmbedMode::Stealth);
+
+        // Assert
+        assert!(matches!(
+            result,
+            Err(LupinError::StealthUnsupportedCarrier { format: "BMP", .. })
+        ));
+    }
+
+    #[test]
+    fn test_stealth_payload_too_large() {
+        // Arrange - 4x4 RGB pixels hold 48 bits = 6 bytes, less than the header
+        let engine = BmpEngine::new();
+        let bmp = create_bmp(4, 4, 24);
+
+        // Act
+        let result = engine.embed(&bmp, b"too big", EmbedMode::Stealth);
+
+        // Assert
+        assert!(matches!(
+            result,
+            Err(LupinError::PayloadTooLarge {
+                capacity: 0,
+                requested: 7
+            })
+        ));
+    }
+
+    #[test]
+    fn test_probe() {
+        // Arrange
+        let engine = BmpEngine::new();
+        let bmp = create_bmp(16, 16, 24);
+        let block = engine
+            .embed(&bmp, b"block payload", EmbedMode::Capacity)
+            .unwrap();
+        let pixels = engine.embed(&bmp, b"pixels", EmbedMode::Stealth).unwrap();
+
+        // Act
+        let clean = engine.probe(&bmp).unwrap();
+        let capacity = engine.probe(&block).unwrap().unwrap();
+        let stealth = engine.probe(&pixels).unwrap().unwrap();
+
+        // Assert
+        assert_eq!(clean, None);
+        assert_eq!(capacity.mode, EmbedMode::Capacity);
+        assert_eq!(capacity.size, 13);
+        assert_eq!(capacity.prefix, b"block payload");
+        assert_eq!(capacity.ranges, vec![bmp.len()..block.len()]);
+        assert_eq!(stealth.mode, EmbedMode::Stealth);
+        assert_eq!(stealth.size, 6);
+        assert_eq!(stealth.prefix, b"pixels");
+        assert_eq!(stealth.ranges, vec![54..bmp.len()]);
+    }
+
+    #[test]
+    fn test_remove() {
+        // Arrange
+        let engine = BmpEngine::new();
+        let bmp = create_bmp(16, 16, 24);
+        let block = engine
+            .embed(&bmp, b"block payload", EmbedMode::Capacity)
+            .unwrap();
+        let pixels = engine.embed(&bmp, b"pixels", EmbedMode::Stealth).unwrap();
+
+        // Act
+        let restored = engine.remove(&block).unwrap();
+        let cleared = engine.remove(&pixels).unwrap();
+        let nothing = engine.remove(&bmp);
+
+        // Assert
+        assert_eq!(restored, bmp);
+        assert_eq!(engine.probe(&cleared).unwrap(), None);
+        assert!(matches!(nothing, Err(LupinError::BmpNoHiddenData)));
+    }
+
+    #[test]
+    fn test_capacity() {
+        // Arrange - 16x16 RGB pixels hold 768 bits = 96 bytes, minus the 16-byte header
+        let engine = BmpEngine::new();
+        let bmp = create_bmp(16, 16, 24);
+
+        // Act
+        let capacity = engine.capacity(&bmp, EmbedMode::Capacity).unwrap();
+        let stealth = engine.capacity(&bmp, EmbedMode::Stealth).unwrap();
+
+        // Assert
+        assert_eq!(capacity, Capacity::Unlimited);
+        assert_eq!(stealth, Capacity::Exact(80));
+        assert!(engine.embed(&bmp, &[0xAB; 80], EmbedMode::Stealth).is_ok());
+        assert!(matches!(
+            engine.embed(&bmp, &[0xAB; 81], EmbedMode::Stealth),
+            Err(LupinError::PayloadTooLarge { capacity: 80, .. })
+        ));
+    }
+
+    #[test]
+    fn test_stealth_collisions() {
+        // Arrange
+        let engine = BmpEngine::new();
+        let bmp = create_bmp(16, 16, 24);
+        let stealthy = engine.embed(&bmp, b"first", EmbedMode::Stealth).unwrap();
+        let blocked = engine.embed(&bmp, b"first", EmbedMode::Capacity).unwrap();
+
+        // Act & Assert - any existing payload blocks a second one, in either mode
+        for (carrier, mode) in [
+            (&stealthy, EmbedMode::Stealth),
+            (&stealthy, EmbedMode::Capacity),
+            (&blocked, EmbedMode::Stealth),
+        ] {
+            assert!(matches!(
+                engine.embed(carrier, b"second", mode),
+                Err(LupinError::EmbedCollision { .. })
+            ));
+        }
+    }
+}
@@ -0,0 +1,1340 @@
+// Copyright 2025 Niclas Hedam
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! GIF steganography engine using an Application Extension block
+//!
+//! # How It Works
+//!
+//! This engine hides data in an Application Extension, the block GIF provides for
+//! application-specific data such as the `NETSCAPE2.0` animation loop count. Decoders
+//! skip extensions they don't recognise, so the image and its animation are unchanged.
+//!
+//! ## Storage Format
+//!
+//! The extension is identified by its 8-byte application identifier and 3-byte
+//! authentication code, and the payload follows raw in data sub-blocks:
+//!
+//! ```text
+//! [0x21 0xFF][0x0B]["LUPINSTG"]["1.0"]
+//! [1 byte: Size][Size bytes: Payload Chunk]...[0x00]
+//! ```
+//!
+//! - `0x21 0xFF` - Extension introducer and Application Extension label
+//! - `0x0B` - Size of the identifier block (always 11)
+//! - Identifier and authentication code - `LUPINSTG` and `1.0`, which tell our
+//!   extension apart from foreign ones
+//! - Sub-blocks - The payload, cut into chunks of at most 255 bytes, each preceded
+//!   by its size; a zero size ends the extension
+//!
+//! The extension is inserted just before the trailer (`0x3B`), after every image,
+//! so it can't get between a Graphic Control Extension and the image it applies to.
+//!
+//! ## GIF Block Structure
+//!
+//! A GIF starts with a 6-byte signature (`GIF87a` or `GIF89a`), the Logical Screen
+//! Descriptor and an optional Global Color Table, followed by blocks:
+//! - **Extension** (`0x21`): a label byte, then data sub-blocks
+//! - **Image** (`0x2C`): an Image Descriptor, an optional Local Color Table, the
+//!   LZW minimum code size and the LZW-compressed pixels in data sub-blocks
+//! - **Trailer** (`0x3B`): the end of the file
+//!
+//! A truncated block or an unknown byte where a block should start ends the scan
+//! without panicking; a Lupin extension cut short by the end of the file yields the
+//! chunks that are still there.
+//!
+//! ## Stealth Mode
+//!
+//! An Application Extension is easy to spot, so [`EmbedMode::Stealth`] adds no
+//! block at all and hides the payload in the palette indices of the pixels instead:
+//!
+//! 1. Every image's pixels are LZW-decoded into indices into its color table (the
+//!    local one if it has one, otherwise the global one).
+//! 2. The table's colors are sorted by luminance and paired off in that order, so
+//!    each color is paired with the one closest to it in brightness. The payload
+//!    bit of a pixel is the low bit of its color's position in the sorted order,
+//!    and a bit is changed by switching the pixel to the other color of its pair.
+//!    The transparent color and a color left without a pair carry no bits.
+//! 3. The payload is written, MSB first, into the pixels of every image in file
+//!    order, and the changed images are LZW-encoded again. The color tables and all
+//!    other blocks are copied verbatim.
+//!
+//! The bit stream starts with a small header so extraction can detect the payload
+//! without being told the mode. The header and payload are whitened after the random
+//! nonce, so the pixels show no fixed pattern (see the [`crate::engines`]
+//! documentation):
+//!
+//! ```text
+//! [8 bytes: Nonce][4 bytes: "LpGf"][4 bytes: Big-endian Payload Length][N bytes: Raw Payload]
+//! ```
+//!
+//! Capacity is one bit per pixel of every image (minus transparent ones) less the
+//! 16-byte header. How visible a switch is depends on the palette: an image whose
+//! colors come in near-identical pairs, as a quantizer tends to produce, hides the
+//! payload well, while one with few, very different colors doesn't.
+
+use super::stealth;
+use crate::error::{LupinError, Result};
+use crate::{Capacity, EmbedMode, Probe, SteganographyEngine};
+use log::debug;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// GIF steganography engine
+///
+/// In capacity mode, stores the payload raw in the data sub-blocks of an Application
+/// Extension with a Lupin identifier, which GIF decoders skip. In stealth mode, the
+/// payload goes into the pixels' palette indices instead, switching each pixel at
+/// most to the color next to it in brightness.
+///
+/// See the module documentation for details on how data is stored.
+pub struct GifEngine;
+
+/// The blocks of a GIF the engine cares about
+#[derive(Debug, Default)]
+struct Layout {
+    /// Byte offset of the trailer, or `None` if the scan stopped before one
+    trailer: Option<usize>,
+    /// Every complete image, in file order
+    images: Vec<ImageBlock>,
+    /// The first Lupin Application Extension
+    lupin: Option<LupinExtension>,
+}
+
+/// An image found by [`GifEngine::parse_layout`]
+#[derive(Debug, Clone)]
+struct ImageBlock {
+    /// Byte range of the color table in effect, local or global
+    palette: Option<Range<usize>>,
+    /// Palette index the preceding Graphic Control Extension marks transparent
+    transparent: Option<u8>,
+    /// Number of pixels in the image
+    pixels: usize,
+    /// Byte range of the LZW minimum code size and the data sub-blocks
+    data: Range<usize>,
+}
+
+/// A Lupin Application Extension found by [`GifEngine::parse_layout`]
+#[derive(Debug, Clone)]
+struct LupinExtension {
+    /// Byte range of the whole extension, from the introducer to the block
+    /// terminator (or the end of the file, if it was cut short)
+    range: Range<usize>,
+    /// Byte ranges of the payload chunks in its data sub-blocks
+    chunks: Vec<Range<usize>>,
+}
+
+/// An image decoded to palette indices, ready for stealth embedding
+struct Frame {
+    /// Byte range of the image data in the source file
+    data: Range<usize>,
+    /// LZW minimum code size, kept when re-encoding
+    min_code_size: u8,
+    /// The pixels' palette indices, in stored order
+    indices: Vec<u8>,
+    /// Position of each palette index in luminance order, or `None` for indices
+    /// that carry no bit
+    rank: [Option<u8>; 256],
+    /// Palette index at each position in luminance order
+    order: [u8; 256],
+    /// Whether embedding changed any index
+    changed: bool,
+}
+
+impl Frame {
+    /// Builds the luminance pairing for a color table
+    fn new(
+        data: Range<usize>,
+        min_code_size: u8,
+        indices: Vec<u8>,
+        palette: &[u8],
+        transparent: Option<u8>,
+    ) -> Self {
+        let mut colors: Vec<u8> = (0..palette.len() / 3)
+            .take(256)
+            .map(|i| i as u8)
+            .filter(|&i| Some(i) != transparent)
+            .collect();
+        // Rec. 601 luma; ties keep table order so the pairing is deterministic
+        colors.sort_by_key(|&i| {
+            let rgb = &palette[i as usize * 3..i as usize * 3 + 3];
+            299 * rgb[0] as u32 + 587 * rgb[1] as u32 + 114 * rgb[2] as u32
+        });
+
+        let mut rank = [None; 256];
+        let mut order = [0; 256];
+        // A color left over at the end has no partner to switch to
+        let paired = colors.len() & !1;
+        for (position, &index) in colors[..paired].iter().enumerate() {
+            rank[index as usize] = Some(position as u8);
+            order[position] = index;
+        }
+        Self {
+            data,
+            min_code_size,
+            indices,
+            rank,
+            order,
+            changed: false,
+        }
+    }
+}
+
+/// Every stealth-capable image of a GIF, with their carrier pixels in order
+struct StegoImage {
+    frames: Vec<Frame>,
+}
+
+impl StegoImage {
+    /// Iterates over the (frame, pixel) positions that carry a bit, in order
+    fn carriers(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.frames.iter().enumerate().flat_map(|(f, frame)| {
+            frame
+                .indices
+                .iter()
+                .enumerate()
+                .filter(|(_, &index)| frame.rank[index as usize].is_some())
+                .map(move |(p, _)| (f, p))
+        })
+    }
+
+    /// Total number of payload bits the images can carry, including the header
+    fn capacity_bits(&self) -> usize {
+        self.carriers().count()
+    }
+
+    /// Reads `len` bytes from the carrier bits, starting at byte `start` of the bit stream
+    fn read_bytes(&self, start: usize, len: usize) -> Vec<u8> {
+        let mut bits = self.carriers().skip(start * 8).map(|(f, p)| {
+            let frame = &self.frames[f];
+            frame.rank[frame.indices[p] as usize].unwrap_or_default() & 1
+        });
+        (0..len)
+            .map(|_| (0..8).fold(0u8, |byte, _| (byte << 1) | bits.next().unwrap_or_default()))
+            .collect()
+    }
+
+    /// Writes `data` into the carrier bits, starting at byte 0 of the bit stream
+    fn write_bytes(&mut self, data: &[u8]) {
+        let carriers: Vec<(usize, usize)> = self.carriers().take(data.len() * 8).collect();
+        let bits = data
+            .iter()
+            .flat_map(|&byte| (0..8).map(move |b| (byte >> (7 - b)) & 1));
+        for ((f, p), bit) in carriers.into_iter().zip(bits) {
+            let frame = &mut self.frames[f];
+            let rank = frame.rank[frame.indices[p] as usize].unwrap_or_default();
+            if rank & 1 != bit {
+                frame.indices[p] = frame.order[(rank ^ 1) as usize];
+                frame.changed = true;
+            }
+        }
+    }
+
+    /// Reads the stealth header, returning it if the magic matches
+    fn stealth_header(&self) -> Option<stealth::Header> {
+        if self.capacity_bits() < GifEngine::STEALTH_HEADER_LEN * 8 {
+            return None;
+        }
+
+        stealth::open(
+            GifEngine::STEALTH_MAGIC,
+            &self.read_bytes(0, GifEngine::STEALTH_HEADER_LEN),
+        )
+    }
+}
+
+/// Reads LZW codes of growing width from the concatenated data sub-blocks
+struct CodeReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    buffer: u32,
+    bits: u8,
+}
+
+impl CodeReader<'_> {
+    /// Reads the next `width`-bit code, least-significant bit first
+    fn read(&mut self, width: u8) -> Option<u16> {
+        while self.bits < width {
+            let byte = *self.data.get(self.pos)?;
+            self.pos += 1;
+            self.buffer |= (byte as u32) << self.bits;
+            self.bits += 8;
+        }
+        let code = (self.buffer & ((1 << width) - 1)) as u16;
+        self.buffer >>= width;
+        self.bits -= width;
+        Some(code)
+    }
+}
+
+/// Writes LZW codes of growing width, least-significant bit first
+#[derive(Default)]
+struct CodeWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    bits: u8,
+}
+
+impl CodeWriter {
+    fn write(&mut self, code: u16, width: u8) {
+        self.buffer |= (code as u32) << self.bits;
+        self.bits += width;
+        while self.bits >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+impl GifEngine {
+    /// Creates a new GIF engine
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Length of the signature and Logical Screen Descriptor
+    const HEADER_LEN: usize = 13;
+
+    /// Extension introducer
+    const EXTENSION: u8 = 0x21;
+
+    /// Image Descriptor introducer
+    const IMAGE: u8 = 0x2C;
+
+    /// Trailer, the last byte of a GIF
+    const TRAILER: u8 = 0x3B;
+
+    /// Application Extension label
+    const APPLICATION_LABEL: u8 = 0xFF;
+
+    /// Graphic Control Extension label
+    const GRAPHIC_CONTROL_LABEL: u8 = 0xF9;
+
+    /// Identifier block of our Application Extension: its size, the application
+    /// identifier and the authentication code.
+    ///
+    /// Other applications store data in Application Extensions too (notably the
+    /// `NETSCAPE2.0` loop count), so this is required to tell ours apart.
+    const LUPIN_APPLICATION: &'static [u8] = b"\x0BLUPINSTG1.0";
+
+    /// Largest data sub-block
+    const MAX_SUB_BLOCK: usize = 255;
+
+    /// Signature at the start of a stealth-mode bit stream
+    const STEALTH_MAGIC: &'static [u8] = b"LpGf";
+
+    /// Stealth header size: nonce + signature + 4-byte big-endian payload length
+    const STEALTH_HEADER_LEN: usize = stealth::HEADER_LEN;
+
+    /// Largest LZW code width
+    const MAX_CODE_WIDTH: u8 = 12;
+
+    /// Number of LZW codes, which is when the table must be cleared
+    const MAX_CODES: u16 = 1 << Self::MAX_CODE_WIDTH;
+
+    /// Reads a little-endian u16 from a slice
+    fn read_u16_le(data: &[u8]) -> u16 {
+        (data[0] as u16) | ((data[1] as u16) << 8)
+    }
+
+    /// Checks the signature and returns the offset of the first block and the
+    /// Global Color Table, if there is one
+    fn parse_header(data: &[u8]) -> Result<(usize, Option<Range<usize>>)> {
+        if data.len() < Self::HEADER_LEN {
+            return Err(LupinError::GifInvalidFormat {
+                reason: "File too short".to_string(),
+            });
+        }
+        if &data[..6] != b"GIF87a" && &data[..6] != b"GIF89a" {
+            return Err(LupinError::GifInvalidFormat {
+                reason: format!(
+                    "Unknown signature {:?}",
+                    String::from_utf8_lossy(&data[..6])
+                ),
+            });
+        }
+
+        let packed = data[10];
+        if packed & 0x80 == 0 {
+            return Ok((Self::HEADER_LEN, None));
+        }
+        let table = Self::HEADER_LEN..Self::HEADER_LEN + Self::color_table_len(packed);
+        if table.end > data.len() {
+            return Err(LupinError::GifInvalidFormat {
+                reason: "Global Color Table runs past the end of the file".to_string(),
+            });
+        }
+        Ok((table.end, Some(table)))
+    }
+
+    /// Byte length of the color table a packed field announces
+    fn color_table_len(packed: u8) -> usize {
+        3 << ((packed & 0x07) + 1)
+    }
+
+    /// Walks data sub-blocks from `pos`, returning the offset just past the block
+    /// terminator, the byte ranges of the sub-blocks' data and whether the terminator
+    /// was reached before the end of the file
+    fn sub_blocks(data: &[u8], mut pos: usize) -> (usize, Vec<Range<usize>>, bool) {
+        let mut chunks = Vec::new();
+        loop {
+            match data.get(pos) {
+                None => return (data.len(), chunks, false),
+                Some(0) => return (pos + 1, chunks, true),
+                Some(&size) => {
+                    let start = pos + 1;
+                    let end = start + size as usize;
+                    if end > data.len() {
+                        if start < data.len() {
+                            chunks.push(start..data.len());
+                        }
+                        return (data.len(), chunks, false);
+                    }
+                    chunks.push(start..end);
+                    pos = end;
+                }
+            }
+        }
+    }
+
+    /// Walks the blocks of a GIF, finding its images, the Lupin Application
+    /// Extension and the trailer.
+    ///
+    /// Only a bad signature is an error. A block that runs past the end of the file,
+    /// or an unknown byte where a block should start, ends the scan with what was
+    /// found so far and no trailer.
+    fn parse_layout(data: &[u8]) -> Result<Layout> {
+        let (mut pos, global_table) = Self::parse_header(data)?;
+        let mut layout = Layout::default();
+        // A Graphic Control Extension applies to the next image only
+        let mut transparent = None;
+
+        loop {
+            match data.get(pos) {
+                Some(&Self::TRAILER) => {
+                    layout.trailer = Some(pos);
+                    break;
+                }
+                Some(&Self::EXTENSION) => {
+                    let Some(&label) = data.get(pos + 1) else {
+                        break;
+                    };
+                    let body = pos + 2;
+                    let (end, chunks, complete) = Self::sub_blocks(data, body);
+
+                    let identifier = data.get(body..body + Self::LUPIN_APPLICATION.len());
+                    if label == Self::APPLICATION_LABEL
+                        && identifier == Some(Self::LUPIN_APPLICATION)
+                        && layout.lupin.is_none()
+                    {
+                        // The first sub-block is the identifier; the payload follows
+                        layout.lupin = Some(LupinExtension {
+                            range: pos..end,
+                            chunks: chunks[1..].to_vec(),
+                        });
+                    } else if label == Self::GRAPHIC_CONTROL_LABEL {
+                        transparent = match data.get(body..body + 5) {
+                            Some([4, flags, _, _, index]) if flags & 0x01 != 0 => Some(*index),
+                            _ => None,
+                        };
+                    }
+
+                    if !complete {
+                        break;
+                    }
+                    pos = end;
+                }
+                Some(&Self::IMAGE) => {
+                    let Some(descriptor) = data.get(pos..pos + 10) else {
+                        break;
+                    };
+                    let width = Self::read_u16_le(&descriptor[5..7]) as usize;
+                    let height = Self::read_u16_le(&descriptor[7..9]) as usize;
+                    let packed = descriptor[9];
+
+                    let mut image_data = pos + 10;
+                    let palette = if packed & 0x80 != 0 {
+                        let table = image_data..image_data + Self::color_table_len(packed);
+                        image_data = table.end;
+                        Some(table)
+                    } else {
+                        global_table.clone()
+                    };
+
+                    // The LZW minimum code size, then the data sub-blocks
+                    let (end, _, complete) = Self::sub_blocks(data, image_data + 1);
+                    if image_data >= data.len() || !complete {
+                        break;
+                    }
+                    layout.images.push(ImageBlock {
+                        palette,
+                        transparent: transparent.take(),
+                        pixels: width * height,
+                        data: image_data..end,
+                    });
+                    pos = end;
+                }
+                _ => break,
+            }
+        }
+
+        Ok(layout)
+    }
+
+    /// Decompresses an image's LZW data into palette indices
+    fn lzw_decode(min_code_size: u8, data: &[u8], pixels: usize) -> Result<Vec<u8>> {
+        let corrupt = |reason: &str| LupinError::GifInvalidFormat {
+            reason: format!("Corrupt image data: {}", reason),
+        };
+        if !(2..=8).contains(&min_code_size) {
+            return Err(corrupt("invalid LZW minimum code size"));
+        }
+
+        let clear = 1u16 << min_code_size;
+        let end = clear + 1;
+        let mut prefix = vec![0u16; Self::MAX_CODES as usize];
+        let mut suffix = vec![0u8; Self::MAX_CODES as usize];
+        let mut first = vec![0u8; Self::MAX_CODES as usize];
+        for code in 0..clear {
+            suffix[code as usize] = code as u8;
+            first[code as usize] = code as u8;
+        }
+
+        let mut reader = CodeReader {
+            data,
+            pos: 0,
+            buffer: 0,
+            bits: 0,
+        };
+        let mut width = min_code_size + 1;
+        let mut next = end + 1;
+        let mut previous: Option<u16> = None;
+        let mut output = Vec::with_capacity(pixels);
+        let mut string = Vec::new();
+
+        // Decoders stop at the end code, or when the data runs out
+        while let Some(code) = reader.read(width) {
+            if code == clear {
+                width = min_code_size + 1;
+                next = end + 1;
+                previous = None;
+                continue;
+            }
+            if code == end {
+                break;
+            }
+
+            // A code one past the table is the previous string plus its first byte
+            let (known, head) = match previous {
+                _ if code < next && (code < clear || code > end) => (code, first[code as usize]),
+                Some(previous) if code == next => (previous, first[previous as usize]),
+                _ => return Err(corrupt("LZW code out of range")),
+            };
+            string.clear();
+            let mut walk = known;
+            while walk > end {
+                string.push(suffix[walk as usize]);
+                walk = prefix[walk as usize];
+            }
+            string.push(walk as u8);
+            output.extend(string.iter().rev());
+            if code == next {
+                output.push(head);
+            }
+
+            if let Some(previous) = previous {
+                if next < Self::MAX_CODES {
+                    prefix[next as usize] = previous;
+                    suffix[next as usize] = head;
+                    first[next as usize] = first[previous as usize];
+                    next += 1;
+                    if next == 1 << width && width < Self::MAX_CODE_WIDTH {
+                        width += 1;
+                    }
+                }
+            }
+            previous = Some(code);
+        }
+
+        if output.len() < pixels {
+            return Err(corrupt("image data ends early"));
+        }
+        output.truncate(pixels);
+        Ok(output)
+    }
+
+    /// Compresses palette indices with LZW, returning the image data as stored: the
+    /// minimum code size followed by data sub-blocks
+    fn lzw_encode(min_code_size: u8, indices: &[u8]) -> Vec<u8> {
+        let clear = 1u16 << min_code_size;
+        let end = clear + 1;
+        let mut writer = CodeWriter::default();
+        let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+        let mut width = min_code_size + 1;
+        let mut next = end + 1;
+
+        writer.write(clear, width);
+        let mut current: Option<u16> = None;
+        for &index in indices {
+            let Some(code) = current else {
+                current = Some(index as u16);
+                continue;
+            };
+            if let Some(&extended) = table.get(&(code, index)) {
+                current = Some(extended);
+                continue;
+            }
+
+            writer.write(code, width);
+            if next == Self::MAX_CODES {
+                // The table is full: start over rather than stop learning
+                writer.write(clear, width);
+                table.clear();
+                width = min_code_size + 1;
+                next = end + 1;
+            } else {
+                table.insert((code, index), next);
+                next += 1;
+                // The decoder adds each code one step later, so widen one step later
+                if next - 1 == 1 << width && width < Self::MAX_CODE_WIDTH {
+                    width += 1;
+                }
+            }
+            current = Some(index as u16);
+        }
+        if let Some(code) = current {
+            writer.write(code, width);
+        }
+        writer.write(end, width);
+
+        let compressed = writer.finish();
+        let mut out = Vec::with_capacity(compressed.len() + compressed.len() / 255 + 2);
+        out.push(min_code_size);
+        for chunk in compressed.chunks(Self::MAX_SUB_BLOCK) {
+            out.push(chunk.len() as u8);
+            out.extend_from_slice(chunk);
+        }
+        out.push(0);
+        out
+    }
+
+    /// Decodes every image that has a color table into palette indices
+    fn decode_image(data: &[u8]) -> Result<StegoImage> {
+        let layout = Self::parse_layout(data)?;
+        let mut frames = Vec::new();
+        for image in &layout.images {
+            // Without a color table a decoder picks its own colors, so there is
+            // nothing to pair
+            let Some(palette) = &image.palette else {
+                continue;
+            };
+            let (_, chunks, _) = Self::sub_blocks(data, image.data.start + 1);
+            let compressed: Vec<u8> = chunks
+                .into_iter()
+                .flat_map(|chunk| data[chunk].iter().copied())
+                .collect();
+            let min_code_size = data[image.data.start];
+            let indices = Self::lzw_decode(min_code_size, &compressed, image.pixels)?;
+            frames.push(Frame::new(
+                image.data.clone(),
+                min_code_size,
+                indices,
+                &data[palette.clone()],
+                image.transparent,
+            ));
+        }
+
+        if frames.is_empty() {
+            return Err(LupinError::StealthUnsupportedCarrier {
+                format: "GIF",
+                reason: "it has no complete image with a color table".to_string(),
+            });
+        }
+        Ok(StegoImage { frames })
+    }
+
+    /// Writes the changed images back into the source GIF; everything else is
+    /// copied verbatim
+    fn encode_image(source: &[u8], image: &StegoImage) -> Vec<u8> {
+        let mut output = Vec::with_capacity(source.len());
+        let mut copied = 0;
+        for frame in image.frames.iter().filter(|frame| frame.changed) {
+            output.extend_from_slice(&source[copied..frame.data.start]);
+            output.extend_from_slice(&Self::lzw_encode(frame.min_code_size, &frame.indices));
+            copied = frame.data.end;
+        }
+        output.extend_from_slice(&source[copied..]);
+        output
+    }
+
+    /// Checks whether the pixels already carry a stealth payload.
+    ///
+    /// Best effort: images that can't be decoded simply report `false`.
+    fn has_stealth_payload(data: &[u8]) -> bool {
+        Self::decode_image(data)
+            .map(|image| image.stealth_header().is_some())
+            .unwrap_or(false)
+    }
+
+    /// Hides the payload in the palette indices of the pixels
+    fn embed_stealth(&self, source_data: &[u8], payload: &[u8]) -> Result<Vec<u8>> {
+        let mut image = Self::decode_image(source_data)?;
+
+        if image.stealth_header().is_some() {
+            return Err(LupinError::EmbedCollision {
+                source: std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    "GIF pixels already carry a Lupin payload",
+                ),
+            });
+        }
+
+        let stream = stealth::seal(Self::STEALTH_MAGIC, payload);
+
+        let capacity = image.capacity_bits() / 8;
+        if stream.len() > capacity {
+            return Err(LupinError::PayloadTooLarge {
+                capacity: capacity.saturating_sub(Self::STEALTH_HEADER_LEN),
+                requested: payload.len(),
+            });
+        }
+
+        debug!(
+            "GIF: Writing {} bytes into the palette indices of {} available carrier bytes",
+            stream.len(),
+            capacity
+        );
+
+        image.write_bytes(&stream);
+        Ok(Self::encode_image(source_data, &image))
+    }
+
+    /// Finds a stealth payload's header and first bytes in the palette indices
+    fn probe_stealth(source_data: &[u8]) -> Option<Probe> {
+        let image = Self::decode_image(source_data).ok()?;
+        let header = image.stealth_header()?;
+        let len = header.len;
+        let available = image.capacity_bits() / 8 - Self::STEALTH_HEADER_LEN;
+        let prefix = image.read_bytes(
+            Self::STEALTH_HEADER_LEN,
+            len.min(Probe::PREFIX_LEN).min(available),
+        );
+        let ranges = image
+            .frames
+            .iter()
+            .map(|frame| frame.data.clone())
+            .collect();
+        Some(Probe::new(
+            EmbedMode::Stealth,
+            len,
+            ranges,
+            header.unmask(prefix, 0),
+        ))
+    }
+
+    /// Writes zero bits over a stealth payload and its header in the palette indices
+    fn remove_stealth(source_data: &[u8]) -> Result<Vec<u8>> {
+        let mut image = Self::decode_image(source_data).map_err(|_| LupinError::GifNoHiddenData)?;
+        let len = image
+            .stealth_header()
+            .ok_or(LupinError::GifNoHiddenData)?
+            .len;
+
+        let wiped = (Self::STEALTH_HEADER_LEN + len).min(image.capacity_bits() / 8);
+        image.write_bytes(&vec![0; wiped]);
+
+        debug!("GIF: Cleared {} byte stealth payload from the pixels", len);
+
+        Ok(Self::encode_image(source_data, &image))
+    }
+
+    /// Recovers a payload hidden in the palette indices of the pixels
+    fn extract_stealth(source_data: &[u8]) -> Result<Vec<u8>> {
+        // A file we can't decode can't carry a stealth payload
+        let image = Self::decode_image(source_data).map_err(|_| LupinError::GifNoHiddenData)?;
+        let header = image.stealth_header().ok_or(LupinError::GifNoHiddenData)?;
+        let len = header.len;
+
+        if Self::STEALTH_HEADER_LEN + len > image.capacity_bits() / 8 {
+            return Err(LupinError::GifCorruptedData);
+        }
+
+        debug!("GIF: Found {} byte stealth payload in the pixels", len);
+
+        Ok(header.unmask(image.read_bytes(Self::STEALTH_HEADER_LEN, len), 0))
+    }
+}
+
+impl Default for GifEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SteganographyEngine for GifEngine {
+    fn magic_bytes(&self) -> &[u8] {
+        b"GIF8" // GIF87a and GIF89a
+    }
+
+    fn matches(&self, data: &[u8]) -> bool {
+        // The full signature and a complete Logical Screen Descriptor, so text that
+        // happens to start with `GIF8` isn't claimed
+        data.len() >= Self::HEADER_LEN
+            && (data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a"))
+    }
+
+    fn format_name(&self) -> &str {
+        "GIF"
+    }
+
+    fn format_ext(&self) -> &str {
+        ".gif"
+    }
+
+    fn capacity(&self, source_data: &[u8], mode: EmbedMode) -> Result<Capacity> {
+        match mode {
+            // An extension takes as many sub-blocks as the payload needs
+            EmbedMode::Capacity => {
+                Self::parse_layout(source_data)?;
+                Ok(Capacity::Unlimited)
+            }
+            EmbedMode::Stealth => {
+                let image = Self::decode_image(source_data)?;
+                let bytes = image.capacity_bits() / 8;
+                Ok(Capacity::Exact(
+                    bytes.saturating_sub(Self::STEALTH_HEADER_LEN),
+                ))
+            }
+        }
+    }
+
+    fn embed(&self, source_data: &[u8], payload: &[u8], mode: EmbedMode) -> Result<Vec<u8>> {
+        // Reject empty payloads so the embed contract is uniform across engines.
+        if payload.is_empty() {
+            return Err(LupinError::EmptyPayload);
+        }
+
+        // Check if there's already a Lupin Application Extension
+        let layout = Self::parse_layout(source_data)?;
+        if let Some(extension) = &layout.lupin {
+            debug!(
+                "GIF: Found existing Lupin Application Extension at {}-{}",
+                extension.range.start, extension.range.end
+            );
+            return Err(LupinError::EmbedCollision {
+                source: std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    "GIF already contains a Lupin Application Extension",
+                ),
+            });
+        }
+
+        // Exhaustive so a future EmbedMode variant is a compile error here rather than
+        // silently falling through to the capacity implementation below.
+        match mode {
+            EmbedMode::Capacity => {}
+            EmbedMode::Stealth => return self.embed_stealth(source_data, payload),
+        }
+
+        // A stealth payload would be shadowed by the extension on extract, so treat
+        // it as a collision too.
+        if Self::has_stealth_payload(source_data) {
+            return Err(LupinError::EmbedCollision {
+                source: std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    "GIF pixels already carry a Lupin payload",
+                ),
+            });
+        }
+
+        // Insert the extension before the trailer, after every image
+        let trailer = layout.trailer.ok_or_else(|| LupinError::GifInvalidFormat {
+            reason: "No trailer found; the file is truncated or malformed".to_string(),
+        })?;
+
+        let mut extension = Vec::with_capacity(
+            3 + Self::LUPIN_APPLICATION.len() + payload.len() + payload.len() / 255 + 1,
+        );
+        extension.extend_from_slice(&[Self::EXTENSION, Self::APPLICATION_LABEL]);
+        extension.extend_from_slice(Self::LUPIN_APPLICATION);
+        for chunk in payload.chunks(Self::MAX_SUB_BLOCK) {
+            extension.push(chunk.len() as u8);
+            extension.extend_from_slice(chunk);
+        }
+        extension.push(0); // Block terminator
+
+        debug!(
+            "GIF: Inserting {} byte Application Extension at position {}",
+            extension.len(),
+            trailer
+        );
+
+        let mut output = Vec::with_capacity(source_data.len() + extension.len());
+        output.extend_from_slice(&source_data[..trailer]);
+        output.extend_from_slice(&extension);
+        output.extend_from_slice(&source_data[trailer..]);
+        Ok(output)
+    }
+
+    fn probe(&self, source_data: &[u8]) -> Result<Option<Probe>> {
+        let Some(extension) = Self::parse_layout(source_data)?.lupin else {
+            return Ok(Self::probe_stealth(source_data));
+        };
+
+        let size = extension.chunks.iter().map(|chunk| chunk.len()).sum();
+        let prefix = extension
+            .chunks
+            .iter()
+            .flat_map(|chunk| &source_data[chunk.clone()])
+            .take(Probe::PREFIX_LEN)
+            .copied()
+            .collect();
+        Ok(Some(Probe::new(
+            EmbedMode::Capacity,
+            size,
+            vec![extension.range],
+            prefix,
+        )))
+    }
+
+    fn remove(&self, source_data: &[u8]) -> Result<Vec<u8>> {
+        let Some(extension) = Self::parse_layout(source_data)?.lupin else {
+            return Self::remove_stealth(source_data);
+        };
+
+        debug!(
+            "GIF: Removing Lupin Application Extension at {}-{}",
+            extension.range.start, extension.range.end
+        );
+
+        let mut output = Vec::with_capacity(source_data.len() - extension.range.len());
+        output.extend_from_slice(&source_data[..extension.range.start]);
+        output.extend_from_slice(&source_data[extension.range.end..]);
+        Ok(output)
+    }
+
+    fn extract(&self, source_data: &[u8]) -> Result<Vec<u8>> {
+        // Concatenate the extension's sub-blocks, falling back to the palette
+        // indices when there is no extension (stealth mode)
+        let Some(extension) = Self::parse_layout(source_data)?.lupin else {
+            return Self::extract_stealth(source_data);
+        };
+
+        debug!(
+            "GIF: Found Lupin Application Extension with {} sub-block(s)",
+            extension.chunks.len()
+        );
+
+        let mut payload = Vec::new();
+        for chunk in extension.chunks {
+            payload.extend_from_slice(&source_data[chunk]);
+        }
+        Ok(payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a GIF89a of `frames` images of `width` x `height` pixels, each with a
+    /// 16-color global table of grey pairs and pseudo-random pixels. With
+    /// `transparent`, color 0 of every frame is transparent.
+    fn create_gif(width: u16, height: u16, frames: usize, transparent: bool) -> Vec<u8> {
+        let mut gif = b"GIF89a".to_vec();
+        gif.extend_from_slice(&width.to_le_bytes());
+        gif.extend_from_slice(&height.to_le_bytes());
+        gif.extend_from_slice(&[0xF3, 0, 0]); // 16-color global table, background 0
+        for i in 0..16u8 {
+            // Pairs of near-identical greys, out of luminance order
+            let grey = (15 - i) / 2 * 32 + i % 2;
+            gif.extend_from_slice(&[grey, grey, grey]);
+        }
+        // A foreign Application Extension, as animated GIFs carry
+        gif.extend_from_slice(b"\x21\xFF\x0BNETSCAPE2.0\x03\x01\x00\x00\x00");
+
+        let mut seed = 0x1234_5678u32;
+        for _ in 0..frames {
+            if transparent {
+                gif.extend_from_slice(&[0x21, 0xF9, 4, 0x01, 0, 0, 0, 0]);
+            }
+            gif.push(GifEngine::IMAGE);
+            gif.extend_from_slice(&[0, 0, 0, 0]);
+            gif.extend_from_slice(&width.to_le_bytes());
+            gif.extend_from_slice(&height.to_le_bytes());
+            gif.push(0);
+            let indices: Vec<u8> = (0..width as usize * height as usize)
+                .map(|_| {
+                    seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                    (seed >> 16) as u8 % 16
+                })
+                .collect();
+            gif.extend_from_slice(&GifEngine::lzw_encode(4, &indices));
+        }
+        gif.push(GifEngine::TRAILER);
+        gif
+    }
+
+    /// Decodes the palette indices of every frame
+    fn indices(gif: &[u8]) -> Vec<Vec<u8>> {
+        GifEngine::decode_image(gif)
+            .unwrap()
+            .frames
+            .into_iter()
+            .map(|frame| frame.indices)
+            .collect()
+    }
+
+    #[test]
+    fn test_gif_magic_bytes() {
+        let engine = GifEngine::new();
+        assert_eq!(engine.magic_bytes(), b"GIF8");
+    }
+
+    #[test]
+    fn test_gif_format_info() {
+        let engine = GifEngine::new();
+        assert_eq!(engine.format_name(), "GIF");
+        assert_eq!(engine.format_ext(), ".gif");
+    }
+
+    #[test]
+    fn test_matches_validates_header() {
+        // Arrange
+        let engine = GifEngine::new();
+
+        // Act & Assert
+        assert!(engine.matches(b"GIF89a\x01\x00\x01\x00\x00\x00\x00"));
+        assert!(engine.matches(b"GIF87a\x01\x00\x01\x00\x00\x00\x00"));
+        assert!(!engine.matches(b"GIF89a\x01\x00"));
+        assert!(!engine.matches(b"GIF8 decoder notes\nline two\n"));
+    }
+
+    #[test]
+    fn test_embed_and_extract() {
+        // Arrange
+        let engine = GifEngine::new();
+        let gif = create_gif(8, 8, 1, false);
+        let payload = b"Secret message hidden in GIF!";
+
+        // Act
+        let embedded = engine.embed(&gif, payload, EmbedMode::Capacity).unwrap();
+        let extracted = engine.extract(&embedded).unwrap();
+
+        // Assert - the extension goes right before the trailer
+        assert_eq!(extracted, payload);
+        assert_eq!(&embedded[..gif.len() - 1], &gif[..gif.len() - 1]);
+        assert_eq!(embedded.last(), Some(&GifEngine::TRAILER));
+        assert_eq!(indices(&embedded), indices(&gif));
+    }
+
+    #[test]
+    fn test_payload_split_into_sub_blocks() {
+        let engine = GifEngine::new();
+        let gif = create_gif(8, 8, 1, false);
+        // Either side of the sub-block size, and many sub-blocks
+        for len in [254, 255, 256, 510, 10_000] {
+            let payload: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+            let embedded = engine.embed(&gif, &payload, EmbedMode::Capacity).unwrap();
+            let extension = GifEngine::parse_layout(&embedded).unwrap().lupin.unwrap();
+            assert_eq!(extension.chunks.len(), (len + 254) / 255);
+            assert_eq!(engine.extract(&embedded).unwrap(), payload, "len {}", len);
+        }
+    }
+
+    #[test]
+    fn test_embed_collision() {
+        let engine = GifEngine::new();
+        let embedded_once = engine
+            .embed(&create_gif(8, 8, 1, false), b"First", EmbedMode::Capacity)
+            .unwrap();
+
+        let result = engine.embed(&embedded_once, b"Second", EmbedMode::Capacity);
+        assert!(matches!(result, Err(LupinError::EmbedCollision { .. })));
+    }
+
+    #[test]
+    fn test_extract_without_data() {
+        let engine = GifEngine::new();
+
+        // The NETSCAPE2.0 extension is not ours
+        let result = engine.extract(&create_gif(8, 8, 1, false));
+        assert!(matches!(result, Err(LupinError::GifNoHiddenData)));
+    }
+
+    #[test]
+    fn test_invalid_gif() {
+        let engine = GifEngine::new();
+
+        for not_gif in [&b"GIF8"[..], b"GIF90a but longer than a header"] {
+            let result = engine.embed(not_gif, b"payload", EmbedMode::Capacity);
+            assert!(matches!(result, Err(LupinError::GifInvalidFormat { .. })));
+        }
+    }
+
+    #[test]
+    fn test_empty_payload_rejected() {
+        let engine = GifEngine::new();
+        assert!(matches!(
+            engine.embed(&create_gif(8, 8, 1, false), b"", EmbedMode::Capacity),
+            Err(LupinError::EmptyPayload)
+        ));
+    }
+
+    #[test]
+    fn test_truncated_extension_does_not_panic() {
+        // Arrange - cut the file in the middle of the payload's second sub-block
+        let engine = GifEngine::new();
+        let gif = create_gif(8, 8, 1, false);
+        let payload = vec![0x42u8; 600];
+        let embedded = engine.embed(&gif, &payload, EmbedMode::Capacity).unwrap();
+        let start = gif.len() - 1 + 2 + GifEngine::LUPIN_APPLICATION.len();
+        let truncated = &embedded[..start + 256 + 100];
+
+        // Act
+        let extracted = engine.extract(truncated).unwrap();
+        let probe = engine.probe(truncated).unwrap().unwrap();
+
+        // Assert - the chunks that are left come back, for the envelope to report
+        assert_eq!(extracted, vec![0x42; 255 + 99]);
+        assert_eq!(probe.size, 255 + 99);
+        assert_eq!(probe.ranges[0].end, truncated.len());
+        assert!(matches!(
+            engine.embed(truncated, b"payload", EmbedMode::Capacity),
+            Err(LupinError::EmbedCollision { .. })
+        ));
+    }
+
+    #[test]
+    fn test_truncated_file_does_not_panic() {
+        let engine = GifEngine::new();
+        let gif = create_gif(8, 8, 1, false);
+
+        // Every prefix either parses or fails cleanly
+        for len in 0..gif.len() {
+            let _ = engine.probe(&gif[..len]);
+            let _ = engine.extract(&gif[..len]);
+            assert!(engine
+                .embed(&gif[..len], b"payload", EmbedMode::Capacity)
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn test_lzw_round_trip() {
+        // Long enough to fill the code table several times over
+        let noisy: Vec<u8> = (0..100_000u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 27) as u8)
+            .collect();
+        let flat = vec![3u8; 50_000];
+        for (min_code_size, indices) in [(5, &noisy), (2, &flat)] {
+            let encoded = GifEngine::lzw_encode(min_code_size, indices);
+            let (_, chunks, complete) = GifEngine::sub_blocks(&encoded, 1);
+            let compressed: Vec<u8> = chunks
+                .into_iter()
+                .flat_map(|chunk| encoded[chunk].to_vec())
+                .collect();
+
+            assert!(complete);
+            assert_eq!(
+                &GifEngine::lzw_decode(min_code_size, &compressed, indices.len()).unwrap(),
+                indices
+            );
+        }
+    }
+
+    #[test]
+    fn test_stealth_round_trip() {
+        // Arrange
+        let engine = GifEngine::new();
+        let gif = create_gif(32, 32, 2, false);
+        let payload = b"Hidden in the palette indices";
+
+        // Act
+        let embedded = engine.embed(&gif, payload, EmbedMode::Stealth).unwrap();
+        let extracted = engine.extract(&embedded).unwrap();
+
+        // Assert - no block was added and everything before the pixels is unchanged
+        let image = GifEngine::parse_layout(&gif).unwrap().images[0].clone();
+        assert_eq!(extracted, payload);
+        assert!(GifEngine::parse_layout(&embedded).unwrap().lupin.is_none());
+        assert_eq!(&embedded[..image.data.start], &gif[..image.data.start]);
+    }
+
+    #[test]
+    fn test_stealth_only_switches_to_paired_colors() {
+        // Arrange
+        let engine = GifEngine::new();
+        let gif = create_gif(32, 32, 1, true);
+
+        // Act
+        let embedded = engine.embed(&gif, &[0xA5; 90], EmbedMode::Stealth).unwrap();
+
+        // Assert - each pixel keeps its grey, give or take one level, and
+        // transparent pixels are never touched
+        let (before, after) = (&indices(&gif)[0], &indices(&embedded)[0]);
+        let grey = |index: u8| gif[13 + index as usize * 3];
+        let mut changed = 0;
+        for (&old, &new) in before.iter().zip(after) {
+            assert!(grey(old).abs_diff(grey(new)) <= 1);
+            assert_eq!(old == 0, new == 0);
+            changed += (old != new) as usize;
+        }
+        assert!(changed > 0);
+    }
+
+    #[test]
+    fn test_stealth_payload_too_large() {
+        // Arrange - 4x4 pixels hold 16 bits, less than the 16-byte header
+        let engine = GifEngine::new();
+        let gif = create_gif(4, 4, 1, false);
+
+        // Act
+        let result = engine.embed(&gif, b"too big", EmbedMode::Stealth);
+
+        // Assert
+        assert!(matches!(
+            result,
+            Err(LupinError::PayloadTooLarge {
+                capacity: 0,
+                requested: 7
+            })
+        ));
+    }
+
+    #[test]
+    fn test_probe() {
+        // Arrange
+        let engine = GifEngine::new();
+        let gif = create_gif(16, 16, 2, false);
+        let extension = engine
+            .embed(&gif, b"extension payload", EmbedMode::Capacity)
+            .unwrap();
+        let pixels = engine.embed(&gif, b"pixels", EmbedMode::Stealth).unwrap();
+
+        // Act
+        let clean = engine.probe(&gif).unwrap();
+        let capacity = engine.probe(&extension).unwrap().unwrap();
+        let stealth = engine.probe(&pixels).unwrap().unwrap();
+
+        // Assert
+        assert_eq!(clean, None);
+        assert_eq!(capacity.mode, EmbedMode::Capacity);
+        assert_eq!(capacity.size, 17);
+        assert_eq!(capacity.prefix, b"extension payloa");
+        let range = capacity.ranges[0].clone();
+        assert_eq!(&extension[range.start..range.start + 3], b"\x21\xFF\x0B");
+        assert_eq!(range.end, extension.len() - 1);
+        assert_eq!(stealth.mode, EmbedMode::Stealth);
+        assert_eq!(stealth.size, 6);
+        assert_eq!(stealth.prefix, b"pixels");
+        assert_eq!(stealth.ranges.len(), 2);
+    }
+
+    #[test]
+    fn test_remove() {
+        // Arrange
+        let engine = GifEngine::new();
+        let gif = create_gif(16, 16, 1, false);
+        let extension = engine
+            .embed(&gif, b"extension payload", EmbedMode::Capacity)
+            .unwrap();
+        let pixels = engine.embed(&gif, b"pixels", EmbedMode::Stealth).unwrap();
+
+        // Act
+        let restored = engine.remove(&extension).unwrap();
+        let cleared = engine.remove(&pixels).unwrap();
+        let nothing = engine.remove(&gif);
+
+        // Assert
+        assert_eq!(restored, gif);
+        assert_eq!(engine.probe(&cleared).unwrap(), None);
+        let again = engine
+            .embed(&cleared, b"again", EmbedMode::Stealth)
+            .unwrap();
+        assert_eq!(engine.extract(&again).unwrap(), b"again");
+        assert!(matches!(nothing, Err(LupinError::GifNoHiddenData)));
+    }
+
+    #[test]
+    fn test_capacity() {
+        // Arrange - 16x16 pixels in two frames hold 512 bits = 64 bytes, minus the
+        // 16-byte header; with color 0 transparent, about one pixel in 16 carries none
+        let engine = GifEngine::new();
+        let gif = create_gif(16, 16, 2, false);
+
+        // Act
+        let capacity = engine.capacity(&gif, EmbedMode::Capacity).unwrap();
+        let stealth = engine.capacity(&gif, EmbedMode::Stealth).unwrap();
+        let transparent = engine
+            .capacity(&create_gif(16, 16, 2, true), EmbedMode::Stealth)
+            .unwrap();
+
+        // Assert
+        assert_eq!(capacity, Capacity::Unlimited);
+        assert_eq!(stealth, Capacity::Exact(48));
+        assert!(transparent.bytes().unwrap() < 48);
+        assert!(engine.embed(&gif, &[0xAB; 48], EmbedMode::Stealth).is_ok());
+        assert!(matches!(
+            engine.embed(&gif, &[0xAB; 49], EmbedMode::Stealth),
+            Err(LupinError::PayloadTooLarge { capacity: 48, .. })
+        ));
+    }
+
+    #[test]
+    fn test_stealth_requires_color_table() {
+        // Arrange - drop the global color table flag, so no image has colors
+        let engine = GifEngine::new();
+        let mut gif = create_gif(8, 8, 1, false);
+        gif[10] = 0;
+        gif.drain(13..13 + 48);
+
+        // Act
+        let result = engine.embed(&gif, b"payload", EmbedMode::Stealth);
+
+        // Assert
+        assert!(matches!(
+            result,
+            Err(LupinError::StealthUnsupportedCarrier { format: "GIF", .. })
+        ));
+    }
+
+    #[test]
+    fn test_stealth_collisions() {
+        // Arrange
+        let engine = GifEngine::new();
+        let gif = create_gif(32, 32, 1, false);
+        let stealthy = engine.embed(&gif, b"first", EmbedMode::Stealth).unwrap();
+        let extended = engine.embed(&gif, b"first", EmbedMode::Capacity).unwrap();
+
+        // Act & Assert - any existing payload blocks a second one, in either mode
+        for (carrier, mode) in [
+            (&stealthy, EmbedMode::Stealth),
+            (&stealthy, EmbedMode::Capacity),
+            (&extended, EmbedMode::Stealth),
+        ] {
+            assert!(matches!(
+                engine.embed(carrier, b"second", mode),
+                Err(LupinError::EmbedCollision { .. })
+            ));
+        }
+    }
+}
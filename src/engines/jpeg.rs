@@ -71,9 +71,44 @@
 //! - **Stealthy** - Looks like legitimate application metadata
 //! - **Standard** - Part of JPEG specification (ISO/IEC 10918-1)
 //!
+//! ## Stealth Mode
+//!
+//! An APP13 segment is easy to spot, so [`EmbedMode::Stealth`] adds no segment at
+//! all and hides the payload in the quantized DCT coefficients using F5:
+//!
+//! 1. The baseline Huffman-coded scan after SOS is entropy-decoded into blocks of
+//!    quantized coefficients (restart intervals and 4:2:0 etc. subsampling included).
+//! 2. The non-zero AC coefficients are visited in a fixed pseudo-random order. Each
+//!    carries one bit (its LSB, inverted for negative values), and a bit is changed
+//!    by decrementing the coefficient's magnitude, never by incrementing it, which
+//!    keeps the coefficient histogram's shape. DC coefficients are never touched.
+//! 3. Payload bits are matrix encoded: `k` bits go into a group of `2^k - 1`
+//!    coefficients while changing at most one of them. The largest `k` that fits is
+//!    used. A coefficient that shrinks to zero no longer carries a bit, so its group
+//!    is re-collected and the word embedded again.
+//! 4. The scan is re-encoded. The original Huffman tables are reused when they can
+//!    still code every symbol; otherwise optimal tables replace the old DHT segments.
+//!
+//! The bit stream starts with a header, embedded one bit per coefficient, so
+//! extraction can detect the payload and learn `k` without being told the mode.
+//! Everything after the random nonce, payload included, is whitened (see the
+//! [`crate::engines`] documentation); the coefficient order comes from a fixed seed, so
+//! it hides nothing from someone who knows the scheme:
+//!
+//! ```text
+//! [8 bytes: Nonce][4 bytes: "LpF5"][1 byte: k][4 bytes: Big-endian Payload Length]
+//! ```
+//!
+//! Capacity depends on the image content: roughly one bit per non-zero AC
+//! coefficient at `k = 1`, falling as `k` grows. Only baseline and extended
+//! sequential Huffman JPEGs with a single scan are supported; progressive,
+//! arithmetic-coded and multi-scan files return
+//! [`LupinError::StealthUnsupportedCarrier`].
+//!
 
+use super::stealth::{self, Whitening};
 use crate::error::{LupinError, Result};
-use crate::{EmbedMode, SteganographyEngine};
+use crate::{Capacity, EmbedMode, Probe, SteganographyEngine};
 use log::debug;
 
 /// JPEG steganography engine
@@ -82,10 +117,509 @@ use log::debug;
 /// The payload is stored raw behind a Lupin signature in an APP13 segment.
 /// This approach is stealthy - the data appears as legitimate application metadata and
 /// is never shown to users (unlike comment segments which may be displayed).
+/// In stealth mode the payload goes into the quantized DCT coefficients instead (F5).
 ///
 /// See the module documentation for details on how data is stored.
 pub struct JpegEngine;
 
+/// A canonical Huffman table, as defined by a DHT segment
+#[derive(Debug, Clone)]
+struct HuffmanTable {
+    /// Number of codes of each length, 1 to 16 bits
+    counts: [u8; 16],
+    /// Symbols in increasing code order
+    symbols: Vec<u8>,
+    /// Smallest code of each length
+    min_code: [u32; 16],
+    /// Largest code of each length, or -1 if there are none
+    max_code: [i32; 16],
+    /// Index into `symbols` of the first code of each length
+    val_ptr: [usize; 16],
+    /// `(code, length)` for every symbol; a length of 0 means "not in the table"
+    codes: [(u16, u8); 256],
+}
+
+impl HuffmanTable {
+    /// Builds the decoding and encoding lookups from a DHT table definition
+    fn new(counts: [u8; 16], symbols: Vec<u8>) -> Result<Self> {
+        let total: usize = counts.iter().map(|&c| c as usize).sum();
+        if total != symbols.len() || total > 256 {
+            return Err(LupinError::JpegInvalidFormat {
+                reason: "Huffman table symbol count mismatch".to_string(),
+            });
+        }
+
+        let mut table = Self {
+            counts,
+            symbols,
+            min_code: [0; 16],
+            max_code: [-1; 16],
+            val_ptr: [0; 16],
+            codes: [(0, 0); 256],
+        };
+
+        let mut code = 0u32;
+        let mut k = 0;
+        for (len, &count) in counts.iter().enumerate() {
+            let count = count as usize;
+            if count > 0 {
+                table.val_ptr[len] = k;
+                table.min_code[len] = code;
+                for _ in 0..count {
+                    table.codes[table.symbols[k] as usize] = (code as u16, len as u8 + 1);
+                    code += 1;
+                    k += 1;
+                }
+                table.max_code[len] = code as i32 - 1;
+            }
+            // A code of all ones for its length would overflow the next length
+            if code > (1 << (len + 1)) {
+                return Err(LupinError::JpegInvalidFormat {
+                    reason: "Huffman table has too many codes".to_string(),
+                });
+            }
+            code <<= 1;
+        }
+
+        Ok(table)
+    }
+
+    /// Builds an optimal table for the given symbol frequencies (ITU T.81, Annex K.2)
+    fn from_frequencies(frequencies: &[u32; 256]) -> Self {
+        // Index 256 is a reserved symbol guaranteeing no code is all ones
+        let mut freq = [0u64; 257];
+        for (f, &count) in freq.iter_mut().zip(frequencies.iter()) {
+            *f = count as u64;
+        }
+        freq[256] = 1;
+
+        let mut code_size = [0usize; 257];
+        let mut others = [usize::MAX; 257];
+
+        loop {
+            // The two least frequent symbols, preferring the larger index on ties
+            let mut v1 = None;
+            let mut v2 = None;
+            for i in 0..257 {
+                if freq[i] == 0 {
+                    continue;
+                }
+                if v1.map_or(true, |v: usize| freq[i] <= freq[v]) {
+                    v2 = v1;
+                    v1 = Some(i);
+                } else if v2.map_or(true, |v: usize| freq[i] <= freq[v]) {
+                    v2 = Some(i);
+                }
+            }
+
+            let (mut v1, mut v2) = match (v1, v2) {
+                (Some(v1), Some(v2)) => (v1, v2),
+                _ => break,
+            };
+
+            freq[v1] += freq[v2];
+            freq[v2] = 0;
+
+            code_size[v1] += 1;
+            while others[v1] != usize::MAX {
+                v1 = others[v1];
+                code_size[v1] += 1;
+            }
+            others[v1] = v2;
+
+            code_size[v2] += 1;
+            while others[v2] != usize::MAX {
+                v2 = others[v2];
+                code_size[v2] += 1;
+            }
+        }
+
+        let mut bits = [0usize; 33];
+        for &size in code_size.iter().filter(|&&size| size > 0) {
+            bits[size] += 1;
+        }
+
+        // Limit code lengths to 16 bits
+        for i in (17..=32).rev() {
+            while bits[i] > 0 {
+                let mut j = i - 2;
+                while bits[j] == 0 {
+                    j -= 1;
+                }
+                bits[i] -= 2;
+                bits[i - 1] += 1;
+                bits[j + 1] += 2;
+                bits[j] -= 1;
+            }
+        }
+
+        // Drop the reserved symbol from the longest code length
+        let mut i = 16;
+        while bits[i] == 0 {
+            i -= 1;
+        }
+        bits[i] -= 1;
+
+        let mut symbols = Vec::new();
+        for size in 1..=32 {
+            for (symbol, &s) in code_size.iter().enumerate().take(256) {
+                if s == size {
+                    symbols.push(symbol as u8);
+                }
+            }
+        }
+
+        let mut counts = [0u8; 16];
+        for (count, &b) in counts.iter_mut().zip(&bits[1..=16]) {
+            *count = b as u8;
+        }
+
+        // The reserved symbol sorts last, so truncating keeps the rest in order
+        symbols.truncate(counts.iter().map(|&c| c as usize).sum());
+        Self::new(counts, symbols).expect("generated Huffman tables are always valid")
+    }
+
+    /// Checks whether every symbol with a non-zero frequency has a code
+    fn can_encode(&self, frequencies: &[u32; 256]) -> bool {
+        frequencies
+            .iter()
+            .zip(self.codes.iter())
+            .all(|(&f, &(_, len))| f == 0 || len > 0)
+    }
+
+    /// Serializes the table body as it appears inside a DHT segment
+    fn to_dht(&self, class: u8, id: u8) -> Vec<u8> {
+        let mut out = vec![(class << 4) | id];
+        out.extend_from_slice(&self.counts);
+        out.extend_from_slice(&self.symbols);
+        out
+    }
+}
+
+/// Reads bits from entropy-coded data, undoing 0xFF00 byte stuffing
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bits: u32,
+    bit_count: u8,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            bits: 0,
+            bit_count: 0,
+        }
+    }
+
+    fn corrupt(reason: &str) -> LupinError {
+        LupinError::JpegInvalidFormat {
+            reason: reason.to_string(),
+        }
+    }
+
+    fn read_bit(&mut self) -> Result<u32> {
+        if self.bit_count == 0 {
+            let byte = *self
+                .data
+                .get(self.pos)
+                .ok_or_else(|| Self::corrupt("entropy-coded data ended early"))?;
+            if byte == 0xFF {
+                if self.data.get(self.pos + 1) != Some(&0x00) {
+                    return Err(Self::corrupt("unexpected marker in entropy-coded data"));
+                }
+                self.pos += 1; // Skip the stuffed zero byte
+            }
+            self.pos += 1;
+            self.bits = byte as u32;
+            self.bit_count = 8;
+        }
+
+        self.bit_count -= 1;
+        Ok((self.bits >> self.bit_count) & 1)
+    }
+
+    /// Reads `count` raw bits as an unsigned value
+    fn receive(&mut self, count: u8) -> Result<u32> {
+        let mut value = 0;
+        for _ in 0..count {
+            value = (value << 1) | self.read_bit()?;
+        }
+        Ok(value)
+    }
+
+    /// Reads a size-category-encoded value and sign-extends it (ITU T.81, F.2.2.1)
+    fn receive_extend(&mut self, size: u8) -> Result<i16> {
+        if size == 0 {
+            return Ok(0);
+        }
+        if size > 15 {
+            return Err(Self::corrupt("coefficient size out of range"));
+        }
+        let value = self.receive(size)? as i32;
+        let value = if value < (1 << (size - 1)) {
+            value - (1 << size) + 1
+        } else {
+            value
+        };
+        Ok(value as i16)
+    }
+
+    fn decode(&mut self, table: &HuffmanTable) -> Result<u8> {
+        let mut code = 0u32;
+        for len in 0..16 {
+            code = (code << 1) | self.read_bit()?;
+            if table.max_code[len] >= 0 && code as i32 <= table.max_code[len] {
+                let index = table.val_ptr[len] + (code - table.min_code[len]) as usize;
+                return Ok(table.symbols[index]);
+            }
+        }
+        Err(Self::corrupt("invalid Huffman code"))
+    }
+
+    /// Discards the remaining bits of the current byte and consumes an RSTn marker
+    fn restart(&mut self) -> Result<()> {
+        self.bit_count = 0;
+        while self.data.get(self.pos) == Some(&0xFF) && self.data.get(self.pos + 1) == Some(&0xFF) {
+            self.pos += 1; // Fill bytes
+        }
+        match (self.data.get(self.pos), self.data.get(self.pos + 1)) {
+            (Some(0xFF), Some(0xD0..=0xD7)) => {
+                self.pos += 2;
+                Ok(())
+            }
+            _ => Err(Self::corrupt("missing restart marker")),
+        }
+    }
+}
+
+/// Writes bits as entropy-coded data, applying 0xFF00 byte stuffing
+#[derive(Default)]
+struct BitWriter {
+    out: Vec<u8>,
+    bits: u32,
+    bit_count: u8,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, count: u8) {
+        for i in (0..count).rev() {
+            self.bits = (self.bits << 1) | ((value >> i) & 1);
+            self.bit_count += 1;
+            if self.bit_count == 8 {
+                self.push_byte();
+            }
+        }
+    }
+
+    fn push_byte(&mut self) {
+        let byte = self.bits as u8;
+        self.out.push(byte);
+        if byte == 0xFF {
+            self.out.push(0x00);
+        }
+        self.bits = 0;
+        self.bit_count = 0;
+    }
+
+    /// Pads the final byte with 1-bits, as required before a marker or the end of scan
+    fn flush(&mut self) {
+        if self.bit_count > 0 {
+            let pad = 8 - self.bit_count;
+            self.write((1 << pad) - 1, pad);
+        }
+    }
+}
+
+/// A frame component as declared in the SOF segment
+#[derive(Debug, Clone, Copy)]
+struct FrameComponent {
+    id: u8,
+    /// Horizontal sampling factor
+    h: usize,
+    /// Vertical sampling factor
+    v: usize,
+}
+
+/// A scan component: which frame component it codes and with which tables
+#[derive(Debug, Clone, Copy)]
+struct ScanComponent {
+    dc_table: usize,
+    ac_table: usize,
+}
+
+/// The quantized DCT coefficients of a baseline JPEG's single scan, plus
+/// everything needed to write them back losslessly
+#[derive(Clone)]
+struct CoefficientImage {
+    /// Every block of the scan in coding order, coefficients in zig-zag order.
+    /// Index 0 holds the DC *difference* exactly as coded, so the DC prediction
+    /// chain (and its restart resets) never has to be recomputed.
+    blocks: Vec<[i16; 64]>,
+    /// The scan component of each block within one MCU, in coding order
+    mcu_layout: Vec<usize>,
+    scan_components: Vec<ScanComponent>,
+    dc_tables: [Option<HuffmanTable>; 4],
+    ac_tables: [Option<HuffmanTable>; 4],
+    /// MCUs between restart markers (0 = no restart markers)
+    restart_interval: usize,
+    /// Byte ranges of the DHT segments preceding the scan
+    dht_segments: Vec<(usize, usize)>,
+    /// Offset of the SOS marker
+    sos_start: usize,
+    /// Offset of the first byte of entropy-coded data
+    scan_start: usize,
+    /// Offset just past the entropy-coded data (the marker that ends the scan)
+    scan_end: usize,
+}
+
+impl CoefficientImage {
+    /// Number of AC coefficient positions across all blocks
+    fn ac_positions(&self) -> usize {
+        self.blocks.len() * 63
+    }
+
+    /// Mutable access to the AC coefficient at `position` (see [`Self::ac_positions`])
+    fn ac_mut(&mut self, position: usize) -> &mut i16 {
+        &mut self.blocks[position / 63][1 + position % 63]
+    }
+
+    fn ac(&self, position: usize) -> i16 {
+        self.blocks[position / 63][1 + position % 63]
+    }
+
+    /// Calls `emit` with every `(table class, table id, symbol)` the scan would code
+    fn for_each_symbol(&self, mut emit: impl FnMut(usize, usize, u8)) {
+        for (i, block) in self.blocks.iter().enumerate() {
+            let component = self.scan_components[self.mcu_layout[i % self.mcu_layout.len()]];
+
+            emit(0, component.dc_table, JpegEngine::size_category(block[0]));
+
+            let mut run = 0;
+            for &coefficient in &block[1..] {
+                if coefficient == 0 {
+                    run += 1;
+                    continue;
+                }
+                while run > 15 {
+                    emit(1, component.ac_table, 0xF0); // ZRL
+                    run -= 16;
+                }
+                emit(
+                    1,
+                    component.ac_table,
+                    (run << 4) | JpegEngine::size_category(coefficient),
+                );
+                run = 0;
+            }
+            if run > 0 {
+                emit(1, component.ac_table, 0x00); // EOB
+            }
+        }
+    }
+}
+
+/// F5 embedding state: walks the non-zero AC coefficients in a fixed pseudo-random order
+struct F5Walker<'a> {
+    image: &'a mut CoefficientImage,
+    order: &'a [u32],
+    cursor: usize,
+}
+
+impl F5Walker<'_> {
+    /// The bit an F5 coefficient carries: its LSB for positive values and the
+    /// inverted LSB for negative ones, so decrementing the magnitude always flips it
+    fn coefficient_bit(value: i16) -> u32 {
+        let lsb = (value.unsigned_abs() & 1) as u32;
+        if value > 0 {
+            lsb
+        } else {
+            1 - lsb
+        }
+    }
+
+    /// Collects the next `n` non-zero coefficient positions starting at `from`
+    fn collect_group(&self, from: usize, n: usize) -> Option<(Vec<usize>, usize)> {
+        let mut group = Vec::with_capacity(n);
+        let mut cursor = from;
+        while group.len() < n {
+            let position = *self.order.get(cursor)? as usize;
+            cursor += 1;
+            if self.image.ac(position) != 0 {
+                group.push(position);
+            }
+        }
+        Some((group, cursor))
+    }
+
+    /// XOR of the (1-based) indices of every group member carrying a 1 bit
+    fn group_hash(&self, group: &[usize]) -> u32 {
+        group
+            .iter()
+            .enumerate()
+            .filter(|(_, &position)| Self::coefficient_bit(self.image.ac(position)) == 1)
+            .fold(0, |hash, (i, _)| hash ^ (i as u32 + 1))
+    }
+
+    /// Embeds a `k`-bit word using (1, 2^k - 1, k) matrix encoding.
+    ///
+    /// At most one coefficient magnitude is decremented. If that shrinks it to
+    /// zero, the extractor would skip it, so the group is re-collected and the
+    /// word embedded again. Returns `false` once the coefficients run out.
+    fn embed_word(&mut self, k: u32, word: u32) -> bool {
+        let n = (1usize << k) - 1;
+        loop {
+            let Some((group, next)) = self.collect_group(self.cursor, n) else {
+                return false;
+            };
+
+            let change = self.group_hash(&group) ^ word;
+            if change == 0 {
+                self.cursor = next;
+                return true;
+            }
+
+            let coefficient = self.image.ac_mut(group[change as usize - 1]);
+            *coefficient -= coefficient.signum();
+            if *coefficient != 0 {
+                self.cursor = next;
+                return true;
+            }
+            // Shrinkage: retry the same word starting from the same group start
+        }
+    }
+
+    /// Reads back a `k`-bit word embedded by [`Self::embed_word`]
+    fn extract_word(&mut self, k: u32) -> Option<u32> {
+        let n = (1usize << k) - 1;
+        let (group, next) = self.collect_group(self.cursor, n)?;
+        self.cursor = next;
+        Some(self.group_hash(&group))
+    }
+}
+
+/// An F5 stealth header read back from the coefficients
+struct F5Header {
+    /// Matrix encoding parameter of the payload words
+    k: u32,
+    /// Declared payload length
+    len: usize,
+    /// Position in the coefficient order where the payload words start
+    cursor: usize,
+    whitening: Whitening,
+}
+
+impl F5Header {
+    /// Unwhitens the payload bytes read from the start of the payload
+    fn unmask(&self, mut payload: Vec<u8>) -> Vec<u8> {
+        let offset = JpegEngine::STEALTH_HEADER_LEN - stealth::NONCE_LEN;
+        self.whitening.apply(&mut payload, offset);
+        payload
+    }
+}
+
 impl JpegEngine {
     /// Creates a new JPEG engine
     pub fn new() -> Self {
@@ -111,6 +645,20 @@ impl JpegEngine {
     /// one when finding, extracting or checking for collisions.
     const LUPIN_SIGNATURE: &'static [u8] = b"Lupin\0";
 
+    /// Signature at the start of a stealth-mode (F5) bit stream
+    const STEALTH_MAGIC: &'static [u8] = b"LpF5";
+
+    /// Stealth header size: nonce + signature + matrix encoding `k` + 4-byte payload
+    /// length
+    const STEALTH_HEADER_LEN: usize = stealth::NONCE_LEN + 9;
+
+    /// Largest matrix encoding parameter tried; (1, 127, 7) changes at most one
+    /// coefficient per 127 while embedding 7 bits
+    const F5_MAX_K: u32 = 7;
+
+    /// Seed for the coefficient permutation shared by embed and extract
+    const F5_SEED: u64 = 0x4C75_7069_6E46_3521; // "LupinF5!"
+
     /// Reads a big-endian u16 from a slice
     fn read_u16_be(data: &[u8]) -> u16 {
         ((data[0] as u16) << 8) | (data[1] as u16)
@@ -225,6 +773,643 @@ impl JpegEngine {
 
         chunks
     }
+
+    /// Number of bits needed to code a coefficient's magnitude (its "SSSS" category)
+    fn size_category(value: i16) -> u8 {
+        (16 - value.unsigned_abs().leading_zeros()) as u8
+    }
+
+    /// Entropy-decodes the single baseline scan into its quantized DCT coefficients.
+    ///
+    /// Only sequential Huffman-coded JPEGs (SOF0/SOF1) whose one scan covers every
+    /// component are supported; anything else returns
+    /// [`LupinError::StealthUnsupportedCarrier`].
+    fn decode_coefficients(&self, jpeg_data: &[u8]) -> Result<CoefficientImage> {
+        let invalid = |reason: &str| LupinError::JpegInvalidFormat {
+            reason: reason.to_string(),
+        };
+        let unsupported = |reason: &str| LupinError::StealthUnsupportedCarrier {
+            format: "JPEG",
+            reason: reason.to_string(),
+        };
+
+        if jpeg_data.len() < 4 || Self::read_u16_be(jpeg_data) != Self::SOI_MARKER {
+            return Err(invalid("Missing SOI marker"));
+        }
+
+        // (width, height, components)
+        let mut frame: Option<(usize, usize, Vec<FrameComponent>)> = None;
+        let mut dc_tables: [Option<HuffmanTable>; 4] = Default::default();
+        let mut ac_tables: [Option<HuffmanTable>; 4] = Default::default();
+        let mut dht_segments = Vec::new();
+        let mut restart_interval = 0;
+        let mut pos = 2;
+
+        let (sos_start, scan_start, scan_components) = loop {
+            // Skip fill bytes before the marker code
+            while jpeg_data.get(pos) == Some(&0xFF) && jpeg_data.get(pos + 1) == Some(&0xFF) {
+                pos += 1;
+            }
+            if pos + 4 > jpeg_data.len() || jpeg_data[pos] != 0xFF {
+                return Err(invalid("no start of scan found"));
+            }
+
+            let marker = Self::read_u16_be(&jpeg_data[pos..pos + 2]);
+            if marker == Self::EOI_MARKER {
+                return Err(invalid("no start of scan found"));
+            }
+            if (0xFFD0..=0xFFD7).contains(&marker) || marker == 0xFF01 {
+                pos += 2; // Standalone marker without a length field
+                continue;
+            }
+
+            let length = Self::read_u16_be(&jpeg_data[pos + 2..pos + 4]) as usize;
+            if length < 2 || pos + 2 + length > jpeg_data.len() {
+                return Err(invalid("truncated segment"));
+            }
+            let segment = &jpeg_data[pos + 4..pos + 2 + length];
+
+            match marker {
+                // Baseline and extended sequential, Huffman-coded
+                0xFFC0 | 0xFFC1 => {
+                    if segment.len() < 6 {
+                        return Err(invalid("truncated frame header"));
+                    }
+                    if segment[0] != 8 {
+                        return Err(unsupported("only 8-bit precision is supported"));
+                    }
+                    let height = Self::read_u16_be(&segment[1..3]) as usize;
+                    let width = Self::read_u16_be(&segment[3..5]) as usize;
+                    let count = segment[5] as usize;
+                    if width == 0 || height == 0 || count == 0 {
+                        return Err(invalid("frame has no pixels"));
+                    }
+                    if segment.len() < 6 + count * 3 {
+                        return Err(invalid("truncated frame header"));
+                    }
+                    let components = segment[6..6 + count * 3]
+                        .chunks(3)
+                        .map(|c| FrameComponent {
+                            id: c[0],
+                            h: (c[1] >> 4) as usize,
+                            v: (c[1] & 0x0F) as usize,
+                        })
+                        .collect::<Vec<_>>();
+                    if components
+                        .iter()
+                        .any(|c| !(1..=4).contains(&c.h) || !(1..=4).contains(&c.v))
+                    {
+                        return Err(invalid("invalid sampling factors"));
+                    }
+                    frame = Some((width, height, components));
+                }
+                // Progressive, lossless, hierarchical and arithmetic-coded frames
+                0xFFC2 | 0xFFC3 | 0xFFC5..=0xFFC7 | 0xFFC9..=0xFFCB | 0xFFCD..=0xFFCF => {
+                    return Err(unsupported(
+                        "only baseline (sequential, Huffman-coded) JPEGs are supported",
+                    ));
+                }
+                // Define Huffman tables (a segment may hold several)
+                0xFFC4 => {
+                    let mut table_data = segment;
+                    while !table_data.is_empty() {
+                        if table_data.len() < 17 {
+                            return Err(invalid("truncated Huffman table"));
+                        }
+                        let class = table_data[0] >> 4;
+                        let id = (table_data[0] & 0x0F) as usize;
+                        let mut counts = [0u8; 16];
+                        counts.copy_from_slice(&table_data[1..17]);
+                        let total: usize = counts.iter().map(|&c| c as usize).sum();
+                        if class > 1 || id > 3 || table_data.len() < 17 + total {
+                            return Err(invalid("malformed Huffman table"));
+                        }
+                        let table = HuffmanTable::new(counts, table_data[17..17 + total].to_vec())?;
+                        if class == 0 {
+                            dc_tables[id] = Some(table);
+                        } else {
+                            ac_tables[id] = Some(table);
+                        }
+                        table_data = &table_data[17 + total..];
+                    }
+                    dht_segments.push((pos, pos + 2 + length));
+                }
+                // Define restart interval
+                0xFFDD => {
+                    if segment.len() < 2 {
+                        return Err(invalid("truncated restart interval"));
+                    }
+                    restart_interval = Self::read_u16_be(segment) as usize;
+                }
+                Self::SOS_MARKER => {
+                    let (_, _, components) = frame
+                        .as_ref()
+                        .ok_or_else(|| invalid("scan before frame header"))?;
+                    let count = *segment.first().unwrap_or(&0) as usize;
+                    if count == 0 || segment.len() < 1 + count * 2 + 3 {
+                        return Err(invalid("truncated scan header"));
+                    }
+                    let selection = &segment[1 + count * 2..];
+                    if selection[0] != 0 || selection[1] != 63 || selection[2] != 0 {
+                        return Err(unsupported("only full sequential scans are supported"));
+                    }
+                    if count != components.len() {
+                        return Err(unsupported("multi-scan JPEGs are not supported"));
+                    }
+
+                    let mut scan_components = Vec::with_capacity(count);
+                    for (i, selector) in segment[1..1 + count * 2].chunks(2).enumerate() {
+                        // Interleaved scans must list components in frame order
+                        if components[i].id != selector[0] {
+                            return Err(invalid("scan component not in frame order"));
+                        }
+                        let component = ScanComponent {
+                            dc_table: (selector[1] >> 4) as usize,
+                            ac_table: (selector[1] & 0x0F) as usize,
+                        };
+                        if component.dc_table > 3
+                            || component.ac_table > 3
+                            || dc_tables[component.dc_table].is_none()
+                            || ac_tables[component.ac_table].is_none()
+                        {
+                            return Err(invalid("scan references a missing Huffman table"));
+                        }
+                        scan_components.push(component);
+                    }
+
+                    break (pos, pos + 2 + length, scan_components);
+                }
+                _ => {}
+            }
+
+            pos += 2 + length;
+        };
+
+        let (width, height, components) = frame.ok_or_else(|| invalid("no frame header"))?;
+
+        // Entropy-coded data runs until the first marker that isn't a stuffed
+        // zero byte or a restart marker.
+        let mut scan_end = scan_start;
+        while scan_end < jpeg_data.len() {
+            if jpeg_data[scan_end] == 0xFF {
+                let mut next = scan_end + 1;
+                while jpeg_data.get(next) == Some(&0xFF) {
+                    next += 1;
+                }
+                match jpeg_data.get(next) {
+                    Some(0x00) | Some(0xD0..=0xD7) => scan_end = next + 1,
+                    _ => break,
+                }
+            } else {
+                scan_end += 1;
+            }
+        }
+
+        // Work out the MCU structure (ITU T.81, A.2)
+        let h_max = components.iter().map(|c| c.h).max().unwrap_or(1);
+        let v_max = components.iter().map(|c| c.v).max().unwrap_or(1);
+        let (mcu_count, mcu_layout) = if components.len() == 1 {
+            // Non-interleaved: one block per MCU, covering the component's own extent
+            let FrameComponent { h, v, .. } = components[0];
+            let blocks_w = ((width * h + h_max - 1) / h_max + 7) / 8;
+            let blocks_h = ((height * v + v_max - 1) / v_max + 7) / 8;
+            (blocks_w * blocks_h, vec![0])
+        } else {
+            let mcus_x = (width + 8 * h_max - 1) / (8 * h_max);
+            let mcus_y = (height + 8 * v_max - 1) / (8 * v_max);
+            let layout = components
+                .iter()
+                .enumerate()
+                .flat_map(|(i, c)| std::iter::repeat(i).take(c.h * c.v))
+                .collect::<Vec<_>>();
+            (mcus_x * mcus_y, layout)
+        };
+
+        let mut reader = BitReader::new(&jpeg_data[scan_start..scan_end]);
+        let mut blocks = Vec::new();
+
+        for mcu in 0..mcu_count {
+            if restart_interval > 0 && mcu > 0 && mcu % restart_interval == 0 {
+                reader.restart()?;
+            }
+
+            for &component in &mcu_layout {
+                let tables = scan_components[component];
+                let dc_table = dc_tables[tables.dc_table].as_ref().expect("checked above");
+                let ac_table = ac_tables[tables.ac_table].as_ref().expect("checked above");
+
+                let mut block = [0i16; 64];
+                let size = reader.decode(dc_table)?;
+                block[0] = reader.receive_extend(size)?;
+
+                let mut k = 1;
+                while k < 64 {
+                    let symbol = reader.decode(ac_table)?;
+                    let (run, size) = ((symbol >> 4) as usize, symbol & 0x0F);
+                    if size == 0 {
+                        if run == 15 {
+                            k += 16; // ZRL: sixteen zeros
+                            continue;
+                        }
+                        break; // EOB
+                    }
+                    k += run;
+                    if k > 63 {
+                        return Err(invalid("coefficient index out of range"));
+                    }
+                    block[k] = reader.receive_extend(size)?;
+                    k += 1;
+                }
+                if k > 64 {
+                    return Err(invalid("coefficient index out of range"));
+                }
+
+                blocks.push(block);
+            }
+        }
+
+        Ok(CoefficientImage {
+            blocks,
+            mcu_layout,
+            scan_components,
+            dc_tables,
+            ac_tables,
+            restart_interval,
+            dht_segments,
+            sos_start,
+            scan_start,
+            scan_end,
+        })
+    }
+
+    /// Entropy-encodes the coefficients back into a complete JPEG file.
+    ///
+    /// The original Huffman tables are reused when they can still code every
+    /// symbol; F5 shrinkage can introduce new (run, size) symbols, in which case
+    /// optimal tables are generated and written in place of the old DHT segments.
+    fn encode_coefficients(&self, jpeg_data: &[u8], image: &CoefficientImage) -> Vec<u8> {
+        let mut frequencies = [[[0u32; 256]; 4]; 2];
+        image.for_each_symbol(|class, id, symbol| frequencies[class][id][symbol as usize] += 1);
+
+        let reusable = (0..4).all(|id| {
+            let fits = |table: &Option<HuffmanTable>, frequency: &[u32; 256]| match table {
+                Some(table) => table.can_encode(frequency),
+                None => frequency.iter().all(|&f| f == 0),
+            };
+            fits(&image.dc_tables[id], &frequencies[0][id])
+                && fits(&image.ac_tables[id], &frequencies[1][id])
+        });
+
+        let mut dc_tables = image.dc_tables.clone();
+        let mut ac_tables = image.ac_tables.clone();
+        let mut dht = Vec::new();
+        if !reusable {
+            for (class, tables) in [(0u8, &mut dc_tables), (1u8, &mut ac_tables)] {
+                for (id, table) in tables.iter_mut().enumerate() {
+                    let frequency = &frequencies[class as usize][id];
+                    if frequency.iter().any(|&f| f > 0) {
+                        let generated = HuffmanTable::from_frequencies(frequency);
+                        dht.extend_from_slice(&generated.to_dht(class, id as u8));
+                        *table = Some(generated);
+                    }
+                }
+            }
+        }
+
+        let mut writer = BitWriter::default();
+        let mcu_size = image.mcu_layout.len();
+        for (i, block) in image.blocks.iter().enumerate() {
+            let mcu = i / mcu_size;
+            if image.restart_interval > 0
+                && mcu > 0
+                && i % mcu_size == 0
+                && mcu % image.restart_interval == 0
+            {
+                writer.flush();
+                let restart = ((mcu / image.restart_interval - 1) % 8) as u8;
+                writer.out.extend_from_slice(&[0xFF, 0xD0 + restart]);
+            }
+
+            let component = image.scan_components[image.mcu_layout[i % mcu_size]];
+            let dc_table = dc_tables[component.dc_table]
+                .as_ref()
+                .expect("table in use");
+            let ac_table = ac_tables[component.ac_table]
+                .as_ref()
+                .expect("table in use");
+
+            let mut emit_value = |table: &HuffmanTable, symbol: u8, value: i16| {
+                let (code, length) = table.codes[symbol as usize];
+                writer.write(code as u32, length);
+                let size = symbol & 0x0F;
+                if size > 0 {
+                    // Negative values are coded as value - 1 in `size` bits
+                    let bits = if value < 0 { value - 1 } else { value };
+                    writer.write(bits as u32 & ((1 << size) - 1), size);
+                }
+            };
+
+            emit_value(dc_table, Self::size_category(block[0]), block[0]);
+
+            let mut run = 0;
+            for &coefficient in &block[1..] {
+                if coefficient == 0 {
+                    run += 1;
+                    continue;
+                }
+                while run > 15 {
+                    emit_value(ac_table, 0xF0, 0);
+                    run -= 16;
+                }
+                let symbol = (run << 4) | Self::size_category(coefficient);
+                emit_value(ac_table, symbol, coefficient);
+                run = 0;
+            }
+            if run > 0 {
+                emit_value(ac_table, 0x00, 0);
+            }
+        }
+        writer.flush();
+
+        let mut output = Vec::with_capacity(jpeg_data.len());
+        if reusable {
+            output.extend_from_slice(&jpeg_data[..image.sos_start]);
+        } else {
+            // Drop the old DHT segments and define the new tables right before the scan
+            let mut pos = 0;
+            for &(start, end) in &image.dht_segments {
+                output.extend_from_slice(&jpeg_data[pos..start]);
+                pos = end;
+            }
+            output.extend_from_slice(&jpeg_data[pos..image.sos_start]);
+            output.extend_from_slice(&Self::write_u16_be(0xFFC4));
+            output.extend_from_slice(&Self::write_u16_be(dht.len() as u16 + 2));
+            output.extend_from_slice(&dht);
+        }
+        output.extend_from_slice(&jpeg_data[image.sos_start..image.scan_start]);
+        output.extend_from_slice(&writer.out);
+        output.extend_from_slice(&jpeg_data[image.scan_end..]);
+        output
+    }
+
+    /// The fixed pseudo-random order in which F5 visits AC coefficient positions.
+    ///
+    /// Spreading the changes over the whole image (instead of filling blocks from
+    /// the top-left) avoids a tell-tale change in statistics between regions.
+    fn f5_order(positions: usize) -> Vec<u32> {
+        let mut order: Vec<u32> = (0..positions as u32).collect();
+        // SplitMix64 seeded with a fixed constant: deterministic on both sides
+        let mut state = Self::F5_SEED;
+        for i in (1..order.len()).rev() {
+            state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^= z >> 31;
+            order.swap(i, (z % (i as u64 + 1)) as usize);
+        }
+        order
+    }
+
+    /// Splits a byte stream into `k`-bit words, MSB first, zero-padding the last word
+    fn to_words(data: &[u8], k: u32) -> Vec<u32> {
+        let bits = data
+            .iter()
+            .flat_map(|&byte| (0..8).rev().map(move |i| (byte >> i) as u32 & 1))
+            .collect::<Vec<_>>();
+        bits.chunks(k as usize)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .enumerate()
+                    .fold(0, |word, (i, &bit)| word | (bit << (k as usize - 1 - i)))
+            })
+            .collect()
+    }
+
+    /// Reads `len` bytes worth of `k`-bit words back into a byte stream
+    fn read_words(walker: &mut F5Walker, k: u32, len: usize) -> Option<Vec<u8>> {
+        let word_count = (len * 8 + k as usize - 1) / k as usize;
+        let mut bits = Vec::with_capacity(word_count * k as usize);
+        for _ in 0..word_count {
+            let word = walker.extract_word(k)?;
+            bits.extend((0..k).rev().map(|i| (word >> i) & 1));
+        }
+        Some(
+            bits.chunks(8)
+                .take(len)
+                .map(|byte| byte.iter().fold(0u8, |b, &bit| (b << 1) | bit as u8))
+                .collect(),
+        )
+    }
+
+    /// Reads the F5 stealth header, returning it if present
+    fn read_stealth_header(image: &mut CoefficientImage, order: &[u32]) -> Option<F5Header> {
+        let mut walker = F5Walker {
+            image,
+            order,
+            cursor: 0,
+        };
+        let header = Self::read_words(&mut walker, 1, Self::STEALTH_HEADER_LEN)?;
+        let whitening = Whitening::from_nonce(&header);
+        let mut fields = header[stealth::NONCE_LEN..].to_vec();
+        whitening.apply(&mut fields, 0);
+        if !fields.starts_with(Self::STEALTH_MAGIC) {
+            return None;
+        }
+        Some(F5Header {
+            k: fields[4] as u32,
+            len: u32::from_be_bytes([fields[5], fields[6], fields[7], fields[8]]) as usize,
+            cursor: walker.cursor,
+            whitening,
+        })
+    }
+
+    /// Checks whether the DCT coefficients already carry a stealth payload.
+    ///
+    /// Best effort: JPEGs that can't be decoded simply report `false`.
+    fn has_stealth_payload(&self, jpeg_data: &[u8]) -> bool {
+        self.decode_coefficients(jpeg_data)
+            .map(|mut image| {
+                let order = Self::f5_order(image.ac_positions());
+                Self::read_stealth_header(&mut image, &order).is_some()
+            })
+            .unwrap_or(false)
+    }
+
+    /// Estimates how many payload bytes F5 can hide in the coefficients.
+    ///
+    /// With k = 1 every non-zero AC coefficient carries one bit, except that about half
+    /// of the +/-1 coefficients shrink to zero when changed and carry nothing, so the
+    /// true figure depends on the payload bits.
+    fn stealth_capacity(image: &CoefficientImage) -> usize {
+        let (non_zero, ones) = (0..image.ac_positions())
+            .map(|position| image.ac(position))
+            .filter(|&value| value != 0)
+            .fold((0, 0), |(non_zero, ones), value| {
+                (non_zero + 1, ones + usize::from(value.abs() == 1))
+            });
+        ((non_zero - ones / 2) / 8).saturating_sub(Self::STEALTH_HEADER_LEN)
+    }
+
+    /// Hides the payload in the non-zero AC coefficients using F5 matrix encoding
+    fn embed_stealth(&self, source_data: &[u8], payload: &[u8]) -> Result<Vec<u8>> {
+        let mut image = self.decode_coefficients(source_data)?;
+        let order = Self::f5_order(image.ac_positions());
+
+        // Reading the header never modifies coefficients, so `image` stays pristine
+        if Self::read_stealth_header(&mut image, &order).is_some() {
+            return Err(LupinError::EmbedCollision {
+                source: std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    "JPEG coefficients already carry a Lupin payload",
+                ),
+            });
+        }
+
+        // Prefer the largest k that fits: it changes the fewest coefficients per
+        // embedded bit. Each attempt works on a fresh copy of the coefficients.
+        for k in (1..=Self::F5_MAX_K).rev() {
+            let mut attempt = image.clone();
+
+            let whitening = Whitening::random();
+            let mut header = Vec::with_capacity(Self::STEALTH_HEADER_LEN);
+            header.extend_from_slice(whitening.nonce());
+            header.extend_from_slice(Self::STEALTH_MAGIC);
+            header.push(k as u8);
+            header.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+            whitening.apply(&mut header[stealth::NONCE_LEN..], 0);
+            let mut whitened = payload.to_vec();
+            whitening.apply(&mut whitened, Self::STEALTH_HEADER_LEN - stealth::NONCE_LEN);
+
+            let mut walker = F5Walker {
+                image: &mut attempt,
+                order: &order,
+                cursor: 0,
+            };
+            let fits = Self::to_words(&header, 1)
+                .into_iter()
+                .all(|word| walker.embed_word(1, word))
+                && Self::to_words(&whitened, k)
+                    .into_iter()
+                    .all(|word| walker.embed_word(k, word));
+
+            if fits {
+                debug!(
+                    "JPEG: Embedded {} bytes with F5 matrix encoding (k = {})",
+                    payload.len(),
+                    k
+                );
+                return Ok(self.encode_coefficients(source_data, &attempt));
+            }
+        }
+
+        Err(LupinError::PayloadTooLarge {
+            capacity: Self::stealth_capacity(&image),
+            requested: payload.len(),
+        })
+    }
+
+    /// Finds an F5 payload's header and first bytes in the AC coefficients
+    fn probe_stealth(&self, source_data: &[u8]) -> Option<Probe> {
+        let mut image = self.decode_coefficients(source_data).ok()?;
+        let order = Self::f5_order(image.ac_positions());
+        let header = Self::read_stealth_header(&mut image, &order)?;
+        let scan = image.scan_start..image.scan_end;
+
+        let prefix = if (1..=Self::F5_MAX_K).contains(&header.k) {
+            let mut walker = F5Walker {
+                image: &mut image,
+                order: &order,
+                cursor: header.cursor,
+            };
+            Self::read_words(&mut walker, header.k, header.len.min(Probe::PREFIX_LEN))
+                .map(|prefix| header.unmask(prefix))
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        Some(Probe::new(
+            EmbedMode::Stealth,
+            header.len,
+            vec![scan],
+            prefix,
+        ))
+    }
+
+    /// Overwrites an F5 payload and its header with zero words.
+    ///
+    /// The payload is cleared first, while its words still line up with the header's
+    /// `k`; clearing the header can shrink coefficients and shift what follows it.
+    fn remove_stealth(&self, source_data: &[u8]) -> Result<Vec<u8>> {
+        let mut image = self
+            .decode_coefficients(source_data)
+            .map_err(|_| LupinError::JpegNoHiddenData)?;
+        let order = Self::f5_order(image.ac_positions());
+
+        let F5Header { k, len, cursor, .. } =
+            Self::read_stealth_header(&mut image, &order).ok_or(LupinError::JpegNoHiddenData)?;
+
+        let mut walker = F5Walker {
+            image: &mut image,
+            order: &order,
+            cursor,
+        };
+        if (1..=Self::F5_MAX_K).contains(&k) {
+            let words = (len * 8 + k as usize - 1) / k as usize;
+            for _ in 0..words {
+                if !walker.embed_word(k, 0) {
+                    break;
+                }
+            }
+        }
+        walker.cursor = 0;
+        for _ in 0..Self::STEALTH_HEADER_LEN * 8 {
+            if !walker.embed_word(1, 0) {
+                break;
+            }
+        }
+
+        debug!("JPEG: Cleared {} byte stealth payload (k = {})", len, k);
+
+        Ok(self.encode_coefficients(source_data, &image))
+    }
+
+    /// Recovers a payload hidden in the AC coefficients by [`Self::embed_stealth`]
+    fn extract_stealth(&self, source_data: &[u8]) -> Result<Vec<u8>> {
+        // A file we can't decode can't carry a stealth payload
+        let mut image = self
+            .decode_coefficients(source_data)
+            .map_err(|_| LupinError::JpegNoHiddenData)?;
+        let order = Self::f5_order(image.ac_positions());
+
+        let header =
+            Self::read_stealth_header(&mut image, &order).ok_or(LupinError::JpegNoHiddenData)?;
+        let F5Header { k, len, cursor, .. } = header;
+        if !(1..=Self::F5_MAX_K).contains(&k) {
+            return Err(LupinError::JpegCorruptedData);
+        }
+
+        // Every k-bit word consumes at least 2^k - 1 coefficient positions
+        let words = (len * 8 + k as usize - 1) / k as usize;
+        if words.saturating_mul((1 << k) - 1) > image.ac_positions() {
+            return Err(LupinError::JpegCorruptedData);
+        }
+
+        debug!(
+            "JPEG: Found {} byte stealth payload in DCT coefficients (k = {})",
+            len, k
+        );
+
+        let mut walker = F5Walker {
+            image: &mut image,
+            order: &order,
+            cursor,
+        };
+        Self::read_words(&mut walker, k, len)
+            .map(|payload| header.unmask(payload))
+            .ok_or(LupinError::JpegCorruptedData)
+    }
 }
 
 impl Default for JpegEngine {
@@ -246,19 +1431,26 @@ impl SteganographyEngine for JpegEngine {
         ".jpg"
     }
 
+    fn capacity(&self, source_data: &[u8], mode: EmbedMode) -> Result<Capacity> {
+        match mode {
+            // Payloads are split across as many APP13 segments as needed
+            EmbedMode::Capacity => {
+                self.find_insert_position(source_data)?;
+                Ok(Capacity::Unlimited)
+            }
+            EmbedMode::Stealth => {
+                let image = self.decode_coefficients(source_data)?;
+                Ok(Capacity::Approximate(Self::stealth_capacity(&image)))
+            }
+        }
+    }
+
     fn embed(&self, source_data: &[u8], payload: &[u8], mode: EmbedMode) -> Result<Vec<u8>> {
         // Reject empty payloads so the embed contract is uniform across engines.
         if payload.is_empty() {
             return Err(LupinError::EmptyPayload);
         }
 
-        // Exhaustive so a future EmbedMode variant is a compile error here rather than
-        // silently falling through to the capacity implementation below.
-        match mode {
-            EmbedMode::Capacity => {}
-            EmbedMode::Stealth => return Err(LupinError::StealthNotSupported { format: "JPEG" }),
-        }
-
         // Check if there's already a Lupin APP13 segment
         if let Some(&(start, end)) = self.find_lupin_segments(source_data).first() {
             debug!(
@@ -273,6 +1465,24 @@ impl SteganographyEngine for JpegEngine {
             });
         }
 
+        // Exhaustive so a future EmbedMode variant is a compile error here rather than
+        // silently falling through to the capacity implementation below.
+        match mode {
+            EmbedMode::Capacity => {}
+            EmbedMode::Stealth => return self.embed_stealth(source_data, payload),
+        }
+
+        // A stealth payload would be shadowed by the APP13 segment on extract, so
+        // treat it as a collision too.
+        if self.has_stealth_payload(source_data) {
+            return Err(LupinError::EmbedCollision {
+                source: std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    "JPEG coefficients already carry a Lupin payload",
+                ),
+            });
+        }
+
         // Find where to insert our APP13 segment(s) (after the leading APPn segments)
         let insert_pos = self.find_insert_position(source_data)?;
 
@@ -316,11 +1526,55 @@ impl SteganographyEngine for JpegEngine {
         Ok(result)
     }
 
+    fn probe(&self, source_data: &[u8]) -> Result<Option<Probe>> {
+        let chunks = self.find_lupin_segments(source_data);
+        if chunks.is_empty() {
+            return Ok(self.probe_stealth(source_data));
+        }
+
+        // Each chunk follows the marker, length field and signature of its segment
+        let header_len = 4 + Self::LUPIN_SIGNATURE.len();
+        let size = chunks.iter().map(|(start, end)| end - start).sum();
+        let ranges = chunks
+            .iter()
+            .map(|&(start, end)| start - header_len..end)
+            .collect();
+        let prefix = chunks
+            .iter()
+            .flat_map(|&(start, end)| &source_data[start..end])
+            .take(Probe::PREFIX_LEN)
+            .copied()
+            .collect();
+        Ok(Some(Probe::new(EmbedMode::Capacity, size, ranges, prefix)))
+    }
+
+    fn remove(&self, source_data: &[u8]) -> Result<Vec<u8>> {
+        let chunks = self.find_lupin_segments(source_data);
+        if chunks.is_empty() {
+            return self.remove_stealth(source_data);
+        }
+
+        debug!("JPEG: Removing {} Lupin APP13 segment(s)", chunks.len());
+
+        // Each chunk follows the marker, length field and signature of its segment;
+        // foreign APP13 segments are never returned, so they are kept
+        let header_len = 4 + Self::LUPIN_SIGNATURE.len();
+        let mut output = Vec::with_capacity(source_data.len());
+        let mut copied = 0;
+        for (start, end) in chunks {
+            output.extend_from_slice(&source_data[copied..start - header_len]);
+            copied = end;
+        }
+        output.extend_from_slice(&source_data[copied..]);
+        Ok(output)
+    }
+
     fn extract(&self, source_data: &[u8]) -> Result<Vec<u8>> {
-        // Find every Lupin APP13 segment and concatenate their chunks in order.
+        // Find every Lupin APP13 segment and concatenate their chunks in order,
+        // falling back to the DCT coefficients when there are none (stealth mode).
         let chunks = self.find_lupin_segments(source_data);
         if chunks.is_empty() {
-            return Err(LupinError::JpegNoHiddenData);
+            return self.extract_stealth(source_data);
         }
 
         debug!("JPEG: Found {} Lupin APP13 segment(s)", chunks.len());
@@ -601,15 +1855,363 @@ mod tests {
         assert_eq!(&embedded[2..4], &[0xFF, 0xE0]); // APP0 (JFIF) unchanged
     }
 
+    /// Builds a decodable baseline JPEG with pseudo-random quantized coefficients.
+    ///
+    /// With `color` it is a 4:2:0 YCbCr image (interleaved MCUs of six blocks),
+    /// otherwise a single-component greyscale image (non-interleaved scan). The
+    /// scan is written by the engine's own encoder, which generates optimal
+    /// Huffman tables for it.
+    fn create_baseline_jpeg(
+        width: u16,
+        height: u16,
+        color: bool,
+        restart_interval: u16,
+    ) -> Vec<u8> {
+        let mut jpeg = MINIMAL_JPEG[..2 + 18 + 69].to_vec(); // SOI, APP0 (JFIF), DQT
+
+        let components: &[(u8, u8, u8)] = if color {
+            &[(1, 0x22, 0x00), (2, 0x11, 0x11), (3, 0x11, 0x11)]
+        } else {
+            &[(1, 0x11, 0x00)]
+        };
+
+        jpeg.extend_from_slice(&[0xFF, 0xC0]); // SOF0
+        jpeg.extend_from_slice(&(8 + 3 * components.len() as u16).to_be_bytes());
+        jpeg.push(8);
+        jpeg.extend_from_slice(&height.to_be_bytes());
+        jpeg.extend_from_slice(&width.to_be_bytes());
+        jpeg.push(components.len() as u8);
+        for &(id, sampling, _) in components {
+            jpeg.extend_from_slice(&[id, sampling, 0]);
+        }
+
+        if restart_interval > 0 {
+            jpeg.extend_from_slice(&[0xFF, 0xDD, 0x00, 0x04]); // DRI
+            jpeg.extend_from_slice(&restart_interval.to_be_bytes());
+        }
+
+        let sos_start = jpeg.len();
+        jpeg.extend_from_slice(&[0xFF, 0xDA]); // SOS
+        jpeg.extend_from_slice(&(6 + 2 * components.len() as u16).to_be_bytes());
+        jpeg.push(components.len() as u8);
+        for &(id, _, tables) in components {
+            jpeg.extend_from_slice(&[id, tables]);
+        }
+        jpeg.extend_from_slice(&[0x00, 0x3F, 0x00]);
+        let scan_start = jpeg.len();
+        jpeg.extend_from_slice(&[0xFF, 0xD9]); // EOI
+
+        let (mcu_layout, scan_components, block_count) = if color {
+            let mcus = (width as usize + 15) / 16;
+            let rows = (height as usize + 15) / 16;
+            let luma = ScanComponent {
+                dc_table: 0,
+                ac_table: 0,
+            };
+            let chroma = ScanComponent {
+                dc_table: 1,
+                ac_table: 1,
+            };
+            (
+                vec![0, 0, 0, 0, 1, 2],
+                vec![luma, chroma, chroma],
+                mcus * rows * 6,
+            )
+        } else {
+            let cols = (width as usize + 7) / 8;
+            let rows = (height as usize + 7) / 8;
+            let luma = ScanComponent {
+                dc_table: 0,
+                ac_table: 0,
+            };
+            (vec![0], vec![luma], cols * rows)
+        };
+
+        // Deterministic LCG so the fixture is stable; low-frequency coefficients
+        // are usually non-zero and mostly small, like a real photo.
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        let mut next = move || {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 33) as u32
+        };
+        let blocks = (0..block_count)
+            .map(|_| {
+                let mut block = [0i16; 64];
+                block[0] = (next() % 41) as i16 - 20;
+                for (k, coefficient) in block.iter_mut().enumerate().skip(1) {
+                    if next() % 64 < (64 - k as u32) / 2 {
+                        let magnitude = 1 + (next() % 6) as i16;
+                        *coefficient = if next() % 2 == 0 {
+                            magnitude
+                        } else {
+                            -magnitude
+                        };
+                    }
+                }
+                block
+            })
+            .collect();
+
+        let image = CoefficientImage {
+            blocks,
+            mcu_layout,
+            scan_components,
+            dc_tables: Default::default(),
+            ac_tables: Default::default(),
+            restart_interval: restart_interval as usize,
+            dht_segments: Vec::new(),
+            sos_start,
+            scan_start,
+            scan_end: scan_start,
+        };
+        JpegEngine::new().encode_coefficients(&jpeg, &image)
+    }
+
     #[test]
-    fn test_stealth_mode_not_supported() {
+    fn test_coefficient_round_trip_is_lossless() {
         let engine = JpegEngine::new();
+        let jpeg = create_baseline_jpeg(64, 48, true, 3);
 
-        let result = engine.embed(MINIMAL_JPEG, b"payload", EmbedMode::Stealth);
+        // Decoding and re-encoding an untouched image reuses its (optimal) tables
+        // and must reproduce the file byte for byte.
+        let image = engine.decode_coefficients(&jpeg).unwrap();
+        assert_eq!(image.blocks.len(), 4 * 3 * 6);
+        assert_eq!(engine.encode_coefficients(&jpeg, &image), jpeg);
+    }
+
+    #[test]
+    fn test_stealth_round_trip() {
+        let engine = JpegEngine::new();
+        let jpeg = create_baseline_jpeg(128, 96, true, 5);
+        let payload = b"Hidden in the quantized DCT coefficients";
+
+        let embedded = engine.embed(&jpeg, payload, EmbedMode::Stealth).unwrap();
+
+        // No APP13 segment or signature is added...
+        assert!(engine.find_lupin_segments(&embedded).is_empty());
+        assert!(!embedded.windows(5).any(|w| w == b"Lupin"));
+        // ...yet extract finds the payload on its own.
+        assert_eq!(engine.extract(&embedded).unwrap(), payload);
+    }
+
+    #[test]
+    fn test_stealth_stream_is_whitened() {
+        let engine = JpegEngine::new();
+        let jpeg = create_baseline_jpeg(128, 96, true, 5);
+        let payload = b"Same payload twice";
+
+        let first = engine.embed(&jpeg, payload, EmbedMode::Stealth).unwrap();
+        let second = engine.embed(&jpeg, payload, EmbedMode::Stealth).unwrap();
+
+        // A fresh nonce whitens the same payload differently each time
+        assert_ne!(first, second);
+        assert_eq!(engine.extract(&first).unwrap(), payload);
+        assert_eq!(engine.extract(&second).unwrap(), payload);
+    }
+
+    #[test]
+    fn test_stealth_greyscale_with_partial_blocks() {
+        let engine = JpegEngine::new();
+        // Dimensions that aren't multiples of 8 exercise the edge-block count
+        let jpeg = create_baseline_jpeg(61, 45, false, 0);
+        let payload: Vec<u8> = (0..64).collect();
+
+        let embedded = engine.embed(&jpeg, &payload, EmbedMode::Stealth).unwrap();
+
+        assert_eq!(engine.extract(&embedded).unwrap(), payload);
+    }
+
+    #[test]
+    fn test_stealth_only_shrinks_ac_coefficients() {
+        let engine = JpegEngine::new();
+        let jpeg = create_baseline_jpeg(128, 96, true, 0);
+        let payload: Vec<u8> = (0..=255).cycle().take(300).collect();
+
+        let embedded = engine.embed(&jpeg, &payload, EmbedMode::Stealth).unwrap();
+
+        let before = engine.decode_coefficients(&jpeg).unwrap();
+        let after = engine.decode_coefficients(&embedded).unwrap();
+        let mut changed = 0;
+        for (a, b) in before.blocks.iter().zip(&after.blocks) {
+            assert_eq!(a[0], b[0], "DC coefficients must never change");
+            for (&x, &y) in a[1..].iter().zip(&b[1..]) {
+                if x != y {
+                    // F5 only ever moves a coefficient one step towards zero
+                    assert_eq!(y, x - x.signum());
+                    changed += 1;
+                }
+            }
+        }
+        assert!(changed > 0);
+        assert_eq!(engine.extract(&embedded).unwrap(), payload);
+    }
+
+    #[test]
+    fn test_stealth_payload_too_large() {
+        let engine = JpegEngine::new();
+        let jpeg = create_baseline_jpeg(16, 16, false, 0);
+        let payload = vec![0xA5u8; 4096];
+
+        let result = engine.embed(&jpeg, &payload, EmbedMode::Stealth);
 
         assert!(matches!(
             result,
-            Err(LupinError::StealthNotSupported { format: "JPEG" })
+            Err(LupinError::PayloadTooLarge {
+                requested: 4096,
+                ..
+            })
         ));
     }
+
+    #[test]
+    fn test_probe() {
+        // Arrange
+        let engine = JpegEngine::new();
+        let jpeg = create_baseline_jpeg(32, 32, false, 0);
+        let payload = vec![0x42u8; 70_000]; // Spans two APP13 segments
+        let segments = engine.embed(&jpeg, &payload, EmbedMode::Capacity).unwrap();
+        let coefficients = engine
+            .embed(&jpeg, b"coefficients", EmbedMode::Stealth)
+            .unwrap();
+
+        // Act
+        let clean = engine.probe(&jpeg).unwrap();
+        let capacity = engine.probe(&segments).unwrap().unwrap();
+        let stealth = engine.probe(&coefficients).unwrap().unwrap();
+
+        // Assert
+        assert_eq!(clean, None);
+        assert_eq!(capacity.mode, EmbedMode::Capacity);
+        assert_eq!(capacity.size, payload.len());
+        assert_eq!(capacity.ranges.len(), 2);
+        assert_eq!(&segments[capacity.ranges[0].start..][..2], &[0xFF, 0xED]);
+        assert_eq!(capacity.ranges[0].end, capacity.ranges[1].start);
+        assert_eq!(capacity.prefix, vec![0x42; Probe::PREFIX_LEN]);
+        assert_eq!(stealth.mode, EmbedMode::Stealth);
+        assert_eq!(stealth.size, 12);
+        assert_eq!(stealth.prefix, b"coefficients");
+    }
+
+    #[test]
+    fn test_remove() {
+        // Arrange
+        let engine = JpegEngine::new();
+        let foreign = jpeg_with_foreign_app13();
+        let payload = vec![0x42u8; 70_000]; // Spans two APP13 segments
+        let segments = engine
+            .embed(&foreign, &payload, EmbedMode::Capacity)
+            .unwrap();
+        let jpeg = create_baseline_jpeg(32, 32, false, 0);
+        let coefficients = engine
+            .embed(&jpeg, b"coefficients", EmbedMode::Stealth)
+            .unwrap();
+
+        // Act
+        let restored = engine.remove(&segments).unwrap();
+        let cleared = engine.remove(&coefficients).unwrap();
+        let nothing = engine.remove(&jpeg);
+
+        // Assert - the foreign APP13 segment survives
+        assert_eq!(restored, foreign);
+        assert_eq!(engine.probe(&cleared).unwrap(), None);
+        let again = engine
+            .embed(&cleared, b"again", EmbedMode::Stealth)
+            .unwrap();
+        assert_eq!(engine.extract(&again).unwrap(), b"again");
+        assert!(matches!(nothing, Err(LupinError::JpegNoHiddenData)));
+    }
+
+    #[test]
+    fn test_capacity() {
+        // Arrange
+        let engine = JpegEngine::new();
+        let jpeg = create_baseline_jpeg(32, 32, false, 0);
+
+        // Act
+        let capacity = engine.capacity(&jpeg, EmbedMode::Capacity).unwrap();
+        let stealth = engine.capacity(&jpeg, EmbedMode::Stealth).unwrap();
+
+        // Assert - a payload well inside the estimate fits
+        assert_eq!(capacity, Capacity::Unlimited);
+        assert!(matches!(stealth, Capacity::Approximate(_)));
+        let bytes = stealth.bytes().unwrap();
+        assert!(bytes > 4);
+        assert!(engine
+            .embed(&jpeg, &vec![0x3C; bytes / 2], EmbedMode::Stealth)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_stealth_rejects_progressive_jpeg() {
+        let engine = JpegEngine::new();
+        let mut jpeg = create_baseline_jpeg(32, 32, false, 0);
+        let sof = jpeg.windows(2).position(|w| w == [0xFF, 0xC0]).unwrap();
+        jpeg[sof + 1] = 0xC2; // SOF2: progressive DCT
+
+        let result = engine.embed(&jpeg, b"payload", EmbedMode::Stealth);
+
+        assert!(matches!(
+            result,
+            Err(LupinError::StealthUnsupportedCarrier { format: "JPEG", .. })
+        ));
+    }
+
+    #[test]
+    fn test_stealth_requires_huffman_tables() {
+        let engine = JpegEngine::new();
+
+        // MINIMAL_JPEG has no DHT segment, so its scan can't be decoded
+        let result = engine.embed(MINIMAL_JPEG, b"payload", EmbedMode::Stealth);
+
+        assert!(matches!(result, Err(LupinError::JpegInvalidFormat { .. })));
+    }
+
+    #[test]
+    fn test_stealth_collisions() {
+        let engine = JpegEngine::new();
+        let jpeg = create_baseline_jpeg(128, 96, true, 0);
+        let stealthy = engine.embed(&jpeg, b"first", EmbedMode::Stealth).unwrap();
+        let segmented = engine.embed(&jpeg, b"first", EmbedMode::Capacity).unwrap();
+
+        for (carrier, mode) in [
+            (&stealthy, EmbedMode::Stealth),
+            (&stealthy, EmbedMode::Capacity),
+            (&segmented, EmbedMode::Stealth),
+        ] {
+            assert!(matches!(
+                engine.embed(carrier, b"second", mode),
+                Err(LupinError::EmbedCollision { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_extract_clean_baseline_jpeg() {
+        let engine = JpegEngine::new();
+        let jpeg = create_baseline_jpeg(64, 64, true, 0);
+
+        assert!(matches!(
+            engine.extract(&jpeg),
+            Err(LupinError::JpegNoHiddenData)
+        ));
+    }
+
+    #[test]
+    fn test_huffman_tables_from_frequencies() {
+        let mut frequencies = [0u32; 256];
+        for (symbol, frequency) in frequencies.iter_mut().enumerate() {
+            *frequency = (symbol as u32 % 17) * 1000 + 1;
+        }
+
+        let table = HuffmanTable::from_frequencies(&frequencies);
+
+        // Every symbol is codable within JPEG's 16-bit limit and no code is all ones
+        assert!(table.can_encode(&frequencies));
+        for &(code, length) in table.codes.iter() {
+            assert!((1..=16).contains(&length));
+            assert_ne!(code as u32, (1 << length) - 1);
+        }
+    }
 }
@@ -13,12 +13,92 @@
 // limitations under the License.
 
 //! Steganography engines for different file formats
+//!
+//! # Stealth Mode Threat Model
+//!
+//! Stealth mode writes a bit stream into the carrier's own data (pixel LSBs, DCT
+//! coefficients, number spellings, whitespace) instead of adding a block. The stream
+//! starts with a random nonce, and the header and payload behind it are whitened with
+//! a keystream derived from that nonce, so there is no fixed signature for a scanner
+//! to match and no two carriers look alike.
+//!
+//! The whitening needs no key, so it only stands up to signature scans and casual
+//! inspection. Anyone who knows the scheme can unwhiten the stream, and the carrier
+//! bits are always visited in the same order, so the statistical traces of the
+//! changes stay where they are. Encrypt the payload (a password or recipients) to keep
+//! its contents confidential.
 
+pub mod bmp;
+pub mod gif;
 pub mod jpeg;
+pub mod ooxml;
 pub mod pdf;
 pub mod png;
+pub(crate) mod stealth;
+pub mod svg;
+pub mod text;
+pub mod tiff;
+pub mod wav;
+pub mod webp;
+pub mod zip;
 
 // Re-export engines for convenience
+pub use bmp::BmpEngine;
+pub use gif::GifEngine;
 pub use jpeg::JpegEngine;
+pub use ooxml::OoxmlEngine;
 pub use pdf::PdfEngine;
 pub use png::PngEngine;
+pub use svg::SvgEngine;
+pub use text::TextEngine;
+pub use tiff::TiffEngine;
+pub use wav::WavEngine;
+pub use webp::WebpEngine;
+pub use zip::ZipEngine;
+
+use crate::Probe;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+
+/// Returns the decoded size and first [`Probe::PREFIX_LEN`] bytes of a base64
+/// payload, decoding only as much of it as the prefix needs
+pub(crate) fn probe_base64(encoded: &[u8]) -> (usize, Vec<u8>) {
+    let padding = encoded.iter().rev().take_while(|&&b| b == b'=').count();
+    let size = (encoded.len() / 4 * 3).saturating_sub(padding.min(2));
+
+    let head = encoded.len().min((Probe::PREFIX_LEN + 2) / 3 * 4);
+    let prefix = STANDARD
+        .decode(&encoded[..head - head % 4])
+        .unwrap_or_default();
+    (size, prefix)
+}
+
+/// Decodes a damaged base64 payload as best it can, for error correction to repair
+///
+/// Characters that aren't base64 count as zero bits, rather than being skipped, so
+/// damage only garbles the bytes it touches; a cut-off tail is dropped. Returns
+/// `None` unless the result carries error correction (see [`crate::fec`]): without
+/// it, damaged data is better reported than returned.
+pub(crate) fn recover_base64(encoded: &[u8]) -> Option<Vec<u8>> {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let end = encoded
+        .iter()
+        .rposition(|b| !b.is_ascii_whitespace() && *b != b'=')
+        .map_or(0, |last| last + 1);
+    let sextets: Vec<u32> = encoded[..end]
+        .iter()
+        .map(|b| ALPHABET.iter().position(|a| a == b).unwrap_or(0) as u32)
+        .collect();
+    let mut decoded = Vec::with_capacity(sextets.len() / 4 * 3 + 2);
+    for group in sextets.chunks(4) {
+        let bits = group
+            .iter()
+            .chain(std::iter::repeat(&0))
+            .take(4)
+            .fold(0, |acc, &sextet| acc << 6 | sextet);
+        let bytes = (group.len() * 6 / 8).min(3);
+        decoded.extend_from_slice(&bits.to_be_bytes()[1..1 + bytes]);
+    }
+
+    crate::fec::is_fec(&decoded).then_some(decoded)
+}
@@ -0,0 +1,548 @@
+// Copyright 2025 Niclas Hedam
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Office Open XML (DOCX/XLSX/PPTX) steganography engine using a hidden part
+//!
+//! # How It Works
+//!
+//! An Office document is a ZIP archive of XML parts tied together by relationships.
+//! Office only loads the parts the relationships reach, so this engine adds a part
+//! that nothing refers to.
+//!
+//! ## Storage Format
+//!
+//! We add a stored (uncompressed) part called `customXml/lupin.xml`, holding the
+//! payload as base64:
+//!
+//! ```text
+//! <?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+//! <lupin xmlns="urn:lupin:payload">[Base64 Payload]</lupin>
+//! ```
+//!
+//! The part goes after the last file, before the central directory, which gets a
+//! record for it. No existing file moves, so the other records are copied as they
+//! are; only the end of central directory record is rewritten.
+//!
+//! ## Package Rules
+//!
+//! Office reports a document as corrupt if a part has no content type. The `.xml`
+//! extension is mapped to `application/xml` by a `Default` in
+//! `[Content_Types].xml` in every document Office writes, so the engine refuses a
+//! package without one rather than rewriting the content types.
+//!
+//! ## Detection
+//!
+//! Every ZIP starts with `PK\x03\x04`, so the engine overrides
+//! [`SteganographyEngine::matches`] to also require a `[Content_Types].xml` file,
+//! which every Office Open XML package has.
+//!
+//! ## Stealth Mode
+//!
+//! The parts are compressed XML, with no slack the engine can hide bits in without
+//! changing the document, so [`EmbedMode::Stealth`] returns
+//! [`LupinError::StealthNotSupported`].
+
+use super::zip::{self, Archive, Entry};
+use crate::error::{LupinError, Result};
+use crate::{Capacity, EmbedMode, Probe, SteganographyEngine};
+use base64::{engine::general_purpose, Engine as _};
+use flate2::read::DeflateDecoder;
+use log::debug;
+use std::io::Read;
+
+/// Office Open XML steganography engine
+///
+/// Stores the payload as base64 in a `customXml/lupin.xml` part that no relationship
+/// refers to, so Word, Excel and PowerPoint open the document unchanged.
+///
+/// See the module documentation for details on how data is stored and limitations.
+pub struct OoxmlEngine;
+
+impl OoxmlEngine {
+    /// Creates a new Office Open XML engine
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Name of the part every package has, mapping parts to content types
+    const CONTENT_TYPES: &'static [u8] = b"[Content_Types].xml";
+
+    /// Name of our part
+    const PART_NAME: &'static [u8] = b"customXml/lupin.xml";
+
+    /// Our part up to the payload
+    const PART_HEAD: &'static [u8] = b"<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\r\n<lupin xmlns=\"urn:lupin:payload\">";
+
+    /// Our part after the payload
+    const PART_TAIL: &'static [u8] = b"</lupin>";
+
+    /// Content types are small; anything bigger than this isn't a real package
+    const CONTENT_TYPES_LIMIT: u64 = 1 << 20;
+
+    /// Reads the central directory, failing unless it lists `[Content_Types].xml`
+    fn parse_package(data: &[u8]) -> Result<Archive> {
+        let archive = Archive::parse(data).map_err(|reason| LupinError::OoxmlInvalidFormat {
+            reason: reason.to_string(),
+        })?;
+        if archive.find(Self::CONTENT_TYPES).is_none() {
+            return Err(LupinError::OoxmlInvalidFormat {
+                reason: "No [Content_Types].xml part".to_string(),
+            });
+        }
+        Ok(archive)
+    }
+
+    /// Whether `[Content_Types].xml` maps the `.xml` extension to a content type
+    fn has_xml_default(data: &[u8], entry: &Entry) -> Result<bool> {
+        let compressed = &data[entry.data.clone()];
+        let mut content_types = Vec::new();
+        match entry.method {
+            0 => content_types.extend_from_slice(compressed),
+            8 => {
+                DeflateDecoder::new(compressed)
+                    .take(Self::CONTENT_TYPES_LIMIT)
+                    .read_to_end(&mut content_types)
+                    .map_err(|_| LupinError::OoxmlInvalidFormat {
+                        reason: "[Content_Types].xml can't be decompressed".to_string(),
+                    })?;
+            }
+            _ => {
+                return Err(LupinError::OoxmlInvalidFormat {
+                    reason: "[Content_Types].xml uses an unknown compression".to_string(),
+                })
+            }
+        }
+
+        // Extensions compare case-insensitively, and either quote may be used
+        content_types.make_ascii_lowercase();
+        Ok([&b"extension=\"xml\""[..], b"extension='xml'"]
+            .iter()
+            .any(|needle| content_types.windows(needle.len()).any(|w| w == *needle)))
+    }
+
+    /// The base64 payload inside our part, without the XML around it, or `None` if
+    /// the part isn't ours. A part cut short keeps whatever is left.
+    fn encoded_payload<'a>(data: &'a [u8], entry: &Entry) -> Option<&'a [u8]> {
+        let part = data[entry.data.clone()].strip_prefix(Self::PART_HEAD)?;
+        Some(part.strip_suffix(Self::PART_TAIL).unwrap_or(part))
+    }
+}
+
+impl Default for OoxmlEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SteganographyEngine for OoxmlEngine {
+    fn magic_bytes(&self) -> &[u8] {
+        zip::LOCAL_SIGNATURE
+    }
+
+    fn matches(&self, data: &[u8]) -> bool {
+        // Every ZIP shares the magic; only a package lists its content types
+        data.starts_with(self.magic_bytes()) && Self::parse_package(data).is_ok()
+    }
+
+    fn format_name(&self) -> &str {
+        "OOXML"
+    }
+
+    fn format_ext(&self) -> &str {
+        ".docx"
+    }
+
+    fn capacity(&self, source_data: &[u8], mode: EmbedMode) -> Result<Capacity> {
+        match mode {
+            // Only the 32-bit offsets of a ZIP without Zip64 limit it
+            EmbedMode::Capacity => {
+                Self::parse_package(source_data)?;
+                Ok(Capacity::Unlimited)
+            }
+            EmbedMode::Stealth => Err(LupinError::StealthNotSupported { format: "OOXML" }),
+        }
+    }
+
+    fn embed(&self, source_data: &[u8], payload: &[u8], mode: EmbedMode) -> Result<Vec<u8>> {
+        // Reject empty payloads so the embed contract is uniform across engines.
+        if payload.is_empty() {
+            return Err(LupinError::EmptyPayload);
+        }
+
+        // Refuse to embed into a package that already carries a Lupin part; a second
+        // one with the same name would make the archive ambiguous.
+        let archive = Self::parse_package(source_data)?;
+        if archive.find(Self::PART_NAME).is_some() {
+            return Err(LupinError::EmbedCollision {
+                source: std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    "Document already contains a Lupin part",
+                ),
+            });
+        }
+
+        // Exhaustive so a future EmbedMode variant is a compile error here rather than
+        // silently falling through to the capacity implementation below.
+        match mode {
+            EmbedMode::Capacity => {}
+            EmbedMode::Stealth => return Err(LupinError::StealthNotSupported { format: "OOXML" }),
+        }
+
+        // Office rejects a part without a content type
+        let content_types = archive
+            .find(Self::CONTENT_TYPES)
+            .expect("checked by parse_package");
+        if !Self::has_xml_default(source_data, content_types)? {
+            return Err(LupinError::OoxmlInvalidFormat {
+                reason: "[Content_Types].xml has no default content type for .xml parts"
+                    .to_string(),
+            });
+        }
+
+        let mut part = Self::PART_HEAD.to_vec();
+        part.extend_from_slice(general_purpose::STANDARD.encode(payload).as_bytes());
+        part.extend_from_slice(Self::PART_TAIL);
+
+        debug!(
+            "OOXML: Adding {} byte part at position {}",
+            part.len(),
+            archive.central.start
+        );
+
+        let (local, record) = zip::stored_entry(Self::PART_NAME, &part, archive.central.start);
+        archive
+            .insert(source_data, &local, Some(&record))
+            .map_err(|reason| LupinError::OoxmlInvalidFormat {
+                reason: reason.to_string(),
+            })
+    }
+
+    fn probe(&self, source_data: &[u8]) -> Result<Option<Probe>> {
+        let archive = Self::parse_package(source_data)?;
+        let Some(entry) = archive.find(Self::PART_NAME) else {
+            return Ok(None);
+        };
+
+        let encoded = Self::encoded_payload(source_data, entry).unwrap_or_default();
+        let (size, prefix) = super::probe_base64(encoded);
+        Ok(Some(Probe::new(
+            EmbedMode::Capacity,
+            size,
+            vec![entry.local.clone(), entry.record.clone()],
+            prefix,
+        )))
+    }
+
+    fn remove(&self, source_data: &[u8]) -> Result<Vec<u8>> {
+        let archive = Self::parse_package(source_data)?;
+        let entry = archive
+            .find(Self::PART_NAME)
+            .ok_or(LupinError::OoxmlNoHiddenData)?;
+
+        // A part overlapping the central directory can't be cut out cleanly
+        if entry.local.end > archive.central.start {
+            return Err(LupinError::OoxmlCorruptedData);
+        }
+
+        debug!(
+            "OOXML: Removing Lupin part at {}-{}",
+            entry.local.start, entry.local.end
+        );
+
+        Ok(archive.remove(source_data, entry.local.clone(), Some(entry.record.clone())))
+    }
+
+    fn extract(&self, source_data: &[u8]) -> Result<Vec<u8>> {
+        let archive = Self::parse_package(source_data)?;
+        let entry = archive
+            .find(Self::PART_NAME)
+            .ok_or(LupinError::OoxmlNoHiddenData)?;
+
+        debug!(
+            "OOXML: Found Lupin part at {}-{}",
+            entry.data.start, entry.data.end
+        );
+
+        // Damaged base64 is only returned if error correction can repair it
+        let encoded =
+            Self::encoded_payload(source_data, entry).ok_or(LupinError::OoxmlCorruptedData)?;
+        general_purpose::STANDARD
+            .decode(encoded)
+            .or_else(|_| super::recover_base64(encoded).ok_or(LupinError::OoxmlCorruptedData))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT_TYPES: &[u8] = br#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/word/document.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml"/></Types>"#;
+
+    /// A minimal DOCX with deflated parts, as Office writes them
+    fn create_docx() -> Vec<u8> {
+        zip::create_archive(
+            &[
+                ("[Content_Types].xml", CONTENT_TYPES),
+                ("_rels/.rels", b"<Relationships/>"),
+                ("word/document.xml", b"<w:document/>"),
+            ],
+            true,
+            b"",
+        )
+    }
+
+    fn part_names(data: &[u8]) -> Vec<String> {
+        Archive::parse(data)
+            .unwrap()
+            .entries
+            .iter()
+            .map(|entry| String::from_utf8_lossy(&entry.name).into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_ooxml_magic_bytes() {
+        let engine = OoxmlEngine::new();
+        assert_eq!(engine.magic_bytes(), b"PK\x03\x04");
+        assert!(engine.matches(&create_docx()));
+
+        // A ZIP that isn't a package
+        let zip = zip::create_archive(&[("readme.txt", b"hello")], false, b"");
+        assert!(!engine.matches(&zip));
+    }
+
+    #[test]
+    fn test_ooxml_format_info() {
+        let engine = OoxmlEngine::new();
+        assert_eq!(engine.format_name(), "OOXML");
+        assert_eq!(engine.format_ext(), ".docx");
+    }
+
+    #[test]
+    fn test_embed_and_extract() {
+        // Arrange
+        let engine = OoxmlEngine::new();
+        let docx = create_docx();
+        let central = Archive::parse(&docx).unwrap().central;
+        let payload = b"Secret message hidden in a document!";
+
+        // Act
+        let embedded = engine.embed(&docx, payload, EmbedMode::Capacity).unwrap();
+        let extracted = engine.extract(&embedded).unwrap();
+
+        // Assert - the part is listed last and the existing files didn't move
+        assert_eq!(extracted, payload);
+        assert_eq!(
+            part_names(&embedded),
+            [
+                "[Content_Types].xml",
+                "_rels/.rels",
+                "word/document.xml",
+                "customXml/lupin.xml"
+            ]
+        );
+        assert_eq!(&embedded[..central.start], &docx[..central.start]);
+        assert!(embedded
+            .windows(OoxmlEngine::PART_HEAD.len())
+            .any(|window| window == OoxmlEngine::PART_HEAD));
+    }
+
+    #[test]
+    fn test_remove() {
+        // Arrange
+        let engine = OoxmlEngine::new();
+        let docx = create_docx();
+        let embedded = engine
+            .embed(&docx, b"payload", EmbedMode::Capacity)
+            .unwrap();
+
+        // Act
+        let removed = engine.remove(&embedded).unwrap();
+
+        // Assert
+        assert_eq!(removed, docx);
+        assert!(matches!(
+            engine.remove(&docx),
+            Err(LupinError::OoxmlNoHiddenData)
+        ));
+    }
+
+    #[test]
+    fn test_remove_with_later_part() {
+        // Arrange - another part saved after ours
+        let engine = OoxmlEngine::new();
+        let embedded = engine
+            .embed(&create_docx(), b"payload", EmbedMode::Capacity)
+            .unwrap();
+        let archive = Archive::parse(&embedded).unwrap();
+        let (local, record) =
+            zip::stored_entry(b"docProps/app.xml", b"<Properties/>", archive.central.start);
+        let saved = archive.insert(&embedded, &local, Some(&record)).unwrap();
+
+        // Act
+        let removed = engine.remove(&saved).unwrap();
+
+        // Assert - the later part's offset was fixed up
+        let archive = Archive::parse(&removed).unwrap();
+        let app = archive.find(b"docProps/app.xml").unwrap();
+        assert_eq!(&removed[app.data.clone()], b"<Properties/>");
+        assert!(archive.find(OoxmlEngine::PART_NAME).is_none());
+    }
+
+    #[test]
+    fn test_embed_collision() {
+        let engine = OoxmlEngine::new();
+        let embedded_once = engine
+            .embed(&create_docx(), b"First", EmbedMode::Capacity)
+            .unwrap();
+
+        let result = engine.embed(&embedded_once, b"Second", EmbedMode::Capacity);
+        assert!(matches!(result, Err(LupinError::EmbedCollision { .. })));
+    }
+
+    #[test]
+    fn test_extract_without_data() {
+        let engine = OoxmlEngine::new();
+
+        let result = engine.extract(&create_docx());
+        assert!(matches!(result, Err(LupinError::OoxmlNoHiddenData)));
+    }
+
+    #[test]
+    fn test_no_xml_content_type() {
+        // Arrange - a package that maps every part by Override
+        let engine = OoxmlEngine::new();
+        let docx = zip::create_archive(
+            &[
+                (
+                    "[Content_Types].xml",
+                    b"<Types><Override PartName=\"/a.xml\"/></Types>",
+                ),
+                ("a.xml", b"<a/>"),
+            ],
+            false,
+            b"",
+        );
+
+        // Act
+        let result = engine.embed(&docx, b"payload", EmbedMode::Capacity);
+
+        // Assert
+        assert!(matches!(result, Err(LupinError::OoxmlInvalidFormat { .. })));
+    }
+
+    #[test]
+    fn test_invalid_ooxml() {
+        let engine = OoxmlEngine::new();
+        let docx = create_docx();
+        let zip = zip::create_archive(&[("readme.txt", b"hello")], false, b"");
+
+        // No end record, and an archive without content types
+        for not_ooxml in [&docx[..docx.len() - 22], &zip] {
+            let result = engine.embed(not_ooxml, b"payload", EmbedMode::Capacity);
+            assert!(matches!(result, Err(LupinError::OoxmlInvalidFormat { .. })));
+        }
+    }
+
+    #[test]
+    fn test_damaged_part() {
+        // Arrange
+        let engine = OoxmlEngine::new();
+        let mut embedded = engine
+            .embed(&create_docx(), b"payload", EmbedMode::Capacity)
+            .unwrap();
+        let archive = Archive::parse(&embedded).unwrap();
+        let data = archive.find(OoxmlEngine::PART_NAME).unwrap().data.clone();
+        embedded[data.start + OoxmlEngine::PART_HEAD.len()] = b'!';
+
+        // Act
+        let result = engine.extract(&embedded);
+
+        // Assert - without error correction it is reported rather than returned
+        assert!(matches!(result, Err(LupinError::OoxmlCorruptedData)));
+    }
+
+    #[test]
+    fn test_empty_payload_rejected() {
+        let engine = OoxmlEngine::new();
+        assert!(matches!(
+            engine.embed(&create_docx(), b"", EmbedMode::Capacity),
+            Err(LupinError::EmptyPayload)
+        ));
+    }
+
+    #[test]
+    fn test_stealth_not_supported() {
+        let engine = OoxmlEngine::new();
+        let docx = create_docx();
+
+        assert!(matches!(
+            engine.embed(&docx, b"payload", EmbedMode::Stealth),
+            Err(LupinError::StealthNotSupported { format: "OOXML" })
+        ));
+        assert!(matches!(
+            engine.capacity(&docx, EmbedMode::Stealth),
+            Err(LupinError::StealthNotSupported { format: "OOXML" })
+        ));
+    }
+
+    #[test]
+    fn test_truncated_file_does_not_panic() {
+        let engine = OoxmlEngine::new();
+        let embedded = engine
+            .embed(&create_docx(), &[0x42; 40], EmbedMode::Capacity)
+            .unwrap();
+
+        // Every prefix either parses or fails cleanly
+        for len in 0..embedded.len() {
+            let _ = engine.probe(&embedded[..len]);
+            let _ = engine.remove(&embedded[..len]);
+            let _ = engine.extract(&embedded[..len]);
+        }
+    }
+
+    #[test]
+    fn test_probe() {
+        // Arrange
+        let engine = OoxmlEngine::new();
+        let docx = create_docx();
+        let embedded = engine
+            .embed(&docx, b"part payload for probing", EmbedMode::Capacity)
+            .unwrap();
+
+        // Act
+        let clean = engine.probe(&docx).unwrap();
+        let probe = engine.probe(&embedded).unwrap().unwrap();
+
+        // Assert - the part and its central directory record
+        let entry = Archive::parse(&embedded).unwrap().entries[3].clone();
+        assert_eq!(clean, None);
+        assert_eq!(probe.mode, EmbedMode::Capacity);
+        assert_eq!(probe.size, 24);
+        assert_eq!(probe.prefix, b"part payload for");
+        assert_eq!(probe.ranges, vec![entry.local, entry.record]);
+    }
+
+    #[test]
+    fn test_capacity() {
+        let engine = OoxmlEngine::new();
+        assert_eq!(
+            engine
+                .capacity(&create_docx(), EmbedMode::Capacity)
+                .unwrap(),
+            Capacity::Unlimited
+        );
+    }
+}
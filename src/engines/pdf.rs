@@ -12,30 +12,355 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use super::stealth;
 use crate::{
     error::{LupinError, Result},
-    EmbedMode, SteganographyEngine,
+    Capacity, EmbedMode, Probe, SteganographyEngine,
 };
 use base64::{engine::general_purpose, Engine as _};
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
 use log::debug;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::io::{Read, Write};
 
 /// PDF steganography engine
 ///
 /// PDFs end with %%EOF, but viewers ignore anything after that.
 /// We append a base64-encoded payload after the EOF marker (capacity mode).
 ///
-/// Only [`EmbedMode::Capacity`] is implemented. Capacity mode is unlimited in size but
-/// trivially spotted by a `strings`/hex-dump pass. A genuine stealth mode for PDF would
-/// need to hide data inside the rendered content (e.g. LSBs of content-stream operands),
-/// which requires decompressing streams and a content parser; until that lands,
-/// [`EmbedMode::Stealth`] returns [`LupinError::StealthNotSupported`].
+/// Capacity mode is unlimited in size but trivially spotted by a `strings`/hex-dump
+/// pass, so [`EmbedMode::Stealth`] hides the payload inside the page content instead:
+///
+/// 1. The cross-reference table and trailer are parsed (following `/Prev` through
+///    incremental updates) to locate every live object.
+/// 2. The content streams of every page are inflated (`FlateDecode` or unfiltered).
+/// 3. Each numeric operand of a `Td`, `TD` or `re` operator and each kerning number
+///    inside a `TJ` array is a carrier for one bit: written in its shortest form
+///    (`72`, `12.5`) it carries a 0, written with one extra trailing zero
+///    (`72.0`, `12.50`) it carries a 1. The numeric value never changes, so the
+///    rendered page is identical.
+/// 4. The touched streams are re-deflated and the file is written out again with its
+///    live objects, a fresh cross-reference table and trailer.
+///
+/// The bit stream uses the same kind of header as the image engines, whitened after
+/// the random nonce (see the [`crate::engines`] documentation):
+///
+/// ```text
+/// [8 bytes: Nonce][4 bytes: "LpPd"][4 bytes: Big-endian Payload Length][N bytes: Raw Payload]
+/// ```
+///
+/// Carriers are visited in object-number order of the content streams. Capacity is
+/// one bit per carrier operand, which makes text-heavy documents far roomier than
+/// image-only ones. Files using cross-reference streams (PDF 1.5 object streams) or
+/// encryption are rejected with [`LupinError::StealthUnsupportedCarrier`].
 pub struct PdfEngine;
 
+/// Kinds of lexical tokens in PDF syntax (ISO 32000-1, 7.2)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenKind {
+    Number,
+    Name,
+    String,
+    HexString,
+    ArrayStart,
+    ArrayEnd,
+    DictStart,
+    DictEnd,
+    /// Any other run of regular characters: `obj`, `R`, `true`, content operators, ...
+    Keyword,
+}
+
+/// A token and the byte range it occupies in the buffer being lexed
+#[derive(Debug, Clone, Copy)]
+struct Token {
+    kind: TokenKind,
+    start: usize,
+    end: usize,
+}
+
+/// A minimal PDF lexer, shared by the object parser and the content stream scanner
+#[derive(Clone)]
+struct Lexer<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        Self { data, pos }
+    }
+
+    fn is_whitespace(byte: u8) -> bool {
+        matches!(byte, b'\0' | b'\t' | b'\n' | b'\x0C' | b'\r' | b' ')
+    }
+
+    fn is_delimiter(byte: u8) -> bool {
+        matches!(
+            byte,
+            b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%'
+        )
+    }
+
+    /// Skips whitespace and comments
+    fn skip_whitespace(&mut self) {
+        while let Some(&byte) = self.data.get(self.pos) {
+            if Self::is_whitespace(byte) {
+                self.pos += 1;
+            } else if byte == b'%' {
+                while self
+                    .data
+                    .get(self.pos)
+                    .is_some_and(|&b| b != b'\n' && b != b'\r')
+                {
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn skip_regular(&mut self) {
+        while self
+            .data
+            .get(self.pos)
+            .is_some_and(|&b| !Self::is_whitespace(b) && !Self::is_delimiter(b))
+        {
+            self.pos += 1;
+        }
+    }
+
+    /// Returns the next token, or `None` at the end of the data or on malformed input
+    fn next_token(&mut self) -> Option<Token> {
+        self.skip_whitespace();
+        let start = self.pos;
+        let byte = *self.data.get(start)?;
+
+        let kind = match byte {
+            b'(' => {
+                // Literal string: balanced parentheses, backslash escapes
+                let mut depth = 0;
+                loop {
+                    match *self.data.get(self.pos)? {
+                        b'\\' => self.pos += 1,
+                        b'(' => depth += 1,
+                        b')' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                self.pos += 1;
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                    self.pos += 1;
+                }
+                TokenKind::String
+            }
+            b'<' if self.data.get(start + 1) == Some(&b'<') => {
+                self.pos += 2;
+                TokenKind::DictStart
+            }
+            b'>' if self.data.get(start + 1) == Some(&b'>') => {
+                self.pos += 2;
+                TokenKind::DictEnd
+            }
+            b'<' => {
+                let len = self.data[start..].iter().position(|&b| b == b'>')?;
+                self.pos = start + len + 1;
+                TokenKind::HexString
+            }
+            b'[' | b'{' => {
+                self.pos += 1;
+                TokenKind::ArrayStart
+            }
+            b']' | b'}' => {
+                self.pos += 1;
+                TokenKind::ArrayEnd
+            }
+            b'/' => {
+                self.pos += 1;
+                self.skip_regular();
+                TokenKind::Name
+            }
+            b')' | b'>' => return None,
+            _ => {
+                self.skip_regular();
+                let text = &self.data[start..self.pos];
+                let numeric = text
+                    .iter()
+                    .all(|&b| b.is_ascii_digit() || matches!(b, b'+' | b'-' | b'.'))
+                    && text.iter().any(u8::is_ascii_digit);
+                if numeric {
+                    TokenKind::Number
+                } else {
+                    TokenKind::Keyword
+                }
+            }
+        };
+
+        Some(Token {
+            kind,
+            start,
+            end: self.pos,
+        })
+    }
+
+    /// Consumes the next token if it is the given keyword
+    fn eat_keyword(&mut self, keyword: &[u8]) -> bool {
+        let mut lookahead = self.clone();
+        match lookahead.next_token() {
+            Some(token) if token.kind == TokenKind::Keyword && lookahead.text(token) == keyword => {
+                *self = lookahead;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Consumes the next token if it is a non-negative integer
+    fn next_integer(&mut self) -> Option<usize> {
+        let token = self.next_token()?;
+        if token.kind != TokenKind::Number {
+            return None;
+        }
+        std::str::from_utf8(self.text(token)).ok()?.parse().ok()
+    }
+
+    fn text(&self, token: Token) -> &'a [u8] {
+        &self.data[token.start..token.end]
+    }
+}
+
+/// A parsed PDF object value, reduced to what stealth mode needs
+#[derive(Debug, Clone)]
+enum Value {
+    Number(f64),
+    Name(Vec<u8>),
+    Reference(u32),
+    Array(Vec<Value>),
+    Dictionary(Dictionary),
+    Other,
+}
+
+/// A PDF dictionary that remembers where each value sits, so entries can be rewritten
+#[derive(Debug, Clone, Default)]
+struct Dictionary {
+    /// `(key without the slash, value, byte range of the value)`
+    entries: Vec<(Vec<u8>, Value, (usize, usize))>,
+}
+
+impl Dictionary {
+    fn get(&self, key: &[u8]) -> Option<&Value> {
+        self.entries
+            .iter()
+            .find(|(k, _, _)| k == key)
+            .map(|(_, value, _)| value)
+    }
+
+    fn span(&self, key: &[u8]) -> Option<(usize, usize)> {
+        self.entries
+            .iter()
+            .find(|(k, _, _)| k == key)
+            .map(|&(_, _, span)| span)
+    }
+}
+
+/// An indirect object located through the cross-reference table
+#[derive(Debug, Clone)]
+struct PdfObject {
+    number: u32,
+    generation: u16,
+    value: Value,
+    /// Offset of the `num gen obj` header
+    start: usize,
+    /// Offset just past `endobj`
+    end: usize,
+    /// Byte range of the object's value (the stream dictionary, for streams)
+    value_span: (usize, usize),
+    /// Byte range of the raw stream data, for stream objects
+    stream: Option<(usize, usize)>,
+}
+
+impl PdfObject {
+    fn dictionary(&self) -> Option<&Dictionary> {
+        match &self.value {
+            Value::Dictionary(dictionary) => Some(dictionary),
+            _ => None,
+        }
+    }
+}
+
+/// Newest cross-reference entry per object number: `(offset, generation)`, or `None`
+/// for a free entry
+type XrefEntries = BTreeMap<u32, Option<(usize, u16)>>;
+
+/// The live objects and newest trailer of a PDF file
+struct PdfDocument {
+    /// Live objects, in file order
+    objects: Vec<PdfObject>,
+    trailer: Dictionary,
+}
+
+/// A decoded page content stream and the operands that can carry payload bits
+struct ContentStream {
+    /// Index into [`PdfDocument::objects`]
+    object: usize,
+    /// Whether the stream is `FlateDecode`d (otherwise it is unfiltered)
+    deflated: bool,
+    content: Vec<u8>,
+    /// Byte ranges of the carrier numbers within `content`
+    carriers: Vec<(usize, usize)>,
+    modified: bool,
+}
+
+impl ContentStream {
+    /// Reads the bit carried by each carrier operand
+    fn bits(&self) -> impl Iterator<Item = bool> + '_ {
+        self.carriers
+            .iter()
+            .map(|&(start, end)| PdfEngine::carrier_bit(&self.content[start..end]))
+    }
+
+    /// Rewrites the first `bits.len()` carriers to carry `bits`
+    fn write_bits(&mut self, bits: &[bool]) {
+        let mut content = Vec::with_capacity(self.content.len() + bits.len());
+        let mut carriers = Vec::with_capacity(self.carriers.len());
+        let mut copied = 0;
+
+        for (i, &(start, end)) in self.carriers.iter().enumerate() {
+            content.extend_from_slice(&self.content[copied..start]);
+            let new_start = content.len();
+            match bits.get(i) {
+                Some(&bit) => {
+                    content.extend(PdfEngine::encode_carrier(&self.content[start..end], bit))
+                }
+                None => content.extend_from_slice(&self.content[start..end]),
+            }
+            carriers.push((new_start, content.len()));
+            copied = end;
+        }
+        content.extend_from_slice(&self.content[copied..]);
+
+        self.content = content;
+        self.carriers = carriers;
+        self.modified = true;
+    }
+}
+
 impl PdfEngine {
     pub fn new() -> Self {
         Self
     }
 
+    /// Marks the start of a stealth payload in the carrier bit stream
+    const STEALTH_MAGIC: &'static [u8] = b"LpPd";
+
+    /// Nonce, stealth magic and a 4-byte big-endian payload length
+    const STEALTH_HEADER_LEN: usize = stealth::HEADER_LEN;
+
     /// Finds where the PDF actually ends (after the last %%EOF marker)
     fn find_eof_end(&self, pdf: &[u8]) -> Option<usize> {
         let eof_marker = b"%%EOF";
@@ -43,6 +368,734 @@ impl PdfEngine {
             .rposition(|window| window == eof_marker)
             .map(|pos| pos + eof_marker.len())
     }
+
+    fn invalid(reason: &str) -> LupinError {
+        LupinError::PdfInvalidFormat {
+            reason: reason.to_string(),
+        }
+    }
+
+    fn unsupported(reason: &str) -> LupinError {
+        LupinError::StealthUnsupportedCarrier {
+            format: "PDF",
+            reason: reason.to_string(),
+        }
+    }
+
+    /// Parses one object value, returning it with its byte range
+    fn parse_value(lexer: &mut Lexer) -> Option<(Value, usize, usize)> {
+        let token = lexer.next_token()?;
+        let value = match token.kind {
+            TokenKind::Number => {
+                // `num gen R` is an indirect reference
+                let mut lookahead = lexer.clone();
+                let generation = lookahead.next_token();
+                let keyword = lookahead.next_token();
+                match (generation, keyword) {
+                    (Some(generation), Some(keyword))
+                        if generation.kind == TokenKind::Number
+                            && keyword.kind == TokenKind::Keyword
+                            && lexer.text(keyword) == b"R" =>
+                    {
+                        *lexer = lookahead;
+                        let number = std::str::from_utf8(lexer.text(token)).ok()?.parse().ok()?;
+                        return Some((Value::Reference(number), token.start, keyword.end));
+                    }
+                    _ => Value::Number(std::str::from_utf8(lexer.text(token)).ok()?.parse().ok()?),
+                }
+            }
+            TokenKind::Name => Value::Name(lexer.text(token)[1..].to_vec()),
+            TokenKind::ArrayStart => {
+                let mut items = Vec::new();
+                loop {
+                    let mut lookahead = lexer.clone();
+                    if lookahead.next_token()?.kind == TokenKind::ArrayEnd {
+                        *lexer = lookahead;
+                        break;
+                    }
+                    items.push(Self::parse_value(lexer)?.0);
+                }
+                Value::Array(items)
+            }
+            TokenKind::DictStart => {
+                let mut dictionary = Dictionary::default();
+                loop {
+                    let key = lexer.next_token()?;
+                    match key.kind {
+                        TokenKind::DictEnd => break,
+                        TokenKind::Name => {
+                            let (value, start, end) = Self::parse_value(lexer)?;
+                            let name = lexer.text(key)[1..].to_vec();
+                            dictionary.entries.push((name, value, (start, end)));
+                        }
+                        _ => return None,
+                    }
+                }
+                Value::Dictionary(dictionary)
+            }
+            TokenKind::String | TokenKind::HexString | TokenKind::Keyword => Value::Other,
+            TokenKind::ArrayEnd | TokenKind::DictEnd => return None,
+        };
+        Some((value, token.start, lexer.pos))
+    }
+
+    /// Walks the cross-reference sections from `startxref` back through `/Prev`,
+    /// returning the newest entry for each object number and the newest trailer.
+    ///
+    /// Free entries map to `None` so they shadow older in-use entries.
+    fn parse_xref(data: &[u8]) -> Result<(XrefEntries, Dictionary)> {
+        let marker = b"startxref";
+        let marker_pos = data
+            .windows(marker.len())
+            .rposition(|window| window == marker)
+            .ok_or_else(|| Self::invalid("no startxref"))?;
+        let mut offset = Lexer::new(data, marker_pos + marker.len())
+            .next_integer()
+            .ok_or_else(|| Self::invalid("startxref has no offset"))?;
+
+        let mut entries = BTreeMap::new();
+        let mut trailer = None;
+        let mut visited = HashSet::new();
+
+        loop {
+            if !visited.insert(offset) {
+                return Err(Self::invalid("cross-reference sections form a loop"));
+            }
+
+            let mut lexer = Lexer::new(data, offset);
+            if !lexer.eat_keyword(b"xref") {
+                // PDF 1.5+ cross-reference streams usually come with compressed object
+                // streams, whose objects can't be rewritten in place.
+                return Err(if data.get(offset).is_some_and(u8::is_ascii_digit) {
+                    Self::unsupported("cross-reference streams are not supported")
+                } else {
+                    Self::invalid("startxref does not point at a cross-reference table")
+                });
+            }
+
+            while !lexer.eat_keyword(b"trailer") {
+                let malformed = || Self::invalid("malformed cross-reference table");
+                let first = lexer.next_integer().ok_or_else(malformed)?;
+                let count = lexer.next_integer().ok_or_else(malformed)?;
+                for number in first..first + count {
+                    let entry_offset = lexer.next_integer().ok_or_else(malformed)?;
+                    let generation = lexer.next_integer().ok_or_else(malformed)?;
+                    let in_use = if lexer.eat_keyword(b"n") {
+                        true
+                    } else if lexer.eat_keyword(b"f") {
+                        false
+                    } else {
+                        return Err(malformed());
+                    };
+                    let entry =
+                        (in_use && number != 0).then_some((entry_offset, generation as u16));
+                    entries.entry(number as u32).or_insert(entry);
+                }
+            }
+
+            let dictionary = match Self::parse_value(&mut lexer) {
+                Some((Value::Dictionary(dictionary), _, _)) => dictionary,
+                _ => return Err(Self::invalid("malformed trailer")),
+            };
+            if dictionary.get(b"XRefStm").is_some() {
+                return Err(Self::unsupported(
+                    "cross-reference streams are not supported",
+                ));
+            }
+
+            let previous = match dictionary.get(b"Prev") {
+                Some(Value::Number(previous)) => Some(*previous as usize),
+                _ => None,
+            };
+            trailer.get_or_insert(dictionary);
+            match previous {
+                Some(previous) => offset = previous,
+                None => break,
+            }
+        }
+
+        let trailer = trailer.ok_or_else(|| Self::invalid("missing trailer"))?;
+        if trailer.get(b"Encrypt").is_some() {
+            return Err(Self::unsupported("encrypted PDFs are not supported"));
+        }
+        Ok((entries, trailer))
+    }
+
+    /// Reads the integer stored in an indirect object, for indirect `/Length` values
+    fn resolve_integer(data: &[u8], offset: usize) -> Option<usize> {
+        let mut lexer = Lexer::new(data, offset);
+        lexer.next_integer()?;
+        lexer.next_integer()?;
+        if !lexer.eat_keyword(b"obj") {
+            return None;
+        }
+        lexer.next_integer()
+    }
+
+    /// Parses the indirect object whose header starts at `offset`
+    fn parse_object(
+        data: &[u8],
+        number: u32,
+        offset: usize,
+        entries: &XrefEntries,
+    ) -> Result<PdfObject> {
+        let misplaced = || Self::invalid(&format!("object {} is not at its xref offset", number));
+        let mut lexer = Lexer::new(data, offset);
+        lexer.skip_whitespace();
+        let start = lexer.pos;
+        if lexer.next_integer() != Some(number as usize) {
+            return Err(misplaced());
+        }
+        let generation = lexer.next_integer().ok_or_else(misplaced)? as u16;
+        if !lexer.eat_keyword(b"obj") {
+            return Err(misplaced());
+        }
+
+        let (value, value_start, value_end) = Self::parse_value(&mut lexer)
+            .ok_or_else(|| Self::invalid(&format!("object {} is malformed", number)))?;
+
+        let mut stream = None;
+        if let Value::Dictionary(dictionary) = &value {
+            if lexer.eat_keyword(b"stream") {
+                // The keyword is followed by CRLF or LF (7.3.8.1)
+                let mut stream_start = lexer.pos;
+                if data[stream_start..].starts_with(b"\r\n") {
+                    stream_start += 2;
+                } else if data[stream_start..].starts_with(b"\n") {
+                    stream_start += 1;
+                }
+
+                let length = match dictionary.get(b"Length") {
+                    Some(Value::Number(length)) => Some(*length as usize),
+                    Some(Value::Reference(reference)) => entries
+                        .get(reference)
+                        .copied()
+                        .flatten()
+                        .and_then(|(offset, _)| Self::resolve_integer(data, offset)),
+                    _ => None,
+                };
+                let declared_end = length
+                    .map(|length| stream_start + length)
+                    .filter(|&end| end <= data.len())
+                    .filter(|&end| Lexer::new(data, end).eat_keyword(b"endstream"));
+
+                // Fall back to scanning for `endstream` when /Length is wrong
+                let stream_end = match declared_end {
+                    Some(end) => end,
+                    None => {
+                        let marker = b"endstream";
+                        let found = data[stream_start..]
+                            .windows(marker.len())
+                            .position(|window| window == marker)
+                            .ok_or_else(|| {
+                                Self::invalid(&format!("stream {} has no endstream", number))
+                            })?;
+                        let mut end = stream_start + found;
+                        if data[..end].ends_with(b"\r\n") {
+                            end -= 2;
+                        } else if data[..end].ends_with(b"\n") || data[..end].ends_with(b"\r") {
+                            end -= 1;
+                        }
+                        end.max(stream_start)
+                    }
+                };
+
+                lexer.pos = stream_end;
+                if !lexer.eat_keyword(b"endstream") {
+                    return Err(Self::invalid(&format!(
+                        "stream {} has no endstream",
+                        number
+                    )));
+                }
+                stream = Some((stream_start, stream_end));
+            }
+        }
+
+        if !lexer.eat_keyword(b"endobj") {
+            return Err(Self::invalid(&format!("object {} has no endobj", number)));
+        }
+
+        Ok(PdfObject {
+            number,
+            generation,
+            value,
+            start,
+            end: lexer.pos,
+            value_span: (value_start, value_end),
+            stream,
+        })
+    }
+
+    /// Locates every live object through the cross-reference table
+    fn parse_document(data: &[u8]) -> Result<PdfDocument> {
+        let (entries, trailer) = Self::parse_xref(data)?;
+
+        let mut objects = entries
+            .iter()
+            .filter_map(|(&number, entry)| entry.map(|(offset, _)| (number, offset)))
+            .map(|(number, offset)| Self::parse_object(data, number, offset, &entries))
+            .collect::<Result<Vec<_>>>()?;
+        if objects.is_empty() {
+            return Err(Self::invalid("no objects in cross-reference table"));
+        }
+        objects.sort_by_key(|object| object.start);
+
+        Ok(PdfDocument { objects, trailer })
+    }
+
+    /// Finds the byte ranges of the carrier operands in a content stream
+    fn scan_carriers(content: &[u8]) -> Vec<(usize, usize)> {
+        let mut lexer = Lexer::new(content, 0);
+        let mut operands: Vec<Token> = Vec::new();
+        let mut carriers = Vec::new();
+
+        while let Some(token) = lexer.next_token() {
+            if token.kind != TokenKind::Keyword {
+                operands.push(token);
+                continue;
+            }
+
+            match lexer.text(token) {
+                b"true" | b"false" | b"null" => {
+                    operands.push(token);
+                    continue;
+                }
+                b"Td" | b"TD" | b"re" | b"TJ" => carriers.extend(
+                    operands
+                        .iter()
+                        .filter(|operand| operand.kind == TokenKind::Number)
+                        .filter(|operand| {
+                            std::str::from_utf8(lexer.text(**operand))
+                                .is_ok_and(|text| text.parse::<f64>().is_ok())
+                        })
+                        .map(|operand| (operand.start, operand.end)),
+                ),
+                b"ID" => {
+                    // Inline image data is binary: skip the single whitespace byte
+                    // after `ID`, then everything up to a whitespace-delimited `EI`.
+                    let data_start = token.end + 1;
+                    let end_marker = content
+                        .get(data_start..)
+                        .unwrap_or_default()
+                        .windows(4)
+                        .position(|window| {
+                            Lexer::is_whitespace(window[0])
+                                && &window[1..3] == b"EI"
+                                && Lexer::is_whitespace(window[3])
+                        });
+                    match end_marker {
+                        Some(pos) => lexer.pos = data_start + pos + 3,
+                        None if content.ends_with(b"EI") => lexer.pos = content.len(),
+                        None => break,
+                    }
+                }
+                _ => {}
+            }
+            operands.clear();
+        }
+
+        carriers
+    }
+
+    /// Reads the bit carried by a number: 1 if it has a redundant trailing zero
+    pub(crate) fn carrier_bit(text: &[u8]) -> bool {
+        text.contains(&b'.') && text.ends_with(b"0")
+    }
+
+    /// Rewrites a number to carry `bit` without changing its value
+    pub(crate) fn encode_carrier(text: &[u8], bit: bool) -> Vec<u8> {
+        let mut number = text.to_vec();
+        if number.contains(&b'.') {
+            while number.last() == Some(&b'0') {
+                number.pop();
+            }
+            if number.last() == Some(&b'.') {
+                number.pop();
+            }
+        }
+        if !number.iter().any(u8::is_ascii_digit) {
+            // e.g. `-.0`, which is just zero
+            number = b"0".to_vec();
+        }
+
+        if bit {
+            if number.contains(&b'.') {
+                number.push(b'0');
+            } else {
+                number.extend_from_slice(b".0");
+            }
+        }
+        number
+    }
+
+    /// Decodes the content streams of every page, in object-number order
+    fn content_streams(data: &[u8], document: &PdfDocument) -> Vec<ContentStream> {
+        let by_number: HashMap<u32, usize> = document
+            .objects
+            .iter()
+            .enumerate()
+            .map(|(index, object)| (object.number, index))
+            .collect();
+
+        let references = |value: &Value| -> Vec<u32> {
+            match value {
+                Value::Reference(reference) => vec![*reference],
+                Value::Array(items) => items
+                    .iter()
+                    .filter_map(|item| match item {
+                        Value::Reference(reference) => Some(*reference),
+                        _ => None,
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            }
+        };
+
+        let mut numbers = BTreeSet::new();
+        for object in &document.objects {
+            let Some(dictionary) = object.dictionary() else {
+                continue;
+            };
+            if !matches!(dictionary.get(b"Type"), Some(Value::Name(name)) if name == b"Page") {
+                continue;
+            }
+            for reference in dictionary
+                .get(b"Contents")
+                .map(references)
+                .unwrap_or_default()
+            {
+                // /Contents may point at an array object instead of a stream
+                match by_number.get(&reference).map(|&i| &document.objects[i]) {
+                    Some(target) if target.stream.is_none() => {
+                        numbers.extend(references(&target.value))
+                    }
+                    Some(_) => {
+                        numbers.insert(reference);
+                    }
+                    None => {}
+                }
+            }
+        }
+
+        let mut streams = Vec::new();
+        for number in numbers {
+            let Some(&index) = by_number.get(&number) else {
+                continue;
+            };
+            let object = &document.objects[index];
+            let (Some(dictionary), Some((start, end))) = (object.dictionary(), object.stream)
+            else {
+                continue;
+            };
+
+            let deflated = match dictionary.get(b"Filter") {
+                None => false,
+                Some(Value::Name(name)) if name == b"FlateDecode" => true,
+                Some(Value::Array(filters)) if matches!(filters.as_slice(), [Value::Name(name)] if name == b"FlateDecode") => {
+                    true
+                }
+                _ => {
+                    debug!(
+                        "PDF: Skipping content stream {} with unsupported filter",
+                        number
+                    );
+                    continue;
+                }
+            };
+            if dictionary.get(b"DecodeParms").is_some() {
+                debug!(
+                    "PDF: Skipping content stream {} with decode parameters",
+                    number
+                );
+                continue;
+            }
+
+            let content = if deflated {
+                let mut content = Vec::new();
+                if ZlibDecoder::new(&data[start..end])
+                    .read_to_end(&mut content)
+                    .is_err()
+                {
+                    debug!(
+                        "PDF: Skipping content stream {} that fails to inflate",
+                        number
+                    );
+                    continue;
+                }
+                content
+            } else {
+                data[start..end].to_vec()
+            };
+
+            streams.push(ContentStream {
+                object: index,
+                deflated,
+                carriers: Self::scan_carriers(&content),
+                content,
+                modified: false,
+            });
+        }
+        streams
+    }
+
+    /// Reads `len` bytes from the carrier bit stream, starting at byte `skip`
+    fn read_bytes(streams: &[ContentStream], skip: usize, len: usize) -> Vec<u8> {
+        let mut bits = streams.iter().flat_map(ContentStream::bits).skip(skip * 8);
+        (0..len)
+            .map(|_| {
+                bits.by_ref()
+                    .take(8)
+                    .fold(0u8, |byte, bit| (byte << 1) | bit as u8)
+            })
+            .collect()
+    }
+
+    /// Number of bytes the carriers can hold, including the stealth header
+    fn carrier_bytes(streams: &[ContentStream]) -> usize {
+        streams.iter().map(|s| s.carriers.len()).sum::<usize>() / 8
+    }
+
+    /// Returns the stealth header if the carriers start with one
+    fn stealth_header(streams: &[ContentStream]) -> Option<stealth::Header> {
+        let capacity = Self::carrier_bytes(streams);
+        if capacity < Self::STEALTH_HEADER_LEN {
+            return None;
+        }
+        stealth::open(
+            Self::STEALTH_MAGIC,
+            &Self::read_bytes(streams, 0, Self::STEALTH_HEADER_LEN),
+        )
+    }
+
+    /// Checks whether the content streams already carry a stealth payload.
+    ///
+    /// Best effort: files that can't be parsed simply report `false`.
+    fn has_stealth_payload(data: &[u8]) -> bool {
+        Self::parse_document(data)
+            .map(|document| Self::stealth_header(&Self::content_streams(data, &document)))
+            .is_ok_and(|header| header.is_some())
+    }
+
+    /// Writes the live objects back out with the rewritten content streams, followed
+    /// by a fresh cross-reference table and trailer
+    fn rebuild(data: &[u8], document: &PdfDocument, streams: &[ContentStream]) -> Result<Vec<u8>> {
+        let mut replacements = HashMap::new();
+        for stream in streams.iter().filter(|stream| stream.modified) {
+            let object = &document.objects[stream.object];
+            let encoded = if stream.deflated {
+                let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(&stream.content)?;
+                encoder.finish()?
+            } else {
+                stream.content.clone()
+            };
+
+            // /Length may have been an indirect reference; a direct value is simpler
+            // and leaves the old length object harmlessly unused.
+            let (value_start, value_end) = object.value_span;
+            let (length_start, length_end) = object
+                .dictionary()
+                .and_then(|dictionary| dictionary.span(b"Length"))
+                .ok_or_else(|| Self::invalid("content stream has no /Length"))?;
+
+            let mut bytes = format!("{} {} obj\n", object.number, object.generation).into_bytes();
+            bytes.extend_from_slice(&data[value_start..length_start]);
+            bytes.extend_from_slice(encoded.len().to_string().as_bytes());
+            bytes.extend_from_slice(&data[length_end..value_end]);
+            bytes.extend_from_slice(b"\nstream\n");
+            bytes.extend_from_slice(&encoded);
+            bytes.extend_from_slice(b"\nendstream\nendobj");
+            replacements.insert(stream.object, bytes);
+        }
+
+        // Keep the header line and binary comment, then every live object in file order
+        let mut output = data[..document.objects[0].start].to_vec();
+        let mut offsets = BTreeMap::new();
+        for (index, object) in document.objects.iter().enumerate() {
+            offsets.insert(object.number, (output.len(), object.generation));
+            match replacements.get(&index) {
+                Some(bytes) => output.extend_from_slice(bytes),
+                None => output.extend_from_slice(&data[object.start..object.end]),
+            }
+            output.push(b'\n');
+        }
+
+        let size = offsets.keys().next_back().map_or(1, |&last| last + 1);
+        let free: Vec<u32> = (1..size).filter(|n| !offsets.contains_key(n)).collect();
+        let xref_start = output.len();
+        output.extend_from_slice(format!("xref\n0 {}\n", size).as_bytes());
+        for number in 0..size {
+            // Entries are exactly 20 bytes; free entries form a linked list from 0
+            let entry = match offsets.get(&number) {
+                Some((offset, generation)) => format!("{:010} {:05} n\r\n", offset, generation),
+                None => {
+                    let next = free.iter().find(|&&n| n > number).copied().unwrap_or(0);
+                    let generation = if number == 0 { 65535 } else { 0 };
+                    format!("{:010} {:05} f\r\n", next, generation)
+                }
+            };
+            output.extend_from_slice(entry.as_bytes());
+        }
+
+        output.extend_from_slice(b"trailer\n<<");
+        for (key, _, (start, end)) in &document.trailer.entries {
+            if matches!(key.as_slice(), b"Size" | b"Prev" | b"XRefStm") {
+                continue;
+            }
+            output.extend_from_slice(b" /");
+            output.extend_from_slice(key);
+            output.push(b' ');
+            output.extend_from_slice(&data[*start..*end]);
+        }
+        output.extend_from_slice(
+            format!(" /Size {} >>\nstartxref\n{}\n%%EOF\n", size, xref_start).as_bytes(),
+        );
+        Ok(output)
+    }
+
+    /// Hides the payload in the precision of the page content operands
+    fn embed_stealth(&self, source_data: &[u8], payload: &[u8]) -> Result<Vec<u8>> {
+        // Data after %%EOF would shadow the stealth payload on extract
+        if self
+            .find_eof_end(source_data)
+            .is_some_and(|end| source_data[end..].iter().any(|b| !b.is_ascii_whitespace()))
+        {
+            return Err(LupinError::EmbedCollision {
+                source: std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    "PDF: Already contains some data after %%EOF",
+                ),
+            });
+        }
+
+        let document = Self::parse_document(source_data)?;
+        let mut streams = Self::content_streams(source_data, &document);
+
+        if Self::stealth_header(&streams).is_some() {
+            return Err(LupinError::EmbedCollision {
+                source: std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    "PDF content streams already carry a Lupin payload",
+                ),
+            });
+        }
+
+        let bit_stream = stealth::seal(Self::STEALTH_MAGIC, payload);
+
+        let capacity = Self::carrier_bytes(&streams);
+        if bit_stream.len() > capacity {
+            return Err(LupinError::PayloadTooLarge {
+                capacity: capacity.saturating_sub(Self::STEALTH_HEADER_LEN),
+                requested: payload.len(),
+            });
+        }
+
+        debug!(
+            "PDF: Writing {} bytes into {} content stream operands",
+            bit_stream.len(),
+            capacity * 8
+        );
+
+        let bits: Vec<bool> = bit_stream
+            .iter()
+            .flat_map(|byte| (0..8).rev().map(move |i| (byte >> i) & 1 == 1))
+            .collect();
+        let mut written = 0;
+        for stream in &mut streams {
+            if written == bits.len() {
+                break;
+            }
+            let take = stream.carriers.len().min(bits.len() - written);
+            if take > 0 {
+                stream.write_bits(&bits[written..written + take]);
+                written += take;
+            }
+        }
+
+        Self::rebuild(source_data, &document, &streams)
+    }
+
+    /// Finds a stealth payload's header, its first bytes and the content stream
+    /// objects holding it
+    fn probe_stealth(source_data: &[u8]) -> Option<Probe> {
+        let document = Self::parse_document(source_data).ok()?;
+        let streams = Self::content_streams(source_data, &document);
+        let header = Self::stealth_header(&streams)?;
+        let len = header.len;
+
+        // Only the streams the header and payload bits actually reach
+        let mut bits = (Self::STEALTH_HEADER_LEN + len) * 8;
+        let mut ranges = Vec::new();
+        for stream in &streams {
+            if bits == 0 || stream.carriers.is_empty() {
+                continue;
+            }
+            let object = &document.objects[stream.object];
+            ranges.push(object.start..object.end);
+            bits = bits.saturating_sub(stream.carriers.len());
+        }
+
+        let available = Self::carrier_bytes(&streams) - Self::STEALTH_HEADER_LEN;
+        let prefix = Self::read_bytes(
+            &streams,
+            Self::STEALTH_HEADER_LEN,
+            len.min(Probe::PREFIX_LEN).min(available),
+        );
+        Some(Probe::new(
+            EmbedMode::Stealth,
+            len,
+            ranges,
+            header.unmask(prefix, 0),
+        ))
+    }
+
+    /// Writes zero bits over a stealth payload and its header.
+    ///
+    /// A zero bit is the shortest spelling of an operand, so the operands most likely
+    /// read as they did before embedding, but the file is still rebuilt.
+    fn remove_stealth(source_data: &[u8]) -> Result<Vec<u8>> {
+        let document =
+            Self::parse_document(source_data).map_err(|_| LupinError::PdfNoHiddenData)?;
+        let mut streams = Self::content_streams(source_data, &document);
+        let len = Self::stealth_header(&streams)
+            .ok_or(LupinError::PdfNoHiddenData)?
+            .len;
+
+        let mut remaining = (Self::STEALTH_HEADER_LEN + len) * 8;
+        for stream in &mut streams {
+            if remaining == 0 {
+                break;
+            }
+            let take = stream.carriers.len().min(remaining);
+            if take > 0 {
+                stream.write_bits(&vec![false; take]);
+                remaining -= take;
+            }
+        }
+
+        debug!("PDF: Cleared {} byte stealth payload", len);
+
+        Self::rebuild(source_data, &document, &streams)
+    }
+
+    /// Recovers a payload hidden in the precision of the page content operands
+    fn extract_stealth(source_data: &[u8]) -> Result<Vec<u8>> {
+        // A file we can't parse can't carry a stealth payload
+        let document =
+            Self::parse_document(source_data).map_err(|_| LupinError::PdfNoHiddenData)?;
+        let streams = Self::content_streams(source_data, &document);
+        let header = Self::stealth_header(&streams).ok_or(LupinError::PdfNoHiddenData)?;
+        let len = header.len;
+
+        let capacity = Self::carrier_bytes(&streams);
+        if Self::STEALTH_HEADER_LEN + len > capacity {
+            return Err(LupinError::PdfCorruptedData);
+        }
+
+        debug!("PDF: Found {} byte stealth payload in content streams", len);
+
+        Ok(header.unmask(Self::read_bytes(&streams, Self::STEALTH_HEADER_LEN, len), 0))
+    }
 }
 
 impl Default for PdfEngine {
@@ -64,6 +1117,24 @@ impl SteganographyEngine for PdfEngine {
         ".pdf"
     }
 
+    fn capacity(&self, source_data: &[u8], mode: EmbedMode) -> Result<Capacity> {
+        match mode {
+            EmbedMode::Capacity => {
+                self.find_eof_end(source_data)
+                    .ok_or(LupinError::PdfNoEofMarker)?;
+                Ok(Capacity::Unlimited)
+            }
+            EmbedMode::Stealth => {
+                let document = Self::parse_document(source_data)?;
+                let streams = Self::content_streams(source_data, &document);
+                let bytes = Self::carrier_bytes(&streams);
+                Ok(Capacity::Exact(
+                    bytes.saturating_sub(Self::STEALTH_HEADER_LEN),
+                ))
+            }
+        }
+    }
+
     fn embed(&self, source_data: &[u8], payload: &[u8], mode: EmbedMode) -> Result<Vec<u8>> {
         // An empty payload would produce a file indistinguishable from the source
         // (nothing to extract). Reject it up front.
@@ -75,7 +1146,7 @@ impl SteganographyEngine for PdfEngine {
         // silently falling through to the capacity implementation below.
         match mode {
             EmbedMode::Capacity => {}
-            EmbedMode::Stealth => return Err(LupinError::StealthNotSupported { format: "PDF" }),
+            EmbedMode::Stealth => return self.embed_stealth(source_data, payload),
         }
 
         let eof_end = self
@@ -100,12 +1171,70 @@ impl SteganographyEngine for PdfEngine {
             });
         }
 
-        let mut result = Vec::with_capacity(eof_end + encoded_payload.len());
-        result.extend_from_slice(&source_data[..eof_end]);
+        // The appended payload would shadow a stealth payload on extract
+        if Self::has_stealth_payload(source_data) {
+            return Err(LupinError::EmbedCollision {
+                source: std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    "PDF content streams already carry a Lupin payload",
+                ),
+            });
+        }
+
+        // Keep any whitespace after %%EOF so `remove` can restore the source exactly
+        let mut result = Vec::with_capacity(source_data.len() + encoded_payload.len());
+        result.extend_from_slice(source_data);
         result.extend_from_slice(encoded_payload.as_bytes());
         Ok(result)
     }
 
+    fn probe(&self, source_data: &[u8]) -> Result<Option<Probe>> {
+        let eof_end = self
+            .find_eof_end(source_data)
+            .ok_or(LupinError::PdfNoEofMarker)?;
+
+        // Same rule as extract: anything after %%EOF is the capacity-mode payload
+        let start = eof_end
+            + source_data[eof_end..]
+                .iter()
+                .take_while(|b| b.is_ascii_whitespace())
+                .count();
+        if start == source_data.len() {
+            return Ok(Self::probe_stealth(source_data));
+        }
+
+        let (size, prefix) = super::probe_base64(&source_data[start..]);
+        let range = start..source_data.len();
+        Ok(Some(Probe::new(
+            EmbedMode::Capacity,
+            size,
+            vec![range],
+            prefix,
+        )))
+    }
+
+    fn remove(&self, source_data: &[u8]) -> Result<Vec<u8>> {
+        let eof_end = self
+            .find_eof_end(source_data)
+            .ok_or(LupinError::PdfNoEofMarker)?;
+
+        // The payload starts at the first non-whitespace byte after %%EOF
+        let start = eof_end
+            + source_data[eof_end..]
+                .iter()
+                .take_while(|b| b.is_ascii_whitespace())
+                .count();
+        if start == source_data.len() {
+            return Self::remove_stealth(source_data);
+        }
+
+        debug!(
+            "PDF: Removing {} bytes after %%EOF",
+            source_data.len() - start
+        );
+        Ok(source_data[..start].to_vec())
+    }
+
     fn extract(&self, source_data: &[u8]) -> Result<Vec<u8>> {
         let eof_marker = b"%%EOF";
         let eof_pos = source_data
@@ -125,13 +1254,15 @@ impl SteganographyEngine for PdfEngine {
             .copied()
             .collect();
 
+        // Nothing after %%EOF: fall back to the content streams (stealth mode)
         if payload.is_empty() {
-            return Err(LupinError::PdfNoHiddenData);
+            return Self::extract_stealth(source_data);
         }
 
+        // Damaged base64 is only returned if error correction can repair it
         general_purpose::STANDARD
             .decode(&payload)
-            .map_err(|_| LupinError::PdfCorruptedData)
+            .or_else(|_| super::recover_base64(&payload).ok_or(LupinError::PdfCorruptedData))
     }
 }
 
@@ -424,19 +1555,412 @@ mod tests {
     }
 
     #[test]
-    fn test_stealth_mode_not_supported() {
-        // Arrange - PDF only implements capacity mode for now; stealth must report a
-        // clear error rather than silently falling back to capacity.
+    fn test_stealth_requires_valid_xref() {
+        // Arrange - the minimal PDF's startxref doesn't point at its xref table, so
+        // stealth mode can't locate the objects (and must not fall back to capacity).
         let engine = PdfEngine::new();
         let pdf = create_minimal_pdf();
 
         // Act
         let result = engine.embed(&pdf, b"payload", EmbedMode::Stealth);
 
+        // Assert
+        assert!(matches!(result, Err(LupinError::PdfInvalidFormat { .. })));
+    }
+
+    /// Builds a PDF from numbered object bodies with a correct xref table
+    fn build_pdf(bodies: &[Vec<u8>]) -> Vec<u8> {
+        let mut pdf = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+        let mut offsets = Vec::new();
+        for (i, body) in bodies.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+            pdf.extend_from_slice(body);
+            pdf.extend_from_slice(b"\nendobj\n");
+        }
+        let xref_start = pdf.len();
+        pdf.extend_from_slice(format!("xref\n0 {}\n", bodies.len() + 1).as_bytes());
+        pdf.extend_from_slice(b"0000000000 65535 f \n");
+        for offset in offsets {
+            pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+        }
+        pdf.extend_from_slice(
+            format!(
+                "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+                bodies.len() + 1,
+                xref_start
+            )
+            .as_bytes(),
+        );
+        pdf
+    }
+
+    fn stream_body(dictionary: &str, data: &[u8]) -> Vec<u8> {
+        let mut body = format!("<< {} >>\nstream\n", dictionary).into_bytes();
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\nendstream");
+        body
+    }
+
+    /// Page content with `count` kerning numbers plus a few positioning operands
+    fn text_content(count: usize) -> Vec<u8> {
+        let mut content = b"BT /F1 12 Tf 72 720 Td [".to_vec();
+        for i in 0..count {
+            content.extend_from_slice(format!("(a) {} ", i as f64 * 0.25 - 40.0).as_bytes());
+        }
+        content.extend_from_slice(b"] TJ 0 -14.50 TD (1 2 Td) Tj ET\n10 10 100 50 re f\n");
+        content
+    }
+
+    /// A one-page PDF whose content is split over a deflated stream and an
+    /// unfiltered one with an indirect /Length
+    fn create_content_pdf(count: usize) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&text_content(count)).unwrap();
+        let deflated = encoder.finish().unwrap();
+        let plain = text_content(count / 2);
+
+        build_pdf(&[
+            b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+            b"<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_vec(),
+            b"<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Contents [4 0 R 5 0 R] >>"
+                .to_vec(),
+            stream_body(
+                &format!("/Length {} /Filter /FlateDecode", deflated.len()),
+                &deflated,
+            ),
+            stream_body("/Length 6 0 R", &plain),
+            plain.len().to_string().into_bytes(),
+        ])
+    }
+
+    /// Parses every carrier operand of every page as a number
+    fn carrier_values(pdf: &[u8]) -> Vec<f64> {
+        let document = PdfEngine::parse_document(pdf).unwrap();
+        PdfEngine::content_streams(pdf, &document)
+            .iter()
+            .flat_map(|stream| {
+                stream.carriers.iter().map(|&(start, end)| {
+                    std::str::from_utf8(&stream.content[start..end])
+                        .unwrap()
+                        .parse()
+                        .unwrap()
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_stealth_round_trip() {
+        // Arrange
+        let engine = PdfEngine::new();
+        let pdf = create_content_pdf(400);
+        let payload = b"hidden in the kerning";
+
+        // Act
+        let embedded = engine.embed(&pdf, payload, EmbedMode::Stealth).unwrap();
+        let extracted = engine.extract(&embedded).unwrap();
+
+        // Assert
+        assert_eq!(extracted, payload);
+        assert!(embedded.starts_with(b"%PDF-1.4\n")); // Header is preserved
+        assert!(embedded.ends_with(b"%%EOF\n")); // Nothing is appended after %%EOF
+    }
+
+    #[test]
+    fn test_stealth_preserves_operand_values() {
+        // Arrange
+        let engine = PdfEngine::new();
+        let pdf = create_content_pdf(400);
+
+        // Act
+        let embedded = engine.embed(&pdf, &[0xA5; 40], EmbedMode::Stealth).unwrap();
+
+        // Assert - every operand keeps its exact value, only its spelling changes
+        assert_eq!(carrier_values(&embedded), carrier_values(&pdf));
+        assert_ne!(embedded, pdf);
+    }
+
+    #[test]
+    fn test_stealth_regenerates_valid_xref() {
+        // Arrange
+        let engine = PdfEngine::new();
+        let pdf = create_content_pdf(400);
+
+        // Act - large enough to spill into the stream with the indirect /Length
+        let embedded = engine.embed(&pdf, &[0x5A; 60], EmbedMode::Stealth).unwrap();
+        let (entries, trailer) = PdfEngine::parse_xref(&embedded).unwrap();
+
+        // Assert - every entry points at its object header, and /Length matches the data
+        assert_eq!(entries.len(), 7);
+        for (number, entry) in entries.iter().filter(|(n, _)| **n != 0) {
+            let (offset, _) = entry.unwrap();
+            assert!(embedded[offset..].starts_with(format!("{} 0 obj", number).as_bytes()));
+        }
+        assert!(matches!(trailer.get(b"Size"), Some(Value::Number(size)) if *size == 7.0));
+
+        let document = PdfEngine::parse_document(&embedded).unwrap();
+        for object in document.objects.iter().filter(|o| o.stream.is_some()) {
+            let (start, end) = object.stream.unwrap();
+            let length = match object.dictionary().unwrap().get(b"Length") {
+                Some(Value::Number(length)) => *length as usize,
+                other => panic!("Expected a direct /Length, got {:?}", other),
+            };
+            assert_eq!(end - start, length);
+        }
+    }
+
+    #[test]
+    fn test_stealth_follows_incremental_updates() {
+        // Arrange - an incremental update replaces the unfiltered content stream
+        let engine = PdfEngine::new();
+        let mut pdf = create_content_pdf(100);
+        let original_xref = PdfEngine::parse_xref(&pdf).unwrap();
+        let previous = pdf
+            .windows(9)
+            .rposition(|w| w == b"startxref")
+            .map(|pos| Lexer::new(&pdf, pos + 9).next_integer().unwrap())
+            .unwrap();
+        assert_eq!(original_xref.0.len(), 7);
+
+        let updated = text_content(300);
+        let offset = pdf.len();
+        pdf.extend_from_slice(b"5 0 obj\n");
+        pdf.extend_from_slice(&stream_body(
+            &format!("/Length {}", updated.len()),
+            &updated,
+        ));
+        pdf.extend_from_slice(b"\nendobj\n");
+        let xref_start = pdf.len();
+        pdf.extend_from_slice(
+            format!(
+                "xref\n5 1\n{:010} 00000 n \ntrailer\n<< /Size 7 /Root 1 0 R /Prev {} >>\nstartxref\n{}\n%%EOF\n",
+                offset, previous, xref_start
+            )
+            .as_bytes(),
+        );
+
+        // Act
+        let embedded = engine.embed(&pdf, &[7; 32], EmbedMode::Stealth).unwrap();
+
+        // Assert - the superseded revision is dropped and the payload survives
+        assert_eq!(engine.extract(&embedded).unwrap(), [7; 32]);
+        assert_eq!(carrier_values(&embedded), carrier_values(&pdf));
+        assert_eq!(embedded.windows(7).filter(|w| w == b"5 0 obj").count(), 1);
+        assert!(!embedded.windows(5).any(|w| w == b"/Prev"));
+    }
+
+    #[test]
+    fn test_scan_carriers_skips_strings_and_inline_images() {
+        // Arrange - numbers inside strings and inline image data are not operands
+        let content = b"BT (3 4 Td) Tj 1 2 Td ET BI /W 1 /H 1 /BPC 8 /CS /G ID \x35\x20 5 Td\x0a EI 7 8 9 10 re [(x) -250 (y)] TJ 0.5 0 0 0.5 0 0 cm";
+
+        // Act
+        let carriers = PdfEngine::scan_carriers(content);
+        let texts: Vec<&[u8]> = carriers.iter().map(|&(s, e)| &content[s..e]).collect();
+
+        // Assert
+        assert_eq!(
+            texts,
+            vec![b"1".as_slice(), b"2", b"7", b"8", b"9", b"10", b"-250"]
+        );
+    }
+
+    #[test]
+    fn test_encode_carrier_keeps_value() {
+        // Arrange
+        let cases: [&[u8]; 8] = [
+            b"72", b"12.5", b"12.50", b"-0.25", b"5.", b".5", b"-.0", b"100",
+        ];
+
+        for text in cases {
+            let value: f64 = std::str::from_utf8(text).unwrap().parse().unwrap();
+            for bit in [false, true] {
+                // Act
+                let encoded = PdfEngine::encode_carrier(text, bit);
+
+                // Assert
+                let decoded: f64 = std::str::from_utf8(&encoded).unwrap().parse().unwrap();
+                assert_eq!(decoded, value);
+                assert_eq!(PdfEngine::carrier_bit(&encoded), bit);
+            }
+        }
+    }
+
+    #[test]
+    fn test_stealth_payload_too_large() {
+        // Arrange
+        let engine = PdfEngine::new();
+        let pdf = create_content_pdf(40);
+
+        // Act
+        let result = engine.embed(&pdf, &[0u8; 100], EmbedMode::Stealth);
+
         // Assert
         assert!(matches!(
             result,
-            Err(LupinError::StealthNotSupported { format: "PDF" })
+            Err(LupinError::PayloadTooLarge { requested: 100, .. })
+        ));
+    }
+
+    #[test]
+    fn test_probe() {
+        // Arrange
+        let engine = PdfEngine::new();
+        let minimal = create_minimal_pdf();
+        let appended = engine
+            .embed(&minimal, b"appended payload", EmbedMode::Capacity)
+            .unwrap();
+        let content = create_content_pdf(400);
+        let stealth = engine
+            .embed(&content, b"operands", EmbedMode::Stealth)
+            .unwrap();
+
+        // Act
+        let clean = engine.probe(&content).unwrap();
+        let capacity = engine.probe(&appended).unwrap().unwrap();
+        let stealth = engine.probe(&stealth).unwrap().unwrap();
+
+        // Assert
+        assert_eq!(clean, None);
+        assert_eq!(capacity.mode, EmbedMode::Capacity);
+        assert_eq!(capacity.size, 16);
+        assert_eq!(capacity.ranges.len(), 1);
+        assert_eq!(capacity.ranges[0], minimal.len()..appended.len());
+        assert_eq!(capacity.prefix, b"appended payload");
+        assert_eq!(stealth.mode, EmbedMode::Stealth);
+        assert_eq!(stealth.size, 8);
+        assert_eq!(stealth.ranges.len(), 1);
+        assert_eq!(stealth.prefix, b"operands");
+    }
+
+    #[test]
+    fn test_remove() {
+        // Arrange
+        let engine = PdfEngine::new();
+        let source = create_content_pdf(400);
+        let appended = engine
+            .embed(&source, b"appended payload", EmbedMode::Capacity)
+            .unwrap();
+        let stealth = engine
+            .embed(&source, b"operands", EmbedMode::Stealth)
+            .unwrap();
+        let contents = |pdf: &[u8]| -> Vec<Vec<u8>> {
+            let document = PdfEngine::parse_document(pdf).unwrap();
+            PdfEngine::content_streams(pdf, &document)
+                .into_iter()
+                .map(|stream| stream.content)
+                .collect()
+        };
+
+        // Act
+        let restored = engine.remove(&appended).unwrap();
+        let cleared = engine.remove(&stealth).unwrap();
+        let nothing = engine.remove(&source);
+
+        // Assert
+        assert_eq!(restored, source);
+        assert_eq!(engine.probe(&cleared).unwrap(), None);
+        assert_eq!(contents(&cleared), contents(&source));
+        let again = engine
+            .embed(&cleared, b"again", EmbedMode::Stealth)
+            .unwrap();
+        assert_eq!(engine.extract(&again).unwrap(), b"again");
+        assert!(matches!(nothing, Err(LupinError::PdfNoHiddenData)));
+    }
+
+    #[test]
+    fn test_capacity() {
+        // Arrange
+        let engine = PdfEngine::new();
+        let pdf = create_content_pdf(80);
+
+        // Act
+        let capacity = engine.capacity(&pdf, EmbedMode::Capacity).unwrap();
+        let stealth = engine.capacity(&pdf, EmbedMode::Stealth).unwrap();
+
+        // Assert - the reported figure is exactly what fits
+        assert_eq!(capacity, Capacity::Unlimited);
+        let bytes = stealth.bytes().unwrap();
+        assert!(bytes > 0);
+        assert!(engine
+            .embed(&pdf, &vec![0x5A; bytes], EmbedMode::Stealth)
+            .is_ok());
+        assert!(matches!(
+            engine.embed(&pdf, &vec![0x5A; bytes + 1], EmbedMode::Stealth),
+            Err(LupinError::PayloadTooLarge { capacity, .. }) if capacity == bytes
         ));
     }
+
+    #[test]
+    fn test_stealth_rejects_unsupported_files() {
+        // Arrange - an encrypted file and one using a cross-reference stream
+        let engine = PdfEngine::new();
+        let pdf = create_content_pdf(400);
+        let encrypted = {
+            let xref_start = pdf.windows(6).position(|w| w == b"\nxref\n").unwrap() + 1;
+            let mut encrypted = pdf[..xref_start].to_vec();
+            encrypted.extend_from_slice(
+                format!(
+                    "xref\n0 1\n0000000000 65535 f \ntrailer\n<< /Size 7 /Root 1 0 R /Encrypt 6 0 R >>\nstartxref\n{}\n%%EOF\n",
+                    xref_start
+                )
+                .as_bytes(),
+            );
+            encrypted
+        };
+        let xref_stream = {
+            let mut pdf = b"%PDF-1.5\n".to_vec();
+            let offset = pdf.len();
+            pdf.extend_from_slice(b"1 0 obj\n");
+            pdf.extend_from_slice(&stream_body(
+                "/Type /XRef /Size 1 /W [1 2 1] /Length 0",
+                b"",
+            ));
+            pdf.extend_from_slice(b"\nendobj");
+            pdf.extend_from_slice(format!("\nstartxref\n{}\n%%EOF\n", offset).as_bytes());
+            pdf
+        };
+
+        // Act & Assert
+        for file in [encrypted, xref_stream] {
+            let result = engine.embed(&file, b"payload", EmbedMode::Stealth);
+            assert!(matches!(
+                result,
+                Err(LupinError::StealthUnsupportedCarrier { format: "PDF", .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_stealth_collisions() {
+        // Arrange
+        let engine = PdfEngine::new();
+        let pdf = create_content_pdf(400);
+        let stealthy = engine.embed(&pdf, b"first", EmbedMode::Stealth).unwrap();
+        let appended = engine.embed(&pdf, b"first", EmbedMode::Capacity).unwrap();
+
+        // Act & Assert - a second payload in either mode would shadow or destroy the first
+        for (source, mode) in [
+            (&stealthy, EmbedMode::Stealth),
+            (&stealthy, EmbedMode::Capacity),
+            (&appended, EmbedMode::Stealth),
+        ] {
+            let result = engine.embed(source, b"second", mode);
+            assert!(matches!(result, Err(LupinError::EmbedCollision { .. })));
+        }
+    }
+
+    #[test]
+    fn test_extract_clean_content_pdf() {
+        // Arrange
+        let engine = PdfEngine::new();
+        let pdf = create_content_pdf(400);
+
+        // Act
+        let result = engine.extract(&pdf);
+
+        // Assert
+        assert!(matches!(result, Err(LupinError::PdfNoHiddenData)));
+    }
 }
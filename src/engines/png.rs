@@ -51,19 +51,162 @@
 //! - Lowercase 'g' = safe to copy (i.e. does not affect rendering if unknown)
 //! - This makes it `lpNg` which PNG readers will safely ignore
 //!
+//! ## Stealth Mode
+//!
+//! [`EmbedMode::Stealth`] leaves the chunk list alone and hides the payload in the
+//! least-significant bits of the pixel samples instead:
+//!
+//! 1. All IDAT chunks are concatenated and inflated (zlib).
+//! 2. Each scanline is unfiltered (None/Sub/Up/Average/Paeth) back to raw samples.
+//! 3. The payload is written, MSB first, into the LSB of every colour sample in
+//!    row-major order. Alpha samples are never touched, and for 16-bit images only
+//!    the low byte of each sample changes.
+//! 4. Every scanline is re-filtered with its original filter type, re-deflated and
+//!    written back as fresh IDAT chunks. All other chunks are copied verbatim.
+//!
+//! The bit stream starts with a small header so extraction can detect the payload
+//! without being told the mode. Everything after the random nonce is whitened, so
+//! neither the `LpSt` signature nor the payload shows in the LSBs (see the
+//! [`crate::engines`] documentation):
+//!
+//! ```text
+//! [8 bytes: Nonce][4 bytes: "LpSt"][4 bytes: Big-endian Payload Length][N bytes: Raw Payload]
+//! ```
+//!
+//! Each sample changes by at most one intensity level, so the image is visually
+//! identical. Capacity is one bit per colour sample (e.g. `width * height * 3 / 8`
+//! bytes for RGB) minus the 16-byte header. Only 8- and 16-bit non-interlaced
+//! greyscale, RGB, greyscale+alpha and RGBA images are supported; flipping the LSB
+//! of a palette index or a sub-byte sample would visibly change the pixel.
+//!
 
+use super::stealth;
 use crate::error::{LupinError, Result};
-use crate::{EmbedMode, SteganographyEngine};
+use crate::{Capacity, EmbedMode, Probe, SteganographyEngine};
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use log::debug;
+use std::io::{Read, Write};
+use std::ops::Range;
 
 /// PNG steganography engine
 ///
-/// Uses custom ancillary chunks to hide data in PNG files without modifying image data.
-/// Data is Base64-encoded and stored in a `lpNg` chunk that standard PNG readers will safely ignore.
+/// In capacity mode, uses custom ancillary chunks to hide data in PNG files without
+/// modifying image data. Data is Base64-encoded and stored in a `lpNg` chunk that
+/// standard PNG readers will safely ignore. In stealth mode, the payload is written
+/// into the least-significant bits of the pixel samples instead.
 ///
 /// See the module documentation for details on how data is stored and limitations.
 pub struct PngEngine;
 
+/// The IHDR fields that matter for pixel-domain (stealth) embedding
+#[derive(Debug, Clone, Copy)]
+struct ImageHeader {
+    width: usize,
+    height: usize,
+    bit_depth: u8,
+    color_type: u8,
+}
+
+impl ImageHeader {
+    /// Number of samples per pixel, including alpha
+    fn channels(&self) -> usize {
+        match self.color_type {
+            0 => 1, // Greyscale
+            2 => 3, // RGB
+            4 => 2, // Greyscale + alpha
+            6 => 4, // RGBA
+            _ => unreachable!("validated in PngEngine::parse_header"),
+        }
+    }
+
+    /// Number of samples per pixel that carry payload bits (alpha is skipped)
+    fn color_channels(&self) -> usize {
+        match self.color_type {
+            0 | 4 => 1,
+            _ => 3,
+        }
+    }
+
+    /// Bytes per sample (1 for 8-bit, 2 for 16-bit)
+    fn sample_bytes(&self) -> usize {
+        self.bit_depth as usize / 8
+    }
+
+    /// Bytes per complete pixel, which is also the filter "bpp" distance
+    fn bytes_per_pixel(&self) -> usize {
+        self.channels() * self.sample_bytes()
+    }
+}
+
+/// A PNG with its image data inflated and unfiltered, ready for LSB embedding
+struct DecodedImage {
+    header: ImageHeader,
+    /// Byte offset of the first IDAT chunk in the source file
+    idat_start: usize,
+    /// Byte offset just past the last IDAT chunk in the source file
+    idat_end: usize,
+    /// The filter type byte of each scanline, preserved for re-filtering
+    filters: Vec<u8>,
+    /// Unfiltered scanlines concatenated (without their filter type bytes)
+    pixels: Vec<u8>,
+}
+
+impl DecodedImage {
+    /// Total number of payload bits the image can carry, including the header
+    fn capacity_bits(&self) -> usize {
+        self.header.width * self.header.height * self.header.color_channels()
+    }
+
+    /// Byte offset in `pixels` of the byte holding carrier bit number `bit`
+    fn carrier_offset(&self, bit: usize) -> usize {
+        let color_channels = self.header.color_channels();
+        let sample_bytes = self.header.sample_bytes();
+        let pixel = bit / color_channels;
+        let channel = bit % color_channels;
+
+        // The low byte of a 16-bit sample is the second (big-endian) byte.
+        pixel * self.header.bytes_per_pixel() + channel * sample_bytes + (sample_bytes - 1)
+    }
+
+    /// Reads `len` bytes from the carrier bits, starting at byte `start` of the bit stream
+    fn read_bytes(&self, start: usize, len: usize) -> Vec<u8> {
+        (0..len)
+            .map(|i| {
+                (0..8).fold(0u8, |byte, b| {
+                    let offset = self.carrier_offset((start + i) * 8 + b);
+                    (byte << 1) | (self.pixels[offset] & 1)
+                })
+            })
+            .collect()
+    }
+
+    /// Writes `data` into the carrier bits, starting at byte 0 of the bit stream
+    fn write_bytes(&mut self, data: &[u8]) {
+        for (i, &byte) in data.iter().enumerate() {
+            for b in 0..8 {
+                let bit = (byte >> (7 - b)) & 1;
+                let offset = self.carrier_offset(i * 8 + b);
+                self.pixels[offset] = (self.pixels[offset] & !1) | bit;
+            }
+        }
+    }
+
+    /// Reads the stealth header, returning it if the magic matches
+    fn stealth_header(&self) -> Option<stealth::Header> {
+        if self.capacity_bits() < PngEngine::STEALTH_HEADER_LEN * 8 {
+            return None;
+        }
+
+        stealth::open(
+            PngEngine::STEALTH_MAGIC,
+            &self.read_bytes(0, PngEngine::STEALTH_HEADER_LEN),
+        )
+    }
+}
+
 impl PngEngine {
     /// Creates a new PNG engine
     pub fn new() -> Self {
@@ -73,6 +216,15 @@ impl PngEngine {
     /// Custom chunk type for steganography data
     const LUPIN_CHUNK_TYPE: &'static [u8] = b"lpNg";
 
+    /// Signature at the start of a stealth-mode bit stream
+    const STEALTH_MAGIC: &'static [u8] = b"LpSt";
+
+    /// Stealth header size: nonce + signature + 4-byte big-endian payload length
+    const STEALTH_HEADER_LEN: usize = stealth::HEADER_LEN;
+
+    /// Maximum data length of each IDAT chunk written in stealth mode
+    const IDAT_CHUNK_LEN: usize = 8192;
+
     /// CRC-32 initial value (all bits set)
     const CRC32_INIT: u32 = 0xFFFFFFFF;
 
@@ -197,8 +349,36 @@ impl PngEngine {
         false
     }
 
-    /// Extracts data from a custom chunk if it exists
-    fn extract_custom_chunk(data: &[u8], chunk_type: &[u8]) -> Result<Vec<u8>> {
+    /// Returns the byte range of the first complete chunk of the given type, from its
+    /// length field through its CRC
+    fn find_chunk(data: &[u8], chunk_type: &[u8]) -> Option<Range<usize>> {
+        let mut pos = 8; // Skip PNG signature
+
+        while pos + 8 <= data.len() {
+            let chunk_length =
+                u32::from_be_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]])
+                    as usize;
+            let current_chunk_type = &data[pos + 4..pos + 8];
+            let chunk_end = pos.checked_add(12 + chunk_length)?;
+
+            if current_chunk_type == chunk_type {
+                return (chunk_end <= data.len()).then_some(pos..chunk_end);
+            }
+
+            // Stop at IEND
+            if current_chunk_type == b"IEND" {
+                break;
+            }
+            pos = chunk_end;
+        }
+
+        None
+    }
+
+    /// Extracts data from a custom chunk if it exists, together with whether it is
+    /// intact: a chunk that fails its CRC or is cut short is still returned, so that
+    /// error correction gets a chance to repair it
+    fn extract_custom_chunk(data: &[u8], chunk_type: &[u8]) -> Result<(Vec<u8>, bool)> {
         let mut pos = 8; // Skip PNG signature
 
         while pos + 8 <= data.len() {
@@ -213,7 +393,8 @@ impl PngEngine {
                 let data_end = data_start + chunk_length;
 
                 if data_end + 4 > data.len() {
-                    return Err(LupinError::PngNoHiddenData);
+                    let available = data_end.min(data.len()).max(data_start);
+                    return Ok((data[data_start..available].to_vec(), false));
                 }
 
                 // Verify CRC
@@ -226,11 +407,7 @@ impl PngEngine {
                 ]);
                 let calculated_crc = Self::calculate_crc(chunk_type, chunk_data);
 
-                if stored_crc != calculated_crc {
-                    return Err(LupinError::PngCorruptedData);
-                }
-
-                return Ok(chunk_data.to_vec());
+                return Ok((chunk_data.to_vec(), stored_crc == calculated_crc));
             }
 
             // Move to next chunk
@@ -244,6 +421,360 @@ impl PngEngine {
 
         Err(LupinError::PngNoHiddenData)
     }
+
+    /// Parses the IHDR chunk and checks the image is usable for stealth embedding
+    fn parse_header(data: &[u8]) -> Result<ImageHeader> {
+        // IHDR must be the first chunk: signature (8) + length (4) + type (4) + data (13)
+        if data.len() < 8 + 8 + 13 || &data[12..16] != b"IHDR" {
+            return Err(LupinError::PngInvalidFormat {
+                reason: "IHDR must be the first chunk".to_string(),
+            });
+        }
+
+        let ihdr = &data[16..29];
+        let width = u32::from_be_bytes([ihdr[0], ihdr[1], ihdr[2], ihdr[3]]) as usize;
+        let height = u32::from_be_bytes([ihdr[4], ihdr[5], ihdr[6], ihdr[7]]) as usize;
+        let bit_depth = ihdr[8];
+        let color_type = ihdr[9];
+        let interlace = ihdr[12];
+
+        if width == 0 || height == 0 {
+            return Err(LupinError::PngInvalidFormat {
+                reason: "image has zero width or height".to_string(),
+            });
+        }
+
+        let unsupported = |reason: &str| LupinError::StealthUnsupportedCarrier {
+            format: "PNG",
+            reason: reason.to_string(),
+        };
+
+        if color_type == 3 {
+            return Err(unsupported("palette images are not supported"));
+        }
+        if !matches!(color_type, 0 | 2 | 4 | 6) {
+            return Err(LupinError::PngInvalidFormat {
+                reason: format!("unknown color type {}", color_type),
+            });
+        }
+        if bit_depth != 8 && bit_depth != 16 {
+            return Err(unsupported("only 8- and 16-bit samples are supported"));
+        }
+        if interlace != 0 {
+            return Err(unsupported("interlaced images are not supported"));
+        }
+
+        Ok(ImageHeader {
+            width,
+            height,
+            bit_depth,
+            color_type,
+        })
+    }
+
+    /// Inflates and unfilters the image data so pixel samples can be modified
+    fn decode_image(data: &[u8]) -> Result<DecodedImage> {
+        Self::decode_rows(data, None)
+    }
+
+    /// Inflates and unfilters only the scanlines holding the first `len` bytes of the
+    /// bit stream, enough to read a stealth header without decoding the whole image.
+    ///
+    /// The result can only be read from, and only that far.
+    fn decode_stream_prefix(data: &[u8], len: usize) -> Result<DecodedImage> {
+        Self::decode_rows(data, Some(len))
+    }
+
+    /// Decodes every scanline, or those holding the first `prefix_len` bytes of the
+    /// bit stream
+    fn decode_rows(data: &[u8], prefix_len: Option<usize>) -> Result<DecodedImage> {
+        let header = Self::parse_header(data)?;
+
+        // Collect the (consecutive) IDAT chunks into a single zlib stream
+        let mut compressed = Vec::new();
+        let mut idat_start = None;
+        let mut idat_end = None;
+        let mut pos = 8; // Skip PNG signature
+
+        while pos + 8 <= data.len() {
+            let chunk_length =
+                u32::from_be_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]])
+                    as usize;
+            let chunk_type = &data[pos + 4..pos + 8];
+            let chunk_end = pos + 12 + chunk_length;
+
+            if chunk_end > data.len() {
+                return Err(LupinError::PngInvalidFormat {
+                    reason: "truncated chunk".to_string(),
+                });
+            }
+
+            if chunk_type == b"IDAT" {
+                idat_start.get_or_insert(pos);
+                idat_end = Some(chunk_end);
+                compressed.extend_from_slice(&data[pos + 8..pos + 8 + chunk_length]);
+            } else if chunk_type == b"IEND" {
+                break;
+            }
+
+            pos = chunk_end;
+        }
+
+        let (idat_start, idat_end) = match (idat_start, idat_end) {
+            (Some(start), Some(end)) => (start, end),
+            _ => {
+                return Err(LupinError::PngInvalidFormat {
+                    reason: "no IDAT chunk found".to_string(),
+                })
+            }
+        };
+
+        let stride = header
+            .width
+            .checked_mul(header.bytes_per_pixel())
+            .ok_or_else(|| LupinError::PngInvalidFormat {
+                reason: "image dimensions are too large".to_string(),
+            })?;
+        let rows = match prefix_len {
+            Some(len) => {
+                let row_bits = header.width * header.color_channels();
+                (len.saturating_mul(8).saturating_add(row_bits - 1) / row_bits).min(header.height)
+            }
+            None => header.height,
+        };
+        let expected_len =
+            (stride + 1)
+                .checked_mul(rows)
+                .ok_or_else(|| LupinError::PngInvalidFormat {
+                    reason: "image dimensions are too large".to_string(),
+                })?;
+
+        // One byte more than the full image data shows up trailing garbage; a prefix
+        // just stops where it has enough
+        let limit = expected_len as u64 + u64::from(rows == header.height);
+        let mut raw = Vec::new();
+        ZlibDecoder::new(&compressed[..])
+            .take(limit)
+            .read_to_end(&mut raw)
+            .map_err(|e| LupinError::PngInvalidFormat {
+                reason: format!("failed to inflate image data: {}", e),
+            })?;
+
+        if raw.len() != expected_len {
+            return Err(LupinError::PngInvalidFormat {
+                reason: format!(
+                    "image data is {} bytes, expected {}",
+                    raw.len(),
+                    expected_len
+                ),
+            });
+        }
+
+        let bpp = header.bytes_per_pixel();
+        let mut filters = Vec::with_capacity(rows);
+        let mut pixels = vec![0u8; stride * rows];
+
+        for row in 0..rows {
+            let line = &raw[row * (stride + 1)..(row + 1) * (stride + 1)];
+            let filter = line[0];
+            if filter > 4 {
+                return Err(LupinError::PngInvalidFormat {
+                    reason: format!("unknown filter type {} on row {}", filter, row),
+                });
+            }
+            filters.push(filter);
+
+            let (done, rest) = pixels.split_at_mut(row * stride);
+            let prev = if row == 0 {
+                None
+            } else {
+                Some(&done[(row - 1) * stride..])
+            };
+            let current = &mut rest[..stride];
+            current.copy_from_slice(&line[1..]);
+
+            for i in 0..stride {
+                let a = if i >= bpp { current[i - bpp] } else { 0 };
+                let b = prev.map_or(0, |p| p[i]);
+                let c = if i >= bpp {
+                    prev.map_or(0, |p| p[i - bpp])
+                } else {
+                    0
+                };
+                current[i] = current[i].wrapping_add(Self::predict(filter, a, b, c));
+            }
+        }
+
+        Ok(DecodedImage {
+            header,
+            idat_start,
+            idat_end,
+            filters,
+            pixels,
+        })
+    }
+
+    /// Re-filters and re-deflates a decoded image, replacing the IDAT chunks of `source`
+    fn encode_image(source: &[u8], image: &DecodedImage) -> Result<Vec<u8>> {
+        let stride = image.header.width * image.header.bytes_per_pixel();
+        let bpp = image.header.bytes_per_pixel();
+
+        let mut raw = Vec::with_capacity((stride + 1) * image.header.height);
+        for (row, &filter) in image.filters.iter().enumerate() {
+            let current = &image.pixels[row * stride..(row + 1) * stride];
+            let prev = if row == 0 {
+                None
+            } else {
+                Some(&image.pixels[(row - 1) * stride..row * stride])
+            };
+
+            raw.push(filter);
+            for i in 0..stride {
+                let a = if i >= bpp { current[i - bpp] } else { 0 };
+                let b = prev.map_or(0, |p| p[i]);
+                let c = if i >= bpp {
+                    prev.map_or(0, |p| p[i - bpp])
+                } else {
+                    0
+                };
+                raw.push(current[i].wrapping_sub(Self::predict(filter, a, b, c)));
+            }
+        }
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&raw)?;
+        let compressed = encoder.finish()?;
+
+        let mut output = Vec::with_capacity(source.len());
+        output.extend_from_slice(&source[..image.idat_start]);
+        for chunk in compressed.chunks(Self::IDAT_CHUNK_LEN) {
+            output.extend_from_slice(&Self::create_chunk(b"IDAT", chunk));
+        }
+        output.extend_from_slice(&source[image.idat_end..]);
+
+        Ok(output)
+    }
+
+    /// Computes the filter predictor for a byte given its left (a), up (b) and
+    /// upper-left (c) neighbours
+    fn predict(filter: u8, a: u8, b: u8, c: u8) -> u8 {
+        match filter {
+            1 => a,
+            2 => b,
+            3 => ((a as u16 + b as u16) / 2) as u8,
+            4 => {
+                // Paeth predictor
+                let p = a as i16 + b as i16 - c as i16;
+                let pa = (p - a as i16).abs();
+                let pb = (p - b as i16).abs();
+                let pc = (p - c as i16).abs();
+                if pa <= pb && pa <= pc {
+                    a
+                } else if pb <= pc {
+                    b
+                } else {
+                    c
+                }
+            }
+            _ => 0,
+        }
+    }
+
+    /// Checks whether the pixel data already carries a stealth payload.
+    ///
+    /// Best effort: images that can't be decoded simply report `false`. Only the
+    /// scanlines holding the header are decoded.
+    fn has_stealth_payload(data: &[u8]) -> bool {
+        Self::decode_stream_prefix(data, Self::STEALTH_HEADER_LEN)
+            .map(|image| image.stealth_header().is_some())
+            .unwrap_or(false)
+    }
+
+    /// Hides the payload in the LSBs of the pixel samples
+    fn embed_stealth(&self, source_data: &[u8], payload: &[u8]) -> Result<Vec<u8>> {
+        let mut image = Self::decode_image(source_data)?;
+
+        if image.stealth_header().is_some() {
+            return Err(LupinError::EmbedCollision {
+                source: std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    "PNG pixel data already carries a Lupin payload",
+                ),
+            });
+        }
+
+        let stream = stealth::seal(Self::STEALTH_MAGIC, payload);
+
+        let capacity = image.capacity_bits() / 8;
+        if stream.len() > capacity {
+            return Err(LupinError::PayloadTooLarge {
+                capacity: capacity.saturating_sub(Self::STEALTH_HEADER_LEN),
+                requested: payload.len(),
+            });
+        }
+
+        debug!(
+            "PNG: Writing {} bytes into the LSBs of {} available carrier bytes",
+            stream.len(),
+            capacity
+        );
+
+        image.write_bytes(&stream);
+        Self::encode_image(source_data, &image)
+    }
+
+    /// Finds a stealth payload's header and first bytes in the pixel LSBs
+    fn probe_stealth(source_data: &[u8]) -> Option<Probe> {
+        let image =
+            Self::decode_stream_prefix(source_data, Self::STEALTH_HEADER_LEN + Probe::PREFIX_LEN)
+                .ok()?;
+        let header = image.stealth_header()?;
+        let available = image.capacity_bits() / 8 - Self::STEALTH_HEADER_LEN;
+        let prefix = image.read_bytes(
+            Self::STEALTH_HEADER_LEN,
+            header.len.min(Probe::PREFIX_LEN).min(available),
+        );
+        let range = image.idat_start..image.idat_end;
+        Some(Probe::new(
+            EmbedMode::Stealth,
+            header.len,
+            vec![range],
+            header.unmask(prefix, 0),
+        ))
+    }
+
+    /// Writes zero bits over a stealth payload and its header in the pixel LSBs
+    fn remove_stealth(source_data: &[u8]) -> Result<Vec<u8>> {
+        let mut image = Self::decode_image(source_data).map_err(|_| LupinError::PngNoHiddenData)?;
+        let len = image
+            .stealth_header()
+            .ok_or(LupinError::PngNoHiddenData)?
+            .len;
+
+        let wiped = (Self::STEALTH_HEADER_LEN + len).min(image.capacity_bits() / 8);
+        image.write_bytes(&vec![0; wiped]);
+
+        debug!("PNG: Cleared {} byte stealth payload from pixel data", len);
+
+        Self::encode_image(source_data, &image)
+    }
+
+    /// Recovers a payload hidden in the LSBs of the pixel samples
+    fn extract_stealth(source_data: &[u8]) -> Result<Vec<u8>> {
+        // A file we can't decode (or whose pixel format stealth mode never writes)
+        // can't carry a stealth payload.
+        let image = Self::decode_image(source_data).map_err(|_| LupinError::PngNoHiddenData)?;
+        let header = image.stealth_header().ok_or(LupinError::PngNoHiddenData)?;
+        let len = header.len;
+
+        if Self::STEALTH_HEADER_LEN + len > image.capacity_bits() / 8 {
+            return Err(LupinError::PngCorruptedData);
+        }
+
+        debug!("PNG: Found {} byte stealth payload in pixel data", len);
+
+        Ok(header.unmask(image.read_bytes(Self::STEALTH_HEADER_LEN, len), 0))
+    }
 }
 
 impl Default for PngEngine {
@@ -265,27 +796,55 @@ impl SteganographyEngine for PngEngine {
         ".png"
     }
 
+    fn capacity(&self, source_data: &[u8], mode: EmbedMode) -> Result<Capacity> {
+        match mode {
+            // A chunk can hold far more than any realistic payload
+            EmbedMode::Capacity => {
+                Self::find_iend_position(source_data)?;
+                Ok(Capacity::Unlimited)
+            }
+            EmbedMode::Stealth => {
+                let image = Self::decode_image(source_data)?;
+                let bytes = image.capacity_bits() / 8;
+                Ok(Capacity::Exact(
+                    bytes.saturating_sub(Self::STEALTH_HEADER_LEN),
+                ))
+            }
+        }
+    }
+
     fn embed(&self, source_data: &[u8], payload: &[u8], mode: EmbedMode) -> Result<Vec<u8>> {
         // Reject empty payloads so the embed contract is uniform across engines.
         if payload.is_empty() {
             return Err(LupinError::EmptyPayload);
         }
 
+        // Refuse to embed into a PNG that already carries a Lupin chunk;
+        // otherwise a second chunk would be appended and silently lost on
+        // extract (which returns the first match).
+        if Self::has_chunk(source_data, Self::LUPIN_CHUNK_TYPE) {
+            return Err(LupinError::EmbedCollision {
+                source: std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    "PNG already contains a Lupin chunk",
+                ),
+            });
+        }
+
         // Exhaustive so a future EmbedMode variant is a compile error here rather than
         // silently falling through to the capacity implementation below.
         match mode {
             EmbedMode::Capacity => {}
-            EmbedMode::Stealth => return Err(LupinError::StealthNotSupported { format: "PNG" }),
+            EmbedMode::Stealth => return self.embed_stealth(source_data, payload),
         }
 
-        // Refuse to embed into a PNG that already carries a Lupin chunk;
-        // otherwise a second chunk would be appended and silently lost on
-        // extract (which returns the first match).
-        if Self::has_chunk(source_data, Self::LUPIN_CHUNK_TYPE) {
+        // A stealth payload would be shadowed by the chunk on extract, so treat
+        // it as a collision too.
+        if Self::has_stealth_payload(source_data) {
             return Err(LupinError::EmbedCollision {
                 source: std::io::Error::new(
                     std::io::ErrorKind::AlreadyExists,
-                    "PNG already contains a Lupin chunk",
+                    "PNG pixel data already carries a Lupin payload",
                 ),
             });
         }
@@ -308,14 +867,49 @@ impl SteganographyEngine for PngEngine {
         Ok(output)
     }
 
-    fn extract(&self, source_data: &[u8]) -> Result<Vec<u8>> {
-        // Extract Base64-encoded data from our custom chunk
-        let encoded_data = Self::extract_custom_chunk(source_data, Self::LUPIN_CHUNK_TYPE)?;
+    fn probe(&self, source_data: &[u8]) -> Result<Option<Probe>> {
+        let Some(chunk) = Self::find_chunk(source_data, Self::LUPIN_CHUNK_TYPE) else {
+            return Ok(Self::probe_stealth(source_data));
+        };
+
+        let (size, prefix) = super::probe_base64(&source_data[chunk.start + 8..chunk.end - 4]);
+        Ok(Some(Probe::new(
+            EmbedMode::Capacity,
+            size,
+            vec![chunk],
+            prefix,
+        )))
+    }
+
+    fn remove(&self, source_data: &[u8]) -> Result<Vec<u8>> {
+        let Some(chunk) = Self::find_chunk(source_data, Self::LUPIN_CHUNK_TYPE) else {
+            return Self::remove_stealth(source_data);
+        };
+
+        debug!("PNG: Removing Lupin chunk at {}-{}", chunk.start, chunk.end);
+
+        let mut output = Vec::with_capacity(source_data.len() - chunk.len());
+        output.extend_from_slice(&source_data[..chunk.start]);
+        output.extend_from_slice(&source_data[chunk.end..]);
+        Ok(output)
+    }
 
-        // Decode from Base64
-        BASE64
-            .decode(&encoded_data)
-            .map_err(|_| LupinError::PngCorruptedData)
+    fn extract(&self, source_data: &[u8]) -> Result<Vec<u8>> {
+        // Extract Base64-encoded data from our custom chunk, falling back to the
+        // pixel LSBs when there is no chunk (stealth mode)
+        let (encoded_data, intact) =
+            match Self::extract_custom_chunk(source_data, Self::LUPIN_CHUNK_TYPE) {
+                Ok(chunk) => chunk,
+                Err(LupinError::PngNoHiddenData) => return Self::extract_stealth(source_data),
+                Err(e) => return Err(e),
+            };
+
+        // Decode from Base64; a damaged chunk is only returned if error correction
+        // can repair it
+        match BASE64.decode(&encoded_data) {
+            Ok(payload) if intact => Ok(payload),
+            _ => super::recover_base64(&encoded_data).ok_or(LupinError::PngCorruptedData),
+        }
     }
 }
 
@@ -548,19 +1142,355 @@ mod tests {
         assert_eq!(crc, 0xae426082);
     }
 
+    /// Builds a real, decodable PNG with the given color type and bit depth.
+    ///
+    /// Rows cycle through all five filter types so stealth embedding has to
+    /// unfilter and re-filter every one of them.
+    fn create_decodable_png(width: u32, height: u32, color_type: u8, bit_depth: u8) -> Vec<u8> {
+        let channels = match color_type {
+            0 => 1,
+            2 => 3,
+            3 => 1,
+            4 => 2,
+            _ => 4,
+        };
+        let stride = width as usize * channels * (bit_depth as usize / 8);
+
+        let mut ihdr = Vec::new();
+        ihdr.extend_from_slice(&width.to_be_bytes());
+        ihdr.extend_from_slice(&height.to_be_bytes());
+        ihdr.extend_from_slice(&[bit_depth, color_type, 0, 0, 0]);
+
+        let mut raw = Vec::new();
+        for row in 0..height as usize {
+            raw.push((row % 5) as u8); // Filter type
+
+            // Gradients with some variation so every filter has work to do
+            raw.extend((0..stride).map(|i| ((i * 7 + row * 13) % 251) as u8));
+        }
+
+        // The bytes above are the *filtered* data; any byte values are valid.
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&raw).unwrap();
+        let idat = encoder.finish().unwrap();
+
+        let mut png = Vec::new();
+        png.extend_from_slice(b"\x89PNG\r\n\x1a\n");
+        png.extend_from_slice(&PngEngine::create_chunk(b"IHDR", &ihdr));
+        png.extend_from_slice(&PngEngine::create_chunk(b"tEXt", b"Comment\0kept as is"));
+        png.extend_from_slice(&PngEngine::create_chunk(b"IDAT", &idat));
+        png.extend_from_slice(&PngEngine::create_chunk(b"IEND", &[]));
+        png
+    }
+
     #[test]
-    fn test_stealth_mode_not_supported() {
+    fn test_stealth_round_trip() {
         // Arrange
         let engine = PngEngine::new();
-        let source = create_minimal_png();
+        let source = create_decodable_png(32, 32, 2, 8);
+        let payload = b"Hidden in the least significant bits";
+
+        // Act
+        let embedded = engine
+            .embed(&source, payload, EmbedMode::Stealth)
+            .expect("Stealth embed should succeed");
+        let extracted = engine.extract(&embedded).expect("Extract should succeed");
+
+        // Assert - no chunk is added and the payload round-trips
+        assert_eq!(extracted, payload);
+        assert!(!PngEngine::has_chunk(
+            &embedded,
+            PngEngine::LUPIN_CHUNK_TYPE
+        ));
+        assert!(embedded
+            .windows(b"kept as is".len())
+            .any(|w| w == b"kept as is"));
+    }
+
+    #[test]
+    fn test_stealth_stream_is_whitened() {
+        // Arrange
+        let engine = PngEngine::new();
+        let source = create_decodable_png(32, 32, 2, 8);
+        let payload = b"LpEv looks like an envelope";
+
+        // Act
+        let first = engine.embed(&source, payload, EmbedMode::Stealth).unwrap();
+        let second = engine.embed(&source, payload, EmbedMode::Stealth).unwrap();
+
+        // Assert - neither the signature nor the payload shows in the LSBs, and the
+        // same payload is written differently each time
+        let stream = |data: &[u8]| {
+            let image = PngEngine::decode_image(data).unwrap();
+            image.read_bytes(0, image.capacity_bits() / 8)
+        };
+        let (first_bits, second_bits) = (stream(&first), stream(&second));
+        assert!(!first_bits.windows(4).any(|w| w == b"LpSt"));
+        assert!(!first_bits.windows(4).any(|w| w == b"LpEv"));
+        assert_ne!(first_bits, second_bits);
+        assert_eq!(engine.extract(&second).unwrap(), payload);
+    }
+
+    #[test]
+    fn test_stealth_changes_only_lsbs() {
+        // Arrange
+        let engine = PngEngine::new();
+        let source = create_decodable_png(40, 40, 6, 8); // RGBA
+        let payload: Vec<u8> = (0..=255).collect();
+
+        // Act
+        let embedded = engine.embed(&source, &payload, EmbedMode::Stealth).unwrap();
+
+        // Assert - every sample differs by at most its LSB, and alpha is untouched
+        let before = PngEngine::decode_image(&source).unwrap();
+        let after = PngEngine::decode_image(&embedded).unwrap();
+        assert_eq!(before.filters, after.filters);
+        for (i, (a, b)) in before.pixels.iter().zip(&after.pixels).enumerate() {
+            assert_eq!(a & !1, b & !1, "sample {} changed beyond its LSB", i);
+            if i % 4 == 3 {
+                assert_eq!(a, b, "alpha sample {} must not change", i);
+            }
+        }
+    }
+
+    #[test]
+    fn test_stealth_sixteen_bit_and_greyscale() {
+        // Arrange
+        let engine = PngEngine::new();
+        let payload = b"deep samples";
+
+        for (color_type, bit_depth) in [(0, 8), (0, 16), (2, 16), (4, 8), (6, 16)] {
+            let source = create_decodable_png(16, 16, color_type, bit_depth);
+
+            // Act
+            let embedded = engine.embed(&source, payload, EmbedMode::Stealth).unwrap();
+
+            // Assert
+            assert_eq!(
+                engine.extract(&embedded).unwrap(),
+                payload,
+                "round trip failed for color type {} / depth {}",
+                color_type,
+                bit_depth
+            );
+        }
+    }
+
+    #[test]
+    fn test_stealth_payload_too_large() {
+        // Arrange - 4x4 RGB holds 48 bits = 6 bytes, less than the 16-byte header
+        let engine = PngEngine::new();
+        let source = create_decodable_png(4, 4, 2, 8);
+
+        // Act
+        let result = engine.embed(&source, b"too big", EmbedMode::Stealth);
+
+        // Assert
+        assert!(matches!(
+            result,
+            Err(LupinError::PayloadTooLarge {
+                capacity: 0,
+                requested: 7
+            })
+        ));
+    }
+
+    #[test]
+    fn test_probe() {
+        // Arrange
+        let engine = PngEngine::new();
+        let source = create_decodable_png(16, 16, 2, 8);
+        let chunk = engine
+            .embed(&source, b"chunk payload", EmbedMode::Capacity)
+            .unwrap();
+        let pixels = engine
+            .embed(&source, b"pixels", EmbedMode::Stealth)
+            .unwrap();
+
+        // Act
+        let clean = engine.probe(&source).unwrap();
+        let capacity = engine.probe(&chunk).unwrap().unwrap();
+        let stealth = engine.probe(&pixels).unwrap().unwrap();
+
+        // Assert
+        assert_eq!(clean, None);
+        assert_eq!(capacity.mode, EmbedMode::Capacity);
+        assert_eq!(capacity.size, 13);
+        assert_eq!(capacity.prefix, b"chunk payload");
+        let range = capacity.ranges[0].clone();
+        assert_eq!(&chunk[range.start + 4..range.start + 8], b"lpNg");
+        assert_eq!(
+            &chunk[range.end..range.end + 8],
+            &[0, 0, 0, 0, b'I', b'E', b'N', b'D']
+        );
+        assert_eq!(stealth.mode, EmbedMode::Stealth);
+        assert_eq!(stealth.size, 6);
+        assert_eq!(stealth.prefix, b"pixels");
+        assert_eq!(
+            &pixels[stealth.ranges[0].start + 4..stealth.ranges[0].start + 8],
+            b"IDAT"
+        );
+    }
+
+    #[test]
+    fn test_remove() {
+        // Arrange
+        let engine = PngEngine::new();
+        let source = create_decodable_png(16, 16, 2, 8);
+        let chunk = engine
+            .embed(&source, b"chunk payload", EmbedMode::Capacity)
+            .unwrap();
+        let pixels = engine
+            .embed(&source, b"pixels", EmbedMode::Stealth)
+            .unwrap();
+
+        // Act
+        let restored = engine.remove(&chunk).unwrap();
+        let cleared = engine.remove(&pixels).unwrap();
+        let nothing = engine.remove(&source);
+
+        // Assert
+        assert_eq!(restored, source);
+        assert_eq!(engine.probe(&cleared).unwrap(), None);
+        let again = engine
+            .embed(&cleared, b"again", EmbedMode::Stealth)
+            .unwrap();
+        assert_eq!(engine.extract(&again).unwrap(), b"again");
+        assert!(matches!(nothing, Err(LupinError::PngNoHiddenData)));
+    }
+
+    #[test]
+    fn test_capacity() {
+        // Arrange - 16x16 RGB holds 768 bits = 96 bytes, minus the 16-byte header
+        let engine = PngEngine::new();
+        let source = create_decodable_png(16, 16, 2, 8);
+
+        // Act
+        let capacity = engine.capacity(&source, EmbedMode::Capacity).unwrap();
+        let stealth = engine.capacity(&source, EmbedMode::Stealth).unwrap();
+        let palette = engine.capacity(&create_decodable_png(16, 16, 3, 8), EmbedMode::Stealth);
+
+        // Assert
+        assert_eq!(capacity, Capacity::Unlimited);
+        assert_eq!(stealth, Capacity::Exact(80));
+        assert!(engine
+            .embed(&source, &[0xAB; 80], EmbedMode::Stealth)
+            .is_ok());
+        assert!(matches!(
+            engine.embed(&source, &[0xAB; 81], EmbedMode::Stealth),
+            Err(LupinError::PayloadTooLarge { capacity: 80, .. })
+        ));
+        assert!(matches!(
+            palette,
+            Err(LupinError::StealthUnsupportedCarrier { .. })
+        ));
+    }
+
+    #[test]
+    fn test_stealth_rejects_palette_images() {
+        // Arrange
+        let engine = PngEngine::new();
+        let source = create_decodable_png(16, 16, 3, 8);
 
         // Act
         let result = engine.embed(&source, b"payload", EmbedMode::Stealth);
 
-        // Assert - PNG has no stealth implementation yet
+        // Assert - flipping palette index LSBs would visibly change the image
         assert!(matches!(
             result,
-            Err(LupinError::StealthNotSupported { format: "PNG" })
+            Err(LupinError::StealthUnsupportedCarrier { format: "PNG", .. })
+        ));
+    }
+
+    #[test]
+    fn test_stealth_collisions() {
+        // Arrange
+        let engine = PngEngine::new();
+        let source = create_decodable_png(32, 32, 2, 8);
+        let stealthy = engine.embed(&source, b"first", EmbedMode::Stealth).unwrap();
+        let chunked = engine
+            .embed(&source, b"first", EmbedMode::Capacity)
+            .unwrap();
+
+        // Act & Assert - any existing payload blocks a second one, in either mode
+        for (carrier, mode) in [
+            (&stealthy, EmbedMode::Stealth),
+            (&stealthy, EmbedMode::Capacity),
+            (&chunked, EmbedMode::Stealth),
+        ] {
+            assert!(matches!(
+                engine.embed(carrier, b"second", mode),
+                Err(LupinError::EmbedCollision { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_stealth_header_decodes_only_needed_rows() {
+        // Arrange - a stealth carrier whose last scanline can't be decoded
+        let engine = PngEngine::new();
+        let source = create_decodable_png(32, 32, 2, 8);
+        let embedded = engine.embed(&source, b"probe", EmbedMode::Stealth).unwrap();
+        let mut image = PngEngine::decode_image(&embedded).unwrap();
+        image.filters[31] = 5;
+        let damaged = PngEngine::encode_image(&embedded, &image).unwrap();
+
+        // Act
+        let probe = engine.probe(&damaged).unwrap().unwrap();
+        let collision = engine.embed(&damaged, b"second", EmbedMode::Capacity);
+        let header_rows =
+            PngEngine::decode_stream_prefix(&source, PngEngine::STEALTH_HEADER_LEN).unwrap();
+
+        // Assert - the header and probe prefix are found without reaching the last row
+        assert!(PngEngine::decode_image(&damaged).is_err());
+        assert_eq!(probe.prefix, b"probe");
+        assert!(matches!(collision, Err(LupinError::EmbedCollision { .. })));
+        assert_eq!(header_rows.filters.len(), 2);
+    }
+
+    #[test]
+    fn test_extract_clean_decodable_png() {
+        // Arrange
+        let engine = PngEngine::new();
+        let source = create_decodable_png(32, 32, 2, 8);
+
+        // Act
+        let result = engine.extract(&source);
+
+        // Assert - neither a chunk nor a stealth header is present
+        assert!(matches!(result, Err(LupinError::PngNoHiddenData)));
+    }
+
+    #[test]
+    fn test_extract_damaged_chunk() {
+        // Arrange - damage a byte of the chunk, so its CRC no longer matches
+        let engine = PngEngine::new();
+        let source = create_minimal_png();
+        let plain = engine
+            .embed(&source, b"meet at dawn", EmbedMode::Capacity)
+            .unwrap();
+        let protected = crate::fec::encode(b"meet at dawn", 8).unwrap();
+        let corrected = engine
+            .embed(&source, &protected, EmbedMode::Capacity)
+            .unwrap();
+        let damage = |png: &[u8], offset: usize| {
+            let mut damaged = png.to_vec();
+            damaged[source.len() - 12 + 8 + offset] ^= 0x02;
+            damaged
+        };
+
+        // Act & Assert - only a payload with error correction is returned
+        assert!(matches!(
+            engine.extract(&damage(&plain, 10)),
+            Err(LupinError::PngCorruptedData)
         ));
+        let recovered = engine.extract(&damage(&corrected, 70)).unwrap();
+        assert_ne!(recovered, protected);
+        assert_eq!(crate::fec::decode(&recovered).unwrap().0, b"meet at dawn");
+
+        // A chunk cut short, here by its last 6 payload bytes, is handed over too
+        let truncated = &corrected[..corrected.len() - 24];
+        let recovered = engine.extract(truncated).unwrap();
+        assert_eq!(crate::fec::decode(&recovered).unwrap().0, b"meet at dawn");
     }
 }
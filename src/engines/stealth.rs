@@ -0,0 +1,181 @@
+// Copyright 2025 Niclas Hedam
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Whitened header shared by the stealth-mode bit streams
+//!
+//! ```text
+//! [8 bytes: Nonce][4 bytes: Magic][4 bytes: Big-endian Payload Length][N bytes: Payload]
+//!                 |<------------- XORed with the keystream ------------->|
+//! ```
+//!
+//! The nonce is random for every embed, and keystream block `i` is
+//! `SHA-256("lupin-stealth" || nonce || i)`, with `i` a big-endian u64. Everything after
+//! the nonce is XORed with it, so the bits look random and no two carriers share a
+//! byte pattern at a fixed position. See the [`crate::engines`] documentation for what
+//! that does and doesn't protect against.
+
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use sha2::{Digest, Sha256};
+
+/// Length of the random nonce starting every stealth bit stream
+pub(crate) const NONCE_LEN: usize = 8;
+
+/// Nonce, magic and 4-byte payload length
+pub(crate) const HEADER_LEN: usize = NONCE_LEN + 4 + 4;
+
+/// Domain separation for the keystream
+const DOMAIN: &[u8] = b"lupin-stealth";
+
+/// Keystream bytes per SHA-256 block
+const BLOCK_LEN: usize = 32;
+
+/// Keystream that whitens a stealth bit stream after its nonce
+pub(crate) struct Whitening {
+    nonce: [u8; NONCE_LEN],
+}
+
+impl Whitening {
+    /// Whitening under a fresh random nonce, for embedding
+    pub(crate) fn random() -> Self {
+        let mut nonce = [0; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+        Self { nonce }
+    }
+
+    /// Whitening under the nonce read back from a carrier
+    pub(crate) fn from_nonce(nonce: &[u8]) -> Self {
+        let mut bytes = [0; NONCE_LEN];
+        bytes.copy_from_slice(&nonce[..NONCE_LEN]);
+        Self { nonce: bytes }
+    }
+
+    /// The nonce, to be stored in front of the whitened bytes
+    pub(crate) fn nonce(&self) -> &[u8] {
+        &self.nonce
+    }
+
+    /// XORs `data` with the keystream, starting `position` bytes after the nonce;
+    /// applying it twice restores the original bytes
+    pub(crate) fn apply(&self, data: &mut [u8], position: usize) {
+        let mut block = [0; BLOCK_LEN];
+        let mut block_index = None;
+        for (i, byte) in data.iter_mut().enumerate() {
+            let offset = position + i;
+            let index = (offset / BLOCK_LEN) as u64;
+            if block_index != Some(index) {
+                block = Sha256::new()
+                    .chain_update(DOMAIN)
+                    .chain_update(self.nonce)
+                    .chain_update(index.to_be_bytes())
+                    .finalize()
+                    .into();
+                block_index = Some(index);
+            }
+            *byte ^= block[offset % BLOCK_LEN];
+        }
+    }
+}
+
+/// A stealth header read back from a carrier
+pub(crate) struct Header {
+    /// Declared payload length
+    pub(crate) len: usize,
+    whitening: Whitening,
+}
+
+impl Header {
+    /// Unwhitens payload bytes read from the carrier, `offset` bytes into the payload
+    pub(crate) fn unmask(&self, mut payload: Vec<u8>, offset: usize) -> Vec<u8> {
+        self.whitening
+            .apply(&mut payload, HEADER_LEN - NONCE_LEN + offset);
+        payload
+    }
+}
+
+/// Builds the whitened bit stream for `payload`: nonce, then magic, length and
+/// payload XORed with the keystream
+pub(crate) fn seal(magic: &[u8], payload: &[u8]) -> Vec<u8> {
+    let whitening = Whitening::random();
+    let mut stream = Vec::with_capacity(HEADER_LEN + payload.len());
+    stream.extend_from_slice(whitening.nonce());
+    stream.extend_from_slice(magic);
+    stream.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    stream.extend_from_slice(payload);
+    whitening.apply(&mut stream[NONCE_LEN..], 0);
+    stream
+}
+
+/// Reads the first [`HEADER_LEN`] bytes of a bit stream, returning the header if they
+/// unwhiten to `magic`
+pub(crate) fn open(magic: &[u8], stream: &[u8]) -> Option<Header> {
+    if stream.len() < HEADER_LEN {
+        return None;
+    }
+    let whitening = Whitening::from_nonce(&stream[..NONCE_LEN]);
+    let mut fields = stream[NONCE_LEN..HEADER_LEN].to_vec();
+    whitening.apply(&mut fields, 0);
+    if &fields[..4] != magic {
+        return None;
+    }
+    let len = u32::from_be_bytes([fields[4], fields[5], fields[6], fields[7]]) as usize;
+    Some(Header { len, whitening })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open() {
+        // Arrange
+        let payload = vec![0x42; 100];
+
+        // Act
+        let stream = seal(b"LpXx", &payload);
+        let header = open(b"LpXx", &stream).unwrap();
+
+        // Assert
+        assert_eq!(stream.len(), HEADER_LEN + payload.len());
+        assert_eq!(header.len, payload.len());
+        assert_eq!(header.unmask(stream[HEADER_LEN..].to_vec(), 0), payload);
+        assert_eq!(
+            header.unmask(stream[HEADER_LEN + 40..].to_vec(), 40),
+            payload[40..]
+        );
+    }
+
+    #[test]
+    fn test_seal_hides_magic_and_payload() {
+        // Act
+        let first = seal(b"LpXx", &[0; 64]);
+        let second = seal(b"LpXx", &[0; 64]);
+
+        // Assert - nothing fixed is left, and every embed differs
+        assert!(!first.windows(4).any(|window| window == b"LpXx"));
+        assert!(first[HEADER_LEN..].iter().any(|&b| b != 0));
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn test_open_rejects_other_streams() {
+        // Arrange
+        let stream = seal(b"LpXx", b"payload");
+
+        // Act & Assert
+        assert!(open(b"LpYy", &stream).is_none());
+        assert!(open(b"LpXx", &stream[..HEADER_LEN - 1]).is_none());
+        assert!(open(b"LpXx", &[0; HEADER_LEN]).is_none());
+    }
+}
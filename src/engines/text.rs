@@ -0,0 +1,743 @@
+// Copyright 2025 Niclas Hedam
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Plain-text steganography engine using zero-width characters and trailing whitespace
+//!
+//! # How It Works
+//!
+//! Text has no container to hide data in, but some characters don't show: zero-width
+//! Unicode characters, and whitespace at the end of a line. This engine encodes the
+//! payload bits as those, in any UTF-8 document such as an email, Markdown or source
+//! code.
+//!
+//! Both modes write the same bit stream, MSB first, starting with a small header so
+//! extraction can detect the payload without being told the mode. The nonce is random
+//! and everything after it is whitened, so neither the zero-width run nor the
+//! whitespace spells out a fixed pattern (see the [`crate::engines`] documentation):
+//!
+//! ```text
+//! [8 bytes: Nonce][4 bytes: "LpTx"][4 bytes: Big-endian Payload Length][N bytes: Raw Payload]
+//! ```
+//!
+//! ## Capacity Mode: Zero-Width Characters
+//!
+//! The bits are written as U+200B (zero width space) for 0 and U+200C (zero width
+//! non-joiner) for 1, between two U+FEFF (zero width no-break space) delimiters,
+//! and the run is inserted at the end of the first line:
+//!
+//! ```text
+//! [U+FEFF][U+200B or U+200C per bit][U+FEFF]
+//! ```
+//!
+//! Every bit takes three bytes of UTF-8, but there is no limit on the run. Editors
+//! and browsers render nothing for it; any tool that shows invisible characters
+//! reveals it.
+//!
+//! ## Stealth Mode: Trailing Whitespace
+//!
+//! Like SNOW, [`EmbedMode::Stealth`] leaves the visible text alone and hides one
+//! byte at the end of each line, before its line break:
+//!
+//! ```text
+//! [Tab][Space for 0 or Tab for 1, 8 times][Tab]
+//! ```
+//!
+//! The chunk has a fixed length, so removing it restores the line exactly, whatever
+//! whitespace it already ended with, and it ends with a tab so Markdown doesn't read
+//! it as a hard line break. Capacity is one byte per line minus the 16-byte header.
+//!
+//! ## Detection
+//!
+//! Text has no magic bytes, so the engine overrides
+//! [`SteganographyEngine::matches`] with a heuristic: valid UTF-8 with at least one
+//! line break and no control characters other than tab, line feed, carriage return
+//! and form feed. The router tries it after every other engine.
+
+use super::stealth;
+use crate::error::{LupinError, Result};
+use crate::{Capacity, EmbedMode, Probe, SteganographyEngine};
+use log::debug;
+use std::ops::Range;
+
+/// Plain-text steganography engine
+///
+/// In capacity mode, inserts the payload as a run of zero-width characters at the
+/// end of the first line. In stealth mode, the payload goes into whitespace appended
+/// to the lines instead, one byte per line.
+///
+/// See the module documentation for details on how data is stored and limitations.
+pub struct TextEngine;
+
+/// A payload found in the text
+#[derive(Debug)]
+struct Found {
+    /// The mode that hid it
+    mode: EmbedMode,
+    /// Byte ranges of the text holding it
+    ranges: Vec<Range<usize>>,
+    /// The payload length its header declares
+    declared_len: usize,
+    /// The payload bytes present and unwhitened, at most `declared_len`
+    payload: Vec<u8>,
+}
+
+impl Found {
+    /// Whether fewer bytes were found than the header declares
+    fn is_truncated(&self) -> bool {
+        self.payload.len() < self.declared_len
+    }
+}
+
+impl TextEngine {
+    /// Creates a new text engine
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Magic at the start of the bit stream
+    const MAGIC: &'static [u8] = b"LpTx";
+
+    /// Length of the bit stream header: nonce, magic and payload length
+    const HEADER_LEN: usize = stealth::HEADER_LEN;
+
+    /// Zero width space, a 0 bit
+    const ZERO: &'static [u8] = "\u{200B}".as_bytes();
+
+    /// Zero width non-joiner, a 1 bit
+    const ONE: &'static [u8] = "\u{200C}".as_bytes();
+
+    /// Zero width no-break space, delimiting the run
+    const DELIMITER: &'static [u8] = "\u{FEFF}".as_bytes();
+
+    /// Length of the whitespace chunk hiding one byte at the end of a line
+    const CHUNK_LEN: usize = 10;
+
+    /// Prefixes the payload with the bit stream header and whitens it
+    fn bit_stream(payload: &[u8]) -> Result<Vec<u8>> {
+        if u32::try_from(payload.len()).is_err() {
+            return Err(LupinError::PayloadTooLarge {
+                capacity: u32::MAX as usize,
+                requested: payload.len(),
+            });
+        }
+        Ok(stealth::seal(Self::MAGIC, payload))
+    }
+
+    /// Builds the payload found from a bit stream, or `None` if it has no header
+    fn found(mode: EmbedMode, ranges: Vec<Range<usize>>, stream: &[u8]) -> Option<Found> {
+        let header = stealth::open(Self::MAGIC, stream)?;
+        let end = stream
+            .len()
+            .min(Self::HEADER_LEN.saturating_add(header.len));
+        Some(Found {
+            mode,
+            ranges,
+            declared_len: header.len,
+            payload: header.unmask(stream[Self::HEADER_LEN..end].to_vec(), 0),
+        })
+    }
+
+    /// Fails unless the carrier is UTF-8
+    fn check_utf8(data: &[u8]) -> Result<()> {
+        std::str::from_utf8(data)
+            .map(|_| ())
+            .map_err(|e| LupinError::TextInvalidFormat {
+                reason: format!("Not valid UTF-8: {e}"),
+            })
+    }
+
+    /// Byte offset of the end of each line, before its line break; a last line
+    /// without a line break counts if it isn't empty
+    fn line_ends(data: &[u8]) -> Vec<usize> {
+        let mut ends: Vec<usize> = data
+            .iter()
+            .enumerate()
+            .filter(|&(_, &byte)| byte == b'\n')
+            .map(|(pos, _)| {
+                if pos > 0 && data[pos - 1] == b'\r' {
+                    pos - 1
+                } else {
+                    pos
+                }
+            })
+            .collect();
+        if data.last().is_some_and(|&byte| byte != b'\n') {
+            ends.push(data.len());
+        }
+        ends
+    }
+
+    /// Finds a zero-width run whose bits start with the header
+    fn find_zero_width(data: &[u8]) -> Option<Found> {
+        let mut from = 0;
+        while let Some(offset) = data[from..]
+            .windows(Self::DELIMITER.len())
+            .position(|window| window == Self::DELIMITER)
+        {
+            let start = from + offset;
+            let mut pos = start + Self::DELIMITER.len();
+            let mut stream = Vec::new();
+            let mut byte = 0u8;
+            let mut bits = 0;
+            loop {
+                let bit = match data.get(pos..pos + 3) {
+                    Some(symbol) if symbol == Self::ZERO => 0,
+                    Some(symbol) if symbol == Self::ONE => 1,
+                    _ => break,
+                };
+                byte = byte << 1 | bit;
+                bits += 1;
+                if bits == 8 {
+                    stream.push(byte);
+                    bits = 0;
+                }
+                pos += 3;
+            }
+            if data[pos..].starts_with(Self::DELIMITER) {
+                pos += Self::DELIMITER.len();
+            }
+
+            let run = start..pos;
+            if let Some(found) = Self::found(EmbedMode::Capacity, vec![run], &stream) {
+                return Some(found);
+            }
+            from = start + Self::DELIMITER.len();
+        }
+        None
+    }
+
+    /// Decodes the whitespace chunk ending at `end`, if there is one
+    fn read_chunk(data: &[u8], end: usize) -> Option<u8> {
+        let chunk = data.get(end.checked_sub(Self::CHUNK_LEN)?..end)?;
+        if chunk[0] != b'\t' || chunk[Self::CHUNK_LEN - 1] != b'\t' {
+            return None;
+        }
+        chunk[1..Self::CHUNK_LEN - 1]
+            .iter()
+            .try_fold(0u8, |byte, &symbol| match symbol {
+                b' ' => Some(byte << 1),
+                b'\t' => Some(byte << 1 | 1),
+                _ => None,
+            })
+    }
+
+    /// Finds whitespace chunks at the ends of the first lines, starting with the header
+    fn find_trailing(data: &[u8]) -> Option<Found> {
+        let mut ranges = Vec::new();
+        let mut stream = Vec::new();
+        for end in Self::line_ends(data) {
+            let Some(byte) = Self::read_chunk(data, end) else {
+                break;
+            };
+            ranges.push(end - Self::CHUNK_LEN..end);
+            stream.push(byte);
+
+            // Stop once the header's length is covered, leaving later lines alone
+            let declared = stealth::open(Self::MAGIC, &stream)
+                .map(|header| Self::HEADER_LEN.saturating_add(header.len));
+            if declared.is_some_and(|declared| stream.len() >= declared) {
+                break;
+            }
+        }
+        Self::found(EmbedMode::Stealth, ranges, &stream)
+    }
+
+    /// Finds a payload in either mode
+    fn find_payload(data: &[u8]) -> Option<Found> {
+        Self::find_zero_width(data).or_else(|| Self::find_trailing(data))
+    }
+
+    /// Inserts the payload as a zero-width run at the end of the first line
+    fn embed_zero_width(data: &[u8], payload: &[u8]) -> Result<Vec<u8>> {
+        let stream = Self::bit_stream(payload)?;
+        let mut run = Vec::with_capacity(stream.len() * 24 + 2 * Self::DELIMITER.len());
+        run.extend_from_slice(Self::DELIMITER);
+        for byte in stream {
+            for shift in (0..8).rev() {
+                let bit = byte >> shift & 1;
+                run.extend_from_slice(if bit == 1 { Self::ONE } else { Self::ZERO });
+            }
+        }
+        run.extend_from_slice(Self::DELIMITER);
+
+        let pos = Self::line_ends(data).first().copied().unwrap_or(data.len());
+        debug!(
+            "Text: Inserting {} byte zero-width run at position {}",
+            run.len(),
+            pos
+        );
+
+        let mut output = Vec::with_capacity(data.len() + run.len());
+        output.extend_from_slice(&data[..pos]);
+        output.extend_from_slice(&run);
+        output.extend_from_slice(&data[pos..]);
+        Ok(output)
+    }
+
+    /// Appends one byte of the payload to the end of each line as whitespace
+    fn embed_trailing(data: &[u8], payload: &[u8]) -> Result<Vec<u8>> {
+        let ends = Self::line_ends(data);
+        let capacity = ends.len().saturating_sub(Self::HEADER_LEN);
+        if payload.len() > capacity {
+            return Err(LupinError::PayloadTooLarge {
+                capacity,
+                requested: payload.len(),
+            });
+        }
+        let stream = Self::bit_stream(payload)?;
+
+        debug!(
+            "Text: Appending whitespace to {} of {} lines",
+            stream.len(),
+            ends.len()
+        );
+
+        let mut output = Vec::with_capacity(data.len() + stream.len() * Self::CHUNK_LEN);
+        let mut copied = 0;
+        for (&end, byte) in ends.iter().zip(stream) {
+            output.extend_from_slice(&data[copied..end]);
+            output.push(b'\t');
+            output.extend((0..8).rev().map(|shift| match byte >> shift & 1 {
+                1 => b'\t',
+                _ => b' ',
+            }));
+            output.push(b'\t');
+            copied = end;
+        }
+        output.extend_from_slice(&data[copied..]);
+        Ok(output)
+    }
+}
+
+impl Default for TextEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SteganographyEngine for TextEngine {
+    fn magic_bytes(&self) -> &[u8] {
+        // Text has no signature; see matches()
+        b""
+    }
+
+    fn matches(&self, data: &[u8]) -> bool {
+        let is_text = |byte: &u8| !byte.is_ascii_control() || b"\t\n\r\x0C".contains(byte);
+        data.contains(&b'\n') && data.iter().all(is_text) && std::str::from_utf8(data).is_ok()
+    }
+
+    fn format_name(&self) -> &str {
+        "Text"
+    }
+
+    fn format_ext(&self) -> &str {
+        ".txt"
+    }
+
+    fn capacity(&self, source_data: &[u8], mode: EmbedMode) -> Result<Capacity> {
+        Self::check_utf8(source_data)?;
+        match mode {
+            // Every bit costs three bytes, but the run can be as long as needed
+            EmbedMode::Capacity => Ok(Capacity::Unlimited),
+            EmbedMode::Stealth => Ok(Capacity::Exact(
+                Self::line_ends(source_data)
+                    .len()
+                    .saturating_sub(Self::HEADER_LEN),
+            )),
+        }
+    }
+
+    fn embed(&self, source_data: &[u8], payload: &[u8], mode: EmbedMode) -> Result<Vec<u8>> {
+        // Reject empty payloads so the embed contract is uniform across engines.
+        if payload.is_empty() {
+            return Err(LupinError::EmptyPayload);
+        }
+
+        Self::check_utf8(source_data)?;
+
+        // Refuse to embed into text that already carries a Lupin payload in either
+        // mode; a second one would be silently lost on extract (which returns the
+        // first match).
+        if Self::find_payload(source_data).is_some() {
+            return Err(LupinError::EmbedCollision {
+                source: std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    "Text already contains a Lupin payload",
+                ),
+            });
+        }
+
+        match mode {
+            EmbedMode::Capacity => Self::embed_zero_width(source_data, payload),
+            EmbedMode::Stealth => Self::embed_trailing(source_data, payload),
+        }
+    }
+
+    fn probe(&self, source_data: &[u8]) -> Result<Option<Probe>> {
+        Ok(Self::find_payload(source_data)
+            .map(|found| Probe::new(found.mode, found.declared_len, found.ranges, found.payload)))
+    }
+
+    fn remove(&self, source_data: &[u8]) -> Result<Vec<u8>> {
+        let found = Self::find_payload(source_data).ok_or(LupinError::TextNoHiddenData)?;
+
+        debug!(
+            "Text: Removing {:?} payload from {} ranges",
+            found.mode,
+            found.ranges.len()
+        );
+
+        // The ranges are in order and don't overlap
+        let mut output = Vec::with_capacity(source_data.len());
+        let mut copied = 0;
+        for range in &found.ranges {
+            output.extend_from_slice(&source_data[copied..range.start]);
+            copied = range.end;
+        }
+        output.extend_from_slice(&source_data[copied..]);
+        Ok(output)
+    }
+
+    fn restores_stealth(&self) -> bool {
+        // The whitespace was only appended to the lines, so cutting it out is exact
+        true
+    }
+
+    fn extract(&self, source_data: &[u8]) -> Result<Vec<u8>> {
+        let found = Self::find_payload(source_data).ok_or(LupinError::TextNoHiddenData)?;
+
+        debug!(
+            "Text: Found {:?} payload of {} bytes",
+            found.mode, found.declared_len
+        );
+
+        // A payload cut short is only returned if error correction can repair it
+        if found.is_truncated() && !crate::fec::is_fec(&found.payload) {
+            return Err(LupinError::TextCorruptedData);
+        }
+        Ok(found.payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_text() -> Vec<u8> {
+        let mut text = b"# Meeting notes\r\n\nAgenda:  \n".to_vec();
+        for item in 1..=28 {
+            text.extend_from_slice(format!("- Item {item}: réunion\n").as_bytes());
+        }
+        text.extend_from_slice(b"Last line without a break");
+        text
+    }
+
+    #[test]
+    fn test_text_engine_info() {
+        let engine = TextEngine::new();
+
+        assert_eq!(engine.magic_bytes(), b"");
+        assert_eq!(engine.format_name(), "Text");
+        assert_eq!(engine.format_ext(), ".txt");
+    }
+
+    #[test]
+    fn test_matches() {
+        let engine = TextEngine::new();
+
+        assert!(engine.matches(&create_test_text()));
+        assert!(engine.matches("Ünïcødé\r\n\tindented\x0C".as_bytes()));
+        assert!(!engine.matches(b"no line break"));
+        assert!(!engine.matches(b"binary\n\x00data"));
+        assert!(!engine.matches(b"invalid\n\xFF\xFE"));
+        assert!(!engine.matches(b""));
+    }
+
+    #[test]
+    fn test_embed_and_extract_zero_width() {
+        // Arrange
+        let engine = TextEngine::new();
+        let text = create_test_text();
+        let payload = b"Secret message!";
+
+        // Act
+        let embedded = engine.embed(&text, payload, EmbedMode::Capacity).unwrap();
+        let extracted = engine.extract(&embedded).unwrap();
+
+        // Assert - the run sits at the end of the first line and the text stays UTF-8
+        assert_eq!(extracted, payload);
+        let embedded = String::from_utf8(embedded).unwrap();
+        assert!(embedded.starts_with("# Meeting notes\u{FEFF}"));
+        assert!(embedded.contains("\u{FEFF}\r\n\nAgenda:  \n"));
+        let visible: String = embedded
+            .chars()
+            .filter(|c| !matches!(c, '\u{200B}' | '\u{200C}' | '\u{FEFF}'))
+            .collect();
+        assert_eq!(visible.as_bytes(), text);
+    }
+
+    #[test]
+    fn test_embed_and_extract_trailing_whitespace() {
+        // Arrange
+        let engine = TextEngine::new();
+        let text = create_test_text();
+        let payload = b"Secret";
+
+        // Act
+        let embedded = engine.embed(&text, payload, EmbedMode::Stealth).unwrap();
+        let extracted = engine.extract(&embedded).unwrap();
+
+        // Assert - only whitespace was added, before the line breaks
+        assert_eq!(extracted, payload);
+        let embedded = String::from_utf8(embedded).unwrap();
+        let original = String::from_utf8(text).unwrap();
+        assert!(embedded.starts_with("# Meeting notes\t"));
+        assert!(embedded.contains("\t\r\n"));
+        for (line, original) in embedded.lines().zip(original.lines()) {
+            assert_eq!(line.trim_end(), original.trim_end());
+        }
+    }
+
+    #[test]
+    fn test_zero_width_without_line_break() {
+        let engine = TextEngine::new();
+
+        let embedded = engine
+            .embed(b"single line", b"payload", EmbedMode::Capacity)
+            .unwrap();
+
+        assert!(embedded.starts_with(b"single line\xEF\xBB\xBF"));
+        assert_eq!(engine.extract(&embedded).unwrap(), b"payload");
+        assert_eq!(engine.remove(&embedded).unwrap(), b"single line");
+    }
+
+    #[test]
+    fn test_remove_restores_original() {
+        let engine = TextEngine::new();
+        let text = create_test_text();
+
+        for mode in [EmbedMode::Capacity, EmbedMode::Stealth] {
+            // Act
+            let embedded = engine.embed(&text, b"payload", mode).unwrap();
+            let removed = engine.remove(&embedded).unwrap();
+
+            // Assert
+            assert_eq!(removed, text, "{mode:?}");
+            assert!(matches!(
+                engine.extract(&removed),
+                Err(LupinError::TextNoHiddenData)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_stealth_fills_every_line() {
+        // Arrange - the last line has no line break but still holds a byte
+        let engine = TextEngine::new();
+        let text = create_test_text();
+        let capacity = engine.capacity(&text, EmbedMode::Stealth).unwrap();
+        let payload = vec![0xA5; 16];
+
+        // Act
+        let embedded = engine.embed(&text, &payload, EmbedMode::Stealth).unwrap();
+
+        // Assert
+        assert_eq!(capacity, Capacity::Exact(16));
+        let last_line = &embedded[embedded.len() - 35..];
+        assert!(last_line.starts_with(b"Last line without a break\t"));
+        assert!(last_line[26..].iter().all(|byte| b" \t".contains(byte)));
+        assert_eq!(engine.extract(&embedded).unwrap(), payload);
+        assert_eq!(engine.remove(&embedded).unwrap(), text);
+    }
+
+    #[test]
+    fn test_stealth_payload_too_large() {
+        let engine = TextEngine::new();
+
+        let result = engine.embed(&create_test_text(), &[0x42; 17], EmbedMode::Stealth);
+
+        assert!(matches!(
+            result,
+            Err(LupinError::PayloadTooLarge {
+                capacity: 16,
+                requested: 17
+            })
+        ));
+    }
+
+    #[test]
+    fn test_existing_trailing_whitespace_is_kept() {
+        // Arrange - lines already ending in whitespace, including a chunk-like one
+        let engine = TextEngine::new();
+        let mut text = Vec::new();
+        for _ in 0..20 {
+            text.extend_from_slice(b"code();  \t\n");
+        }
+        text.extend_from_slice(b"end\t        \t\n");
+
+        // Act
+        let embedded = engine.embed(&text, b"hi", EmbedMode::Stealth).unwrap();
+
+        // Assert
+        assert_eq!(engine.extract(&embedded).unwrap(), b"hi");
+        assert_eq!(engine.remove(&embedded).unwrap(), text);
+    }
+
+    #[test]
+    fn test_embed_collision() {
+        let engine = TextEngine::new();
+
+        for first in [EmbedMode::Capacity, EmbedMode::Stealth] {
+            let embedded_once = engine.embed(&create_test_text(), b"First", first).unwrap();
+
+            for second in [EmbedMode::Capacity, EmbedMode::Stealth] {
+                let result = engine.embed(&embedded_once, b"Second", second);
+                assert!(matches!(result, Err(LupinError::EmbedCollision { .. })));
+            }
+        }
+    }
+
+    #[test]
+    fn test_no_hidden_data() {
+        let engine = TextEngine::new();
+        let text = create_test_text();
+
+        assert!(matches!(
+            engine.extract(&text),
+            Err(LupinError::TextNoHiddenData)
+        ));
+        assert!(matches!(
+            engine.remove(&text),
+            Err(LupinError::TextNoHiddenData)
+        ));
+    }
+
+    #[test]
+    fn test_stray_zero_width_characters_ignored() {
+        // Arrange - a byte order mark and zero-width characters that aren't a payload
+        let engine = TextEngine::new();
+        let text = "\u{FEFF}Title\u{200B}\u{200C}\n\u{FEFF}\u{200B}\u{FEFF}\n".as_bytes();
+
+        // Act
+        let embedded = engine.embed(text, b"payload", EmbedMode::Capacity).unwrap();
+
+        // Assert
+        assert_eq!(engine.probe(text).unwrap(), None);
+        assert_eq!(engine.extract(&embedded).unwrap(), b"payload");
+        assert_eq!(engine.remove(&embedded).unwrap(), text);
+    }
+
+    #[test]
+    fn test_truncated_payload() {
+        // Arrange - drop the last bit and closing delimiter of the run
+        let engine = TextEngine::new();
+        let embedded = engine
+            .embed(b"line\n", b"payload", EmbedMode::Capacity)
+            .unwrap();
+        let mut damaged = embedded[..embedded.len() - 7].to_vec();
+        damaged.push(b'\n');
+
+        // Act
+        let result = engine.extract(&damaged);
+
+        // Assert - without error correction it is reported rather than returned
+        assert!(matches!(result, Err(LupinError::TextCorruptedData)));
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let engine = TextEngine::new();
+
+        for mode in [EmbedMode::Capacity, EmbedMode::Stealth] {
+            assert!(matches!(
+                engine.embed(b"caf\xE9\n", b"payload", mode),
+                Err(LupinError::TextInvalidFormat { .. })
+            ));
+            assert!(matches!(
+                engine.capacity(b"caf\xE9\n", mode),
+                Err(LupinError::TextInvalidFormat { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_empty_payload_rejected() {
+        let engine = TextEngine::new();
+        assert!(matches!(
+            engine.embed(&create_test_text(), b"", EmbedMode::Capacity),
+            Err(LupinError::EmptyPayload)
+        ));
+    }
+
+    #[test]
+    fn test_truncated_file_does_not_panic() {
+        let engine = TextEngine::new();
+
+        for mode in [EmbedMode::Capacity, EmbedMode::Stealth] {
+            let embedded = engine
+                .embed(&create_test_text(), &[0x42; 12], mode)
+                .unwrap();
+
+            // Every prefix either parses or fails cleanly
+            for len in 0..embedded.len() {
+                let _ = engine.probe(&embedded[..len]);
+                let _ = engine.remove(&embedded[..len]);
+                let _ = engine.extract(&embedded[..len]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_probe() {
+        // Arrange
+        let engine = TextEngine::new();
+        let text = create_test_text();
+        let zero_width = engine
+            .embed(&text, b"text payload for probing", EmbedMode::Capacity)
+            .unwrap();
+        let trailing = engine.embed(&text, b"probe", EmbedMode::Stealth).unwrap();
+
+        // Act
+        let clean = engine.probe(&text).unwrap();
+        let zero_width = engine.probe(&zero_width).unwrap().unwrap();
+        let trailing = engine.probe(&trailing).unwrap().unwrap();
+
+        // Assert - one range for the run, one per line for the whitespace
+        assert_eq!(clean, None);
+        assert_eq!(zero_width.mode, EmbedMode::Capacity);
+        assert_eq!(zero_width.size, 24);
+        assert_eq!(zero_width.prefix, b"text payload for");
+        assert_eq!(zero_width.ranges, vec![15..15 + 6 + 40 * 8 * 3]);
+        assert_eq!(trailing.mode, EmbedMode::Stealth);
+        assert_eq!(trailing.size, 5);
+        assert_eq!(trailing.prefix, b"probe");
+        assert_eq!(trailing.ranges.len(), 21);
+        assert_eq!(trailing.ranges[0], 15..25);
+    }
+
+    #[test]
+    fn test_capacity() {
+        let engine = TextEngine::new();
+        let text = create_test_text();
+
+        assert_eq!(
+            engine.capacity(&text, EmbedMode::Capacity).unwrap(),
+            Capacity::Unlimited
+        );
+        assert_eq!(
+            engine.capacity(b"one\ntwo\n", EmbedMode::Stealth).unwrap(),
+            Capacity::Exact(0)
+        );
+    }
+}
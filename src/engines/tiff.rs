@@ -0,0 +1,854 @@
+// Copyright 2025 Niclas Hedam
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! TIFF steganography engine using a private tag
+//!
+//! # How It Works
+//!
+//! A TIFF file is a chain of image file directories (IFDs), one per page, whose
+//! tags point at the image data by absolute offset. Readers skip tags they don't
+//! know, so this engine hides data in a tag from the private range.
+//!
+//! ## Storage Format
+//!
+//! The payload is appended to the file, and a copy of the first IFD with one more
+//! tag, 65100 (`0xFE4C`, type `UNDEFINED`), pointing at it is appended after that:
+//!
+//! ```text
+//! [Original file][Pad][8 bytes: Original Length][8 bytes: Original IFD Offset][N bytes: Raw Payload][Pad][New IFD]
+//! ```
+//!
+//! The header is pointed at the new IFD. Nothing else moves, so the offsets of the
+//! existing strips, tiles and other IFDs stay valid without fixups, and the copied
+//! IFD links on to the second page as before. The two lengths are written in the
+//! file's byte order and let removal cut the appended bytes off and point the
+//! header back at the original IFD, restoring the file exactly.
+//!
+//! ## TIFF Structure
+//!
+//! - **Header**: `II` (little-endian) or `MM` (big-endian), then 42 and a 32-bit
+//!   offset of the first IFD, or, for BigTIFF, 43, the offset size 8, a reserved
+//!   zero and a 64-bit offset
+//! - **IFD**: an entry count (16-bit, 64-bit in BigTIFF), the entries sorted by tag,
+//!   and the offset of the next IFD, 0 for the last one. An entry is a tag, a type,
+//!   a count and the value itself if it fits in 4 bytes (8 in BigTIFF), or else the
+//!   offset of the value
+//!
+//! The IFD chain is walked with bounds checks and a loop guard, so a truncated file
+//! or a cycle of IFDs ends the walk without panicking. Every IFD is searched for the
+//! tag.
+//!
+//! ## Stealth Mode
+//!
+//! TIFF image data may be stored in any of a dozen compressions and sample layouts,
+//! and the engine has no decoder for them, so [`EmbedMode::Stealth`] returns
+//! [`LupinError::StealthNotSupported`].
+
+use crate::error::{LupinError, Result};
+use crate::{Capacity, EmbedMode, Probe, SteganographyEngine};
+use log::debug;
+use std::collections::HashSet;
+use std::ops::Range;
+
+/// TIFF steganography engine
+///
+/// Stores the payload in a private tag of the first IFD, appending both the payload
+/// and a copy of the IFD to the file, so the existing image data is left untouched.
+/// Supports both byte orders and BigTIFF.
+///
+/// See the module documentation for details on how data is stored and limitations.
+pub struct TiffEngine;
+
+/// Byte order and offset size of a TIFF file
+#[derive(Debug, Clone, Copy)]
+struct Format {
+    /// `II` byte order
+    little_endian: bool,
+    /// BigTIFF, with 64-bit counts and offsets
+    big: bool,
+}
+
+impl Format {
+    /// Reads an unsigned integer of `data.len()` bytes in the file's byte order
+    fn read(&self, data: &[u8]) -> u64 {
+        let fold = |acc: u64, &byte: &u8| acc << 8 | byte as u64;
+        if self.little_endian {
+            data.iter().rev().fold(0, fold)
+        } else {
+            data.iter().fold(0, fold)
+        }
+    }
+
+    /// Writes the low `len` bytes of `value` in the file's byte order
+    fn write(&self, output: &mut Vec<u8>, value: u64, len: usize) {
+        let bytes = value.to_le_bytes();
+        if self.little_endian {
+            output.extend_from_slice(&bytes[..len]);
+        } else {
+            output.extend(bytes[..len].iter().rev());
+        }
+    }
+
+    /// Size of an offset or value field
+    fn offset_len(&self) -> usize {
+        if self.big {
+            8
+        } else {
+            4
+        }
+    }
+
+    /// Size of the entry count of an IFD
+    fn count_len(&self) -> usize {
+        if self.big {
+            8
+        } else {
+            2
+        }
+    }
+
+    /// Size of an IFD entry: tag, type, count and value
+    fn entry_len(&self) -> usize {
+        4 + 2 * self.offset_len()
+    }
+
+    /// Largest offset the file can hold
+    fn max_offset(&self) -> u64 {
+        if self.big {
+            u64::MAX
+        } else {
+            u32::MAX as u64
+        }
+    }
+}
+
+/// An image file directory found by [`TiffEngine::parse_ifds`]
+#[derive(Debug)]
+struct Ifd {
+    /// Byte range of the whole IFD, from its entry count to its next-IFD offset
+    range: Range<usize>,
+    /// Number of entries
+    count: usize,
+}
+
+/// Our tag, found by [`TiffEngine::parse_ifds`]
+#[derive(Debug)]
+struct Tag {
+    /// Index of the IFD holding it
+    ifd: usize,
+    /// Byte offset of its entry
+    entry: usize,
+    /// Byte range of its value, clamped to the file
+    value: Range<usize>,
+    /// The length its entry declares
+    declared_len: usize,
+}
+
+impl Tag {
+    /// Whether the value was cut short by the end of the file
+    fn is_truncated(&self) -> bool {
+        self.value.len() < self.declared_len
+    }
+}
+
+/// The IFDs of a TIFF the engine cares about
+#[derive(Debug)]
+struct Layout {
+    /// Byte order and offset size
+    format: Format,
+    /// Offset of the first IFD, from the header
+    first_ifd: u64,
+    /// The chain of IFDs, starting with the one the header points at
+    ifds: Vec<Ifd>,
+    /// The first of our tags
+    lupin: Option<Tag>,
+}
+
+impl TiffEngine {
+    /// Creates a new TIFF engine
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Our private tag, 65100, in the range reserved for reusable private tags
+    const LUPIN_TAG: u16 = 0xFE4C;
+
+    /// Field type of our tag: `UNDEFINED`, one byte per count
+    const TYPE_UNDEFINED: u16 = 7;
+
+    /// Length of the header written in front of the payload: the original length
+    /// and the original first IFD offset
+    const BLOCK_HEADER_LEN: usize = 16;
+
+    /// Reads the header: byte order, classic or BigTIFF, and the first IFD offset
+    fn parse_header(data: &[u8]) -> Result<(Format, u64)> {
+        let invalid = |reason: &str| LupinError::TiffInvalidFormat {
+            reason: reason.to_string(),
+        };
+
+        let little_endian = match data.get(..2) {
+            Some(b"II") => true,
+            Some(b"MM") => false,
+            _ => return Err(invalid("File too short or missing II/MM byte order")),
+        };
+        let mut format = Format {
+            little_endian,
+            big: false,
+        };
+        match data.get(2..4).map(|version| format.read(version)) {
+            Some(42) if data.len() >= 8 => Ok((format, format.read(&data[4..8]))),
+            Some(43) if data.len() >= 16 => {
+                if format.read(&data[4..6]) != 8 || format.read(&data[6..8]) != 0 {
+                    return Err(invalid("BigTIFF offset size isn't 8"));
+                }
+                format.big = true;
+                Ok((format, format.read(&data[8..16])))
+            }
+            _ => Err(invalid("File too short or unknown TIFF version")),
+        }
+    }
+
+    /// Walks the chain of IFDs of a TIFF file.
+    ///
+    /// Fails if the header or the first IFD can't be read. The walk stops at an IFD
+    /// that runs past the end of the file or was already visited, so a truncated
+    /// file or a cycle can't make it panic or loop. A tag value that runs past the
+    /// end of the file is still reported, clamped, so a cut-off payload can be read
+    /// as far as it goes.
+    fn parse_ifds(data: &[u8]) -> Result<Layout> {
+        let (format, first) = Self::parse_header(data)?;
+        let (count_len, entry_len, offset_len) =
+            (format.count_len(), format.entry_len(), format.offset_len());
+
+        let mut ifds = Vec::new();
+        let mut lupin = None;
+        let mut visited = HashSet::new();
+        let mut next = first;
+        while next != 0 && visited.insert(next) {
+            let Some(start) = usize::try_from(next).ok().filter(|&start| {
+                start
+                    .checked_add(count_len)
+                    .is_some_and(|end| end <= data.len())
+            }) else {
+                break;
+            };
+            let count = format.read(&data[start..start + count_len]) as usize;
+            let Some(end) = count
+                .checked_mul(entry_len)
+                .and_then(|len| (start + count_len).checked_add(len))
+                .and_then(|end| end.checked_add(offset_len))
+                .filter(|&end| end <= data.len())
+            else {
+                break;
+            };
+
+            let entries = start + count_len;
+            if lupin.is_none() {
+                lupin = (0..count)
+                    .map(|index| entries + index * entry_len)
+                    .find(|&entry| format.read(&data[entry..entry + 2]) == Self::LUPIN_TAG as u64)
+                    .map(|entry| Self::read_tag(format, data, ifds.len(), entry));
+            }
+            next = format.read(&data[end - offset_len..end]);
+            ifds.push(Ifd {
+                range: start..end,
+                count,
+            });
+        }
+
+        if ifds.is_empty() {
+            return Err(LupinError::TiffInvalidFormat {
+                reason: "First IFD is missing or runs past the end of the file".to_string(),
+            });
+        }
+
+        Ok(Layout {
+            format,
+            first_ifd: first,
+            ifds,
+            lupin,
+        })
+    }
+
+    /// Reads the entry of our tag, finding its value inline or at its offset
+    fn read_tag(format: Format, data: &[u8], ifd: usize, entry: usize) -> Tag {
+        let offset_len = format.offset_len();
+        let count_field = entry + 4;
+        let value_field = count_field + offset_len;
+
+        // Only a single-byte type counts bytes; anything else isn't ours
+        let declared_len =
+            if format.read(&data[entry + 2..entry + 4]) == Self::TYPE_UNDEFINED as u64 {
+                usize::try_from(format.read(&data[count_field..value_field])).unwrap_or(usize::MAX)
+            } else {
+                0
+            };
+        let value = if declared_len <= offset_len {
+            value_field..value_field + declared_len
+        } else {
+            let start = usize::try_from(format.read(&data[value_field..value_field + offset_len]))
+                .unwrap_or(usize::MAX)
+                .min(data.len());
+            start..start.saturating_add(declared_len).min(data.len())
+        };
+
+        Tag {
+            ifd,
+            entry,
+            value,
+            declared_len,
+        }
+    }
+
+    /// Points the header at a new first IFD
+    fn set_first_ifd(format: Format, output: &mut [u8], offset: u64) {
+        // After the version in a classic TIFF, after the offset size in a BigTIFF
+        let field = if format.big { 8 } else { 4 };
+        let mut bytes = Vec::with_capacity(format.offset_len());
+        format.write(&mut bytes, offset, format.offset_len());
+        output[field..field + bytes.len()].copy_from_slice(&bytes);
+    }
+
+    /// Pads `output` with a zero byte to the word boundary TIFF offsets must fall on
+    fn pad_to_word(output: &mut Vec<u8>) {
+        if output.len() % 2 == 1 {
+            output.push(0);
+        }
+    }
+
+    /// Where embedding started, if the file still ends the way [`embed`] left it: the
+    /// original length and first IFD offset, or `None` if it was rewritten since
+    ///
+    /// [`embed`]: SteganographyEngine::embed
+    fn appended_at(layout: &Layout, tag: &Tag, data: &[u8]) -> Option<(usize, u64)> {
+        let format = layout.format;
+        let value = &data[tag.value.clone()];
+        if tag.ifd != 0 || tag.is_truncated() || value.len() < Self::BLOCK_HEADER_LEN {
+            return None;
+        }
+
+        let original_len = usize::try_from(format.read(&value[..8])).ok()?;
+        let original_ifd = format.read(&value[8..16]);
+        let ifd = &layout.ifds[0].range;
+        (original_len <= tag.value.start
+            && tag.value.end <= ifd.start
+            && ifd.end == data.len()
+            && original_ifd < original_len as u64)
+            .then_some((original_len, original_ifd))
+    }
+}
+
+impl Default for TiffEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SteganographyEngine for TiffEngine {
+    fn magic_bytes(&self) -> &[u8] {
+        b"II*\0"
+    }
+
+    fn matches(&self, data: &[u8]) -> bool {
+        // Both byte orders, classic and BigTIFF
+        [b"II*\0", b"MM\0*", b"II+\0", b"MM\0+"]
+            .iter()
+            .any(|magic| data.starts_with(*magic))
+    }
+
+    fn format_name(&self) -> &str {
+        "TIFF"
+    }
+
+    fn format_ext(&self) -> &str {
+        ".tiff"
+    }
+
+    fn capacity(&self, source_data: &[u8], mode: EmbedMode) -> Result<Capacity> {
+        match mode {
+            // Only the 32-bit offsets of a classic TIFF limit it
+            EmbedMode::Capacity => {
+                Self::parse_ifds(source_data)?;
+                Ok(Capacity::Unlimited)
+            }
+            EmbedMode::Stealth => Err(LupinError::StealthNotSupported { format: "TIFF" }),
+        }
+    }
+
+    fn embed(&self, source_data: &[u8], payload: &[u8], mode: EmbedMode) -> Result<Vec<u8>> {
+        // Reject empty payloads so the embed contract is uniform across engines.
+        if payload.is_empty() {
+            return Err(LupinError::EmptyPayload);
+        }
+
+        // Refuse to embed into a TIFF that already carries a Lupin tag; a second one
+        // would be silently lost on extract (which returns the first match).
+        let layout = Self::parse_ifds(source_data)?;
+        if layout.lupin.is_some() {
+            return Err(LupinError::EmbedCollision {
+                source: std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    "TIFF already contains a Lupin tag",
+                ),
+            });
+        }
+
+        // Exhaustive so a future EmbedMode variant is a compile error here rather than
+        // silently falling through to the capacity implementation below.
+        match mode {
+            EmbedMode::Capacity => {}
+            EmbedMode::Stealth => return Err(LupinError::StealthNotSupported { format: "TIFF" }),
+        }
+
+        let format = layout.format;
+        let (count_len, entry_len, offset_len) =
+            (format.count_len(), format.entry_len(), format.offset_len());
+        let ifd = &layout.ifds[0];
+        if !format.big && ifd.count >= u16::MAX as usize {
+            return Err(LupinError::TiffInvalidFormat {
+                reason: "First IFD has no room for another entry".to_string(),
+            });
+        }
+
+        let mut output = source_data.to_vec();
+        Self::pad_to_word(&mut output);
+        let value_at = output.len();
+        let value_len = Self::BLOCK_HEADER_LEN + payload.len();
+        let ifd_at = (value_at + value_len + 1) / 2 * 2;
+        let ifd_end = ifd_at + count_len + (ifd.count + 1) * entry_len + offset_len;
+        if ifd_end as u64 > format.max_offset() {
+            return Err(LupinError::PayloadTooLarge {
+                capacity: (format.max_offset() as usize).saturating_sub(ifd_end - payload.len()),
+                requested: payload.len(),
+            });
+        }
+
+        debug!(
+            "TIFF: Appending {} byte value at {} and first IFD at {}",
+            value_len, value_at, ifd_at
+        );
+
+        format.write(&mut output, source_data.len() as u64, 8);
+        format.write(&mut output, layout.first_ifd, 8);
+        output.extend_from_slice(payload);
+        Self::pad_to_word(&mut output);
+
+        // The entries are sorted by tag, so ours goes before the first higher one
+        let entries = ifd.range.start + count_len;
+        let position = (0..ifd.count)
+            .find(|&index| {
+                let entry = entries + index * entry_len;
+                format.read(&source_data[entry..entry + 2]) > Self::LUPIN_TAG as u64
+            })
+            .unwrap_or(ifd.count);
+        let split = entries + position * entry_len;
+
+        format.write(&mut output, ifd.count as u64 + 1, count_len);
+        output.extend_from_slice(&source_data[entries..split]);
+        format.write(&mut output, Self::LUPIN_TAG as u64, 2);
+        format.write(&mut output, Self::TYPE_UNDEFINED as u64, 2);
+        format.write(&mut output, value_len as u64, offset_len);
+        format.write(&mut output, value_at as u64, offset_len);
+        output.extend_from_slice(&source_data[split..ifd.range.end]);
+
+        Self::set_first_ifd(format, &mut output, ifd_at as u64);
+        Ok(output)
+    }
+
+    fn probe(&self, source_data: &[u8]) -> Result<Option<Probe>> {
+        let Some(tag) = Self::parse_ifds(source_data)?.lupin else {
+            return Ok(None);
+        };
+
+        let payload = (tag.value.start + Self::BLOCK_HEADER_LEN).min(tag.value.end);
+        let prefix_end = tag.value.end.min(payload + Probe::PREFIX_LEN);
+        Ok(Some(Probe::new(
+            EmbedMode::Capacity,
+            tag.declared_len.saturating_sub(Self::BLOCK_HEADER_LEN),
+            vec![tag.value],
+            source_data[payload..prefix_end].to_vec(),
+        )))
+    }
+
+    fn remove(&self, source_data: &[u8]) -> Result<Vec<u8>> {
+        let layout = Self::parse_ifds(source_data)?;
+        let tag = layout.lupin.as_ref().ok_or(LupinError::TiffNoHiddenData)?;
+        let format = layout.format;
+        let (count_len, entry_len, offset_len) =
+            (format.count_len(), format.entry_len(), format.offset_len());
+
+        // Cut off what embedding appended and point the header back at the old IFD
+        if let Some((original_len, original_ifd)) = Self::appended_at(&layout, tag, source_data) {
+            debug!("TIFF: Truncating appended tag and IFD at {}", original_len);
+
+            let mut output = source_data[..original_len].to_vec();
+            Self::set_first_ifd(format, &mut output, original_ifd);
+            return Ok(output);
+        }
+
+        // The file was rewritten since: drop the entry in place and clear the value
+        debug!("TIFF: Removing Lupin tag entry at {}", tag.entry);
+
+        let ifd = &layout.ifds[tag.ifd];
+        let mut output = source_data.to_vec();
+        if tag.declared_len > offset_len {
+            output[tag.value.clone()].fill(0);
+        }
+        output.copy_within(tag.entry + entry_len..ifd.range.end, tag.entry);
+        output[ifd.range.end - entry_len..ifd.range.end].fill(0);
+        let mut count = Vec::with_capacity(count_len);
+        format.write(&mut count, ifd.count as u64 - 1, count_len);
+        output[ifd.range.start..ifd.range.start + count_len].copy_from_slice(&count);
+        Ok(output)
+    }
+
+    fn extract(&self, source_data: &[u8]) -> Result<Vec<u8>> {
+        // Read the payload from our tag, in whichever IFD it is
+        let tag = Self::parse_ifds(source_data)?
+            .lupin
+            .ok_or(LupinError::TiffNoHiddenData)?;
+
+        debug!(
+            "TIFF: Found Lupin tag value at {}-{}",
+            tag.value.start, tag.value.end
+        );
+
+        if tag.value.len() < Self::BLOCK_HEADER_LEN {
+            return Err(LupinError::TiffCorruptedData);
+        }
+
+        // A value cut short is only returned if error correction can repair it
+        let payload = &source_data[tag.value.start + Self::BLOCK_HEADER_LEN..tag.value.end];
+        if tag.is_truncated() && !crate::fec::is_fec(payload) {
+            return Err(LupinError::TiffCorruptedData);
+        }
+        Ok(payload.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tag numbers of the IFD entries the test TIFFs carry
+    const IFD_TAGS: [u16; 8] = [256, 257, 258, 262, 273, 278, 279, 65500];
+
+    /// A grayscale TIFF with one 2x2 strip per page and a private tag above ours
+    fn create_tiff(little_endian: bool, big: bool, pages: usize) -> Vec<u8> {
+        let format = Format { little_endian, big };
+        let offset_len = format.offset_len();
+
+        let mut tiff = if little_endian { b"II" } else { b"MM" }.to_vec();
+        if big {
+            format.write(&mut tiff, 43, 2);
+            format.write(&mut tiff, 8, 2);
+            format.write(&mut tiff, 0, 2);
+        } else {
+            format.write(&mut tiff, 42, 2);
+        }
+        let mut next_field = tiff.len();
+        format.write(&mut tiff, 0, offset_len);
+
+        for page in 0..pages {
+            let strip_at = tiff.len() as u64;
+            tiff.extend_from_slice(&[page as u8, 0x40, 0x80, 0xC0]);
+
+            let ifd_at = tiff.len() as u64;
+            let mut field = Vec::new();
+            format.write(&mut field, ifd_at, offset_len);
+            tiff[next_field..next_field + offset_len].copy_from_slice(&field);
+
+            format.write(&mut tiff, IFD_TAGS.len() as u64, format.count_len());
+            for (tag, field_type, value) in [
+                (256, 3, 2),
+                (257, 3, 2),
+                (258, 3, 8),
+                (262, 3, 1),
+                (273, 4, strip_at),
+                (278, 3, 2),
+                (279, 4, 4),
+                (65500, 3, 7),
+            ] {
+                // Values are left-justified in the value field
+                let value_len = if field_type == 3 { 2 } else { 4 };
+                format.write(&mut tiff, tag, 2);
+                format.write(&mut tiff, field_type, 2);
+                format.write(&mut tiff, 1, offset_len);
+                format.write(&mut tiff, value, value_len);
+                tiff.resize(tiff.len() + offset_len - value_len, 0);
+            }
+            next_field = tiff.len();
+            format.write(&mut tiff, 0, offset_len);
+        }
+        tiff
+    }
+
+    /// Every combination of byte order and classic or BigTIFF, with two pages
+    fn create_tiffs() -> Vec<Vec<u8>> {
+        [(true, false), (false, false), (true, true), (false, true)]
+            .into_iter()
+            .map(|(little_endian, big)| create_tiff(little_endian, big, 2))
+            .collect()
+    }
+
+    /// Lists the tags of every IFD in the chain
+    fn ifd_tags(tiff: &[u8]) -> Vec<Vec<u64>> {
+        let layout = TiffEngine::parse_ifds(tiff).unwrap();
+        let format = layout.format;
+        layout
+            .ifds
+            .iter()
+            .map(|ifd| {
+                let entries = ifd.range.start + format.count_len();
+                (0..ifd.count)
+                    .map(|index| {
+                        let entry = entries + index * format.entry_len();
+                        format.read(&tiff[entry..entry + 2])
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_tiff_magic_bytes() {
+        let engine = TiffEngine::new();
+        assert_eq!(engine.magic_bytes(), b"II*\0");
+        for tiff in create_tiffs() {
+            assert!(engine.matches(&tiff));
+        }
+        assert!(!engine.matches(b"II\0*"));
+    }
+
+    #[test]
+    fn test_tiff_format_info() {
+        let engine = TiffEngine::new();
+        assert_eq!(engine.format_name(), "TIFF");
+        assert_eq!(engine.format_ext(), ".tiff");
+    }
+
+    #[test]
+    fn test_embed_and_extract() {
+        let engine = TiffEngine::new();
+        let payload = b"Secret message hidden in TIFF!";
+
+        for tiff in create_tiffs() {
+            // Act
+            let embedded = engine.embed(&tiff, payload, EmbedMode::Capacity).unwrap();
+            let extracted = engine.extract(&embedded).unwrap();
+
+            // Assert - the original bytes stay put apart from the first IFD offset,
+            // the tag is sorted in, and the second page is still linked
+            assert_eq!(extracted, payload);
+            assert_eq!(&embedded[16..tiff.len()], &tiff[16..]);
+            let mut tags: Vec<u64> = IFD_TAGS.iter().map(|&tag| tag as u64).collect();
+            let second_page = tags.clone();
+            tags.insert(7, TiffEngine::LUPIN_TAG as u64);
+            assert_eq!(ifd_tags(&embedded), [tags, second_page]);
+        }
+    }
+
+    #[test]
+    fn test_remove() {
+        let engine = TiffEngine::new();
+
+        // Removal cuts off everything embedding appended and restores the header
+        for tiff in create_tiffs() {
+            for payload in [&b"odd"[..], b"even"] {
+                let embedded = engine.embed(&tiff, payload, EmbedMode::Capacity).unwrap();
+                assert_eq!(engine.remove(&embedded).unwrap(), tiff);
+            }
+            assert!(matches!(
+                engine.remove(&tiff),
+                Err(LupinError::TiffNoHiddenData)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_remove_after_rewrite() {
+        // Arrange - bytes appended after embedding, so truncating would lose them
+        let engine = TiffEngine::new();
+        let tiff = create_tiff(false, false, 1);
+        let mut embedded = engine
+            .embed(&tiff, b"payload", EmbedMode::Capacity)
+            .unwrap();
+        embedded.extend_from_slice(b"appended later");
+
+        // Act
+        let removed = engine.remove(&embedded).unwrap();
+
+        // Assert - the entry is dropped in place and the payload cleared
+        assert_eq!(removed.len(), embedded.len());
+        assert!(removed.ends_with(b"appended later"));
+        assert!(!removed.windows(7).any(|window| window == b"payload"));
+        assert_eq!(ifd_tags(&removed), ifd_tags(&tiff));
+        assert!(matches!(
+            engine.extract(&removed),
+            Err(LupinError::TiffNoHiddenData)
+        ));
+    }
+
+    #[test]
+    fn test_ifd_cycle() {
+        // Arrange - the second page links back to the first
+        let engine = TiffEngine::new();
+        let mut tiff = create_tiff(true, false, 2);
+        let first_ifd = tiff[4..8].to_vec();
+        let len = tiff.len();
+        tiff[len - 4..].copy_from_slice(&first_ifd);
+
+        // Act
+        let embedded = engine
+            .embed(&tiff, b"payload", EmbedMode::Capacity)
+            .unwrap();
+
+        // Assert
+        assert_eq!(ifd_tags(&tiff).len(), 2);
+        assert_eq!(engine.extract(&embedded).unwrap(), b"payload");
+        assert_eq!(engine.remove(&embedded).unwrap(), tiff);
+    }
+
+    #[test]
+    fn test_embed_collision() {
+        let engine = TiffEngine::new();
+        let embedded_once = engine
+            .embed(&create_tiff(true, false, 1), b"First", EmbedMode::Capacity)
+            .unwrap();
+
+        let result = engine.embed(&embedded_once, b"Second", EmbedMode::Capacity);
+        assert!(matches!(result, Err(LupinError::EmbedCollision { .. })));
+    }
+
+    #[test]
+    fn test_extract_without_data() {
+        let engine = TiffEngine::new();
+
+        let result = engine.extract(&create_tiff(true, true, 2));
+        assert!(matches!(result, Err(LupinError::TiffNoHiddenData)));
+    }
+
+    #[test]
+    fn test_invalid_tiff() {
+        let engine = TiffEngine::new();
+
+        // Unknown version, a bad BigTIFF offset size and a first IFD past the end
+        for not_tiff in [
+            &b"II\x2A\x01\x08\x00\x00\x00"[..],
+            b"II\x2B\x00\x04\x00\x00\x00\x10\x00\x00\x00\x00\x00\x00\x00",
+            b"MM\x00\x2A\x00\x00\x01\x00",
+        ] {
+            let result = engine.embed(not_tiff, b"payload", EmbedMode::Capacity);
+            assert!(matches!(result, Err(LupinError::TiffInvalidFormat { .. })));
+        }
+    }
+
+    #[test]
+    fn test_empty_payload_rejected() {
+        let engine = TiffEngine::new();
+        assert!(matches!(
+            engine.embed(&create_tiff(true, false, 1), b"", EmbedMode::Capacity),
+            Err(LupinError::EmptyPayload)
+        ));
+    }
+
+    #[test]
+    fn test_stealth_not_supported() {
+        let engine = TiffEngine::new();
+        let tiff = create_tiff(true, false, 1);
+
+        assert!(matches!(
+            engine.embed(&tiff, b"payload", EmbedMode::Stealth),
+            Err(LupinError::StealthNotSupported { format: "TIFF" })
+        ));
+        assert!(matches!(
+            engine.capacity(&tiff, EmbedMode::Stealth),
+            Err(LupinError::StealthNotSupported { format: "TIFF" })
+        ));
+    }
+
+    #[test]
+    fn test_truncated_file_does_not_panic() {
+        let engine = TiffEngine::new();
+
+        for tiff in create_tiffs() {
+            let embedded = engine
+                .embed(&tiff, &[0x42; 40], EmbedMode::Capacity)
+                .unwrap();
+
+            // Every prefix either parses or fails cleanly
+            for len in 0..embedded.len() {
+                let _ = engine.probe(&embedded[..len]);
+                let _ = engine.remove(&embedded[..len]);
+                let _ = engine.extract(&embedded[..len]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_truncated_value() {
+        // Arrange - a tag whose value runs past the end of the file
+        let engine = TiffEngine::new();
+        let embedded = engine
+            .embed(
+                &create_tiff(true, false, 1),
+                &[0x42; 40],
+                EmbedMode::Capacity,
+            )
+            .unwrap();
+        let layout = TiffEngine::parse_ifds(&embedded).unwrap();
+        let tag = layout.lupin.unwrap();
+        let mut cut = embedded.clone();
+        cut[tag.entry + 4..tag.entry + 8].copy_from_slice(&1000u32.to_le_bytes());
+
+        // Act & Assert - without error correction it is reported rather than returned
+        assert!(matches!(
+            engine.extract(&cut),
+            Err(LupinError::TiffCorruptedData)
+        ));
+        assert_eq!(engine.probe(&cut).unwrap().unwrap().size, 984);
+    }
+
+    #[test]
+    fn test_probe() {
+        // Arrange
+        let engine = TiffEngine::new();
+        let tiff = create_tiff(false, true, 2);
+        let embedded = engine
+            .embed(&tiff, b"tag payload for probing", EmbedMode::Capacity)
+            .unwrap();
+
+        // Act
+        let clean = engine.probe(&tiff).unwrap();
+        let probe = engine.probe(&embedded).unwrap().unwrap();
+
+        // Assert - the range covers the value, length header included
+        assert_eq!(clean, None);
+        assert_eq!(probe.mode, EmbedMode::Capacity);
+        assert_eq!(probe.size, 23);
+        assert_eq!(probe.prefix, b"tag payload for ");
+        assert_eq!(probe.ranges, vec![tiff.len()..tiff.len() + 39]);
+    }
+
+    #[test]
+    fn test_capacity() {
+        let engine = TiffEngine::new();
+        assert_eq!(
+            engine
+                .capacity(&create_tiff(true, false, 1), EmbedMode::Capacity)
+                .unwrap(),
+            Capacity::Unlimited
+        );
+    }
+}
@@ -0,0 +1,982 @@
+// Copyright 2025 Niclas Hedam
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! WAV steganography engine using a custom RIFF chunk
+//!
+//! # How It Works
+//!
+//! A WAV file is a RIFF container: a list of chunks, each with a four-character id.
+//! Players read the `fmt ` and `data` chunks and skip every chunk they don't know,
+//! so this engine hides data in a chunk of its own.
+//!
+//! ## Storage Format
+//!
+//! We add a chunk called `LUPN` after the last chunk of the file:
+//!
+//! ```text
+//! ["LUPN"][4 bytes: Little-endian Length][N bytes: Raw Payload][Pad byte if N is odd]
+//! ```
+//!
+//! The size field of the `RIFF` header is grown to cover the new chunk, and shrunk
+//! again when it is removed. Some writers leave out the pad byte of an odd-sized
+//! last chunk; it is added ahead of `LUPN` so the chunk starts where the walk looks
+//! for it, and removed along with it.
+//!
+//! ## RIFF Structure
+//!
+//! - **Header** (12 bytes): `RIFF`, the little-endian size of everything after the
+//!   size field, and the form type `WAVE`
+//! - **Chunks**: a four-character id, a little-endian size and the chunk data,
+//!   padded to an even length. Besides `fmt ` and `data`, files commonly carry
+//!   `LIST` (metadata), `bext` (broadcast extension) or `fact` chunks
+//!
+//! Foreign chunks are skipped by their size, whatever their id, so only a chunk
+//! that is really ours is read. A chunk that runs past the end of the file ends the
+//! scan without panicking.
+//!
+//! ## Stealth Mode
+//!
+//! [`EmbedMode::Stealth`] leaves the chunk list alone and hides the payload in the
+//! least-significant bits of the samples instead. 16-bit PCM is stored raw, so
+//! nothing needs decoding:
+//!
+//! 1. The payload is written, MSB first, into the LSB of every sample of the `data`
+//!    chunk in stored order, which interleaves the channels.
+//! 2. Every other byte of the file is copied verbatim.
+//!
+//! The bit stream starts with a small header so extraction can detect the payload
+//! without being told the mode. The nonce is random and everything after it is
+//! whitened, so the sample LSBs carry no fixed pattern (see the [`crate::engines`]
+//! documentation):
+//!
+//! ```text
+//! [8 bytes: Nonce][4 bytes: "LpWv"][4 bytes: Big-endian Payload Length][N bytes: Raw Payload]
+//! ```
+//!
+//! Each sample changes by at most one step out of 65536, far below audibility.
+//! Capacity is one bit per sample (e.g. `seconds * 44100 * 2 / 8` bytes for CD-quality
+//! stereo) minus the 16-byte header. Only 16-bit PCM is supported.
+
+use super::stealth;
+use crate::error::{LupinError, Result};
+use crate::{Capacity, EmbedMode, Probe, SteganographyEngine};
+use log::debug;
+use std::ops::Range;
+
+/// WAV steganography engine
+///
+/// In capacity mode, stores the payload in a custom `LUPN` RIFF chunk, which players
+/// skip, and fixes up the RIFF size. In stealth mode, the payload is written into the
+/// least-significant bits of the 16-bit PCM samples instead.
+///
+/// See the module documentation for details on how data is stored and limitations.
+pub struct WavEngine;
+
+/// A chunk found by [`WavEngine::parse_chunks`]
+#[derive(Debug, Clone)]
+struct Chunk {
+    /// Byte range of the whole chunk, from its id to its pad byte, clamped to the file
+    range: Range<usize>,
+    /// Byte range of the chunk data, clamped to the file
+    body: Range<usize>,
+    /// The size the chunk header declares
+    declared_len: usize,
+}
+
+impl Chunk {
+    /// Whether the chunk data was cut short by the end of the file
+    fn is_truncated(&self) -> bool {
+        self.body.len() < self.declared_len
+    }
+}
+
+/// The chunks of a WAV the engine cares about
+#[derive(Debug, Default)]
+struct Layout {
+    /// Byte offset just past the last complete chunk
+    end: usize,
+    /// Whether the scan stopped at a chunk that runs past the end of the file
+    truncated: bool,
+    /// The `fmt ` chunk
+    format: Option<Chunk>,
+    /// The `data` chunk
+    samples: Option<Chunk>,
+    /// The first `LUPN` chunk
+    lupin: Option<Chunk>,
+    /// Whether the `LUPN` chunk follows the pad byte of an odd-sized chunk
+    lupin_after_pad: bool,
+}
+
+/// The samples of a 16-bit PCM WAV, ready for LSB embedding
+struct DecodedAudio {
+    /// Byte range of the sample data in the file, whole samples only
+    samples: Range<usize>,
+    /// The whole file, modified in place
+    data: Vec<u8>,
+}
+
+impl DecodedAudio {
+    /// Total number of payload bits the audio can carry, including the header
+    fn capacity_bits(&self) -> usize {
+        self.samples.len() / 2
+    }
+
+    /// Byte offset in the file of the byte holding carrier bit number `bit`
+    fn carrier_offset(&self, bit: usize) -> usize {
+        // Samples are little-endian, so the low byte comes first
+        self.samples.start + bit * 2
+    }
+
+    /// Reads `len` bytes from the carrier bits, starting at byte `start` of the bit stream
+    fn read_bytes(&self, start: usize, len: usize) -> Vec<u8> {
+        (0..len)
+            .map(|i| {
+                (0..8).fold(0u8, |byte, b| {
+                    let offset = self.carrier_offset((start + i) * 8 + b);
+                    (byte << 1) | (self.data[offset] & 1)
+                })
+            })
+            .collect()
+    }
+
+    /// Writes `data` into the carrier bits, starting at byte 0 of the bit stream
+    fn write_bytes(&mut self, data: &[u8]) {
+        for (i, &byte) in data.iter().enumerate() {
+            for b in 0..8 {
+                let bit = (byte >> (7 - b)) & 1;
+                let offset = self.carrier_offset(i * 8 + b);
+                self.data[offset] = (self.data[offset] & !1) | bit;
+            }
+        }
+    }
+
+    /// Reads the stealth header, returning it if the magic matches
+    fn stealth_header(&self) -> Option<stealth::Header> {
+        if self.capacity_bits() < WavEngine::STEALTH_HEADER_LEN * 8 {
+            return None;
+        }
+
+        stealth::open(
+            WavEngine::STEALTH_MAGIC,
+            &self.read_bytes(0, WavEngine::STEALTH_HEADER_LEN),
+        )
+    }
+}
+
+impl WavEngine {
+    /// Creates a new WAV engine
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Length of the RIFF header: `RIFF`, size and form type
+    const RIFF_HEADER_LEN: usize = 12;
+
+    /// Length of a chunk header: id and size
+    const CHUNK_HEADER_LEN: usize = 8;
+
+    /// Form type of a WAV file, at offset 8
+    const FORM_TYPE: &'static [u8] = b"WAVE";
+
+    /// Id of our custom chunk
+    const LUPIN_CHUNK_ID: &'static [u8] = b"LUPN";
+
+    /// PCM format tag
+    const WAVE_FORMAT_PCM: u16 = 0x0001;
+
+    /// Extensible format tag, whose sub-format GUID starts with the real format tag
+    const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+    /// Signature at the start of a stealth-mode bit stream
+    const STEALTH_MAGIC: &'static [u8] = b"LpWv";
+
+    /// Stealth header size: nonce + signature + 4-byte big-endian payload length
+    const STEALTH_HEADER_LEN: usize = stealth::HEADER_LEN;
+
+    /// Reads a little-endian u16 from a slice
+    fn read_u16_le(data: &[u8]) -> u16 {
+        u16::from_le_bytes([data[0], data[1]])
+    }
+
+    /// Reads a little-endian u32 from a slice
+    fn read_u32_le(data: &[u8]) -> u32 {
+        u32::from_le_bytes([data[0], data[1], data[2], data[3]])
+    }
+
+    /// Walks the chunks of a WAV file.
+    ///
+    /// Only a bad RIFF header is an error. The walk stops at the end of the RIFF
+    /// chunk or of the file, whichever comes first, and at a chunk that runs past the
+    /// end of the file, which is still reported, clamped, so a cut-off `data` or
+    /// `LUPN` chunk can be read as far as it goes.
+    fn parse_chunks(data: &[u8]) -> Result<Layout> {
+        if data.len() < Self::RIFF_HEADER_LEN
+            || !data.starts_with(b"RIFF")
+            || &data[8..12] != Self::FORM_TYPE
+        {
+            return Err(LupinError::WavInvalidFormat {
+                reason: "File too short or missing RIFF/WAVE header".to_string(),
+            });
+        }
+
+        let riff_end = (Self::read_u32_le(&data[4..8]) as usize).saturating_add(8);
+        let limit = riff_end.min(data.len());
+        let mut layout = Layout {
+            end: Self::RIFF_HEADER_LEN,
+            ..Layout::default()
+        };
+
+        let mut pos = Self::RIFF_HEADER_LEN;
+        let mut after_pad = false;
+        while pos + Self::CHUNK_HEADER_LEN <= limit {
+            let id = &data[pos..pos + 4];
+            let declared_len = Self::read_u32_le(&data[pos + 4..pos + 8]) as usize;
+            let body_start = pos + Self::CHUNK_HEADER_LEN;
+            // Chunks are padded to an even length
+            let end = body_start
+                .saturating_add(declared_len)
+                .saturating_add(declared_len & 1);
+
+            let chunk = Chunk {
+                range: pos..end.min(data.len()),
+                body: body_start..body_start.saturating_add(declared_len).min(data.len()),
+                declared_len,
+            };
+            let truncated = chunk.is_truncated();
+
+            match id {
+                b"fmt " if layout.format.is_none() => layout.format = Some(chunk),
+                b"data" if layout.samples.is_none() => layout.samples = Some(chunk),
+                _ if id == Self::LUPIN_CHUNK_ID && layout.lupin.is_none() => {
+                    layout.lupin = Some(chunk);
+                    layout.lupin_after_pad = after_pad;
+                }
+                _ => {}
+            }
+
+            if truncated {
+                layout.truncated = true;
+                break;
+            }
+            // A missing pad byte after the last chunk is tolerated, leaving the end
+            // of the chunk list odd
+            pos = end.min(data.len());
+            layout.end = pos;
+            after_pad = declared_len & 1 == 1;
+        }
+
+        Ok(layout)
+    }
+
+    /// Rewrites the RIFF size when the chunk list ending at `old_end` now ends at
+    /// `new_end`.
+    ///
+    /// Only a size that matched the chunk list is updated, so one that was wrong (or
+    /// a placeholder left by a streaming writer) is restored exactly on removal.
+    fn update_riff_size(output: &mut [u8], old_end: usize, new_end: usize) {
+        if Self::read_u32_le(&output[4..8]) as usize + 8 == old_end {
+            let size = (new_end - 8) as u32;
+            output[4..8].copy_from_slice(&size.to_le_bytes());
+        }
+    }
+
+    /// Finds the samples of a 16-bit PCM WAV for stealth mode
+    fn decode_audio(data: &[u8]) -> Result<DecodedAudio> {
+        let layout = Self::parse_chunks(data)?;
+
+        let (Some(format), Some(samples)) = (layout.format, layout.samples) else {
+            return Err(LupinError::WavInvalidFormat {
+                reason: "Missing fmt or data chunk".to_string(),
+            });
+        };
+        let format = &data[format.body];
+        if format.len() < 16 {
+            return Err(LupinError::WavInvalidFormat {
+                reason: "fmt chunk too short".to_string(),
+            });
+        }
+
+        let unsupported = |reason: &str| LupinError::StealthUnsupportedCarrier {
+            format: "WAV",
+            reason: reason.to_string(),
+        };
+
+        let mut format_tag = Self::read_u16_le(&format[0..2]);
+        if format_tag == Self::WAVE_FORMAT_EXTENSIBLE && format.len() >= 26 {
+            format_tag = Self::read_u16_le(&format[24..26]);
+        }
+        if format_tag != Self::WAVE_FORMAT_PCM {
+            return Err(unsupported("only PCM audio is supported"));
+        }
+        if Self::read_u16_le(&format[14..16]) != 16 {
+            return Err(unsupported("only 16-bit samples are supported"));
+        }
+
+        // A cut-off data chunk still carries bits in the samples that are left
+        let whole = samples.body.len() & !1;
+        Ok(DecodedAudio {
+            samples: samples.body.start..samples.body.start + whole,
+            data: data.to_vec(),
+        })
+    }
+
+    /// Checks whether the samples already carry a stealth payload.
+    ///
+    /// Best effort: files stealth mode can't write to simply report `false`.
+    fn has_stealth_payload(data: &[u8]) -> bool {
+        Self::decode_audio(data)
+            .map(|audio| audio.stealth_header().is_some())
+            .unwrap_or(false)
+    }
+
+    /// Hides the payload in the LSBs of the samples
+    fn embed_stealth(&self, source_data: &[u8], payload: &[u8]) -> Result<Vec<u8>> {
+        let mut audio = Self::decode_audio(source_data)?;
+
+        if audio.stealth_header().is_some() {
+            return Err(LupinError::EmbedCollision {
+                source: std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    "WAV samples already carry a Lupin payload",
+                ),
+            });
+        }
+
+        let stream = stealth::seal(Self::STEALTH_MAGIC, payload);
+
+        let capacity = audio.capacity_bits() / 8;
+        if stream.len() > capacity {
+            return Err(LupinError::PayloadTooLarge {
+                capacity: capacity.saturating_sub(Self::STEALTH_HEADER_LEN),
+                requested: payload.len(),
+            });
+        }
+
+        debug!(
+            "WAV: Writing {} bytes into the LSBs of {} available carrier bytes",
+            stream.len(),
+            capacity
+        );
+
+        audio.write_bytes(&stream);
+        Ok(audio.data)
+    }
+
+    /// Finds a stealth payload's header and first bytes in the sample LSBs
+    fn probe_stealth(source_data: &[u8]) -> Option<Probe> {
+        let audio = Self::decode_audio(source_data).ok()?;
+        let header = audio.stealth_header()?;
+        let len = header.len;
+        let available = audio.capacity_bits() / 8 - Self::STEALTH_HEADER_LEN;
+        let prefix = audio.read_bytes(
+            Self::STEALTH_HEADER_LEN,
+            len.min(Probe::PREFIX_LEN).min(available),
+        );
+        Some(Probe::new(
+            EmbedMode::Stealth,
+            len,
+            vec![audio.samples.clone()],
+            header.unmask(prefix, 0),
+        ))
+    }
+
+    /// Writes zero bits over a stealth payload and its header in the sample LSBs
+    fn remove_stealth(source_data: &[u8]) -> Result<Vec<u8>> {
+        let mut audio = Self::decode_audio(source_data).map_err(|_| LupinError::WavNoHiddenData)?;
+        let len = audio
+            .stealth_header()
+            .ok_or(LupinError::WavNoHiddenData)?
+            .len;
+
+        let wiped = (Self::STEALTH_HEADER_LEN + len).min(audio.capacity_bits() / 8);
+        audio.write_bytes(&vec![0; wiped]);
+
+        debug!("WAV: Cleared {} byte stealth payload from the samples", len);
+
+        Ok(audio.data)
+    }
+
+    /// Recovers a payload hidden in the LSBs of the samples
+    fn extract_stealth(source_data: &[u8]) -> Result<Vec<u8>> {
+        // A file whose sample format stealth mode never writes can't carry a
+        // stealth payload.
+        let audio = Self::decode_audio(source_data).map_err(|_| LupinError::WavNoHiddenData)?;
+        let header = audio.stealth_header().ok_or(LupinError::WavNoHiddenData)?;
+        let len = header.len;
+
+        if Self::STEALTH_HEADER_LEN + len > audio.capacity_bits() / 8 {
+            return Err(LupinError::WavCorruptedData);
+        }
+
+        debug!("WAV: Found {} byte stealth payload in the samples", len);
+
+        Ok(header.unmask(audio.read_bytes(Self::STEALTH_HEADER_LEN, len), 0))
+    }
+}
+
+impl Default for WavEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SteganographyEngine for WavEngine {
+    fn magic_bytes(&self) -> &[u8] {
+        b"RIFF"
+    }
+
+    fn matches(&self, data: &[u8]) -> bool {
+        // Other RIFF formats (WebP, AVI) share the magic; the form type tells them apart
+        data.starts_with(self.magic_bytes()) && data.get(8..12) == Some(Self::FORM_TYPE)
+    }
+
+    fn format_name(&self) -> &str {
+        "WAV"
+    }
+
+    fn format_ext(&self) -> &str {
+        ".wav"
+    }
+
+    fn capacity(&self, source_data: &[u8], mode: EmbedMode) -> Result<Capacity> {
+        match mode {
+            // The chunk's 32-bit size field is the only limit
+            EmbedMode::Capacity => {
+                Self::parse_chunks(source_data)?;
+                Ok(Capacity::Unlimited)
+            }
+            EmbedMode::Stealth => {
+                let audio = Self::decode_audio(source_data)?;
+                let bytes = audio.capacity_bits() / 8;
+                Ok(Capacity::Exact(
+                    bytes.saturating_sub(Self::STEALTH_HEADER_LEN),
+                ))
+            }
+        }
+    }
+
+    fn embed(&self, source_data: &[u8], payload: &[u8], mode: EmbedMode) -> Result<Vec<u8>> {
+        // Reject empty payloads so the embed contract is uniform across engines.
+        if payload.is_empty() {
+            return Err(LupinError::EmptyPayload);
+        }
+
+        // Refuse to embed into a WAV that already carries a Lupin chunk; a second
+        // one would be silently lost on extract (which returns the first match).
+        let layout = Self::parse_chunks(source_data)?;
+        if layout.lupin.is_some() {
+            return Err(LupinError::EmbedCollision {
+                source: std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    "WAV already contains a Lupin chunk",
+                ),
+            });
+        }
+
+        // Exhaustive so a future EmbedMode variant is a compile error here rather than
+        // silently falling through to the capacity implementation below.
+        match mode {
+            EmbedMode::Capacity => {}
+            EmbedMode::Stealth => return self.embed_stealth(source_data, payload),
+        }
+
+        // A stealth payload would be shadowed by the chunk on extract, so treat it
+        // as a collision too.
+        if Self::has_stealth_payload(source_data) {
+            return Err(LupinError::EmbedCollision {
+                source: std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    "WAV samples already carry a Lupin payload",
+                ),
+            });
+        }
+
+        // A chunk after a cut-off one would be read as part of it
+        if layout.truncated {
+            return Err(LupinError::WavInvalidFormat {
+                reason: "A chunk runs past the end of the file; the file is truncated".to_string(),
+            });
+        }
+
+        let len = u32::try_from(payload.len()).map_err(|_| LupinError::PayloadTooLarge {
+            capacity: (u32::MAX - 1) as usize,
+            requested: payload.len(),
+        })?;
+
+        let mut chunk = Vec::with_capacity(Self::CHUNK_HEADER_LEN + payload.len() + 1);
+        chunk.extend_from_slice(Self::LUPIN_CHUNK_ID);
+        chunk.extend_from_slice(&len.to_le_bytes());
+        chunk.extend_from_slice(payload);
+        if payload.len() % 2 == 1 {
+            chunk.push(0);
+        }
+
+        // Chunks start at even offsets, so an odd end means the last chunk lacks its
+        // pad byte; add it, or the walk would step past our chunk's first byte
+        let pad = layout.end % 2;
+
+        debug!(
+            "WAV: Inserting {} byte chunk at position {}",
+            chunk.len(),
+            layout.end + pad
+        );
+
+        // After the last chunk, ahead of any bytes trailing the RIFF chunk
+        let mut output = Vec::with_capacity(source_data.len() + pad + chunk.len());
+        output.extend_from_slice(&source_data[..layout.end]);
+        output.resize(layout.end + pad, 0);
+        output.extend_from_slice(&chunk);
+        output.extend_from_slice(&source_data[layout.end..]);
+        Self::update_riff_size(&mut output, layout.end, layout.end + pad + chunk.len());
+
+        Ok(output)
+    }
+
+    fn probe(&self, source_data: &[u8]) -> Result<Option<Probe>> {
+        let Some(chunk) = Self::parse_chunks(source_data)?.lupin else {
+            return Ok(Self::probe_stealth(source_data));
+        };
+
+        let prefix_end = chunk.body.end.min(chunk.body.start + Probe::PREFIX_LEN);
+        let prefix = source_data[chunk.body.start..prefix_end].to_vec();
+        Ok(Some(Probe::new(
+            EmbedMode::Capacity,
+            chunk.declared_len,
+            vec![chunk.range],
+            prefix,
+        )))
+    }
+
+    fn remove(&self, source_data: &[u8]) -> Result<Vec<u8>> {
+        let layout = Self::parse_chunks(source_data)?;
+        let Some(chunk) = layout.lupin else {
+            return Self::remove_stealth(source_data);
+        };
+
+        // As the last chunk after an odd-sized one, it is where embed added the pad
+        // byte the chunk list was missing, so that goes too
+        let mut range = chunk.range.clone();
+        if layout.lupin_after_pad && range.end == layout.end {
+            range.start -= 1;
+        }
+
+        debug!("WAV: Removing Lupin chunk at {}-{}", range.start, range.end);
+
+        let mut output = Vec::with_capacity(source_data.len() - range.len());
+        output.extend_from_slice(&source_data[..range.start]);
+        output.extend_from_slice(&source_data[range.end..]);
+        // A cut-off chunk was never counted in the chunk list
+        if !chunk.is_truncated() {
+            Self::update_riff_size(&mut output, layout.end, layout.end - range.len());
+        }
+        Ok(output)
+    }
+
+    fn extract(&self, source_data: &[u8]) -> Result<Vec<u8>> {
+        // Read the payload from our chunk, skipping foreign chunks, and fall back to
+        // the sample LSBs when there is none (stealth mode)
+        let Some(chunk) = Self::parse_chunks(source_data)?.lupin else {
+            return Self::extract_stealth(source_data);
+        };
+
+        debug!(
+            "WAV: Found Lupin chunk at {}-{}",
+            chunk.range.start, chunk.range.end
+        );
+
+        // A chunk cut short is only returned if error correction can repair it
+        let payload = &source_data[chunk.body.clone()];
+        if chunk.is_truncated() && !crate::fec::is_fec(payload) {
+            return Err(LupinError::WavCorruptedData);
+        }
+        Ok(payload.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a PCM WAV with `samples` pseudo-random samples of `bits_per_sample`
+    /// bits, preceded by a `LIST` chunk and followed by a `bext` chunk, as broadcast
+    /// tools write them
+    fn create_wav(samples: usize, bits_per_sample: u16) -> Vec<u8> {
+        let channels = 2u16;
+        let sample_bytes = bits_per_sample as usize / 8;
+        let block_align = channels * sample_bytes as u16;
+
+        let mut chunks = Vec::new();
+        chunks.extend_from_slice(b"fmt \x10\x00\x00\x00");
+        chunks.extend_from_slice(&WavEngine::WAVE_FORMAT_PCM.to_le_bytes());
+        chunks.extend_from_slice(&channels.to_le_bytes());
+        chunks.extend_from_slice(&44_100u32.to_le_bytes());
+        chunks.extend_from_slice(&(44_100 * block_align as u32).to_le_bytes());
+        chunks.extend_from_slice(&block_align.to_le_bytes());
+        chunks.extend_from_slice(&bits_per_sample.to_le_bytes());
+        // Odd-sized, so the pad byte is exercised
+        chunks.extend_from_slice(b"LIST\x0B\x00\x00\x00INFOISFT\x03\x00\x00\x00");
+
+        let data_len = samples * sample_bytes;
+        chunks.extend_from_slice(b"data");
+        chunks.extend_from_slice(&(data_len as u32).to_le_bytes());
+        let mut seed = 0x1234_5678u32;
+        for _ in 0..data_len {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            chunks.push((seed >> 16) as u8);
+        }
+        chunks.extend_from_slice(b"bext\x04\x00\x00\x00none");
+
+        let mut wav = b"RIFF".to_vec();
+        wav.extend_from_slice(&(chunks.len() as u32 + 4).to_le_bytes());
+        wav.extend_from_slice(b"WAVE");
+        wav.extend_from_slice(&chunks);
+        wav
+    }
+
+    #[test]
+    fn test_wav_magic_bytes() {
+        let engine = WavEngine::new();
+        assert_eq!(engine.magic_bytes(), b"RIFF");
+        assert!(engine.matches(&create_wav(4, 16)));
+        assert!(!engine.matches(b"RIFF\x04\x00\x00\x00WEBP"));
+        assert!(!engine.matches(b"RIFF"));
+    }
+
+    #[test]
+    fn test_wav_format_info() {
+        let engine = WavEngine::new();
+        assert_eq!(engine.format_name(), "WAV");
+        assert_eq!(engine.format_ext(), ".wav");
+    }
+
+    #[test]
+    fn test_embed_and_extract() {
+        // Arrange
+        let engine = WavEngine::new();
+        let wav = create_wav(64, 16);
+        let payload = b"Secret message hidden in WAV!";
+
+        // Act
+        let embedded = engine.embed(&wav, payload, EmbedMode::Capacity).unwrap();
+        let extracted = engine.extract(&embedded).unwrap();
+
+        // Assert - the chunk is appended, padded, and the RIFF size covers it
+        assert_eq!(extracted, payload);
+        assert_eq!(&embedded[8..wav.len()], &wav[8..]);
+        assert_eq!(embedded.len(), wav.len() + 8 + payload.len() + 1);
+        assert_eq!(
+            WavEngine::read_u32_le(&embedded[4..8]) as usize,
+            embedded.len() - 8
+        );
+    }
+
+    #[test]
+    fn test_foreign_chunks_skipped() {
+        // Arrange - a foreign chunk whose data happens to look like ours
+        let engine = WavEngine::new();
+        let mut wav = create_wav(16, 16);
+        wav.extend_from_slice(b"junk\x0C\x00\x00\x00LUPN\x04\x00\x00\x00fake");
+        let riff_size = wav.len() as u32 - 8;
+        wav[4..8].copy_from_slice(&riff_size.to_le_bytes());
+
+        // Act
+        let clean = engine.extract(&wav);
+        let embedded = engine.embed(&wav, b"real", EmbedMode::Capacity).unwrap();
+
+        // Assert
+        assert!(matches!(clean, Err(LupinError::WavNoHiddenData)));
+        assert_eq!(engine.extract(&embedded).unwrap(), b"real");
+    }
+
+    #[test]
+    fn test_unpadded_last_chunk() {
+        // Arrange - mono 8-bit audio with an odd number of samples and no pad byte
+        // after them, as Python's wave module writes it
+        let engine = WavEngine::new();
+        let mut wav = b"RIFF\0\0\0\0WAVEfmt \x10\0\0\0\x01\0\x01\0".to_vec();
+        wav.extend_from_slice(&8_000u32.to_le_bytes());
+        wav.extend_from_slice(&8_000u32.to_le_bytes());
+        wav.extend_from_slice(b"\x01\0\x08\0data");
+        wav.extend_from_slice(&1001u32.to_le_bytes());
+        wav.extend((0..1001).map(|i| (i % 251) as u8));
+        let riff_size = wav.len() as u32 - 8;
+        wav[4..8].copy_from_slice(&riff_size.to_le_bytes());
+
+        // Act
+        let embedded = engine.embed(&wav, b"payload", EmbedMode::Capacity).unwrap();
+        let probe = engine.probe(&embedded).unwrap().unwrap();
+        let restored = engine.remove(&embedded).unwrap();
+
+        // Assert - the pad byte goes in ahead of the chunk and is dropped with it
+        assert_eq!(engine.extract(&embedded).unwrap(), b"payload");
+        assert_eq!(probe.ranges, vec![wav.len() + 1..embedded.len()]);
+        assert_eq!(embedded[wav.len()], 0);
+        assert_eq!(
+            WavEngine::read_u32_le(&embedded[4..8]) as usize,
+            embedded.len() - 8
+        );
+        assert_eq!(restored, wav);
+    }
+
+    #[test]
+    fn test_trailing_bytes_kept() {
+        // Arrange - bytes after the RIFF chunk, which its size doesn't cover
+        let engine = WavEngine::new();
+        let mut wav = create_wav(16, 16);
+        wav.extend_from_slice(b"trailer");
+
+        // Act
+        let embedded = engine.embed(&wav, b"payload", EmbedMode::Capacity).unwrap();
+        let restored = engine.remove(&embedded).unwrap();
+
+        // Assert - the chunk goes inside the RIFF chunk, before the trailer
+        assert_eq!(engine.extract(&embedded).unwrap(), b"payload");
+        assert!(embedded.ends_with(b"payload\0trailer"));
+        assert_eq!(restored, wav);
+    }
+
+    #[test]
+    fn test_embed_collision() {
+        let engine = WavEngine::new();
+        let embedded_once = engine
+            .embed(&create_wav(16, 16), b"First", EmbedMode::Capacity)
+            .unwrap();
+
+        let result = engine.embed(&embedded_once, b"Second", EmbedMode::Capacity);
+        assert!(matches!(result, Err(LupinError::EmbedCollision { .. })));
+    }
+
+    #[test]
+    fn test_extract_without_data() {
+        let engine = WavEngine::new();
+
+        // Too short for a stealth header, and no chunk
+        let result = engine.extract(&create_wav(16, 16));
+        assert!(matches!(result, Err(LupinError::WavNoHiddenData)));
+    }
+
+    #[test]
+    fn test_invalid_wav() {
+        let engine = WavEngine::new();
+
+        for not_wav in [&b"RIFF"[..], b"RIFF\x04\x00\x00\x00AVI "] {
+            let result = engine.embed(not_wav, b"payload", EmbedMode::Capacity);
+            assert!(matches!(result, Err(LupinError::WavInvalidFormat { .. })));
+        }
+    }
+
+    #[test]
+    fn test_empty_payload_rejected() {
+        let engine = WavEngine::new();
+        assert!(matches!(
+            engine.embed(&create_wav(16, 16), b"", EmbedMode::Capacity),
+            Err(LupinError::EmptyPayload)
+        ));
+    }
+
+    #[test]
+    fn test_truncated_file_does_not_panic() {
+        let engine = WavEngine::new();
+        let wav = create_wav(16, 16);
+        let embedded = engine
+            .embed(&wav, &[0x42; 40], EmbedMode::Capacity)
+            .unwrap();
+
+        // Every prefix either parses or fails cleanly; a cut-off chunk without
+        // error correction is reported rather than returned
+        for len in 0..embedded.len() {
+            let _ = engine.probe(&embedded[..len]);
+            let _ = engine.remove(&embedded[..len]);
+            let _ = engine.extract(&embedded[..len]);
+        }
+        assert!(matches!(
+            engine.extract(&embedded[..embedded.len() - 1]),
+            Err(LupinError::WavCorruptedData)
+        ));
+        assert!(matches!(
+            engine.embed(&wav[..wav.len() - 2], b"payload", EmbedMode::Capacity),
+            Err(LupinError::WavInvalidFormat { .. })
+        ));
+    }
+
+    #[test]
+    fn test_stealth_round_trip() {
+        // Arrange
+        let engine = WavEngine::new();
+        let wav = create_wav(1024, 16);
+        let payload = b"Hidden in the sample LSBs";
+
+        // Act
+        let embedded = engine.embed(&wav, payload, EmbedMode::Stealth).unwrap();
+        let extracted = engine.extract(&embedded).unwrap();
+
+        // Assert - only the low bit of each sample's low byte changed
+        assert_eq!(extracted, payload);
+        assert_eq!(embedded.len(), wav.len());
+        let samples = WavEngine::decode_audio(&wav).unwrap().samples;
+        for (i, (a, b)) in embedded.iter().zip(&wav).enumerate() {
+            if samples.contains(&i) && (i - samples.start) % 2 == 0 {
+                assert_eq!(a | 1, b | 1);
+            } else {
+                assert_eq!(a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_stealth_rejects_other_sample_formats() {
+        // Arrange
+        let engine = WavEngine::new();
+        let eight_bit = create_wav(1024, 8);
+        let mut float = create_wav(1024, 16);
+        float[20] = 3; // WAVE_FORMAT_IEEE_FLOAT
+
+        // Act & Assert
+        for wav in [eight_bit, float] {
+            assert!(matches!(
+                engine.embed(&wav, b"payload", EmbedMode::Stealth),
+                Err(LupinError::StealthUnsupportedCarrier { format: "WAV", .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_stealth_extensible_format() {
+        // Arrange - WAVE_FORMAT_EXTENSIBLE wrapping PCM
+        let engine = WavEngine::new();
+        let mut wav = create_wav(1024, 16);
+        let fmt = 12;
+        wav[fmt + 4] = 40;
+        wav[fmt + 8..fmt + 10].copy_from_slice(&WavEngine::WAVE_FORMAT_EXTENSIBLE.to_le_bytes());
+        let mut extension = vec![22, 0, 16, 0, 3, 0, 0, 0, 1, 0, 0, 0];
+        extension.extend_from_slice(b"\x00\x00\x10\x00\x80\x00\x00\xAA\x00\x38\x9B\x71");
+        wav.splice(fmt + 24..fmt + 24, extension);
+        let riff_size = wav.len() as u32 - 8;
+        wav[4..8].copy_from_slice(&riff_size.to_le_bytes());
+
+        // Act
+        let embedded = engine.embed(&wav, b"payload", EmbedMode::Stealth).unwrap();
+
+        // Assert
+        assert_eq!(engine.extract(&embedded).unwrap(), b"payload");
+    }
+
+    #[test]
+    fn test_stealth_payload_too_large() {
+        // Arrange - 128 samples hold 128 bits, exactly the 16-byte header
+        let engine = WavEngine::new();
+        let wav = create_wav(128, 16);
+
+        // Act
+        let result = engine.embed(&wav, b"too big", EmbedMode::Stealth);
+
+        // Assert
+        assert!(matches!(
+            result,
+            Err(LupinError::PayloadTooLarge {
+                capacity: 0,
+                requested: 7
+            })
+        ));
+    }
+
+    #[test]
+    fn test_probe() {
+        // Arrange
+        let engine = WavEngine::new();
+        let wav = create_wav(1024, 16);
+        let chunk = engine
+            .embed(&wav, b"chunk payload", EmbedMode::Capacity)
+            .unwrap();
+        let samples = engine.embed(&wav, b"samples", EmbedMode::Stealth).unwrap();
+
+        // Act
+        let clean = engine.probe(&wav).unwrap();
+        let capacity = engine.probe(&chunk).unwrap().unwrap();
+        let stealth = engine.probe(&samples).unwrap().unwrap();
+
+        // Assert
+        assert_eq!(clean, None);
+        assert_eq!(capacity.mode, EmbedMode::Capacity);
+        assert_eq!(capacity.size, 13);
+        assert_eq!(capacity.prefix, b"chunk payload");
+        assert_eq!(capacity.ranges, vec![wav.len()..chunk.len()]);
+        assert_eq!(stealth.mode, EmbedMode::Stealth);
+        assert_eq!(stealth.size, 7);
+        assert_eq!(stealth.prefix, b"samples");
+        assert_eq!(stealth.ranges[0].len(), 2048);
+    }
+
+    #[test]
+    fn test_remove() {
+        // Arrange
+        let engine = WavEngine::new();
+        let wav = create_wav(1024, 16);
+        let chunk = engine
+            .embed(&wav, b"chunk payload", EmbedMode::Capacity)
+            .unwrap();
+        let samples = engine.embed(&wav, b"samples", EmbedMode::Stealth).unwrap();
+
+        // Act
+        let restored = engine.remove(&chunk).unwrap();
+        let cleared = engine.remove(&samples).unwrap();
+        let nothing = engine.remove(&wav);
+
+        // Assert
+        assert_eq!(restored, wav);
+        assert_eq!(engine.probe(&cleared).unwrap(), None);
+        assert!(matches!(nothing, Err(LupinError::WavNoHiddenData)));
+    }
+
+    #[test]
+    fn test_capacity() {
+        // Arrange - 1024 samples hold 1024 bits = 128 bytes, minus the 16-byte header
+        let engine = WavEngine::new();
+        let wav = create_wav(1024, 16);
+
+        // Act
+        let capacity = engine.capacity(&wav, EmbedMode::Capacity).unwrap();
+        let stealth = engine.capacity(&wav, EmbedMode::Stealth).unwrap();
+
+        // Assert
+        assert_eq!(capacity, Capacity::Unlimited);
+        assert_eq!(stealth, Capacity::Exact(112));
+        assert!(engine.embed(&wav, &[0xAB; 112], EmbedMode::Stealth).is_ok());
+        assert!(matches!(
+            engine.embed(&wav, &[0xAB; 113], EmbedMode::Stealth),
+            Err(LupinError::PayloadTooLarge { capacity: 112, .. })
+        ));
+    }
+
+    #[test]
+    fn test_stealth_collisions() {
+        // Arrange
+        let engine = WavEngine::new();
+        let wav = create_wav(1024, 16);
+        let stealthy = engine.embed(&wav, b"first", EmbedMode::Stealth).unwrap();
+        let chunked = engine.embed(&wav, b"first", EmbedMode::Capacity).unwrap();
+
+        // Act & Assert - any existing payload blocks a second one, in either mode
+        for (carrier, mode) in [
+            (&stealthy, EmbedMode::Stealth),
+            (&stealthy, EmbedMode::Capacity),
+            (&chunked, EmbedMode::Stealth),
+        ] {
+            assert!(matches!(
+                engine.embed(carrier, b"second", mode),
+                Err(LupinError::EmbedCollision { .. })
+            ));
+        }
+    }
+}
@@ -0,0 +1,792 @@
+// Copyright 2025 Niclas Hedam
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! WebP steganography engine using a private RIFF chunk
+//!
+//! # How It Works
+//!
+//! A WebP file is a RIFF container whose chunks hold the image. The extended format
+//! allows unknown chunks at the end of the file, which decoders skip, so this engine
+//! hides data in a chunk of its own there.
+//!
+//! ## Storage Format
+//!
+//! We add a chunk called `LUPN` after the last chunk of the file:
+//!
+//! ```text
+//! ["LUPN"][4 bytes: Little-endian Length][1 byte: Flags][N bytes: Raw Payload][Pad byte if odd]
+//! ```
+//!
+//! The length covers the flags byte and the payload. The size field of the `RIFF`
+//! header is grown to cover the new chunk, and shrunk again when it is removed.
+//!
+//! Some writers leave out the pad byte of an odd-sized last chunk. The engine adds
+//! it ahead of `LUPN`, so the chunk starts where the walk looks for it, and flag bit
+//! 1 records this so removal takes the pad byte out again.
+//!
+//! ## WebP Structure
+//!
+//! - **Simple format**: a single `VP8 ` (lossy) or `VP8L` (lossless) chunk. The
+//!   specification allows no other chunks here, so before adding ours the engine
+//!   converts the file to the extended format by putting a `VP8X` chunk in front,
+//!   with the canvas size and alpha flag read from the image chunk. Flag bit 0 of
+//!   our chunk records this, and removal takes the `VP8X` chunk out again.
+//! - **Extended format**: a `VP8X` chunk first, then `ICCP`, `ANIM`/`ANMF` or
+//!   `ALPH` and the image, `EXIF` and `XMP `, in that order. Our chunk goes after
+//!   all of them, so the order decoders rely on is kept.
+//!
+//! Chunks are walked by their size fields and padded to an even length. A chunk that
+//! runs past the end of the file ends the scan without panicking.
+//!
+//! ## Stealth Mode
+//!
+//! WebP image data is VP8 or VP8L compressed, and the engine has no codec to hide
+//! bits in it, so [`EmbedMode::Stealth`] returns
+//! [`LupinError::StealthNotSupported`].
+
+use crate::error::{LupinError, Result};
+use crate::{Capacity, EmbedMode, Probe, SteganographyEngine};
+use log::debug;
+use std::ops::Range;
+
+/// WebP steganography engine
+///
+/// Stores the payload in a private `LUPN` RIFF chunk at the end of an extended-format
+/// WebP, converting a simple-format file to the extended format first, and fixes up
+/// the RIFF size. Decoders skip the chunk, so the image is unchanged.
+///
+/// See the module documentation for details on how data is stored and limitations.
+pub struct WebpEngine;
+
+/// A chunk found by [`WebpEngine::parse_chunks`]
+#[derive(Debug, Clone)]
+struct Chunk {
+    /// Byte range of the whole chunk, from its id to its pad byte, clamped to the file
+    range: Range<usize>,
+    /// Byte range of the chunk data, clamped to the file
+    body: Range<usize>,
+    /// The size the chunk header declares
+    declared_len: usize,
+}
+
+impl Chunk {
+    /// Whether the chunk data was cut short by the end of the file
+    fn is_truncated(&self) -> bool {
+        self.body.len() < self.declared_len
+    }
+}
+
+/// The chunks of a WebP the engine cares about
+#[derive(Debug)]
+struct Layout {
+    /// The first chunk: `VP8X` in the extended format, the image in the simple one
+    first: Chunk,
+    /// Id of the first chunk
+    first_id: [u8; 4],
+    /// Byte offset just past the last complete chunk
+    end: usize,
+    /// Whether the scan stopped at a chunk that runs past the end of the file
+    truncated: bool,
+    /// The first `LUPN` chunk
+    lupin: Option<Chunk>,
+}
+
+impl WebpEngine {
+    /// Creates a new WebP engine
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Length of the RIFF header: `RIFF`, size and form type
+    const RIFF_HEADER_LEN: usize = 12;
+
+    /// Length of a chunk header: id and size
+    const CHUNK_HEADER_LEN: usize = 8;
+
+    /// Form type of a WebP file, at offset 8
+    const FORM_TYPE: &'static [u8] = b"WEBP";
+
+    /// Id of our private chunk
+    const LUPIN_CHUNK_ID: &'static [u8] = b"LUPN";
+
+    /// Flag bit of our chunk: the `VP8X` chunk was added by Lupin
+    const FLAG_ADDED_VP8X: u8 = 0x01;
+
+    /// Flag bit of our chunk: the pad byte in front of it was added by Lupin
+    const FLAG_ADDED_PAD: u8 = 0x02;
+
+    /// Length of the `VP8X` chunk data
+    const VP8X_LEN: usize = 10;
+
+    /// `VP8X` flag bit for an image with alpha
+    const VP8X_ALPHA: u8 = 0x10;
+
+    /// Signature byte of a `VP8L` bitstream
+    const VP8L_SIGNATURE: u8 = 0x2F;
+
+    /// Start code of a `VP8 ` key frame, after the 3-byte frame tag
+    const VP8_START_CODE: &'static [u8] = b"\x9D\x01\x2A";
+
+    /// Reads a little-endian u32 from a slice
+    fn read_u32_le(data: &[u8]) -> u32 {
+        u32::from_le_bytes([data[0], data[1], data[2], data[3]])
+    }
+
+    /// Walks the chunks of a WebP file.
+    ///
+    /// Fails if the RIFF header or the first chunk isn't WebP. The walk stops at the
+    /// end of the RIFF chunk or of the file, whichever comes first, and at a chunk
+    /// that runs past the end of the file, which is still reported, clamped, so a
+    /// cut-off `LUPN` chunk can be read as far as it goes.
+    fn parse_chunks(data: &[u8]) -> Result<Layout> {
+        if data.len() < Self::RIFF_HEADER_LEN + Self::CHUNK_HEADER_LEN
+            || !data.starts_with(b"RIFF")
+            || &data[8..12] != Self::FORM_TYPE
+        {
+            return Err(LupinError::WebpInvalidFormat {
+                reason: "File too short or missing RIFF/WEBP header".to_string(),
+            });
+        }
+
+        let riff_end = (Self::read_u32_le(&data[4..8]) as usize).saturating_add(8);
+        let limit = riff_end.min(data.len());
+        let mut chunks = Vec::new();
+        let mut end = Self::RIFF_HEADER_LEN;
+        let mut truncated = false;
+
+        let mut pos = Self::RIFF_HEADER_LEN;
+        while pos + Self::CHUNK_HEADER_LEN <= limit {
+            let mut id = [0; 4];
+            id.copy_from_slice(&data[pos..pos + 4]);
+            let declared_len = Self::read_u32_le(&data[pos + 4..pos + 8]) as usize;
+            let body_start = pos + Self::CHUNK_HEADER_LEN;
+            // Chunks are padded to an even length
+            let chunk_end = body_start
+                .saturating_add(declared_len)
+                .saturating_add(declared_len & 1);
+
+            let chunk = Chunk {
+                range: pos..chunk_end.min(data.len()),
+                body: body_start..body_start.saturating_add(declared_len).min(data.len()),
+                declared_len,
+            };
+            let is_truncated = chunk.is_truncated();
+            chunks.push((id, chunk));
+
+            if is_truncated {
+                truncated = true;
+                break;
+            }
+            pos = chunk_end.min(data.len());
+            end = pos;
+        }
+
+        let Some((first_id, first)) = chunks.first().cloned() else {
+            return Err(LupinError::WebpInvalidFormat {
+                reason: "No chunks found".to_string(),
+            });
+        };
+        if !matches!(&first_id, b"VP8 " | b"VP8L" | b"VP8X") {
+            return Err(LupinError::WebpInvalidFormat {
+                reason: format!(
+                    "Unexpected first chunk {:?}",
+                    String::from_utf8_lossy(&first_id)
+                ),
+            });
+        }
+
+        let lupin = chunks
+            .into_iter()
+            .find(|(id, _)| id == Self::LUPIN_CHUNK_ID)
+            .map(|(_, chunk)| chunk);
+
+        Ok(Layout {
+            first,
+            first_id,
+            end,
+            truncated,
+            lupin,
+        })
+    }
+
+    /// Builds the `VP8X` chunk for a simple-format image chunk, with the canvas size
+    /// and alpha flag of the bitstream
+    fn create_vp8x(id: &[u8; 4], image: &[u8]) -> Result<Vec<u8>> {
+        let invalid = |reason: &str| LupinError::WebpInvalidFormat {
+            reason: reason.to_string(),
+        };
+
+        let (width, height, flags) = match id {
+            b"VP8 " => {
+                // Frame tag, start code, then 14-bit width and height with 2 scale bits
+                if image.len() < 10 || &image[3..6] != Self::VP8_START_CODE {
+                    return Err(invalid("VP8 bitstream doesn't start with a key frame"));
+                }
+                let width = u16::from_le_bytes([image[6], image[7]]) & 0x3FFF;
+                let height = u16::from_le_bytes([image[8], image[9]]) & 0x3FFF;
+                (width as u32, height as u32, 0)
+            }
+            _ => {
+                // Signature, then 14-bit width - 1, 14-bit height - 1 and the alpha bit
+                if image.len() < 5 || image[0] != Self::VP8L_SIGNATURE {
+                    return Err(invalid("VP8L bitstream has no signature"));
+                }
+                let bits = Self::read_u32_le(&image[1..5]);
+                let alpha = if bits & (1 << 28) != 0 {
+                    Self::VP8X_ALPHA
+                } else {
+                    0
+                };
+                ((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1, alpha)
+            }
+        };
+        if width == 0 || height == 0 {
+            return Err(invalid("Image has zero width or height"));
+        }
+
+        let mut chunk = Vec::with_capacity(Self::CHUNK_HEADER_LEN + Self::VP8X_LEN);
+        chunk.extend_from_slice(b"VP8X");
+        chunk.extend_from_slice(&(Self::VP8X_LEN as u32).to_le_bytes());
+        chunk.extend_from_slice(&[flags, 0, 0, 0]);
+        chunk.extend_from_slice(&(width - 1).to_le_bytes()[..3]);
+        chunk.extend_from_slice(&(height - 1).to_le_bytes()[..3]);
+        Ok(chunk)
+    }
+
+    /// Adds `delta` bytes to the RIFF size
+    fn adjust_riff_size(output: &mut [u8], delta: isize) {
+        let size = Self::read_u32_le(&output[4..8]) as isize + delta;
+        output[4..8].copy_from_slice(&(size as u32).to_le_bytes());
+    }
+}
+
+impl Default for WebpEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SteganographyEngine for WebpEngine {
+    fn magic_bytes(&self) -> &[u8] {
+        b"RIFF"
+    }
+
+    fn matches(&self, data: &[u8]) -> bool {
+        // Other RIFF formats (WAV, AVI) share the magic; the form type tells them apart
+        data.starts_with(self.magic_bytes()) && data.get(8..12) == Some(Self::FORM_TYPE)
+    }
+
+    fn format_name(&self) -> &str {
+        "WebP"
+    }
+
+    fn format_ext(&self) -> &str {
+        ".webp"
+    }
+
+    fn capacity(&self, source_data: &[u8], mode: EmbedMode) -> Result<Capacity> {
+        match mode {
+            // The chunk's 32-bit size field is the only limit
+            EmbedMode::Capacity => {
+                Self::parse_chunks(source_data)?;
+                Ok(Capacity::Unlimited)
+            }
+            EmbedMode::Stealth => Err(LupinError::StealthNotSupported { format: "WebP" }),
+        }
+    }
+
+    fn embed(&self, source_data: &[u8], payload: &[u8], mode: EmbedMode) -> Result<Vec<u8>> {
+        // Reject empty payloads so the embed contract is uniform across engines.
+        if payload.is_empty() {
+            return Err(LupinError::EmptyPayload);
+        }
+
+        // Refuse to embed into a WebP that already carries a Lupin chunk; a second
+        // one would be silently lost on extract (which returns the first match).
+        let layout = Self::parse_chunks(source_data)?;
+        if layout.lupin.is_some() {
+            return Err(LupinError::EmbedCollision {
+                source: std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    "WebP already contains a Lupin chunk",
+                ),
+            });
+        }
+
+        // Exhaustive so a future EmbedMode variant is a compile error here rather than
+        // silently falling through to the capacity implementation below.
+        match mode {
+            EmbedMode::Capacity => {}
+            EmbedMode::Stealth => return Err(LupinError::StealthNotSupported { format: "WebP" }),
+        }
+
+        // A chunk after a cut-off one would be read as part of it
+        if layout.truncated {
+            return Err(LupinError::WebpInvalidFormat {
+                reason: "A chunk runs past the end of the file; the file is truncated".to_string(),
+            });
+        }
+
+        // Unknown chunks are only allowed in the extended format
+        let vp8x = match &layout.first_id {
+            b"VP8X" => None,
+            id => Some(Self::create_vp8x(
+                id,
+                &source_data[layout.first.body.clone()],
+            )?),
+        };
+
+        let len = payload
+            .len()
+            .checked_add(1)
+            .and_then(|len| u32::try_from(len).ok())
+            .ok_or(LupinError::PayloadTooLarge {
+                capacity: (u32::MAX - 2) as usize,
+                requested: payload.len(),
+            })?;
+        // Chunks start at even offsets, so an odd end means the last chunk lacks its
+        // pad byte; add it, or the walk would step past our chunk's first byte
+        let pad = layout.end % 2;
+        let mut flags = 0;
+        if vp8x.is_some() {
+            flags |= Self::FLAG_ADDED_VP8X;
+        }
+        if pad == 1 {
+            flags |= Self::FLAG_ADDED_PAD;
+        }
+
+        let mut chunk = Vec::with_capacity(Self::CHUNK_HEADER_LEN + payload.len() + 2);
+        chunk.extend_from_slice(Self::LUPIN_CHUNK_ID);
+        chunk.extend_from_slice(&len.to_le_bytes());
+        chunk.push(flags);
+        chunk.extend_from_slice(payload);
+        if len % 2 == 1 {
+            chunk.push(0);
+        }
+
+        debug!(
+            "WebP: Inserting {} byte chunk at position {}{}",
+            chunk.len(),
+            layout.end + pad,
+            if vp8x.is_some() {
+                ", converting to the extended format"
+            } else {
+                ""
+            }
+        );
+
+        // After the last chunk, ahead of any bytes trailing the RIFF chunk
+        let vp8x = vp8x.unwrap_or_default();
+        let mut output = Vec::with_capacity(source_data.len() + vp8x.len() + pad + chunk.len());
+        output.extend_from_slice(&source_data[..Self::RIFF_HEADER_LEN]);
+        output.extend_from_slice(&vp8x);
+        output.extend_from_slice(&source_data[Self::RIFF_HEADER_LEN..layout.end]);
+        output.resize(output.len() + pad, 0);
+        output.extend_from_slice(&chunk);
+        output.extend_from_slice(&source_data[layout.end..]);
+        Self::adjust_riff_size(&mut output, (vp8x.len() + pad + chunk.len()) as isize);
+
+        Ok(output)
+    }
+
+    fn probe(&self, source_data: &[u8]) -> Result<Option<Probe>> {
+        let Some(chunk) = Self::parse_chunks(source_data)?.lupin else {
+            return Ok(None);
+        };
+
+        let payload = (chunk.body.start + 1).min(chunk.body.end);
+        let prefix_end = chunk.body.end.min(payload + Probe::PREFIX_LEN);
+        Ok(Some(Probe::new(
+            EmbedMode::Capacity,
+            chunk.declared_len.saturating_sub(1),
+            vec![chunk.range],
+            source_data[payload..prefix_end].to_vec(),
+        )))
+    }
+
+    fn remove(&self, source_data: &[u8]) -> Result<Vec<u8>> {
+        let layout = Self::parse_chunks(source_data)?;
+        let chunk = layout.lupin.ok_or(LupinError::WebpNoHiddenData)?;
+
+        // Take out the VP8X chunk and the pad byte in front of ours too if embedding
+        // added them
+        let flags = source_data.get(chunk.body.start).copied().unwrap_or(0);
+        let added_vp8x = flags & Self::FLAG_ADDED_VP8X != 0 && &layout.first_id == b"VP8X";
+        let vp8x = if added_vp8x {
+            layout.first.range.clone()
+        } else {
+            Self::RIFF_HEADER_LEN..Self::RIFF_HEADER_LEN
+        };
+        let mut range = chunk.range.clone();
+        if flags & Self::FLAG_ADDED_PAD != 0 && range.start > vp8x.end {
+            range.start -= 1;
+        }
+
+        debug!(
+            "WebP: Removing Lupin chunk at {}-{}",
+            range.start, range.end
+        );
+
+        let mut output = Vec::with_capacity(source_data.len() - range.len() - vp8x.len());
+        output.extend_from_slice(&source_data[..vp8x.start]);
+        output.extend_from_slice(&source_data[vp8x.end..range.start]);
+        output.extend_from_slice(&source_data[range.end..]);
+        // A cut-off chunk was never counted in the RIFF size as written
+        if !chunk.is_truncated() {
+            Self::adjust_riff_size(&mut output, -((range.len() + vp8x.len()) as isize));
+        }
+        Ok(output)
+    }
+
+    fn extract(&self, source_data: &[u8]) -> Result<Vec<u8>> {
+        // Read the payload from our chunk, skipping every other chunk
+        let chunk = Self::parse_chunks(source_data)?
+            .lupin
+            .ok_or(LupinError::WebpNoHiddenData)?;
+
+        debug!(
+            "WebP: Found Lupin chunk at {}-{}",
+            chunk.range.start, chunk.range.end
+        );
+
+        if chunk.body.is_empty() {
+            return Err(LupinError::WebpCorruptedData);
+        }
+
+        // A chunk cut short is only returned if error correction can repair it
+        let payload = &source_data[chunk.body.start + 1..chunk.body.end];
+        if chunk.is_truncated() && !crate::fec::is_fec(payload) {
+            return Err(LupinError::WebpCorruptedData);
+        }
+        Ok(payload.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Wraps chunks in a RIFF/WEBP header with the right size
+    fn riff(chunks: &[u8]) -> Vec<u8> {
+        let mut webp = b"RIFF".to_vec();
+        webp.extend_from_slice(&(chunks.len() as u32 + 4).to_le_bytes());
+        webp.extend_from_slice(b"WEBP");
+        webp.extend_from_slice(chunks);
+        webp
+    }
+
+    /// Builds a chunk, padded to an even length
+    fn chunk(id: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut chunk = id.to_vec();
+        chunk.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            chunk.push(0);
+        }
+        chunk
+    }
+
+    /// A simple lossy WebP of 300x200 pixels; the bitstream after the header is filler
+    fn create_lossy_webp() -> Vec<u8> {
+        let mut vp8 = vec![0x50, 0x0A, 0x00, 0x9D, 0x01, 0x2A];
+        vp8.extend_from_slice(&300u16.to_le_bytes());
+        vp8.extend_from_slice(&200u16.to_le_bytes());
+        vp8.extend_from_slice(&[0xAB; 41]);
+        riff(&chunk(b"VP8 ", &vp8))
+    }
+
+    /// A simple lossless WebP of 17x9 pixels, with or without alpha
+    fn create_lossless_webp(alpha: bool) -> Vec<u8> {
+        let bits: u32 = 16 | (8 << 14) | ((alpha as u32) << 28);
+        let mut vp8l = vec![WebpEngine::VP8L_SIGNATURE];
+        vp8l.extend_from_slice(&bits.to_le_bytes());
+        vp8l.extend_from_slice(&[0xCD; 20]);
+        riff(&chunk(b"VP8L", &vp8l))
+    }
+
+    /// An extended WebP with alpha, an image and EXIF metadata
+    fn create_extended_webp() -> Vec<u8> {
+        let lossy = create_lossy_webp();
+        let mut chunks = chunk(b"VP8X", &[0x18, 0, 0, 0, 43, 1, 0, 199, 0, 0]);
+        chunks.extend_from_slice(&chunk(b"ALPH", &[0; 7]));
+        chunks.extend_from_slice(&lossy[12..]);
+        chunks.extend_from_slice(&chunk(b"EXIF", b"Exif\0\0MM"));
+        riff(&chunks)
+    }
+
+    /// Lists the chunk ids of a WebP in order
+    fn chunk_ids(webp: &[u8]) -> Vec<String> {
+        let mut ids = Vec::new();
+        let mut pos = 12;
+        while pos + 8 <= webp.len() {
+            ids.push(String::from_utf8_lossy(&webp[pos..pos + 4]).into_owned());
+            let len = WebpEngine::read_u32_le(&webp[pos + 4..pos + 8]) as usize;
+            pos += 8 + len + len % 2;
+        }
+        ids
+    }
+
+    #[test]
+    fn test_webp_magic_bytes() {
+        let engine = WebpEngine::new();
+        assert_eq!(engine.magic_bytes(), b"RIFF");
+        assert!(engine.matches(&create_lossy_webp()));
+        assert!(!engine.matches(b"RIFF\x04\x00\x00\x00WAVE"));
+    }
+
+    #[test]
+    fn test_webp_format_info() {
+        let engine = WebpEngine::new();
+        assert_eq!(engine.format_name(), "WebP");
+        assert_eq!(engine.format_ext(), ".webp");
+    }
+
+    #[test]
+    fn test_embed_and_extract_extended() {
+        // Arrange
+        let engine = WebpEngine::new();
+        let webp = create_extended_webp();
+        let payload = b"Secret message hidden in WebP!";
+
+        // Act
+        let embedded = engine.embed(&webp, payload, EmbedMode::Capacity).unwrap();
+        let extracted = engine.extract(&embedded).unwrap();
+
+        // Assert - the chunk goes last, and the RIFF size covers it
+        assert_eq!(extracted, payload);
+        assert_eq!(
+            chunk_ids(&embedded),
+            ["VP8X", "ALPH", "VP8 ", "EXIF", "LUPN"]
+        );
+        assert_eq!(&embedded[8..webp.len()], &webp[8..]);
+        assert_eq!(
+            WebpEngine::read_u32_le(&embedded[4..8]) as usize,
+            embedded.len() - 8
+        );
+    }
+
+    #[test]
+    fn test_simple_format_converted() {
+        // Arrange
+        let engine = WebpEngine::new();
+        let lossy = create_lossy_webp();
+        let lossless = create_lossless_webp(true);
+
+        // Act
+        let embedded_lossy = engine.embed(&lossy, b"odd", EmbedMode::Capacity).unwrap();
+        let embedded_lossless = engine
+            .embed(&lossless, b"even", EmbedMode::Capacity)
+            .unwrap();
+
+        // Assert - a VP8X chunk with the canvas size (minus one) and alpha flag
+        assert_eq!(chunk_ids(&embedded_lossy), ["VP8X", "VP8 ", "LUPN"]);
+        assert_eq!(&embedded_lossy[20..30], &[0, 0, 0, 0, 43, 1, 0, 199, 0, 0]);
+        assert_eq!(
+            &embedded_lossless[20..30],
+            &[0x10, 0, 0, 0, 16, 0, 0, 8, 0, 0]
+        );
+        for (embedded, payload) in [
+            (&embedded_lossy, &b"odd"[..]),
+            (&embedded_lossless, b"even"),
+        ] {
+            assert_eq!(engine.extract(embedded).unwrap(), payload);
+            assert_eq!(
+                WebpEngine::read_u32_le(&embedded[4..8]) as usize,
+                embedded.len() - 8
+            );
+        }
+    }
+
+    #[test]
+    fn test_remove() {
+        let engine = WebpEngine::new();
+
+        // Removal restores every kind of file exactly, VP8X and all
+        for webp in [
+            create_lossy_webp(),
+            create_lossless_webp(false),
+            create_extended_webp(),
+        ] {
+            for payload in [&b"odd"[..], b"even"] {
+                let embedded = engine.embed(&webp, payload, EmbedMode::Capacity).unwrap();
+                assert_eq!(engine.remove(&embedded).unwrap(), webp);
+            }
+            assert!(matches!(
+                engine.remove(&webp),
+                Err(LupinError::WebpNoHiddenData)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_unpadded_last_chunk() {
+        // Arrange - a simple lossless WebP whose 21-byte VP8L chunk has no pad byte
+        let engine = WebpEngine::new();
+        let mut vp8l = vec![WebpEngine::VP8L_SIGNATURE];
+        vp8l.extend_from_slice(&(16 | (8 << 14) as u32).to_le_bytes());
+        vp8l.extend_from_slice(&[0xCD; 16]);
+        let padded = chunk(b"VP8L", &vp8l);
+        let webp = riff(&padded[..padded.len() - 1]);
+
+        // Act
+        let embedded = engine
+            .embed(&webp, b"payload", EmbedMode::Capacity)
+            .unwrap();
+        let restored = engine.remove(&embedded).unwrap();
+
+        // Assert - the pad byte goes in ahead of the chunk and is dropped with it
+        assert_eq!(engine.extract(&embedded).unwrap(), b"payload");
+        assert_eq!(chunk_ids(&embedded), ["VP8X", "VP8L", "LUPN"]);
+        assert_eq!(
+            WebpEngine::read_u32_le(&embedded[4..8]) as usize,
+            embedded.len() - 8
+        );
+        assert_eq!(restored, webp);
+    }
+
+    #[test]
+    fn test_trailing_bytes_kept() {
+        // Arrange - bytes after the RIFF chunk, which its size doesn't cover
+        let engine = WebpEngine::new();
+        let mut webp = create_extended_webp();
+        webp.extend_from_slice(b"trailer");
+
+        // Act
+        let embedded = engine
+            .embed(&webp, b"payload", EmbedMode::Capacity)
+            .unwrap();
+
+        // Assert
+        assert_eq!(engine.extract(&embedded).unwrap(), b"payload");
+        assert!(embedded.ends_with(b"payloadtrailer"));
+        assert_eq!(engine.remove(&embedded).unwrap(), webp);
+    }
+
+    #[test]
+    fn test_embed_collision() {
+        let engine = WebpEngine::new();
+        let embedded_once = engine
+            .embed(&create_lossy_webp(), b"First", EmbedMode::Capacity)
+            .unwrap();
+
+        let result = engine.embed(&embedded_once, b"Second", EmbedMode::Capacity);
+        assert!(matches!(result, Err(LupinError::EmbedCollision { .. })));
+    }
+
+    #[test]
+    fn test_extract_without_data() {
+        let engine = WebpEngine::new();
+
+        let result = engine.extract(&create_extended_webp());
+        assert!(matches!(result, Err(LupinError::WebpNoHiddenData)));
+    }
+
+    #[test]
+    fn test_invalid_webp() {
+        let engine = WebpEngine::new();
+        let bad_vp8 = riff(&chunk(b"VP8 ", &[0; 10]));
+
+        // No chunks, an unknown first chunk and a VP8 chunk with no key frame
+        for not_webp in [
+            &b"RIFF\x04\x00\x00\x00WEBP"[..],
+            &riff(&chunk(b"ICCP", &[0; 4])),
+            &bad_vp8,
+        ] {
+            let result = engine.embed(not_webp, b"payload", EmbedMode::Capacity);
+            assert!(matches!(result, Err(LupinError::WebpInvalidFormat { .. })));
+        }
+    }
+
+    #[test]
+    fn test_empty_payload_rejected() {
+        let engine = WebpEngine::new();
+        assert!(matches!(
+            engine.embed(&create_lossy_webp(), b"", EmbedMode::Capacity),
+            Err(LupinError::EmptyPayload)
+        ));
+    }
+
+    #[test]
+    fn test_stealth_not_supported() {
+        let engine = WebpEngine::new();
+        let webp = create_lossy_webp();
+
+        assert!(matches!(
+            engine.embed(&webp, b"payload", EmbedMode::Stealth),
+            Err(LupinError::StealthNotSupported { format: "WebP" })
+        ));
+        assert!(matches!(
+            engine.capacity(&webp, EmbedMode::Stealth),
+            Err(LupinError::StealthNotSupported { format: "WebP" })
+        ));
+    }
+
+    #[test]
+    fn test_truncated_file_does_not_panic() {
+        let engine = WebpEngine::new();
+        let webp = create_extended_webp();
+        let embedded = engine
+            .embed(&webp, &[0x42; 40], EmbedMode::Capacity)
+            .unwrap();
+
+        // Every prefix either parses or fails cleanly
+        for len in 0..embedded.len() {
+            let _ = engine.probe(&embedded[..len]);
+            let _ = engine.remove(&embedded[..len]);
+            let _ = engine.extract(&embedded[..len]);
+        }
+
+        // A cut-off chunk without error correction is reported rather than returned
+        assert!(matches!(
+            engine.extract(&embedded[..embedded.len() - 2]),
+            Err(LupinError::WebpCorruptedData)
+        ));
+        assert!(matches!(
+            engine.embed(&webp[..webp.len() - 2], b"payload", EmbedMode::Capacity),
+            Err(LupinError::WebpInvalidFormat { .. })
+        ));
+    }
+
+    #[test]
+    fn test_probe() {
+        // Arrange
+        let engine = WebpEngine::new();
+        let webp = create_lossy_webp();
+        let embedded = engine
+            .embed(&webp, b"chunk payload for probing", EmbedMode::Capacity)
+            .unwrap();
+
+        // Act
+        let clean = engine.probe(&webp).unwrap();
+        let probe = engine.probe(&embedded).unwrap().unwrap();
+
+        // Assert
+        assert_eq!(clean, None);
+        assert_eq!(probe.mode, EmbedMode::Capacity);
+        assert_eq!(probe.size, 25);
+        assert_eq!(probe.prefix, b"chunk payload fo");
+        assert_eq!(probe.ranges, vec![webp.len() + 18..embedded.len()]);
+    }
+
+    #[test]
+    fn test_capacity() {
+        let engine = WebpEngine::new();
+        assert_eq!(
+            engine
+                .capacity(&create_lossy_webp(), EmbedMode::Capacity)
+                .unwrap(),
+            Capacity::Unlimited
+        );
+    }
+}
@@ -0,0 +1,1054 @@
+// Copyright 2025 Niclas Hedam
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! ZIP steganography engine using an unlisted local entry, and the ZIP container
+//! handling shared by the ZIP-based engines
+//!
+//! # How It Works
+//!
+//! Unzip tools list an archive from its central directory, which points at a local
+//! entry for every file. Bytes between the local entries that no record points at
+//! are never listed or extracted, so this engine hides data in a local entry of its
+//! own that the central directory leaves out. This works for any ZIP-based format,
+//! such as EPUB, JAR or ODT.
+//!
+//! ## Storage Format
+//!
+//! We add a stored local entry called `.lupin` after the last file, before the
+//! central directory:
+//!
+//! ```text
+//! ["PK\x03\x04"][26 bytes: Header with CRC-32 and Sizes][".lupin"][N bytes: Raw Payload]
+//! ```
+//!
+//! The offset of the central directory in the end record is moved past it. No
+//! record is added, so the archive lists exactly the files it did before.
+//!
+//! The archive comment would hold at most 64 KiB and is printed by `unzip`, so the
+//! engine doesn't use it.
+//!
+//! An APK signed with scheme v2 or later has an APK Signing Block between the last
+//! file and the central directory, ending in `APK Sig Block 42`. Its signature
+//! covers every byte before the block, so any entry added there breaks it, and
+//! Android refuses to install the package. Such archives return
+//! [`LupinError::ZipApkSigned`] instead.
+//!
+//! ## Finding the Entry
+//!
+//! Extraction walks the local entries from the first file to the central directory,
+//! skipping listed entries by the extent their record gives and unlisted ones by the
+//! sizes in their own header. Unlisted entries with another name are skipped, and a
+//! missing signature or an entry that runs past the central directory ends the walk
+//! without panicking. The CRC-32 in the header tells a damaged payload apart.
+//!
+//! ## Stealth Mode
+//!
+//! The files are compressed, with no slack the engine can hide bits in without
+//! changing them, so [`EmbedMode::Stealth`] returns
+//! [`LupinError::StealthNotSupported`].
+//!
+//! # ZIP Structure
+//!
+//! - **Local entries**: for every file, a local header (`PK\x03\x04`, 30 bytes plus
+//!   the name and an extra field) followed by the compressed data, and a data
+//!   descriptor if flag bit 3 is set
+//! - **Central directory**: a record (`PK\x01\x02`, 46 bytes plus the name, extra
+//!   field and comment) for every file, holding the offset of its local header
+//! - **End of central directory** (`PK\x05\x06`, 22 bytes plus the archive comment):
+//!   the number of records, and the size and offset of the central directory
+//!
+//! Readers find the end record by scanning back from the end of the file, then list
+//! the archive from the central directory. Entries are added before the central
+//! directory so no existing local header moves; removing one shifts the ones after
+//! it, so the offsets in the central directory are fixed up to match.
+//!
+//! Zip64 archives and archives split across disks are rejected.
+
+use crate::error::{LupinError, Result};
+use crate::{Capacity, EmbedMode, Probe, SteganographyEngine};
+use log::debug;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Signature of a local file header
+pub(crate) const LOCAL_SIGNATURE: &[u8] = b"PK\x03\x04";
+
+/// Signature of a central directory record
+const CENTRAL_SIGNATURE: &[u8] = b"PK\x01\x02";
+
+/// Signature of the end of central directory record
+const END_SIGNATURE: &[u8] = b"PK\x05\x06";
+
+/// Signature of the Zip64 end of central directory locator
+const ZIP64_LOCATOR_SIGNATURE: &[u8] = b"PK\x06\x07";
+
+/// Signature an optional data descriptor may start with
+const DESCRIPTOR_SIGNATURE: &[u8] = b"PK\x07\x08";
+
+/// Magic ending the APK Signing Block, right before the central directory
+const APK_SIGNING_BLOCK_MAGIC: &[u8] = b"APK Sig Block 42";
+
+/// Length of a local file header before its name
+pub(crate) const LOCAL_HEADER_LEN: usize = 30;
+
+/// Length of a central directory record before its name
+const CENTRAL_HEADER_LEN: usize = 46;
+
+/// Length of the end of central directory record before its comment
+const END_LEN: usize = 22;
+
+/// Version 2.0, enough for stored entries, as written by Office
+const VERSION: u16 = 20;
+
+/// DOS date of 1980-01-01, the earliest a ZIP can record, as written by Office
+const DOS_DATE: u16 = 0x0021;
+
+/// Reads a little-endian u16 from a slice
+pub(crate) fn read_u16(data: &[u8]) -> u16 {
+    u16::from_le_bytes([data[0], data[1]])
+}
+
+/// Reads a little-endian u32 from a slice
+pub(crate) fn read_u32(data: &[u8]) -> u32 {
+    u32::from_le_bytes([data[0], data[1], data[2], data[3]])
+}
+
+/// A file listed in the central directory
+#[derive(Debug, Clone)]
+pub(crate) struct Entry {
+    /// The file name
+    pub(crate) name: Vec<u8>,
+    /// Byte range of its central directory record
+    pub(crate) record: Range<usize>,
+    /// Byte range of its local header, data and data descriptor, clamped to the file
+    pub(crate) local: Range<usize>,
+    /// Byte range of its compressed data, clamped to the file
+    pub(crate) data: Range<usize>,
+    /// Compression method: 0 for stored, 8 for deflated
+    pub(crate) method: u16,
+}
+
+/// The central directory of a ZIP archive
+#[derive(Debug)]
+pub(crate) struct Archive {
+    /// The files, in central directory order
+    pub(crate) entries: Vec<Entry>,
+    /// Byte range of the central directory
+    pub(crate) central: Range<usize>,
+    /// Byte offset of the end of central directory record
+    pub(crate) end: usize,
+}
+
+impl Archive {
+    /// Reads the central directory of a ZIP archive.
+    ///
+    /// Fails, with a reason for the engine's error, if there is no end record or the
+    /// central directory doesn't hold the records it promises. Local entries are
+    /// only located, not checked, so a damaged one can still be listed.
+    pub(crate) fn parse(data: &[u8]) -> std::result::Result<Self, &'static str> {
+        // The end record is last, before a comment of at most 64 KiB
+        let lowest = data.len().saturating_sub(END_LEN + u16::MAX as usize);
+        let end = (lowest..=data.len().saturating_sub(END_LEN))
+            .rev()
+            .filter(|&pos| pos + END_LEN <= data.len())
+            .find(|&pos| {
+                data[pos..].starts_with(END_SIGNATURE)
+                    && pos + END_LEN + read_u16(&data[pos + 20..pos + 22]) as usize <= data.len()
+            })
+            .ok_or("No end of central directory record")?;
+
+        let record = &data[end..end + END_LEN];
+        let count = read_u16(&record[10..12]);
+        let size = read_u32(&record[12..16]);
+        let offset = read_u32(&record[16..20]);
+        if read_u16(&record[4..6]) != 0 || read_u16(&record[6..8]) != 0 {
+            return Err("Archives split across disks aren't supported");
+        }
+        if count == u16::MAX
+            || size == u32::MAX
+            || offset == u32::MAX
+            || (end >= 20 && data[end - 20..].starts_with(ZIP64_LOCATOR_SIGNATURE))
+        {
+            return Err("Zip64 archives aren't supported");
+        }
+
+        let central = offset as usize..offset as usize + size as usize;
+        if central.end > end {
+            return Err("Central directory runs past its end record");
+        }
+
+        let mut entries = Vec::with_capacity(count as usize);
+        let mut pos = central.start;
+        for _ in 0..count {
+            if pos + CENTRAL_HEADER_LEN > central.end || !data[pos..].starts_with(CENTRAL_SIGNATURE)
+            {
+                return Err("Central directory record is missing or cut short");
+            }
+            let header = &data[pos..pos + CENTRAL_HEADER_LEN];
+            let name_len = read_u16(&header[28..30]) as usize;
+            let record_end = pos
+                + CENTRAL_HEADER_LEN
+                + name_len
+                + read_u16(&header[30..32]) as usize
+                + read_u16(&header[32..34]) as usize;
+            if record_end > central.end {
+                return Err("Central directory record is cut short");
+            }
+
+            let name_start = pos + CENTRAL_HEADER_LEN;
+            let (local, data_range) = Self::locate_local(
+                data,
+                read_u32(&header[42..46]) as usize,
+                read_u32(&header[20..24]) as usize,
+                read_u16(&header[8..10]),
+            );
+            entries.push(Entry {
+                name: data[name_start..name_start + name_len].to_vec(),
+                record: pos..record_end,
+                local,
+                data: data_range,
+                method: read_u16(&header[10..12]),
+            });
+            pos = record_end;
+        }
+
+        Ok(Self {
+            entries,
+            central,
+            end,
+        })
+    }
+
+    /// Finds the local header, data and data descriptor of a file, clamped to the
+    /// file. The sizes come from the central directory, since a local header with a
+    /// data descriptor may leave them zero.
+    fn locate_local(
+        data: &[u8],
+        offset: usize,
+        compressed_len: usize,
+        flags: u16,
+    ) -> (Range<usize>, Range<usize>) {
+        let clamp = |pos: usize| pos.min(data.len());
+        let Some(header) = data.get(offset..offset.saturating_add(LOCAL_HEADER_LEN)) else {
+            return (clamp(offset)..data.len(), data.len()..data.len());
+        };
+        let data_start = offset
+            + LOCAL_HEADER_LEN
+            + read_u16(&header[26..28]) as usize
+            + read_u16(&header[28..30]) as usize;
+        let data_end = data_start.saturating_add(compressed_len);
+
+        let mut local_end = data_end;
+        if flags & 0x08 != 0 {
+            let signed = data
+                .get(data_end..)
+                .is_some_and(|rest| rest.starts_with(DESCRIPTOR_SIGNATURE));
+            local_end = local_end.saturating_add(if signed { 16 } else { 12 });
+        }
+        (offset..clamp(local_end), clamp(data_start)..clamp(data_end))
+    }
+
+    /// Whether an APK Signing Block sits right before the central directory
+    pub(crate) fn has_apk_signing_block(&self, data: &[u8]) -> bool {
+        self.central
+            .start
+            .checked_sub(APK_SIGNING_BLOCK_MAGIC.len())
+            .is_some_and(|start| &data[start..self.central.start] == APK_SIGNING_BLOCK_MAGIC)
+    }
+
+    /// Finds a file by name
+    pub(crate) fn find(&self, name: &[u8]) -> Option<&Entry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    /// Rewrites the archive with a local entry added before the central directory
+    /// and, if given, its record added at the end of the central directory.
+    ///
+    /// Fails if the archive would outgrow the 32-bit offsets or 16-bit count.
+    pub(crate) fn insert(
+        &self,
+        data: &[u8],
+        local: &[u8],
+        record: Option<&[u8]>,
+    ) -> std::result::Result<Vec<u8>, &'static str> {
+        let record = record.unwrap_or_default();
+        let count = self.entries.len() + usize::from(!record.is_empty());
+        let central_len = self.central.len() + record.len();
+        if count >= u16::MAX as usize
+            || self.central.start + local.len() + central_len >= u32::MAX as usize
+        {
+            return Err("Archive would need Zip64");
+        }
+
+        let mut output = Vec::with_capacity(data.len() + local.len() + record.len());
+        output.extend_from_slice(&data[..self.central.start]);
+        output.extend_from_slice(local);
+        output.extend_from_slice(&data[self.central.clone()]);
+        output.extend_from_slice(record);
+        output.extend_from_slice(&data[self.central.end..self.end]);
+        let end = output.len();
+        output.extend_from_slice(&data[self.end..]);
+
+        Self::write_end(
+            &mut output[end..],
+            count,
+            central_len,
+            self.central.start + local.len(),
+        );
+        Ok(output)
+    }
+
+    /// Rewrites the archive with a range of local entry bytes and, if given, a
+    /// central directory record taken out, fixing up the offsets of the entries
+    /// after it
+    pub(crate) fn remove(
+        &self,
+        data: &[u8],
+        local: Range<usize>,
+        record: Option<Range<usize>>,
+    ) -> Vec<u8> {
+        let record = record.unwrap_or(self.central.end..self.central.end);
+        let shift = |offset: usize| {
+            if offset >= local.end {
+                offset - local.len()
+            } else {
+                offset
+            }
+        };
+
+        let mut output = Vec::with_capacity(data.len());
+        output.extend_from_slice(&data[..local.start]);
+        output.extend_from_slice(&data[local.end..self.central.start]);
+        for entry in self.entries.iter().filter(|entry| entry.record != record) {
+            let start = output.len();
+            output.extend_from_slice(&data[entry.record.clone()]);
+            let offset = shift(entry.local.start) as u32;
+            output[start + 42..start + 46].copy_from_slice(&offset.to_le_bytes());
+        }
+        let central_len = self.central.len() - record.len();
+        output.extend_from_slice(&data[self.central.end..self.end]);
+        let end = output.len();
+        output.extend_from_slice(&data[self.end..]);
+
+        let count = self.entries.len() - usize::from(!record.is_empty());
+        Self::write_end(
+            &mut output[end..],
+            count,
+            central_len,
+            shift(self.central.start),
+        );
+        output
+    }
+
+    /// Updates the counts, size and offset of an end of central directory record
+    fn write_end(end: &mut [u8], count: usize, central_len: usize, central_offset: usize) {
+        end[8..10].copy_from_slice(&(count as u16).to_le_bytes());
+        end[10..12].copy_from_slice(&(count as u16).to_le_bytes());
+        end[12..16].copy_from_slice(&(central_len as u32).to_le_bytes());
+        end[16..20].copy_from_slice(&(central_offset as u32).to_le_bytes());
+    }
+}
+
+/// A local entry read from its own header, for entries the central directory
+/// doesn't list
+#[derive(Debug, Clone)]
+pub(crate) struct LocalEntry {
+    /// The file name
+    pub(crate) name: Vec<u8>,
+    /// Byte range of its local header and data
+    pub(crate) local: Range<usize>,
+    /// Byte range of its compressed data
+    pub(crate) data: Range<usize>,
+    /// The CRC-32 of its uncompressed data
+    pub(crate) crc: u32,
+}
+
+impl LocalEntry {
+    /// Reads the local entry at `pos`, or `None` if there isn't a complete one there.
+    /// An entry whose sizes follow it in a data descriptor can't be measured from its
+    /// header, so it counts as missing.
+    pub(crate) fn read(data: &[u8], pos: usize) -> Option<Self> {
+        let header = data.get(pos..pos.checked_add(LOCAL_HEADER_LEN)?)?;
+        if !header.starts_with(LOCAL_SIGNATURE) || read_u16(&header[6..8]) & 0x08 != 0 {
+            return None;
+        }
+
+        let name_start = pos + LOCAL_HEADER_LEN;
+        let name_end = name_start + read_u16(&header[26..28]) as usize;
+        let data_start = name_end + read_u16(&header[28..30]) as usize;
+        let data_end = data_start.checked_add(read_u32(&header[18..22]) as usize)?;
+        if data_end > data.len() {
+            return None;
+        }
+
+        Some(Self {
+            name: data[name_start..name_end].to_vec(),
+            local: pos..data_end,
+            data: data_start..data_end,
+            crc: read_u32(&header[14..18]),
+        })
+    }
+}
+
+/// ZIP steganography engine
+///
+/// Stores the payload in a local entry that the central directory doesn't list, so
+/// unzip tools neither show nor extract it.
+///
+/// See the module documentation for details on how data is stored and limitations.
+pub struct ZipEngine;
+
+impl ZipEngine {
+    /// Creates a new ZIP engine
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Name of our local entry
+    const ENTRY_NAME: &'static [u8] = b".lupin";
+
+    /// Reads the central directory
+    fn parse_archive(data: &[u8]) -> Result<Archive> {
+        Archive::parse(data).map_err(|reason| LupinError::ZipInvalidFormat {
+            reason: reason.to_string(),
+        })
+    }
+
+    /// Finds every unlisted `.lupin` local entry, walking the local entries from the
+    /// first file to the central directory.
+    ///
+    /// Listed entries are skipped by the extent their record gives, since their own
+    /// header may leave the sizes to a data descriptor; unlisted ones by the sizes in
+    /// their header. Unlisted entries with another name are skipped over. A missing
+    /// signature or an entry that runs past the central directory terminates the walk
+    /// without panicking.
+    fn find_lupin_entries(&self, data: &[u8], archive: &Archive) -> Vec<LocalEntry> {
+        let listed: HashMap<usize, usize> = archive
+            .entries
+            .iter()
+            .map(|entry| (entry.local.start, entry.local.end))
+            .collect();
+
+        let mut entries = Vec::new();
+        let mut pos = archive
+            .entries
+            .iter()
+            .map(|entry| entry.local.start)
+            .min()
+            .unwrap_or(0);
+
+        while pos < archive.central.start {
+            if let Some(&end) = listed.get(&pos) {
+                if end <= pos || end > archive.central.start {
+                    break;
+                }
+                pos = end;
+                continue;
+            }
+
+            let Some(entry) = LocalEntry::read(data, pos)
+                .filter(|entry| entry.local.end <= archive.central.start)
+            else {
+                break;
+            };
+            pos = entry.local.end;
+            if entry.name == Self::ENTRY_NAME {
+                entries.push(entry);
+            }
+        }
+
+        entries
+    }
+}
+
+impl Default for ZipEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SteganographyEngine for ZipEngine {
+    fn magic_bytes(&self) -> &[u8] {
+        LOCAL_SIGNATURE
+    }
+
+    fn format_name(&self) -> &str {
+        "ZIP"
+    }
+
+    fn format_ext(&self) -> &str {
+        ".zip"
+    }
+
+    fn capacity(&self, source_data: &[u8], mode: EmbedMode) -> Result<Capacity> {
+        match mode {
+            // Only the 32-bit offsets of a ZIP without Zip64 limit it
+            EmbedMode::Capacity => {
+                let archive = Self::parse_archive(source_data)?;
+                if archive.has_apk_signing_block(source_data) {
+                    return Err(LupinError::ZipApkSigned);
+                }
+                Ok(Capacity::Unlimited)
+            }
+            EmbedMode::Stealth => Err(LupinError::StealthNotSupported { format: "ZIP" }),
+        }
+    }
+
+    fn embed(&self, source_data: &[u8], payload: &[u8], mode: EmbedMode) -> Result<Vec<u8>> {
+        // Reject empty payloads so the embed contract is uniform across engines.
+        if payload.is_empty() {
+            return Err(LupinError::EmptyPayload);
+        }
+
+        // Refuse to embed into an archive that already carries a Lupin entry; a
+        // second one would be silently lost on extract (which returns the first match).
+        let archive = Self::parse_archive(source_data)?;
+        if !self.find_lupin_entries(source_data, &archive).is_empty() {
+            return Err(LupinError::EmbedCollision {
+                source: std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    "ZIP already contains a Lupin entry",
+                ),
+            });
+        }
+
+        // Exhaustive so a future EmbedMode variant is a compile error here rather than
+        // silently falling through to the capacity implementation below.
+        match mode {
+            EmbedMode::Capacity => {}
+            EmbedMode::Stealth => return Err(LupinError::StealthNotSupported { format: "ZIP" }),
+        }
+
+        // The APK signature covers everything before the central directory, so the
+        // entry can go neither before nor after its signing block
+        if archive.has_apk_signing_block(source_data) {
+            return Err(LupinError::ZipApkSigned);
+        }
+
+        debug!(
+            "ZIP: Inserting {} byte unlisted entry at position {}",
+            LOCAL_HEADER_LEN + Self::ENTRY_NAME.len() + payload.len(),
+            archive.central.start
+        );
+
+        // Only the local entry; without a record it stays out of every listing
+        let (local, _) = stored_entry(Self::ENTRY_NAME, payload, archive.central.start);
+        archive
+            .insert(source_data, &local, None)
+            .map_err(|_| LupinError::PayloadTooLarge {
+                capacity: (u32::MAX as usize)
+                    .saturating_sub(source_data.len() + LOCAL_HEADER_LEN + Self::ENTRY_NAME.len()),
+                requested: payload.len(),
+            })
+    }
+
+    fn probe(&self, source_data: &[u8]) -> Result<Option<Probe>> {
+        let archive = Self::parse_archive(source_data)?;
+        let Some(entry) = self
+            .find_lupin_entries(source_data, &archive)
+            .into_iter()
+            .next()
+        else {
+            return Ok(None);
+        };
+
+        let prefix_end = entry.data.end.min(entry.data.start + Probe::PREFIX_LEN);
+        Ok(Some(Probe::new(
+            EmbedMode::Capacity,
+            entry.data.len(),
+            vec![entry.local],
+            source_data[entry.data.start..prefix_end].to_vec(),
+        )))
+    }
+
+    fn remove(&self, source_data: &[u8]) -> Result<Vec<u8>> {
+        let archive = Self::parse_archive(source_data)?;
+        let entry = self
+            .find_lupin_entries(source_data, &archive)
+            .into_iter()
+            .next()
+            .ok_or(LupinError::ZipNoHiddenData)?;
+
+        debug!(
+            "ZIP: Removing Lupin entry at {}-{}",
+            entry.local.start, entry.local.end
+        );
+
+        Ok(archive.remove(source_data, entry.local, None))
+    }
+
+    fn extract(&self, source_data: &[u8]) -> Result<Vec<u8>> {
+        // Read the payload from our unlisted entry, skipping every other entry
+        let archive = Self::parse_archive(source_data)?;
+        let entry = self
+            .find_lupin_entries(source_data, &archive)
+            .into_iter()
+            .next()
+            .ok_or(LupinError::ZipNoHiddenData)?;
+
+        debug!(
+            "ZIP: Found Lupin entry at {}-{}",
+            entry.local.start, entry.local.end
+        );
+
+        // A payload failing its CRC is only returned if error correction can repair it
+        let payload = &source_data[entry.data];
+        if crc32fast::hash(payload) != entry.crc && !crate::fec::is_fec(payload) {
+            return Err(LupinError::ZipCorruptedData);
+        }
+        Ok(payload.to_vec())
+    }
+}
+
+/// Builds the local header and central directory record of a stored (uncompressed)
+/// file whose local header will sit at `offset`
+pub(crate) fn stored_entry(name: &[u8], contents: &[u8], offset: usize) -> (Vec<u8>, Vec<u8>) {
+    let crc = crc32fast::hash(contents);
+
+    // Fields the local header and central record share: version needed, flags,
+    // method, time, date, CRC, sizes and name length, then extra field length
+    let mut common = Vec::with_capacity(26);
+    common.extend_from_slice(&VERSION.to_le_bytes());
+    common.extend_from_slice(&0u16.to_le_bytes());
+    common.extend_from_slice(&0u16.to_le_bytes());
+    common.extend_from_slice(&0u16.to_le_bytes());
+    common.extend_from_slice(&DOS_DATE.to_le_bytes());
+    common.extend_from_slice(&crc.to_le_bytes());
+    common.extend_from_slice(&(contents.len() as u32).to_le_bytes());
+    common.extend_from_slice(&(contents.len() as u32).to_le_bytes());
+    common.extend_from_slice(&(name.len() as u16).to_le_bytes());
+    common.extend_from_slice(&0u16.to_le_bytes());
+
+    let mut local = Vec::with_capacity(LOCAL_HEADER_LEN + name.len() + contents.len());
+    local.extend_from_slice(LOCAL_SIGNATURE);
+    local.extend_from_slice(&common);
+    local.extend_from_slice(name);
+    local.extend_from_slice(contents);
+
+    // Plus version made by, comment length, disk, attributes and the local offset
+    let mut record = Vec::with_capacity(CENTRAL_HEADER_LEN + name.len());
+    record.extend_from_slice(CENTRAL_SIGNATURE);
+    record.extend_from_slice(&VERSION.to_le_bytes());
+    record.extend_from_slice(&common);
+    record.extend_from_slice(&[0; 10]);
+    record.extend_from_slice(&(offset as u32).to_le_bytes());
+    record.extend_from_slice(name);
+
+    (local, record)
+}
+
+/// Builds an archive of the given files, stored or deflated, with an archive comment
+#[cfg(test)]
+pub(crate) fn create_archive(files: &[(&str, &[u8])], deflate: bool, comment: &[u8]) -> Vec<u8> {
+    use flate2::write::DeflateEncoder;
+    use std::io::Write;
+
+    let mut archive = Vec::new();
+    let mut central = Vec::new();
+    for (name, contents) in files {
+        let mut compressed = contents.to_vec();
+        if deflate {
+            let mut encoder = DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(contents).unwrap();
+            compressed = encoder.finish().unwrap();
+        }
+        let (mut local, mut record) = stored_entry(name.as_bytes(), &compressed, archive.len());
+        if deflate {
+            // Method, CRC and uncompressed size, at the same spot after the version
+            for (header, common) in [(&mut local, 4), (&mut record, 6)] {
+                header[common + 4..common + 6].copy_from_slice(&8u16.to_le_bytes());
+                header[common + 10..common + 14]
+                    .copy_from_slice(&crc32fast::hash(contents).to_le_bytes());
+                header[common + 18..common + 22]
+                    .copy_from_slice(&(contents.len() as u32).to_le_bytes());
+            }
+        }
+        archive.extend_from_slice(&local);
+        central.extend_from_slice(&record);
+    }
+
+    let offset = archive.len();
+    archive.extend_from_slice(&central);
+    archive.extend_from_slice(END_SIGNATURE);
+    archive.extend_from_slice(&[0; 4]);
+    archive.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    archive.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    archive.extend_from_slice(&(central.len() as u32).to_le_bytes());
+    archive.extend_from_slice(&(offset as u32).to_le_bytes());
+    archive.extend_from_slice(&(comment.len() as u16).to_le_bytes());
+    archive.extend_from_slice(comment);
+    archive
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_archive() -> Vec<u8> {
+        create_archive(
+            &[("first.txt", b"first file"), ("second.txt", b"second file")],
+            false,
+            b"archive comment",
+        )
+    }
+
+    #[test]
+    fn test_parse() {
+        // Arrange
+        let data = create_test_archive();
+
+        // Act
+        let archive = Archive::parse(&data).unwrap();
+
+        // Assert
+        let names: Vec<&[u8]> = archive.entries.iter().map(|e| &e.name[..]).collect();
+        assert_eq!(names, [&b"first.txt"[..], b"second.txt"]);
+        assert_eq!(&data[archive.entries[1].data.clone()], b"second file");
+        assert_eq!(archive.entries[1].local.start, archive.entries[0].local.end);
+        assert_eq!(archive.entries[1].local.end, archive.central.start);
+        assert!(data[archive.end..].ends_with(b"archive comment"));
+    }
+
+    /// An archive whose first file's sizes follow its data in a signed descriptor
+    fn create_descriptor_archive() -> (Vec<u8>, usize) {
+        let (mut first, mut first_record) = stored_entry(b"first.txt", b"first file", 0);
+        first[6] |= 0x08;
+        first_record[8] |= 0x08;
+        let descriptor = [DESCRIPTOR_SIGNATURE, &first[14..26]].concat();
+        first.extend_from_slice(&descriptor);
+        let (second, second_record) = stored_entry(b"second.txt", b"second file", first.len());
+        let central = [first_record, second_record].concat();
+        let mut data = [first.clone(), second].concat();
+        let offset = data.len() as u32;
+        data.extend_from_slice(&central);
+        data.extend_from_slice(END_SIGNATURE);
+        data.extend_from_slice(&[0, 0, 0, 0, 2, 0, 2, 0]);
+        data.extend_from_slice(&(central.len() as u32).to_le_bytes());
+        data.extend_from_slice(&offset.to_le_bytes());
+        data.extend_from_slice(&[0, 0]);
+        (data, first.len())
+    }
+
+    #[test]
+    fn test_parse_data_descriptor() {
+        // Arrange
+        let (data, first_len) = create_descriptor_archive();
+
+        // Act
+        let archive = Archive::parse(&data).unwrap();
+
+        // Assert - the descriptor belongs to the first file's local entry
+        assert_eq!(archive.entries[0].local, 0..first_len);
+        assert_eq!(archive.entries[1].local.start, archive.entries[0].local.end);
+        assert_eq!(&data[archive.entries[1].data.clone()], b"second file");
+    }
+
+    #[test]
+    fn test_insert_and_remove() {
+        // Arrange
+        let data = create_test_archive();
+        let archive = Archive::parse(&data).unwrap();
+        let (local, record) = stored_entry(b"third.txt", b"third file", archive.central.start);
+
+        // Act
+        let inserted = archive.insert(&data, &local, Some(&record)).unwrap();
+        let with_third = Archive::parse(&inserted).unwrap();
+        let third = with_third.entries[2].clone();
+        let removed = with_third.remove(&inserted, third.local, Some(third.record));
+
+        // Assert
+        assert_eq!(
+            &inserted[..archive.central.start],
+            &data[..archive.central.start]
+        );
+        assert_eq!(&inserted[with_third.entries[2].data.clone()], b"third file");
+        assert_eq!(removed, data);
+    }
+
+    #[test]
+    fn test_remove_fixes_offsets() {
+        // Arrange
+        let data = create_test_archive();
+        let archive = Archive::parse(&data).unwrap();
+        let first = archive.entries[0].clone();
+
+        // Act
+        let removed = archive.remove(&data, first.local, Some(first.record));
+        let after = Archive::parse(&removed).unwrap();
+
+        // Assert - the second file moved up and its record points at it
+        assert_eq!(after.entries.len(), 1);
+        assert_eq!(after.entries[0].local.start, 0);
+        assert_eq!(&removed[after.entries[0].data.clone()], b"second file");
+    }
+
+    #[test]
+    fn test_parse_rejects() {
+        let data = create_test_archive();
+        let end = Archive::parse(&data).unwrap().end;
+
+        // No end record, a central directory past its end, and Zip64
+        let mut past_end = data.clone();
+        past_end[end + 16..end + 20].copy_from_slice(&(end as u32).to_le_bytes());
+        let mut zip64 = data.clone();
+        zip64[end + 8..end + 12].copy_from_slice(&[0xFF; 4]);
+        for not_zip in [&data[..end], &past_end, &zip64] {
+            assert!(Archive::parse(not_zip).is_err());
+        }
+    }
+
+    #[test]
+    fn test_parse_truncated_does_not_panic() {
+        let data = create_test_archive();
+        for len in 0..data.len() {
+            let _ = Archive::parse(&data[..len]);
+        }
+    }
+
+    fn names(data: &[u8]) -> Vec<Vec<u8>> {
+        let archive = Archive::parse(data).unwrap();
+        archive
+            .entries
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect()
+    }
+
+    #[test]
+    fn test_zip_magic_bytes() {
+        let engine = ZipEngine::new();
+        assert_eq!(engine.magic_bytes(), b"PK\x03\x04");
+        assert!(engine.matches(&create_test_archive()));
+    }
+
+    #[test]
+    fn test_zip_format_info() {
+        let engine = ZipEngine::new();
+        assert_eq!(engine.format_name(), "ZIP");
+        assert_eq!(engine.format_ext(), ".zip");
+    }
+
+    #[test]
+    fn test_embed_and_extract() {
+        // Arrange
+        let engine = ZipEngine::new();
+        let zip = create_test_archive();
+        let central = Archive::parse(&zip).unwrap().central;
+        let payload = b"Secret message hidden in a ZIP!";
+
+        // Act
+        let embedded = engine.embed(&zip, payload, EmbedMode::Capacity).unwrap();
+        let extracted = engine.extract(&embedded).unwrap();
+
+        // Assert - the archive lists the same files and the existing ones didn't move
+        assert_eq!(extracted, payload);
+        assert_eq!(names(&embedded), names(&zip));
+        assert_eq!(&embedded[..central.start], &zip[..central.start]);
+        assert!(embedded.ends_with(b"archive comment"));
+    }
+
+    #[test]
+    fn test_embed_skips_entries() {
+        // Arrange - a listed file with a data descriptor and a foreign unlisted entry
+        let engine = ZipEngine::new();
+        let (zip, _) = create_descriptor_archive();
+        let archive = Archive::parse(&zip).unwrap();
+        let (foreign, _) = stored_entry(b"foreign", b"not ours", archive.central.start);
+        let zip = archive.insert(&zip, &foreign, None).unwrap();
+
+        // Act
+        let embedded = engine.embed(&zip, b"payload", EmbedMode::Capacity).unwrap();
+
+        // Assert
+        assert_eq!(engine.extract(&embedded).unwrap(), b"payload");
+        assert_eq!(engine.remove(&embedded).unwrap(), zip);
+        assert!(matches!(
+            engine.extract(&zip),
+            Err(LupinError::ZipNoHiddenData)
+        ));
+    }
+
+    #[test]
+    fn test_remove() {
+        // Arrange
+        let engine = ZipEngine::new();
+        let zip = create_test_archive();
+        let embedded = engine.embed(&zip, b"payload", EmbedMode::Capacity).unwrap();
+
+        // Act
+        let removed = engine.remove(&embedded).unwrap();
+
+        // Assert
+        assert_eq!(removed, zip);
+        assert!(matches!(
+            engine.remove(&zip),
+            Err(LupinError::ZipNoHiddenData)
+        ));
+    }
+
+    #[test]
+    fn test_remove_with_later_file() {
+        // Arrange - another file added after ours
+        let engine = ZipEngine::new();
+        let embedded = engine
+            .embed(&create_test_archive(), b"payload", EmbedMode::Capacity)
+            .unwrap();
+        let archive = Archive::parse(&embedded).unwrap();
+        let (local, record) = stored_entry(b"third.txt", b"third file", archive.central.start);
+        let updated = archive.insert(&embedded, &local, Some(&record)).unwrap();
+
+        // Act
+        let removed = engine.remove(&updated).unwrap();
+
+        // Assert - the later file's offset was fixed up
+        let archive = Archive::parse(&removed).unwrap();
+        assert_eq!(
+            &removed[archive.find(b"third.txt").unwrap().data.clone()],
+            b"third file"
+        );
+        assert_eq!(archive.entries[2].local.start, archive.entries[1].local.end);
+    }
+
+    #[test]
+    fn test_embed_collision() {
+        let engine = ZipEngine::new();
+        let embedded_once = engine
+            .embed(&create_test_archive(), b"First", EmbedMode::Capacity)
+            .unwrap();
+
+        let result = engine.embed(&embedded_once, b"Second", EmbedMode::Capacity);
+        assert!(matches!(result, Err(LupinError::EmbedCollision { .. })));
+    }
+
+    #[test]
+    fn test_damaged_payload() {
+        // Arrange
+        let engine = ZipEngine::new();
+        let mut embedded = engine
+            .embed(&create_test_archive(), b"payload", EmbedMode::Capacity)
+            .unwrap();
+        let central = Archive::parse(&embedded).unwrap().central;
+        embedded[central.start - 1] ^= 0x01;
+
+        // Act
+        let result = engine.extract(&embedded);
+
+        // Assert - without error correction it is reported rather than returned
+        assert!(matches!(result, Err(LupinError::ZipCorruptedData)));
+    }
+
+    #[test]
+    fn test_invalid_zip() {
+        let engine = ZipEngine::new();
+        let zip = create_test_archive();
+
+        let result = engine.embed(&zip[..zip.len() - 40], b"payload", EmbedMode::Capacity);
+        assert!(matches!(result, Err(LupinError::ZipInvalidFormat { .. })));
+    }
+
+    #[test]
+    fn test_empty_payload_rejected() {
+        let engine = ZipEngine::new();
+        assert!(matches!(
+            engine.embed(&create_test_archive(), b"", EmbedMode::Capacity),
+            Err(LupinError::EmptyPayload)
+        ));
+    }
+
+    #[test]
+    fn test_stealth_not_supported() {
+        let engine = ZipEngine::new();
+        let zip = create_test_archive();
+
+        assert!(matches!(
+            engine.embed(&zip, b"payload", EmbedMode::Stealth),
+            Err(LupinError::StealthNotSupported { format: "ZIP" })
+        ));
+        assert!(matches!(
+            engine.capacity(&zip, EmbedMode::Stealth),
+            Err(LupinError::StealthNotSupported { format: "ZIP" })
+        ));
+    }
+
+    #[test]
+    fn test_truncated_file_does_not_panic() {
+        let engine = ZipEngine::new();
+        let embedded = engine
+            .embed(&create_test_archive(), &[0x42; 40], EmbedMode::Capacity)
+            .unwrap();
+
+        // Every prefix either parses or fails cleanly
+        for len in 0..embedded.len() {
+            let _ = engine.probe(&embedded[..len]);
+            let _ = engine.remove(&embedded[..len]);
+            let _ = engine.extract(&embedded[..len]);
+        }
+    }
+
+    #[test]
+    fn test_apk_signing_block_refused() {
+        // Arrange - a v2-signed APK: an APK Signing Block before the central directory
+        let engine = ZipEngine::new();
+        let zip = create_test_archive();
+        let mut block = 24u64.to_le_bytes().to_vec();
+        block.extend_from_slice(&24u64.to_le_bytes());
+        block.extend_from_slice(APK_SIGNING_BLOCK_MAGIC);
+        let apk = Archive::parse(&zip)
+            .unwrap()
+            .insert(&zip, &block, None)
+            .unwrap();
+
+        // Act
+        let embedded = engine.embed(&apk, b"payload", EmbedMode::Capacity);
+        let capacity = engine.capacity(&apk, EmbedMode::Capacity);
+
+        // Assert - the unsigned archive is still fine
+        assert!(matches!(embedded, Err(LupinError::ZipApkSigned)));
+        assert!(matches!(capacity, Err(LupinError::ZipApkSigned)));
+        assert!(engine.embed(&zip, b"payload", EmbedMode::Capacity).is_ok());
+    }
+
+    #[test]
+    fn test_probe() {
+        // Arrange
+        let engine = ZipEngine::new();
+        let zip = create_test_archive();
+        let central = Archive::parse(&zip).unwrap().central;
+        let embedded = engine
+            .embed(&zip, b"entry payload for probing", EmbedMode::Capacity)
+            .unwrap();
+
+        // Act
+        let clean = engine.probe(&zip).unwrap();
+        let probe = engine.probe(&embedded).unwrap().unwrap();
+
+        // Assert - the range covers the whole local entry
+        assert_eq!(clean, None);
+        assert_eq!(probe.mode, EmbedMode::Capacity);
+        assert_eq!(probe.size, 25);
+        assert_eq!(probe.prefix, b"entry payload fo");
+        assert_eq!(probe.ranges, vec![central.start..central.start + 61]);
+    }
+
+    #[test]
+    fn test_capacity() {
+        let engine = ZipEngine::new();
+        assert_eq!(
+            engine
+                .capacity(&create_test_archive(), EmbedMode::Capacity)
+                .unwrap(),
+            Capacity::Unlimited
+        );
+    }
+}
@@ -0,0 +1,742 @@
+// Copyright 2025 Niclas Hedam
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Self-describing envelope around a payload
+//!
+//! Every payload is wrapped in an envelope before it is encrypted and handed to an
+//! engine, so extraction can recover the original filename and content type and can
+//! tell a complete payload from a truncated or damaged one:
+//!
+//! ```text
+//! [4 bytes: "LpEv"][1 byte: Version = 1][1 byte: Flags]
+//! [2 bytes: Filename Length][Filename (UTF-8)]
+//! [2 bytes: Content Type Length][Content Type (UTF-8)]
+//! [8 bytes: Payload Length][N bytes: Payload]
+//! [4 bytes: CRC32][32 bytes: SHA-256]
+//! [32 bytes: Signer's Public Key][64 bytes: Ed25519 Signature]   (signed only)
+//! ```
+//!
+//! All integers are big-endian and an empty filename or content type means "unknown".
+//! The CRC32 covers everything from the magic to the end of the payload, catching a
+//! damaged header as well as a damaged payload; the SHA-256 covers the original
+//! payload alone, so it can be compared against a hash of the original file.
+//!
+//! The low two flag bits record the [`Compression`] (0 none, 1 DEFLATE, 2 zstd). A
+//! compressed payload starts with its 8-byte uncompressed length, followed by the
+//! compressed data, and the payload length field counts both. An envelope with
+//! unknown flags or a newer version is rejected rather than misread.
+//!
+//! Flag bit 2 marks a signed envelope (see [`sign`]), which ends with the signer's
+//! public key and an Ed25519 signature over everything before the signature.
+//!
+//! Data that doesn't start with the magic is treated as a bare payload from before
+//! envelopes existed; see [`is_envelope`].
+
+use crate::compression::{self, Compression};
+use crate::error::{LupinError, Result};
+use crate::signing::{SigningKey, VerifyingKey, PUBLIC_KEY_LEN, SIGNATURE_LEN};
+use sha2::{Digest, Sha256};
+
+/// Marks an enveloped payload
+pub const ENVELOPE_MAGIC: &[u8] = b"LpEv";
+
+/// Current version of the envelope format
+pub const ENVELOPE_VERSION: u8 = 1;
+
+/// Flag bits holding the compression algorithm
+const COMPRESSION_MASK: u8 = 0b11;
+
+const COMPRESSION_NONE: u8 = 0;
+const COMPRESSION_DEFLATE: u8 = 1;
+const COMPRESSION_ZSTD: u8 = 2;
+
+/// Flag bit marking a signed envelope
+const FLAG_SIGNED: u8 = 0b100;
+
+/// Flag bits this version understands
+const KNOWN_FLAGS: u8 = COMPRESSION_MASK | FLAG_SIGNED;
+
+/// Magic, version, flags, filename length, content type length and payload length
+const HEADER_LEN: usize = 4 + 1 + 1 + 2 + 2 + 8;
+
+/// CRC32 and SHA-256
+const TRAILER_LEN: usize = 4 + 32;
+
+/// Fixed number of bytes an envelope adds around the payload and its metadata
+pub const ENVELOPE_OVERHEAD: usize = HEADER_LEN + TRAILER_LEN;
+
+/// Bytes [`sign`] adds to an envelope: the signer's public key and the signature
+pub const SIGNATURE_OVERHEAD: usize = PUBLIC_KEY_LEN + SIGNATURE_LEN;
+
+/// A raw Ed25519 signature
+type Signature = [u8; SIGNATURE_LEN];
+
+/// An unwrapped payload together with the metadata stored alongside it
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Envelope {
+    /// Envelope format version
+    pub version: u8,
+    /// Flag bits
+    pub flags: u8,
+    /// Original filename, if one was stored
+    pub filename: Option<String>,
+    /// MIME type of the payload, if one was stored
+    pub content_type: Option<String>,
+    /// How the payload was compressed inside the envelope
+    pub compression: Compression,
+    /// Size of the payload as stored, after compression
+    pub stored_size: usize,
+    /// Size of the original payload
+    pub size: usize,
+    /// CRC32 of the envelope up to the end of the payload
+    pub crc32: u32,
+    /// SHA-256 of the payload
+    pub sha256: [u8; 32],
+    /// Public key of whoever signed the envelope; [`unwrap`] has checked the
+    /// signature, [`peek`] has not
+    pub signer: Option<VerifyingKey>,
+    /// The payload itself
+    pub payload: Vec<u8>,
+}
+
+/// What [`wrap`] stored for the payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Stored {
+    /// Compression actually used, which is [`Compression::None`] when the requested
+    /// algorithm didn't make the payload smaller
+    pub compression: Compression,
+    /// Size of the payload as stored, after compression
+    pub size: usize,
+}
+
+/// Returns true if the data starts like an enveloped payload
+pub fn is_envelope(data: &[u8]) -> bool {
+    data.starts_with(ENVELOPE_MAGIC)
+}
+
+/// Returns the envelope version from the first bytes of an enveloped payload, or
+/// `None` if they aren't an envelope
+pub fn version(data: &[u8]) -> Option<u8> {
+    if !is_envelope(data) {
+        return None;
+    }
+    data.get(ENVELOPE_MAGIC.len()).copied()
+}
+
+/// Wraps a payload in an envelope with the given filename and content type,
+/// compressing it with `compression` if that makes it smaller
+pub fn wrap(
+    payload: &[u8],
+    filename: Option<&str>,
+    content_type: Option<&str>,
+    compression: Compression,
+) -> Result<(Vec<u8>, Stored)> {
+    let filename = filename.unwrap_or_default().as_bytes();
+    let content_type = content_type.unwrap_or_default().as_bytes();
+
+    // Compressed data is prefixed with the uncompressed length, so only keep it if it
+    // still comes out smaller
+    let mut stored = Vec::new();
+    if compression != Compression::None {
+        stored.extend_from_slice(&(payload.len() as u64).to_be_bytes());
+        stored.extend_from_slice(&compression::compress(payload, compression)?);
+    }
+    let (compression, stored) = if !stored.is_empty() && stored.len() < payload.len() {
+        (compression, &stored[..])
+    } else {
+        (Compression::None, payload)
+    };
+
+    let mut out =
+        Vec::with_capacity(ENVELOPE_OVERHEAD + filename.len() + content_type.len() + stored.len());
+    out.extend_from_slice(ENVELOPE_MAGIC);
+    out.push(ENVELOPE_VERSION);
+    out.push(compression_flag(compression));
+    write_field(&mut out, filename, "filename")?;
+    write_field(&mut out, content_type, "content type")?;
+    out.extend_from_slice(&(stored.len() as u64).to_be_bytes());
+    out.extend_from_slice(stored);
+
+    let crc32 = crc32fast::hash(&out);
+    out.extend_from_slice(&crc32.to_be_bytes());
+    out.extend_from_slice(&Sha256::digest(payload));
+    let stored = Stored {
+        compression,
+        size: stored.len(),
+    };
+    Ok((out, stored))
+}
+
+/// Returns the flag bits recording `compression`
+fn compression_flag(compression: Compression) -> u8 {
+    match compression {
+        Compression::None => COMPRESSION_NONE,
+        Compression::Deflate => COMPRESSION_DEFLATE,
+        Compression::Zstd => COMPRESSION_ZSTD,
+    }
+}
+
+/// Writes a length-prefixed metadata field
+fn write_field(out: &mut Vec<u8>, value: &[u8], name: &str) -> Result<()> {
+    let len = u16::try_from(value.len()).map_err(|_| LupinError::EmbedFailed {
+        source: std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("{} is longer than {} bytes", name, u16::MAX),
+        ),
+    })?;
+    out.extend_from_slice(&len.to_be_bytes());
+    out.extend_from_slice(value);
+    Ok(())
+}
+
+/// Signs an envelope with `key`, replacing any signature it already has
+///
+/// The signature covers the whole envelope, metadata included, so a signed payload
+/// can't be renamed or altered without invalidating it. Fails with
+/// [`LupinError::ChecksumMismatch`] rather than sign a damaged envelope.
+pub fn sign(data: &[u8], key: &SigningKey) -> Result<Vec<u8>> {
+    let (envelope, _, signature) = parse(data)?;
+    let unsigned_len = data.len() - signature.map_or(0, |_| SIGNATURE_OVERHEAD);
+    let mut out = data[..unsigned_len].to_vec();
+    let body_len = unsigned_len - TRAILER_LEN;
+    if crc32fast::hash(&out[..body_len]) != envelope.crc32 {
+        return Err(LupinError::ChecksumMismatch);
+    }
+
+    // The flags are covered by the CRC32, so it changes with them
+    out[ENVELOPE_MAGIC.len() + 1] |= FLAG_SIGNED;
+    let crc32 = crc32fast::hash(&out[..body_len]);
+    out[body_len..body_len + 4].copy_from_slice(&crc32.to_be_bytes());
+    out.extend_from_slice(key.verifying_key().as_bytes());
+    let signature = key.sign(&out);
+    out.extend_from_slice(&signature);
+    Ok(out)
+}
+
+/// Unwraps an envelope, verifying its length, checksums and signature
+///
+/// Fails with [`LupinError::PayloadTruncated`] if the data ends early,
+/// [`LupinError::ChecksumMismatch`] if it was damaged,
+/// [`LupinError::SignatureInvalid`] if it is signed but doesn't match its signature
+/// and [`LupinError::UnsupportedEnvelope`] if it was written by a newer version.
+pub fn unwrap(data: &[u8]) -> Result<Envelope> {
+    let (mut envelope, stored, signature) = parse(data)?;
+    let signature_len = signature.map_or(0, |_| SIGNATURE_OVERHEAD);
+    if crc32fast::hash(&data[..data.len() - signature_len - TRAILER_LEN]) != envelope.crc32 {
+        return Err(LupinError::ChecksumMismatch);
+    }
+    if let (Some(signer), Some(signature)) = (&envelope.signer, &signature) {
+        signer.verify(&data[..data.len() - SIGNATURE_LEN], signature)?;
+    }
+
+    let payload = match envelope.compression {
+        Compression::None => stored.to_vec(),
+        compression => compression::decompress(&stored[8..], compression, envelope.size as u64)?,
+    };
+    if Sha256::digest(&payload).as_slice() != envelope.sha256 {
+        return Err(LupinError::ChecksumMismatch);
+    }
+
+    envelope.payload = payload;
+    Ok(envelope)
+}
+
+/// Reads an envelope's metadata without verifying or decompressing the payload
+///
+/// The returned envelope's `payload` is empty; use [`unwrap`] to get at it. Fails
+/// like [`unwrap`] on a truncated or malformed envelope, but not on a damaged one.
+pub fn peek(data: &[u8]) -> Result<Envelope> {
+    parse(data).map(|(envelope, _, _)| envelope)
+}
+
+/// Parses an envelope's framing, returning its metadata (with an empty payload), the
+/// stored payload and the signature, if it is signed
+fn parse(data: &[u8]) -> Result<(Envelope, &[u8], Option<Signature>)> {
+    let mut reader = Reader { data, pos: 0 };
+    if reader.take(4)? != ENVELOPE_MAGIC {
+        return Err(LupinError::InvalidEnvelope {
+            reason: "missing envelope magic".to_string(),
+        });
+    }
+
+    let version = reader.take(1)?[0];
+    if version != ENVELOPE_VERSION {
+        return Err(LupinError::UnsupportedEnvelope {
+            reason: format!("version {}", version),
+        });
+    }
+    let flags = reader.take(1)?[0];
+    let compression = match flags & COMPRESSION_MASK {
+        COMPRESSION_NONE => Some(Compression::None),
+        COMPRESSION_DEFLATE => Some(Compression::Deflate),
+        COMPRESSION_ZSTD => Some(Compression::Zstd),
+        _ => None,
+    };
+    let compression = match compression {
+        Some(compression) if flags & !KNOWN_FLAGS == 0 => compression,
+        _ => {
+            return Err(LupinError::UnsupportedEnvelope {
+                reason: format!("flags {:#04x}", flags),
+            })
+        }
+    };
+
+    let filename = reader.text_field("filename")?;
+    let content_type = reader.text_field("content type")?;
+    let payload_len = u64::from_be_bytes(reader.take(8)?.try_into().unwrap());
+    let payload_len = usize::try_from(payload_len)
+        .ok()
+        .filter(|len| len.checked_add(TRAILER_LEN).is_some())
+        .ok_or_else(|| reader.truncated(usize::MAX))?;
+    reader.need(payload_len + TRAILER_LEN)?;
+    let stored = reader.take(payload_len)?;
+    let crc32 = u32::from_be_bytes(reader.take(4)?.try_into().unwrap());
+    let sha256: [u8; 32] = reader.take(32)?.try_into().unwrap();
+    let (signer, signature) = if flags & FLAG_SIGNED != 0 {
+        let signer = reader.take(PUBLIC_KEY_LEN)?.try_into().unwrap();
+        let signer = VerifyingKey::from_bytes(&signer).map_err(|_| LupinError::SignatureInvalid)?;
+        let signature: Signature = reader.take(SIGNATURE_LEN)?.try_into().unwrap();
+        (Some(signer), Some(signature))
+    } else {
+        (None, None)
+    };
+
+    if reader.pos != data.len() {
+        return Err(LupinError::InvalidEnvelope {
+            reason: format!(
+                "{} unexpected bytes after the envelope",
+                data.len() - reader.pos
+            ),
+        });
+    }
+
+    // A compressed payload starts with its uncompressed length
+    let size = match compression {
+        Compression::None => stored.len(),
+        _ => {
+            if stored.len() < 8 {
+                return Err(LupinError::InvalidEnvelope {
+                    reason: "compressed payload is missing its length".to_string(),
+                });
+            }
+            let size = u64::from_be_bytes(stored[..8].try_into().unwrap());
+            usize::try_from(size).map_err(|_| LupinError::InvalidEnvelope {
+                reason: format!("payload claims to inflate to {} bytes", size),
+            })?
+        }
+    };
+
+    let envelope = Envelope {
+        version,
+        flags,
+        filename,
+        content_type,
+        compression,
+        stored_size: stored.len(),
+        size,
+        crc32,
+        sha256,
+        signer,
+        payload: Vec::new(),
+    };
+    Ok((envelope, stored, signature))
+}
+
+/// Cursor over the envelope bytes that reports running out as truncation
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn truncated(&self, expected: usize) -> LupinError {
+        LupinError::PayloadTruncated {
+            expected,
+            actual: self.data.len(),
+        }
+    }
+
+    fn need(&self, len: usize) -> Result<()> {
+        match self.pos.checked_add(len) {
+            Some(end) if end <= self.data.len() => Ok(()),
+            end => Err(self.truncated(end.unwrap_or(usize::MAX))),
+        }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        self.need(len)?;
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn text_field(&mut self, name: &str) -> Result<Option<String>> {
+        let len = u16::from_be_bytes(self.take(2)?.try_into().unwrap());
+        let bytes = self.take(len as usize)?;
+        let text = std::str::from_utf8(bytes).map_err(|_| LupinError::InvalidEnvelope {
+            reason: format!("{} is not valid UTF-8", name),
+        })?;
+        Ok((!text.is_empty()).then(|| text.to_string()))
+    }
+}
+
+/// Returns the stored filename if it is safe to create in the current directory
+///
+/// The filename comes from the carrier, so it is untrusted: anything with a path
+/// separator, a `.`/`..` component or a control character is refused.
+pub fn safe_filename(filename: &str) -> Option<&str> {
+    let unsafe_char = |c: char| c == '/' || c == '\\' || c == ':' || c.is_control();
+    if filename.is_empty() || filename == "." || filename == ".." || filename.contains(unsafe_char)
+    {
+        return None;
+    }
+    Some(filename)
+}
+
+/// Guesses a MIME type from a filename's extension
+pub fn guess_content_type(filename: &str) -> Option<&'static str> {
+    let (_, ext) = filename.rsplit_once('.')?;
+    let content_type = match ext.to_ascii_lowercase().as_str() {
+        "txt" | "text" | "log" => "text/plain",
+        "md" => "text/markdown",
+        "csv" => "text/csv",
+        "html" | "htm" => "text/html",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "pdf" => "application/pdf",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "tar" => "application/x-tar",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "mp4" => "video/mp4",
+        _ => return None,
+    };
+    Some(content_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap_unwrap_round_trip() {
+        // Arrange
+        let payload = b"meet at dawn";
+
+        // Act
+        let (wrapped, _) = wrap(
+            payload,
+            Some("plan.txt"),
+            Some("text/plain"),
+            Compression::None,
+        )
+        .unwrap();
+        let envelope = unwrap(&wrapped).unwrap();
+
+        // Assert
+        assert!(is_envelope(&wrapped));
+        assert_eq!(wrapped.len(), ENVELOPE_OVERHEAD + 8 + 10 + payload.len());
+        assert_eq!(envelope.payload, payload);
+        assert_eq!(envelope.version, ENVELOPE_VERSION);
+        assert_eq!(envelope.filename.as_deref(), Some("plan.txt"));
+        assert_eq!(envelope.content_type.as_deref(), Some("text/plain"));
+        assert_eq!(
+            envelope.sha256.as_slice(),
+            Sha256::digest(payload).as_slice()
+        );
+    }
+
+    #[test]
+    fn test_unwrap_without_metadata() {
+        // Arrange
+        let wrapped = wrap(b"anonymous", None, None, Compression::None).unwrap().0;
+
+        // Act
+        let envelope = unwrap(&wrapped).unwrap();
+
+        // Assert
+        assert_eq!(envelope.filename, None);
+        assert_eq!(envelope.content_type, None);
+        assert_eq!(envelope.payload, b"anonymous");
+    }
+
+    #[test]
+    fn test_unwrap_truncated() {
+        // Arrange
+        let wrapped = wrap(
+            b"this payload gets cut short",
+            Some("a.txt"),
+            None,
+            Compression::None,
+        )
+        .unwrap()
+        .0;
+
+        // Act & Assert - every possible cut is reported as truncation
+        for len in 5..wrapped.len() {
+            let result = unwrap(&wrapped[..len]);
+            assert!(
+                matches!(result, Err(LupinError::PayloadTruncated { .. })),
+                "cut at {} gave {:?}",
+                len,
+                result
+            );
+        }
+    }
+
+    #[test]
+    fn test_unwrap_detects_corruption() {
+        // Arrange
+        let wrapped = wrap(
+            b"flip a bit in here",
+            Some("a.txt"),
+            None,
+            Compression::None,
+        )
+        .unwrap()
+        .0;
+        let payload_start = HEADER_LEN + 5;
+
+        // Act - damage the payload and, separately, the filename
+        let mut damaged_payload = wrapped.clone();
+        damaged_payload[payload_start] ^= 0x01;
+        let mut damaged_name = wrapped.clone();
+        damaged_name[8] ^= 0x01;
+
+        // Assert
+        assert!(matches!(
+            unwrap(&damaged_payload),
+            Err(LupinError::ChecksumMismatch)
+        ));
+        assert!(matches!(
+            unwrap(&damaged_name),
+            Err(LupinError::ChecksumMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_unwrap_rejects_newer_formats() {
+        // Arrange
+        let wrapped = wrap(b"payload", None, None, Compression::None).unwrap().0;
+        let mut newer_version = wrapped.clone();
+        newer_version[4] = ENVELOPE_VERSION + 1;
+        let mut unknown_flags = wrapped.clone();
+        unknown_flags[5] = 0x80;
+        let mut unknown_compression = wrapped;
+        unknown_compression[5] = COMPRESSION_MASK;
+
+        // Act & Assert
+        assert!(matches!(
+            unwrap(&newer_version),
+            Err(LupinError::UnsupportedEnvelope { .. })
+        ));
+        assert!(matches!(
+            unwrap(&unknown_flags),
+            Err(LupinError::UnsupportedEnvelope { .. })
+        ));
+        assert!(matches!(
+            unwrap(&unknown_compression),
+            Err(LupinError::UnsupportedEnvelope { .. })
+        ));
+    }
+
+    #[test]
+    fn test_wrap_compresses_when_smaller() {
+        // Arrange
+        let text = b"the quick brown fox jumps over the lazy dog. ".repeat(40);
+
+        // Act
+        let (wrapped, stored) = wrap(&text, None, None, Compression::Deflate).unwrap();
+        let envelope = unwrap(&wrapped).unwrap();
+
+        // Assert
+        assert_eq!(stored.compression, Compression::Deflate);
+        assert!(stored.size < text.len() / 5);
+        assert_eq!(wrapped[5] & COMPRESSION_MASK, COMPRESSION_DEFLATE);
+        assert_eq!(envelope.compression, Compression::Deflate);
+        assert_eq!(envelope.stored_size, stored.size);
+        assert_eq!(envelope.payload, text);
+    }
+
+    #[test]
+    fn test_wrap_skips_compression_when_larger() {
+        // Arrange - too short for DEFLATE to win back its overhead
+        let payload = b"tiny";
+
+        // Act
+        let (wrapped, stored) = wrap(payload, None, None, Compression::Deflate).unwrap();
+
+        // Assert
+        assert_eq!(stored.compression, Compression::None);
+        assert_eq!(stored.size, payload.len());
+        assert_eq!(unwrap(&wrapped).unwrap().payload, payload);
+    }
+
+    #[test]
+    fn test_unwrap_detects_corrupted_compressed_payload() {
+        // Arrange - rewrite the CRC so only the SHA-256/decompression can catch it
+        let text = b"compressible compressible compressible ".repeat(20);
+        let (mut wrapped, stored) = wrap(&text, None, None, Compression::Deflate).unwrap();
+        let data_start = HEADER_LEN + 8;
+        wrapped[data_start + stored.size / 2] ^= 0x10;
+        let crc_start = HEADER_LEN + stored.size;
+        let crc32 = crc32fast::hash(&wrapped[..crc_start]);
+        wrapped[crc_start..crc_start + 4].copy_from_slice(&crc32.to_be_bytes());
+
+        // Act
+        let result = unwrap(&wrapped);
+
+        // Assert
+        assert!(matches!(
+            result,
+            Err(LupinError::ChecksumMismatch) | Err(LupinError::InvalidEnvelope { .. })
+        ));
+    }
+
+    #[test]
+    fn test_peek_reads_metadata_only() {
+        // Arrange
+        let text = b"the quick brown fox jumps over the lazy dog. ".repeat(40);
+        let (mut wrapped, _) = wrap(
+            &text,
+            Some("fox.txt"),
+            Some("text/plain"),
+            Compression::Deflate,
+        )
+        .unwrap();
+        let last = wrapped.len() - 1;
+        wrapped[last] ^= 0xff;
+
+        // Act
+        let envelope = peek(&wrapped).unwrap();
+
+        // Assert - a damaged checksum goes unnoticed, as nothing is verified
+        assert_eq!(envelope.filename.as_deref(), Some("fox.txt"));
+        assert_eq!(envelope.content_type.as_deref(), Some("text/plain"));
+        assert_eq!(envelope.compression, Compression::Deflate);
+        assert_eq!(envelope.size, text.len());
+        assert!(envelope.payload.is_empty());
+        assert!(matches!(
+            peek(&wrapped[..wrapped.len() - 1]),
+            Err(LupinError::PayloadTruncated { .. })
+        ));
+    }
+
+    #[test]
+    fn test_unwrap_rejects_trailing_bytes() {
+        // Arrange
+        let mut wrapped = wrap(b"payload", None, None, Compression::None).unwrap().0;
+        wrapped.extend_from_slice(b"junk");
+
+        // Act & Assert
+        assert!(matches!(
+            unwrap(&wrapped),
+            Err(LupinError::InvalidEnvelope { .. })
+        ));
+    }
+
+    #[test]
+    fn test_sign_round_trip() {
+        // Arrange
+        let key = SigningKey::generate();
+        let wrapped = wrap(
+            b"signed orders",
+            Some("orders.txt"),
+            None,
+            Compression::None,
+        )
+        .unwrap()
+        .0;
+
+        // Act
+        let signed = sign(&wrapped, &key).unwrap();
+        let resigned = sign(&signed, &SigningKey::generate()).unwrap();
+        let envelope = unwrap(&signed).unwrap();
+
+        // Assert
+        assert_eq!(signed.len(), wrapped.len() + SIGNATURE_OVERHEAD);
+        assert_eq!(envelope.signer, Some(key.verifying_key()));
+        assert_eq!(envelope.payload, b"signed orders");
+        assert_eq!(peek(&signed).unwrap().signer, Some(key.verifying_key()));
+        assert_eq!(unwrap(&wrapped).unwrap().signer, None);
+        // Signing again replaces the signature rather than stacking another
+        assert_eq!(resigned.len(), signed.len());
+        assert_ne!(unwrap(&resigned).unwrap().signer, envelope.signer);
+    }
+
+    #[test]
+    fn test_unwrap_detects_forged_signature() {
+        // Arrange - swap in another key, or re-sign altered metadata with a fixed CRC
+        let key = SigningKey::generate();
+        let wrapped = wrap(b"payload", Some("a.txt"), None, Compression::None)
+            .unwrap()
+            .0;
+        let signed = sign(&wrapped, &key).unwrap();
+        let key_start = signed.len() - SIGNATURE_OVERHEAD;
+        let mut other_signer = signed.clone();
+        other_signer[key_start..key_start + PUBLIC_KEY_LEN]
+            .copy_from_slice(SigningKey::generate().verifying_key().as_bytes());
+        let mut renamed = signed.clone();
+        renamed[8] = b'b'; // first byte of the filename
+        let crc_start = key_start - TRAILER_LEN;
+        let crc32 = crc32fast::hash(&renamed[..crc_start]);
+        renamed[crc_start..crc_start + 4].copy_from_slice(&crc32.to_be_bytes());
+
+        // Act & Assert
+        assert!(matches!(
+            unwrap(&other_signer),
+            Err(LupinError::SignatureInvalid)
+        ));
+        assert!(matches!(
+            unwrap(&renamed),
+            Err(LupinError::SignatureInvalid)
+        ));
+        assert!(matches!(
+            unwrap(&signed[..signed.len() - 1]),
+            Err(LupinError::PayloadTruncated { .. })
+        ));
+    }
+
+    #[test]
+    fn test_safe_filename() {
+        assert_eq!(safe_filename("report.pdf"), Some("report.pdf"));
+        assert_eq!(safe_filename(".hidden"), Some(".hidden"));
+        assert_eq!(safe_filename(""), None);
+        assert_eq!(safe_filename(".."), None);
+        assert_eq!(safe_filename("../etc/passwd"), None);
+        assert_eq!(safe_filename("/etc/passwd"), None);
+        assert_eq!(safe_filename("..\\boot.ini"), None);
+        assert_eq!(safe_filename("C:evil"), None);
+        assert_eq!(safe_filename("bell\x07"), None);
+    }
+
+    #[test]
+    fn test_guess_content_type() {
+        assert_eq!(guess_content_type("notes.TXT"), Some("text/plain"));
+        assert_eq!(
+            guess_content_type("archive.tar.gz"),
+            Some("application/gzip")
+        );
+        assert_eq!(guess_content_type("README"), None);
+        assert_eq!(guess_content_type("data.unknown"), None);
+    }
+}
@@ -44,6 +44,27 @@ pub enum LupinError {
         source: io::Error,
     },
 
+    #[error("Failed to read password file '{path}'")]
+    PasswordFileRead {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("Failed to read identity file '{path}'")]
+    IdentityFileRead {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("Failed to read signing key file '{path}'")]
+    SigningKeyFileRead {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
     /// I/O errors with explicit context for stdout operations
     #[error("Failed to write to stdout")]
     StdoutWrite {
@@ -58,6 +79,9 @@ pub enum LupinError {
         source: io::Error,
     },
 
+    #[error("Unknown format {format:?}: expected a format name or extension such as \"png\"")]
+    UnknownFormat { format: String },
+
     #[error("Embedding operation failed")]
     EmbedFailed {
         #[source]
@@ -73,19 +97,132 @@ pub enum LupinError {
     #[error("Cannot embed an empty payload")]
     EmptyPayload,
 
+    #[error("Payload is too large: {requested} bytes to hide, but the carrier holds {capacity}")]
+    PayloadTooLarge { capacity: usize, requested: usize },
+
     #[error("Stealth mode is not yet supported for {format}")]
     StealthNotSupported { format: &'static str },
 
+    #[error("Stealth mode cannot use this {format} file: {reason}")]
+    StealthUnsupportedCarrier {
+        format: &'static str,
+        reason: String,
+    },
+
     #[error("Extraction operation failed")]
     ExtractFailed {
         #[source]
         source: io::Error,
     },
 
+    /// Encryption errors
+    #[error("Password must not be empty")]
+    EmptyPassword,
+
+    #[error("The hidden payload is encrypted; a password is required to extract it")]
+    PasswordRequired,
+
+    #[error("Wrong password for the encrypted payload")]
+    WrongPassword,
+
+    #[error("Encrypted payload failed authentication: it was corrupted or tampered with")]
+    AuthenticationFailed,
+
+    #[error("Invalid key: {reason}")]
+    InvalidKey { reason: String },
+
+    #[error(
+        "The hidden payload is encrypted to recipients; an identity is required to extract it"
+    )]
+    IdentityRequired,
+
+    #[error("None of the given identities can decrypt the hidden payload")]
+    NoMatchingIdentity,
+
+    /// Signature errors
+    #[error(
+        "The hidden payload's signature is invalid: it was tampered with or signed by another key"
+    )]
+    SignatureInvalid,
+
+    #[error("The hidden payload is not signed, so it can't be verified")]
+    SignatureRequired,
+
+    /// Envelope errors
+    #[error("Hidden payload is truncated: expected {expected} bytes but found {actual}")]
+    PayloadTruncated { expected: usize, actual: usize },
+
+    #[error("Hidden payload failed its checksum: it was corrupted or tampered with")]
+    ChecksumMismatch,
+
+    #[error("Hidden payload was written by a newer version of Lupin ({reason})")]
+    UnsupportedEnvelope { reason: String },
+
+    #[error("Invalid payload envelope: {reason}")]
+    InvalidEnvelope { reason: String },
+
+    #[error("{algorithm} compression is not available in this build of Lupin")]
+    CompressionUnavailable { algorithm: &'static str },
+
+    #[error("The hidden payload has no stored filename; give an output path")]
+    NoStoredFilename,
+
+    #[error("Refusing to restore unsafe stored filename {filename:?}; give an output path")]
+    UnsafeFilename { filename: String },
+
+    /// Archive errors
+    #[error("The hidden payload holds {count} entries; name the one to extract")]
+    EntryRequired { count: usize },
+
+    #[error("The hidden payload has no entry named {name:?}")]
+    EntryNotFound { name: String },
+
+    #[error("The hidden payload already has an entry named {name:?}")]
+    DuplicateEntry { name: String },
+
+    #[error("An entry added to the hidden payload needs a name")]
+    UnnamedEntry,
+
+    #[error("The hidden payload is encrypted; give a password or recipients to encrypt it again")]
+    EncryptionRequired,
+
+    /// Directory errors
+    #[error("Invalid hidden directory: {reason}")]
+    InvalidTree { reason: String },
+
+    #[error("Refusing to restore unsafe path {path:?} from the hidden directory")]
+    UnsafeTreePath { path: String },
+
+    #[error("The hidden payload is a directory and can't be written to stdout; give an output directory")]
+    TreeToStdout,
+
+    /// Split errors
+    #[error("Cannot split the payload: {reason}")]
+    InvalidSplit { reason: String },
+
+    #[error("Only {found} of the {needed} shares needed to rebuild the payload were found")]
+    NotEnoughShares { found: usize, needed: u8 },
+
+    #[error("The carriers hold shares of different payloads")]
+    ShareMismatch,
+
+    #[error("A carrier holds a whole payload rather than a share; extract it on its own")]
+    NotAShare,
+
+    /// Error correction errors
+    #[error("Error correction needs 2 to 128 parity bytes per codeword; got {parity}")]
+    InvalidRedundancy { parity: u8 },
+
+    #[error("The hidden payload is too damaged for its error correction to repair")]
+    TooManyErrors,
+
     /// PDF-specific errors
     #[error("Invalid PDF: no %%EOF marker found")]
     PdfNoEofMarker,
 
+    #[error("Invalid PDF format: {reason}")]
+    PdfInvalidFormat { reason: String },
+
     #[error("No hidden data found in PDF")]
     PdfNoHiddenData,
 
@@ -96,6 +233,9 @@ pub enum LupinError {
     #[error("Invalid PNG: no IEND chunk found")]
     PngNoIendChunk,
 
+    #[error("Invalid PNG format: {reason}")]
+    PngInvalidFormat { reason: String },
+
     #[error("No hidden data found in PNG")]
     PngNoHiddenData,
 
@@ -109,6 +249,104 @@ pub enum LupinError {
     #[error("No hidden data found in JPEG")]
     JpegNoHiddenData,
 
+    #[error("Corrupted hidden data in JPEG")]
+    JpegCorruptedData,
+
+    /// GIF-specific errors
+    #[error("Invalid GIF format: {reason}")]
+    GifInvalidFormat { reason: String },
+
+    #[error("No hidden data found in GIF")]
+    GifNoHiddenData,
+
+    #[error("Corrupted hidden data in GIF")]
+    GifCorruptedData,
+
+    /// BMP-specific errors
+    #[error("Invalid BMP format: {reason}")]
+    BmpInvalidFormat { reason: String },
+
+    #[error("No hidden data found in BMP")]
+    BmpNoHiddenData,
+
+    #[error("Corrupted hidden data in BMP")]
+    BmpCorruptedData,
+
+    /// WAV-specific errors
+    #[error("Invalid WAV format: {reason}")]
+    WavInvalidFormat { reason: String },
+
+    #[error("No hidden data found in WAV")]
+    WavNoHiddenData,
+
+    #[error("Corrupted hidden data in WAV")]
+    WavCorruptedData,
+
+    /// WebP-specific errors
+    #[error("Invalid WebP format: {reason}")]
+    WebpInvalidFormat { reason: String },
+
+    #[error("No hidden data found in WebP")]
+    WebpNoHiddenData,
+
+    #[error("Corrupted hidden data in WebP")]
+    WebpCorruptedData,
+
+    /// TIFF-specific errors
+    #[error("Invalid TIFF format: {reason}")]
+    TiffInvalidFormat { reason: String },
+
+    #[error("No hidden data found in TIFF")]
+    TiffNoHiddenData,
+
+    #[error("Corrupted hidden data in TIFF")]
+    TiffCorruptedData,
+
+    /// Office Open XML-specific errors
+    #[error("Invalid Office Open XML document: {reason}")]
+    OoxmlInvalidFormat { reason: String },
+
+    #[error("No hidden data found in Office Open XML document")]
+    OoxmlNoHiddenData,
+
+    #[error("Corrupted hidden data in Office Open XML document")]
+    OoxmlCorruptedData,
+
+    /// ZIP-specific errors
+    #[error("Invalid ZIP format: {reason}")]
+    ZipInvalidFormat { reason: String },
+
+    #[error("No hidden data found in ZIP")]
+    ZipNoHiddenData,
+
+    #[error("Corrupted hidden data in ZIP")]
+    ZipCorruptedData,
+
+    #[error(
+        "Refusing to embed into a signed APK: a new entry would invalidate its APK Signing Block"
+    )]
+    ZipApkSigned,
+
+    /// Text-specific errors
+    #[error("Invalid text format: {reason}")]
+    TextInvalidFormat { reason: String },
+
+    #[error("No hidden data found in text")]
+    TextNoHiddenData,
+
+    #[error("Corrupted hidden data in text")]
+    TextCorruptedData,
+
+    /// SVG-specific errors
+    #[error("Invalid SVG format: {reason}")]
+    SvgInvalidFormat { reason: String },
+
+    #[error("No hidden data found in SVG")]
+    SvgNoHiddenData,
+
+    #[error("Corrupted hidden data in SVG")]
+    SvgCorruptedData,
+
     /// Generic I/O error for cases where automatic conversion is desired
     #[error("I/O operation failed")]
     Io {
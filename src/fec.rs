@@ -0,0 +1,508 @@
+// Copyright 2025 Niclas Hedam
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reed-Solomon error correction of the bytes an engine hides
+//!
+//! Error correction is the last step before a payload reaches the engine, so it
+//! covers the envelope and any encryption too. The payload is cut into Reed-Solomon
+//! codewords over GF(2^8) of at most 255 bytes, each with the same number of parity
+//! bytes; `parity` of them repair up to `parity / 2` flipped bytes per codeword, or
+//! up to `parity` missing ones. The codewords are interleaved byte by byte, so a
+//! burst of damage, or a payload cut short, is spread thinly over all of them:
+//!
+//! ```text
+//! [14 bytes: Header] three times, where the header is
+//!     [4 bytes: "LpFc"][1 byte: Version = 1][1 byte: Parity][8 bytes: Payload Length]
+//! [Codewords, interleaved]
+//! ```
+//!
+//! The header is repeated so that it survives damage too: each byte is read by
+//! majority vote. All integers are big-endian.
+
+use crate::error::{LupinError, Result};
+use crate::gf256;
+
+/// Marks a payload with error correction
+pub const FEC_MAGIC: &[u8] = b"LpFc";
+
+/// Current version of the error correction format
+pub const FEC_VERSION: u8 = 1;
+
+/// Parity bytes per codeword that [`encode`] accepts
+pub const PARITY_RANGE: std::ops::RangeInclusive<u8> = 2..=128;
+
+/// Magic, version, parity and payload length
+const HEADER_LEN: usize = 4 + 1 + 1 + 8;
+
+/// Copies of the header
+const HEADER_COPIES: usize = 3;
+
+/// Longest codeword, parity included
+const MAX_CODEWORD: usize = 255;
+
+/// Returns true if the data, possibly damaged, starts like a payload with error
+/// correction
+pub fn is_fec(data: &[u8]) -> bool {
+    read_header(data).is_some_and(|header| header.starts_with(FEC_MAGIC))
+}
+
+/// Returns the parity bytes per codeword of an undamaged header, which is all a
+/// short prefix of the payload can show
+pub fn peek_parity(data: &[u8]) -> Option<u8> {
+    (data.starts_with(FEC_MAGIC) && data.get(4) == Some(&FEC_VERSION))
+        .then(|| data.get(5).copied())
+        .flatten()
+}
+
+/// Returns the size of a payload of `len` bytes once encoded with `parity` bytes per
+/// codeword
+pub fn encoded_len(len: usize, parity: u8) -> usize {
+    let (count, data_len) = layout(len, parity);
+    HEADER_LEN * HEADER_COPIES + count * (data_len + parity as usize)
+}
+
+/// Adds `parity` Reed-Solomon parity bytes to every codeword of the payload
+///
+/// Fails with [`LupinError::InvalidRedundancy`] if `parity` is outside
+/// [`PARITY_RANGE`].
+pub fn encode(payload: &[u8], parity: u8) -> Result<Vec<u8>> {
+    if !PARITY_RANGE.contains(&parity) {
+        return Err(LupinError::InvalidRedundancy { parity });
+    }
+
+    let (count, data_len) = layout(payload.len(), parity);
+    let codeword_len = data_len + parity as usize;
+    let generator = generator_poly(parity as usize);
+
+    let mut out = Vec::with_capacity(encoded_len(payload.len(), parity));
+    for _ in 0..HEADER_COPIES {
+        out.extend_from_slice(FEC_MAGIC);
+        out.push(FEC_VERSION);
+        out.push(parity);
+        out.extend_from_slice(&(payload.len() as u64).to_be_bytes());
+    }
+
+    // Codeword j holds payload bytes j, j + count, j + 2 * count, ..., zero-padded
+    let body_start = out.len();
+    out.resize(body_start + count * codeword_len, 0);
+    let mut codeword = vec![0u8; data_len];
+    for j in 0..count {
+        for (i, symbol) in codeword.iter_mut().enumerate() {
+            *symbol = payload.get(i * count + j).copied().unwrap_or(0);
+        }
+        let parity_bytes = rs_parity(&codeword, &generator);
+        for (i, &symbol) in codeword.iter().chain(&parity_bytes).enumerate() {
+            out[body_start + i * count + j] = symbol;
+        }
+    }
+    Ok(out)
+}
+
+/// Repairs and strips the error correction, returning the payload and the number of
+/// bytes repaired
+///
+/// Bytes missing from the end of a truncated payload count as repaired. Fails with
+/// [`LupinError::TooManyErrors`] if any codeword is damaged beyond repair and
+/// [`LupinError::InvalidEnvelope`] if the header can't be read.
+pub fn decode(data: &[u8]) -> Result<(Vec<u8>, usize)> {
+    let header = read_header(data).ok_or_else(|| LupinError::InvalidEnvelope {
+        reason: "unreadable error correction header".to_string(),
+    })?;
+    if !header.starts_with(FEC_MAGIC) {
+        return Err(LupinError::InvalidEnvelope {
+            reason: "missing error correction magic".to_string(),
+        });
+    }
+    if header[4] != FEC_VERSION {
+        return Err(LupinError::UnsupportedEnvelope {
+            reason: format!("error correction version {}", header[4]),
+        });
+    }
+    let parity = header[5];
+    let len = u64::from_be_bytes(header[6..].try_into().unwrap());
+    let len = usize::try_from(len).unwrap_or(usize::MAX);
+    // A damaged length that claims more than the data could hold can't be trusted
+    if !PARITY_RANGE.contains(&parity) || len > data.len() {
+        return Err(LupinError::InvalidEnvelope {
+            reason: format!("error correction for {} bytes with parity {}", len, parity),
+        });
+    }
+
+    let (count, data_len) = layout(len, parity);
+    let codeword_len = data_len + parity as usize;
+    let body = &data[HEADER_LEN * HEADER_COPIES..];
+
+    let mut payload = vec![0u8; count * data_len];
+    let mut repaired = 0;
+    let mut codeword = vec![0u8; codeword_len];
+    let mut erasures = Vec::new();
+    for j in 0..count {
+        erasures.clear();
+        for (i, symbol) in codeword.iter_mut().enumerate() {
+            match body.get(i * count + j) {
+                Some(&byte) => *symbol = byte,
+                None => {
+                    *symbol = 0;
+                    erasures.push(i);
+                }
+            }
+        }
+        repaired += rs_correct(&mut codeword, parity as usize, &erasures)
+            .ok_or(LupinError::TooManyErrors)?;
+        for (i, &symbol) in codeword[..data_len].iter().enumerate() {
+            payload[i * count + j] = symbol;
+        }
+    }
+
+    payload.truncate(len);
+    Ok((payload, repaired))
+}
+
+/// Number of codewords and data bytes per codeword for a payload of `len` bytes
+fn layout(len: usize, parity: u8) -> (usize, usize) {
+    let max_data = MAX_CODEWORD - parity as usize;
+    let count = ((len + max_data - 1) / max_data).max(1);
+    let data_len = ((len + count - 1) / count).max(1);
+    (count, data_len)
+}
+
+/// Reads the header by majority vote over its copies
+fn read_header(data: &[u8]) -> Option<[u8; HEADER_LEN]> {
+    if data.len() < HEADER_LEN * HEADER_COPIES {
+        return None;
+    }
+    let mut header = [0u8; HEADER_LEN];
+    for (i, byte) in header.iter_mut().enumerate() {
+        let (a, b, c) = (data[i], data[HEADER_LEN + i], data[2 * HEADER_LEN + i]);
+        // Bitwise majority, which is the majority byte whenever two copies agree
+        *byte = (a & b) | (a & c) | (b & c);
+    }
+    Some(header)
+}
+
+// Polynomials are stored highest degree first.
+
+fn poly_scale(p: &[u8], x: u8) -> Vec<u8> {
+    p.iter().map(|&c| gf256::mul(c, x)).collect()
+}
+
+fn poly_add(p: &[u8], q: &[u8]) -> Vec<u8> {
+    let len = p.len().max(q.len());
+    let mut r = vec![0u8; len];
+    for (i, &c) in p.iter().enumerate() {
+        r[i + len - p.len()] = c;
+    }
+    for (i, &c) in q.iter().enumerate() {
+        r[i + len - q.len()] ^= c;
+    }
+    r
+}
+
+fn poly_mul(p: &[u8], q: &[u8]) -> Vec<u8> {
+    let mut r = vec![0u8; p.len() + q.len() - 1];
+    for (i, &a) in p.iter().enumerate() {
+        for (j, &b) in q.iter().enumerate() {
+            r[i + j] ^= gf256::mul(a, b);
+        }
+    }
+    r
+}
+
+fn poly_eval(p: &[u8], x: u8) -> u8 {
+    p.iter().fold(0, |y, &c| gf256::mul(y, x) ^ c)
+}
+
+/// The generator polynomial, with roots at the first `parity` powers of the generator
+fn generator_poly(parity: usize) -> Vec<u8> {
+    (0..parity).fold(vec![1], |g, i| poly_mul(&g, &[1, gf256::exp(i)]))
+}
+
+/// The parity bytes of a codeword: the remainder of dividing it, shifted, by the
+/// generator polynomial
+fn rs_parity(data: &[u8], generator: &[u8]) -> Vec<u8> {
+    let parity = generator.len() - 1;
+    let mut remainder = data.to_vec();
+    remainder.resize(data.len() + parity, 0);
+    for i in 0..data.len() {
+        let coef = remainder[i];
+        if coef != 0 {
+            for (j, &g) in generator.iter().enumerate().skip(1) {
+                remainder[i + j] ^= gf256::mul(g, coef);
+            }
+        }
+    }
+    remainder.split_off(data.len())
+}
+
+/// Syndromes of a codeword, with a leading zero so they index from one
+fn syndromes(codeword: &[u8], parity: usize) -> Vec<u8> {
+    std::iter::once(0)
+        .chain((0..parity).map(|i| poly_eval(codeword, gf256::exp(i))))
+        .collect()
+}
+
+/// Corrects a codeword in place, given the positions of known erasures
+///
+/// Returns the number of bytes corrected, or `None` if there are too many errors.
+fn rs_correct(codeword: &mut [u8], parity: usize, erasures: &[usize]) -> Option<usize> {
+    if erasures.len() > parity {
+        return None;
+    }
+    let synd = syndromes(codeword, parity);
+    if synd.iter().all(|&s| s == 0) {
+        return Some(0);
+    }
+
+    // Find the errors, with the erasures' contribution taken out of the syndromes
+    let forney = forney_syndromes(&synd, erasures, codeword.len());
+    let locator = error_locator(&forney, parity, erasures.len())?;
+    let mut reversed = locator.clone();
+    reversed.reverse();
+    let errors = find_errors(&reversed, codeword.len())?;
+
+    let mut positions = erasures.to_vec();
+    positions.extend(errors);
+    correct_errata(codeword, &synd, &positions)?;
+    syndromes(codeword, parity)
+        .iter()
+        .all(|&s| s == 0)
+        .then_some(positions.len())
+}
+
+/// Syndromes with the known erasures factored out
+fn forney_syndromes(synd: &[u8], erasures: &[usize], len: usize) -> Vec<u8> {
+    let mut forney = synd[1..].to_vec();
+    for &position in erasures {
+        let x = gf256::exp(len - 1 - position);
+        for j in 0..forney.len() - 1 {
+            forney[j] = gf256::mul(forney[j], x) ^ forney[j + 1];
+        }
+    }
+    forney
+}
+
+/// Berlekamp-Massey: the error locator polynomial for the errors left once the
+/// erasures are known
+fn error_locator(synd: &[u8], parity: usize, erasure_count: usize) -> Option<Vec<u8>> {
+    let mut locator = vec![1u8];
+    let mut old = vec![1u8];
+    for k in 0..parity - erasure_count {
+        let mut delta = synd[k];
+        for j in 1..locator.len() {
+            delta ^= gf256::mul(locator[locator.len() - 1 - j], synd[k - j]);
+        }
+        old.push(0);
+        if delta != 0 {
+            if old.len() > locator.len() {
+                let new = poly_scale(&old, delta);
+                old = poly_scale(&locator, gf256::inv(delta));
+                locator = new;
+            }
+            locator = poly_add(&locator, &poly_scale(&old, delta));
+        }
+    }
+    let leading = locator.iter().take_while(|&&c| c == 0).count();
+    locator.drain(..leading);
+
+    let errors = locator.len().saturating_sub(1);
+    (errors * 2 + erasure_count <= parity).then_some(locator)
+}
+
+/// Chien search: the positions of the errors, from the reversed locator polynomial
+fn find_errors(reversed_locator: &[u8], len: usize) -> Option<Vec<usize>> {
+    let errors = reversed_locator.len() - 1;
+    let positions: Vec<usize> = (0..len)
+        .filter(|&i| poly_eval(reversed_locator, gf256::exp(i)) == 0)
+        .map(|i| len - 1 - i)
+        .collect();
+    (positions.len() == errors).then_some(positions)
+}
+
+/// Forney's algorithm: computes the error values at the given positions and
+/// corrects them
+fn correct_errata(codeword: &mut [u8], synd: &[u8], positions: &[usize]) -> Option<()> {
+    let len = codeword.len();
+    let coef_positions: Vec<usize> = positions.iter().map(|&p| len - 1 - p).collect();
+
+    // The errata locator, with a root for every known error position
+    let locator = coef_positions.iter().fold(vec![1u8], |loc, &i| {
+        poly_mul(&loc, &poly_add(&[1], &[gf256::exp(i), 0]))
+    });
+
+    // The errata evaluator: the syndromes times the locator, modulo x^(errata + 1)
+    let mut reversed_synd = synd.to_vec();
+    reversed_synd.reverse();
+    let product = poly_mul(&reversed_synd, &locator);
+    let evaluator = &product[product.len() - locator.len()..];
+
+    let xs: Vec<u8> = coef_positions.iter().map(|&i| gf256::exp(i)).collect();
+    for (i, &x) in xs.iter().enumerate() {
+        let x_inv = gf256::inv(x);
+        let locator_prime = xs
+            .iter()
+            .enumerate()
+            .filter(|&(j, _)| j != i)
+            .fold(1, |acc, (_, &xj)| {
+                gf256::mul(acc, 1 ^ gf256::mul(x_inv, xj))
+            });
+        if locator_prime == 0 {
+            return None;
+        }
+        let y = gf256::mul(x, poly_eval(evaluator, x_inv));
+        codeword[positions[i]] ^= gf256::div(y, locator_prime);
+    }
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A small deterministic generator, so damage lands in the same places every run
+    fn noise(seed: &mut u64) -> usize {
+        *seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (*seed >> 33) as usize
+    }
+
+    fn payload(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 31 % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_encode_decode_round_trip() {
+        for len in [1, 100, 223, 224, 1000, 5000] {
+            // Arrange
+            let data = payload(len);
+
+            // Act
+            let encoded = encode(&data, 32).unwrap();
+            let (decoded, repaired) = decode(&encoded).unwrap();
+
+            // Assert
+            assert!(is_fec(&encoded));
+            assert_eq!(peek_parity(&encoded), Some(32));
+            assert_eq!(encoded.len(), encoded_len(len, 32));
+            assert_eq!(decoded, data);
+            assert_eq!(repaired, 0);
+        }
+    }
+
+    #[test]
+    fn test_decode_repairs_flipped_bytes() {
+        // Arrange - 2000 bytes make 9 codewords; flip 16 random bytes in each
+        let data = payload(2000);
+        let mut encoded = encode(&data, 32).unwrap();
+        let (count, data_len) = layout(data.len(), 32);
+        let body = HEADER_LEN * HEADER_COPIES;
+        let mut seed = 7;
+        for j in 0..count {
+            let mut symbols = Vec::new();
+            while symbols.len() < 16 {
+                let symbol = noise(&mut seed) % (data_len + 32);
+                if !symbols.contains(&symbol) {
+                    symbols.push(symbol);
+                }
+            }
+            for symbol in symbols {
+                encoded[body + symbol * count + j] ^= 1 + (noise(&mut seed) % 255) as u8;
+            }
+        }
+        // And damage one copy of the header
+        encoded[4] ^= 0xff;
+
+        // Act
+        let (decoded, repaired) = decode(&encoded).unwrap();
+
+        // Assert
+        assert_eq!(decoded, data);
+        assert_eq!(repaired, count * 16);
+    }
+
+    #[test]
+    fn test_decode_repairs_errors_up_to_half_the_parity() {
+        // Arrange - one codeword of 100 data bytes
+        let data = payload(100);
+        let encoded = encode(&data, 16).unwrap();
+        let body = HEADER_LEN * HEADER_COPIES;
+
+        for damaged in [1, 5, 8] {
+            let mut corrupted = encoded.clone();
+            for i in 0..damaged {
+                corrupted[body + i * 13] ^= 0x5a;
+            }
+
+            // Act
+            let (decoded, repaired) = decode(&corrupted).unwrap();
+
+            // Assert
+            assert_eq!(decoded, data);
+            assert_eq!(repaired, damaged);
+        }
+
+        // One error too many is detected, not miscorrected
+        let mut corrupted = encoded.clone();
+        for i in 0..9 {
+            corrupted[body + i * 13] ^= 0x5a;
+        }
+        assert!(matches!(decode(&corrupted), Err(LupinError::TooManyErrors)));
+    }
+
+    #[test]
+    fn test_decode_repairs_truncation() {
+        // Arrange - losing the last 24 bytes costs each of the 3 codewords 8 bytes
+        let data = payload(600);
+        let encoded = encode(&data, 16).unwrap();
+        let truncated = &encoded[..encoded.len() - 24];
+
+        // Act
+        let (decoded, repaired) = decode(truncated).unwrap();
+
+        // Assert
+        assert_eq!(decoded, data);
+        assert_eq!(repaired, 24);
+    }
+
+    #[test]
+    fn test_decode_repairs_erasures_and_errors() {
+        // Arrange - one codeword: 4 missing bytes and 6 flipped ones need 16 parity
+        let data = payload(200);
+        let mut encoded = encode(&data, 16).unwrap();
+        encoded.truncate(encoded.len() - 4);
+        let body = HEADER_LEN * HEADER_COPIES;
+        for i in 0..6 {
+            encoded[body + 10 + i * 17] ^= 0x33;
+        }
+
+        // Act
+        let (decoded, repaired) = decode(&encoded).unwrap();
+
+        // Assert
+        assert_eq!(decoded, data);
+        assert_eq!(repaired, 10);
+    }
+
+    #[test]
+    fn test_encode_rejects_bad_parity() {
+        assert!(matches!(
+            encode(b"payload", 1),
+            Err(LupinError::InvalidRedundancy { parity: 1 })
+        ));
+        assert!(matches!(
+            encode(b"payload", 200),
+            Err(LupinError::InvalidRedundancy { parity: 200 })
+        ));
+    }
+}
@@ -0,0 +1,89 @@
+// Copyright 2025 Niclas Hedam
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Arithmetic in GF(2^8), shared by secret sharing and error correction
+//!
+//! The field uses the AES polynomial x^8 + x^4 + x^3 + x + 1 with generator 3.
+//! Addition and subtraction are both XOR.
+
+/// Exponent and logarithm tables of the field
+const TABLES: ([u8; 256], [u8; 256]) = tables();
+
+const fn tables() -> ([u8; 256], [u8; 256]) {
+    let mut exp = [0u8; 256];
+    let mut log = [0u8; 256];
+    let mut x: u16 = 1;
+    let mut i = 0;
+    while i < 255 {
+        exp[i] = x as u8;
+        log[x as usize] = i as u8;
+        // Multiply by the generator, x + 1
+        x ^= x << 1;
+        if x & 0x100 != 0 {
+            x ^= 0x11b;
+        }
+        i += 1;
+    }
+    exp[255] = exp[0];
+    (exp, log)
+}
+
+/// The generator raised to `power`
+pub(crate) fn exp(power: usize) -> u8 {
+    TABLES.0[power % 255]
+}
+
+/// Multiplies two field elements
+pub(crate) fn mul(a: u8, b: u8) -> u8 {
+    if a == 0 || b == 0 {
+        return 0;
+    }
+    let (exp, log) = &TABLES;
+    exp[(log[a as usize] as usize + log[b as usize] as usize) % 255]
+}
+
+/// Divides two field elements; `b` must not be zero
+pub(crate) fn div(a: u8, b: u8) -> u8 {
+    if a == 0 {
+        return 0;
+    }
+    let (exp, log) = &TABLES;
+    exp[(log[a as usize] as usize + 255 - log[b as usize] as usize) % 255]
+}
+
+/// The multiplicative inverse of a non-zero element
+pub(crate) fn inv(a: u8) -> u8 {
+    div(1, a)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arithmetic() {
+        // Known AES field products, and division undoing multiplication
+        assert_eq!(mul(0x57, 0x83), 0xc1);
+        assert_eq!(mul(0x57, 0x13), 0xfe);
+        assert_eq!(exp(0), 1);
+        assert_eq!(exp(1), 3);
+        assert_eq!(exp(255), 1);
+        for a in 1..=255u8 {
+            assert_eq!(mul(a, inv(a)), 1);
+            for b in [1u8, 2, 3, 0x53, 0xff] {
+                assert_eq!(div(mul(a, b), b), a);
+            }
+        }
+    }
+}
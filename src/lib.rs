@@ -13,13 +13,26 @@
 // limitations under the License.
 
 // Module declarations
+pub mod archive;
+pub mod compression;
+pub mod crypto;
 pub mod engines;
+pub mod envelope;
 pub mod error;
+pub mod fec;
+mod gf256;
 pub mod operations;
-
-use crate::engines::{JpegEngine, PdfEngine, PngEngine};
+pub mod shamir;
+pub mod signing;
+pub mod tree;
+
+use crate::engines::{
+    BmpEngine, GifEngine, JpegEngine, OoxmlEngine, PdfEngine, PngEngine, SvgEngine, TextEngine,
+    TiffEngine, WavEngine, WebpEngine, ZipEngine,
+};
 use crate::error::Result;
 use std::io;
+use std::ops::Range;
 
 /// Selects which embedding strategy an engine should use.
 ///
@@ -38,20 +51,143 @@ pub enum EmbedMode {
     Stealth,
 }
 
+/// How many payload bytes a carrier can hold in a given [`EmbedMode`]
+///
+/// Marked `#[non_exhaustive]` so further kinds of limit can be added without breaking
+/// downstream `match` expressions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Capacity {
+    /// No practical limit (e.g. appending after the end of the file)
+    Unlimited,
+    /// Exactly this many bytes fit
+    Exact(usize),
+    /// About this many bytes fit; the exact figure depends on the payload itself
+    Approximate(usize),
+}
+
+impl Capacity {
+    /// Returns the byte limit, or `None` if the capacity is unlimited
+    pub fn bytes(&self) -> Option<usize> {
+        match self {
+            Capacity::Unlimited => None,
+            Capacity::Exact(bytes) | Capacity::Approximate(bytes) => Some(*bytes),
+        }
+    }
+
+    /// Returns true if a payload of `len` bytes can fit
+    ///
+    /// For [`Capacity::Approximate`] this is only an estimate, so embedding may still
+    /// fail with [`error::LupinError::PayloadTooLarge`].
+    pub fn fits(&self, len: usize) -> bool {
+        self.bytes().map_or(true, |bytes| len <= bytes)
+    }
+
+    /// Returns the capacity with `overhead` bytes reserved for framing
+    pub fn saturating_sub(self, overhead: usize) -> Self {
+        match self {
+            Capacity::Unlimited => Capacity::Unlimited,
+            Capacity::Exact(bytes) => Capacity::Exact(bytes.saturating_sub(overhead)),
+            Capacity::Approximate(bytes) => Capacity::Approximate(bytes.saturating_sub(overhead)),
+        }
+    }
+}
+
+/// A hidden payload found by [`SteganographyEngine::probe`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Probe {
+    /// Mode that embedded the payload
+    pub mode: EmbedMode,
+    /// Size of the payload [`SteganographyEngine::extract`] would return
+    pub size: usize,
+    /// Byte ranges of the file that hold the payload
+    pub ranges: Vec<Range<usize>>,
+    /// The first bytes of the payload (at most [`Probe::PREFIX_LEN`]), enough to tell
+    /// an envelope or encrypted payload apart without extracting the rest
+    pub prefix: Vec<u8>,
+}
+
+impl Probe {
+    /// Number of leading payload bytes a probe reads
+    pub const PREFIX_LEN: usize = 16;
+
+    /// Creates a probe result; `prefix` is cut down to [`Probe::PREFIX_LEN`] bytes
+    pub fn new(
+        mode: EmbedMode,
+        size: usize,
+        ranges: Vec<Range<usize>>,
+        mut prefix: Vec<u8>,
+    ) -> Self {
+        prefix.truncate(Self::PREFIX_LEN);
+        Self {
+            mode,
+            size,
+            ranges,
+            prefix,
+        }
+    }
+}
+
 /// Trait for steganography engines that can embed and extract hidden data
 pub trait SteganographyEngine {
     /// Returns the magic bytes that identify this file format
     fn magic_bytes(&self) -> &[u8];
 
+    /// Returns true if the data is in this engine's format.
+    ///
+    /// Defaults to checking that the data starts with [`Self::magic_bytes`]. Formats
+    /// whose signature isn't a plain prefix, like the RIFF containers whose form type
+    /// follows the size field, override it, as do formats whose magic is short enough
+    /// to begin an ordinary text file (BMP, GIF) and so check the rest of the header
+    /// before claiming the data.
+    fn matches(&self, data: &[u8]) -> bool {
+        data.starts_with(self.magic_bytes())
+    }
+
     /// Returns a human-readable name for this file format
     fn format_name(&self) -> &str;
 
     /// Returns a human-readable extension for this file format
     fn format_ext(&self) -> &str;
 
-    /// Embeds payload data into the source file data using the given mode
+    /// Returns how many payload bytes [`Self::embed`] can hide in the source file data
+    /// using the given mode.
+    ///
+    /// Fails the way `embed` would if the file can't be used in that mode at all, e.g.
+    /// with [`error::LupinError::StealthUnsupportedCarrier`].
+    fn capacity(&self, source_data: &[u8], mode: EmbedMode) -> Result<Capacity>;
+
+    /// Embeds payload data into the source file data using the given mode.
+    ///
+    /// Fails with [`error::LupinError::PayloadTooLarge`] if the payload doesn't fit.
     fn embed(&self, source_data: &[u8], payload: &[u8], mode: EmbedMode) -> Result<Vec<u8>>;
 
+    /// Looks for a hidden payload without extracting it.
+    ///
+    /// Returns `Ok(None)` if the file carries no payload. Only the engine's own framing
+    /// and the first [`Probe::PREFIX_LEN`] payload bytes are read, so this is cheap and
+    /// never fails on a payload that is damaged further in.
+    fn probe(&self, source_data: &[u8]) -> Result<Option<Probe>>;
+
+    /// Removes a hidden payload, returning the file without it.
+    ///
+    /// A capacity-mode payload is cut out, which restores the file as it was before
+    /// embedding. Stealth mode overwrote the original bits, so a stealth payload is
+    /// cleared to zero bits instead: the file no longer carries a payload and can be
+    /// embedded into again, but isn't byte-identical to the original (unless
+    /// [`SteganographyEngine::restores_stealth`] says otherwise). Fails like
+    /// [`SteganographyEngine::extract`] if there is no payload.
+    fn remove(&self, source_data: &[u8]) -> Result<Vec<u8>>;
+
+    /// Returns true if removing a stealth-mode payload restores the file exactly.
+    ///
+    /// Defaults to false. Engines whose stealth payload is added to the file rather
+    /// than written over its bits, like trailing whitespace in text, override it.
+    fn restores_stealth(&self) -> bool {
+        false
+    }
+
     /// Extracts hidden payload from the file data.
     ///
     /// Extraction is not told which [`EmbedMode`] produced the file, so implementations
@@ -73,6 +209,15 @@ impl EngineRouter {
                 Box::new(PdfEngine::new()),
                 Box::new(PngEngine::new()),
                 Box::new(JpegEngine::new()),
+                Box::new(GifEngine::new()),
+                Box::new(BmpEngine::new()),
+                Box::new(WavEngine::new()),
+                Box::new(WebpEngine::new()),
+                Box::new(TiffEngine::new()),
+                Box::new(OoxmlEngine::new()),
+                Box::new(ZipEngine::new()),
+                Box::new(SvgEngine::new()),
+                Box::new(TextEngine::new()),
             ],
         }
     }
@@ -80,7 +225,7 @@ impl EngineRouter {
     /// Detects the appropriate engine for the given data
     pub fn detect_engine(&self, data: &[u8]) -> Result<&dyn SteganographyEngine> {
         for engine in &self.engines {
-            if data.starts_with(engine.magic_bytes()) {
+            if engine.matches(data) {
                 return Ok(engine.as_ref());
             }
         }
@@ -92,6 +237,41 @@ impl EngineRouter {
             ),
         })
     }
+
+    /// Returns the engine for `format`, a format name or extension with or without the
+    /// leading dot, matched case-insensitively (`"PNG"`, `"png"`, `".png"`)
+    pub fn engine_for_format(&self, format: &str) -> Result<&dyn SteganographyEngine> {
+        let wanted = format.trim_start_matches('.');
+        self.engines
+            .iter()
+            .find(|engine| {
+                engine.format_name().eq_ignore_ascii_case(wanted)
+                    || engine
+                        .format_ext()
+                        .trim_start_matches('.')
+                        .eq_ignore_ascii_case(wanted)
+            })
+            .map(|engine| engine.as_ref())
+            .ok_or_else(|| crate::error::LupinError::UnknownFormat {
+                format: format.to_string(),
+            })
+    }
+
+    /// Returns the engine named by `format` if given, skipping detection, and the
+    /// detected one otherwise
+    ///
+    /// Forcing the format helps with files whose content misleads detection, such as a
+    /// text file that starts with another format's magic bytes.
+    pub fn select_engine(
+        &self,
+        data: &[u8],
+        format: Option<&str>,
+    ) -> Result<&dyn SteganographyEngine> {
+        match format {
+            Some(format) => self.engine_for_format(format),
+            None => self.detect_engine(data),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -113,7 +293,7 @@ mod tests {
     }
 
     fn create_unsupported_format() -> Vec<u8> {
-        b"RIFF....WEBP".to_vec() // WebP format
+        b"RIFF....AVI ".to_vec() // AVI format
     }
 
     #[test]
@@ -148,6 +328,195 @@ mod tests {
         assert_eq!(engine.format_name(), "PNG");
     }
 
+    #[test]
+    fn test_detect_engine_gif() {
+        // Arrange
+        let router = EngineRouter::new();
+        let gif_data = b"GIF89a\x01\x00\x01\x00\x00\x00\x00\x3B";
+
+        // Act
+        let result = router.detect_engine(gif_data);
+
+        // Assert
+        assert!(result.is_ok());
+
+        let engine = result.unwrap();
+        assert_eq!(engine.format_name(), "GIF");
+    }
+
+    #[test]
+    fn test_detect_engine_bmp() {
+        // Arrange
+        let router = EngineRouter::new();
+        let bmp_data = b"BM\x1a\x00\x00\x00\x00\x00\x00\x00\x1a\x00\x00\x00\x0c\x00\x00\x00\x01\x00\x01\x00\x01\x00\x18\x00";
+
+        // Act
+        let result = router.detect_engine(bmp_data);
+
+        // Assert
+        assert!(result.is_ok());
+
+        let engine = result.unwrap();
+        assert_eq!(engine.format_name(), "BMP");
+    }
+
+    #[test]
+    fn test_detect_engine_wav() {
+        // Arrange
+        let router = EngineRouter::new();
+        let wav_data = b"RIFF\x04\x00\x00\x00WAVE";
+
+        // Act
+        let result = router.detect_engine(wav_data);
+
+        // Assert
+        assert!(result.is_ok());
+
+        let engine = result.unwrap();
+        assert_eq!(engine.format_name(), "WAV");
+    }
+
+    #[test]
+    fn test_detect_engine_webp() {
+        // Arrange
+        let router = EngineRouter::new();
+        let webp_data = b"RIFF\x04\x00\x00\x00WEBP";
+
+        // Act
+        let result = router.detect_engine(webp_data);
+
+        // Assert
+        assert!(result.is_ok());
+
+        let engine = result.unwrap();
+        assert_eq!(engine.format_name(), "WebP");
+    }
+
+    #[test]
+    fn test_detect_engine_tiff() {
+        // Arrange
+        let router = EngineRouter::new();
+
+        // Act & Assert - both byte orders, classic and BigTIFF
+        for tiff_data in [&b"II*\0"[..], b"MM\0*", b"II+\0", b"MM\0+"] {
+            let engine = router.detect_engine(tiff_data).unwrap();
+            assert_eq!(engine.format_name(), "TIFF");
+        }
+    }
+
+    #[test]
+    fn test_detect_engine_ooxml() {
+        // Arrange
+        let router = EngineRouter::new();
+        let docx_data = crate::engines::zip::create_archive(
+            &[("[Content_Types].xml", b"<Types/>")],
+            false,
+            b"",
+        );
+
+        // Act
+        let result = router.detect_engine(&docx_data);
+
+        // Assert
+        assert!(result.is_ok());
+
+        let engine = result.unwrap();
+        assert_eq!(engine.format_name(), "OOXML");
+    }
+
+    #[test]
+    fn test_detect_engine_zip() {
+        // Arrange
+        let router = EngineRouter::new();
+        let zip_data = crate::engines::zip::create_archive(&[("readme.txt", b"hello")], false, b"");
+
+        // Act
+        let result = router.detect_engine(&zip_data);
+
+        // Assert
+        assert!(result.is_ok());
+
+        let engine = result.unwrap();
+        assert_eq!(engine.format_name(), "ZIP");
+    }
+
+    #[test]
+    fn test_detect_engine_svg() {
+        // Arrange
+        let router = EngineRouter::new();
+        let svg_data =
+            b"<?xml version=\"1.0\"?>\n<svg xmlns=\"http://www.w3.org/2000/svg\"></svg>\n";
+
+        // Act
+        let result = router.detect_engine(svg_data);
+
+        // Assert
+        assert!(result.is_ok());
+
+        let engine = result.unwrap();
+        assert_eq!(engine.format_name(), "SVG");
+    }
+
+    #[test]
+    fn test_detect_engine_text() {
+        // Arrange
+        let router = EngineRouter::new();
+        let text_data = "# Notes\n\nPlain text is tried after every other format.\n".as_bytes();
+
+        // Act
+        let result = router.detect_engine(text_data);
+
+        // Assert
+        assert!(result.is_ok());
+
+        let engine = result.unwrap();
+        assert_eq!(engine.format_name(), "Text");
+
+        // XML that isn't SVG is text as well
+        let xml_data = b"<?xml version=\"1.0\"?>\n<html></html>\n";
+        assert_eq!(
+            router.detect_engine(xml_data).unwrap().format_name(),
+            "Text"
+        );
+    }
+
+    #[test]
+    fn test_detect_engine_text_with_magic_prefix() {
+        // Arrange
+        let router = EngineRouter::new();
+        let bm_text: String = (0..100)
+            .map(|i| format!("BMW service log line {i}\n"))
+            .collect();
+        let gif_text = "GIF8 decoder notes\nframe timings follow\n";
+
+        // Act
+        let bm_result = router.detect_engine(bm_text.as_bytes());
+        let gif_result = router.detect_engine(gif_text.as_bytes());
+
+        // Assert - the BMP and GIF engines turn down a bad header, so text gets it
+        assert_eq!(bm_result.unwrap().format_name(), "Text");
+        assert_eq!(gif_result.unwrap().format_name(), "Text");
+    }
+
+    #[test]
+    fn test_select_engine_format() {
+        // Arrange
+        let router = EngineRouter::new();
+        let png_data = create_minimal_png();
+
+        // Act & Assert - a name or extension overrides detection
+        for format in ["text", "TXT", ".txt"] {
+            let engine = router.select_engine(&png_data, Some(format)).unwrap();
+            assert_eq!(engine.format_name(), "Text");
+        }
+        let detected = router.select_engine(&png_data, None).unwrap();
+        assert_eq!(detected.format_name(), "PNG");
+        assert!(matches!(
+            router.select_engine(&png_data, Some("avi")),
+            Err(crate::error::LupinError::UnknownFormat { .. })
+        ));
+    }
+
     #[test]
     fn test_detect_engine_unsupported() {
         // Arrange
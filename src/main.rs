@@ -13,15 +13,24 @@
 // limitations under the License.
 
 use clap::{Parser, Subcommand, ValueEnum};
+use filetime::FileTime;
 use log::{debug, error, info, warn};
+use lupin::compression::Compression;
+use lupin::crypto::{self, Identity, Recipient};
+use lupin::engines::TextEngine;
+use lupin::envelope;
 use lupin::error::{LupinError, Result};
-use lupin::operations;
-use lupin::EmbedMode;
+use lupin::operations::{self, EmbedOptions, ExtractOptions, ExtractResult};
+use lupin::signing::{self, SigningKey, VerifyingKey};
+use lupin::tree;
+use lupin::{Capacity, EmbedMode, EngineRouter, SteganographyEngine};
 use simplelog::{ColorChoice, Config, TermLogger, TerminalMode};
+use std::cmp::Ordering;
 use std::fs;
 use std::io::{self, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
+use std::time::UNIX_EPOCH;
 
 /// Log level for controlling output verbosity
 #[derive(Debug, Clone, ValueEnum)]
@@ -36,6 +45,27 @@ enum LogLevel {
     Debug,
 }
 
+/// Payload compression algorithm
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum CompressionArg {
+    /// Store the payload uncompressed
+    None,
+    /// DEFLATE (default)
+    Deflate,
+    /// Zstandard (needs a build with the `zstd` feature)
+    Zstd,
+}
+
+impl From<CompressionArg> for Compression {
+    fn from(arg: CompressionArg) -> Self {
+        match arg {
+            CompressionArg::None => Compression::None,
+            CompressionArg::Deflate => Compression::Deflate,
+            CompressionArg::Zstd => Compression::Zstd,
+        }
+    }
+}
+
 /// A blazing-fast steganography tool for concealing data inside files
 #[derive(Parser, Debug)]
 #[command(name = "lupin")]
@@ -54,6 +84,11 @@ struct CliArgs {
     #[arg(short, long)]
     quiet: bool,
 
+    /// Treat the file as this format (a name or extension such as png or txt) instead
+    /// of detecting it
+    #[arg(long, global = true)]
+    format: Option<String>,
+
     #[command(subcommand)]
     command: Command,
 }
@@ -65,7 +100,7 @@ enum Command {
     Embed {
         /// Source file to embed data into
         src: PathBuf,
-        /// Payload file to embed
+        /// Payload file to embed, or a directory to embed as a whole
         payload: PathBuf,
         /// Output file path
         output: PathBuf,
@@ -75,14 +110,247 @@ enum Command {
         /// Optimize for capacity: unlimited size, easier to detect (default)
         #[arg(long, conflicts_with = "stealth")]
         capacity: bool,
+        /// Encrypt the payload with this password
+        /// (visible to other local users; prefer --password-file)
+        #[arg(long, conflicts_with = "password_file")]
+        password: Option<String>,
+        /// Encrypt the payload with the password stored in this file
+        #[arg(long)]
+        password_file: Option<PathBuf>,
+        /// Encrypt the payload to this public key (from `lupin keygen`); repeat the flag
+        /// to let any of several people extract it
+        #[arg(
+            long = "recipient",
+            value_name = "PUBLIC_KEY",
+            conflicts_with_all = ["password", "password_file"]
+        )]
+        recipients: Vec<String>,
+        /// Don't store the payload's filename and content type alongside it
+        #[arg(long)]
+        no_name: bool,
+        /// Compress the payload before embedding; it is stored uncompressed if that
+        /// comes out smaller
+        #[arg(long, value_enum, default_value = "deflate")]
+        compression: CompressionArg,
+        /// Add error correction with this many parity bytes (2 to 128) per 255-byte
+        /// codeword, which repairs up to half as many damaged bytes in each
+        #[arg(long, value_name = "PARITY")]
+        fec: Option<u8>,
+        /// Sign the payload with the signing key in this file (from `lupin keygen
+        /// --sign`), so extraction can check who embedded it
+        #[arg(long, value_name = "FILE")]
+        sign_key: Option<PathBuf>,
+        /// Replace a payload the source already carries instead of failing
+        #[arg(long)]
+        replace: bool,
     },
     /// Extract hidden data from a file
     Extract {
         /// Source file to extract from
         src: PathBuf,
+        /// Output file path (use "-" for stdout, which forces log-level to ERROR), or
+        /// target directory for a hidden directory; omit it to restore the original
+        /// name in the current directory
+        output: Option<PathBuf>,
+        /// Password for an encrypted payload
+        /// (visible to other local users; prefer --password-file)
+        #[arg(long, conflicts_with = "password_file")]
+        password: Option<String>,
+        /// Read the password for an encrypted payload from this file
+        #[arg(long)]
+        password_file: Option<PathBuf>,
+        /// Identity file (from `lupin keygen`) for a payload encrypted to recipients;
+        /// may be repeated
+        #[arg(long = "identity", value_name = "FILE")]
+        identities: Vec<PathBuf>,
+        /// Name of the entry to extract, if the file hides several
+        #[arg(long, value_name = "NAME")]
+        entry: Option<String>,
+        /// Require the payload to be signed by this verifying key (from `lupin keygen
+        /// --sign`); repeat the flag to accept any of several
+        #[arg(long = "verify-key", value_name = "KEY")]
+        verify_keys: Vec<String>,
+    },
+    /// Add a payload as a new entry next to the data a file already hides
+    Add {
+        /// Source file that hides the existing data
+        src: PathBuf,
+        /// Payload file or directory to add; its name names the entry
+        payload: PathBuf,
+        /// Output file path
+        output: PathBuf,
+        /// Password of the hidden data, which the new data is encrypted with too
+        /// (visible to other local users; prefer --password-file)
+        #[arg(long, conflicts_with = "password_file")]
+        password: Option<String>,
+        /// Read the password from this file
+        #[arg(long)]
+        password_file: Option<PathBuf>,
+        /// Encrypt the hidden data to this public key; may be repeated
+        #[arg(
+            long = "recipient",
+            value_name = "PUBLIC_KEY",
+            conflicts_with_all = ["password", "password_file"]
+        )]
+        recipients: Vec<String>,
+        /// Identity file to decrypt hidden data encrypted to recipients; may be repeated
+        #[arg(long = "identity", value_name = "FILE")]
+        identities: Vec<PathBuf>,
+        /// Compress the payload before embedding; it is stored uncompressed if that
+        /// comes out smaller
+        #[arg(long, value_enum, default_value = "deflate")]
+        compression: CompressionArg,
+        /// Add error correction with this many parity bytes (2 to 128) per 255-byte
+        /// codeword; by default the hidden data keeps the error correction it has
+        #[arg(long, value_name = "PARITY")]
+        fec: Option<u8>,
+        /// Sign the new entry with the signing key in this file
+        #[arg(long, value_name = "FILE")]
+        sign_key: Option<PathBuf>,
+    },
+    /// Remove an entry from the data a file hides
+    Rm {
+        /// Source file that hides the data
+        src: PathBuf,
+        /// Name of the entry to remove
+        entry: String,
         /// Output file path
-        /// (use "-" for stdout, which forces log-level to ERROR)
         output: PathBuf,
+        /// Password of the hidden data, which the remaining entries are encrypted
+        /// with again (visible to other local users; prefer --password-file)
+        #[arg(long, conflicts_with = "password_file")]
+        password: Option<String>,
+        /// Read the password from this file
+        #[arg(long)]
+        password_file: Option<PathBuf>,
+        /// Encrypt the remaining entries to this public key; may be repeated
+        #[arg(
+            long = "recipient",
+            value_name = "PUBLIC_KEY",
+            conflicts_with_all = ["password", "password_file"]
+        )]
+        recipients: Vec<String>,
+        /// Identity file to decrypt hidden data encrypted to recipients; may be repeated
+        #[arg(long = "identity", value_name = "FILE")]
+        identities: Vec<PathBuf>,
+    },
+    /// List the entries of the data a file hides
+    Ls {
+        /// File to inspect
+        src: PathBuf,
+        /// Password for an encrypted payload
+        /// (visible to other local users; prefer --password-file)
+        #[arg(long, conflicts_with = "password_file")]
+        password: Option<String>,
+        /// Read the password for an encrypted payload from this file
+        #[arg(long)]
+        password_file: Option<PathBuf>,
+        /// Identity file for a payload encrypted to recipients; may be repeated
+        #[arg(long = "identity", value_name = "FILE")]
+        identities: Vec<PathBuf>,
+    },
+    /// Remove hidden data from a file
+    Strip {
+        /// Source file to remove hidden data from
+        src: PathBuf,
+        /// Output file path
+        output: PathBuf,
+    },
+    /// Show how many payload bytes a file can hide in each mode
+    Capacity {
+        /// File to inspect
+        src: PathBuf,
+    },
+    /// Report whether a file carries a hidden payload, without extracting it
+    ///
+    /// Exits with 0 if a payload is found, 1 if none is, and 2 on error.
+    Probe {
+        /// File to inspect
+        src: PathBuf,
+    },
+    /// Split a payload across several files, so that any K of them rebuild it
+    Split {
+        /// Payload file to split, or a directory to split as a whole
+        payload: PathBuf,
+        /// Source files to embed a share into, one each
+        #[arg(required = true, num_args = 2..)]
+        sources: Vec<PathBuf>,
+        /// Number of files needed to rebuild the payload
+        #[arg(short = 'k', long, value_name = "K")]
+        threshold: u8,
+        /// Directory to write the output files to, under the sources' names
+        #[arg(short, long, value_name = "DIR")]
+        output_dir: PathBuf,
+        /// Optimize for stealth (harder to detect) at the cost of capacity or format support
+        #[arg(long, conflicts_with = "capacity")]
+        stealth: bool,
+        /// Optimize for capacity: unlimited size, easier to detect (default)
+        #[arg(long, conflicts_with = "stealth")]
+        capacity: bool,
+        /// Encrypt the payload with this password
+        /// (visible to other local users; prefer --password-file)
+        #[arg(long, conflicts_with = "password_file")]
+        password: Option<String>,
+        /// Encrypt the payload with the password stored in this file
+        #[arg(long)]
+        password_file: Option<PathBuf>,
+        /// Encrypt the payload to this public key (from `lupin keygen`); may be repeated
+        #[arg(
+            long = "recipient",
+            value_name = "PUBLIC_KEY",
+            conflicts_with_all = ["password", "password_file"]
+        )]
+        recipients: Vec<String>,
+        /// Don't store the payload's filename and content type alongside it
+        #[arg(long)]
+        no_name: bool,
+        /// Compress the payload before splitting; it is stored uncompressed if that
+        /// comes out smaller
+        #[arg(long, value_enum, default_value = "deflate")]
+        compression: CompressionArg,
+        /// Add error correction with this many parity bytes (2 to 128) per 255-byte
+        /// codeword to every share, which repairs up to half as many damaged bytes in
+        /// each
+        #[arg(long, value_name = "PARITY")]
+        fec: Option<u8>,
+        /// Sign the payload with the signing key in this file (from `lupin keygen
+        /// --sign`)
+        #[arg(long, value_name = "FILE")]
+        sign_key: Option<PathBuf>,
+    },
+    /// Rebuild a payload split with `lupin split` from enough of its files
+    Join {
+        /// Files holding the shares
+        #[arg(required = true)]
+        sources: Vec<PathBuf>,
+        /// Output file path (use "-" for stdout, which forces log-level to ERROR), or
+        /// target directory for a hidden directory; omit it to restore the original
+        /// name in the current directory
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Password for an encrypted payload
+        /// (visible to other local users; prefer --password-file)
+        #[arg(long, conflicts_with = "password_file")]
+        password: Option<String>,
+        /// Read the password for an encrypted payload from this file
+        #[arg(long)]
+        password_file: Option<PathBuf>,
+        /// Identity file (from `lupin keygen`) for a payload encrypted to recipients;
+        /// may be repeated
+        #[arg(long = "identity", value_name = "FILE")]
+        identities: Vec<PathBuf>,
+        /// Require the payload to be signed by this verifying key; may be repeated
+        #[arg(long = "verify-key", value_name = "KEY")]
+        verify_keys: Vec<String>,
+    },
+    /// Generate an X25519 identity for recipient encryption, or an Ed25519 signing key
+    Keygen {
+        /// Write the key to this file instead of stdout (never overwrites)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Generate a signing key for `--sign-key` instead of an identity
+        #[arg(long)]
+        sign: bool,
     },
 }
 
@@ -128,29 +396,130 @@ fn format_size(size: usize) -> String {
     }
 }
 
+/// Resolves the password from `--password` or `--password-file`
+///
+/// A single trailing newline is stripped from the file, since most editors add one.
+fn read_password(
+    password: Option<String>,
+    password_file: Option<PathBuf>,
+) -> Result<Option<Vec<u8>>> {
+    if let Some(path) = password_file {
+        let mut password =
+            fs::read(&path).map_err(|e| LupinError::PasswordFileRead { path, source: e })?;
+        if password.ends_with(b"\n") {
+            password.pop();
+            if password.ends_with(b"\r") {
+                password.pop();
+            }
+        }
+        return Ok(Some(password));
+    }
+    Ok(password.map(String::into_bytes))
+}
+
+/// Builds the embed options from the mode, compression, error correction, signing and
+/// encryption flags
+fn embed_options(
+    mode: EmbedMode,
+    compression: CompressionArg,
+    fec: Option<u8>,
+    sign_key: Option<PathBuf>,
+    password: Option<String>,
+    password_file: Option<PathBuf>,
+    recipients: Vec<String>,
+) -> Result<EmbedOptions> {
+    let mut options = EmbedOptions::new(mode).with_compression(compression.into());
+    if let Some(parity) = fec {
+        options = options.with_fec(parity);
+    }
+    if let Some(path) = sign_key {
+        let text = fs::read_to_string(&path)
+            .map_err(|e| LupinError::SigningKeyFileRead { path, source: e })?;
+        options = options.with_signing_key(signing::parse_signing_key(&text)?);
+    }
+    if let Some(password) = read_password(password, password_file)? {
+        options = options.with_password(password);
+    }
+    for recipient in recipients {
+        options = options.with_recipient(recipient.parse::<Recipient>()?);
+    }
+    Ok(options)
+}
+
+/// Builds the extract options from the entry, decryption and verification flags
+fn extract_options(
+    entry: Option<String>,
+    password: Option<String>,
+    password_file: Option<PathBuf>,
+    identity_files: Vec<PathBuf>,
+    verify_keys: Vec<String>,
+) -> Result<ExtractOptions> {
+    let mut options = ExtractOptions::default();
+    if let Some(entry) = entry {
+        options = options.with_entry(entry);
+    }
+    for key in verify_keys {
+        options = options.with_verify_key(key.parse::<VerifyingKey>()?);
+    }
+    if let Some(password) = read_password(password, password_file)? {
+        options = options.with_password(password);
+    }
+    for path in identity_files {
+        let text = fs::read_to_string(&path)
+            .map_err(|e| LupinError::IdentityFileRead { path, source: e })?;
+        for identity in crypto::parse_identities(&text)? {
+            options = options.with_identity(identity);
+        }
+    }
+    Ok(options)
+}
+
 /// Handle embed command
-fn handle_embed(src: PathBuf, payload: PathBuf, output: PathBuf, mode: EmbedMode) -> Result<()> {
+fn handle_embed(
+    src: PathBuf,
+    payload: PathBuf,
+    output: PathBuf,
+    mut options: EmbedOptions,
+    no_name: bool,
+    replace: bool,
+) -> Result<()> {
     debug!("Running command: embed");
     debug!(
-        "Source: {}, Payload: {}, Output: {}, Mode: {:?}",
+        "Source: {}, Payload: {}, Output: {}, Options: {:?}",
         src.display(),
         payload.display(),
         output.display(),
-        mode
+        options
     );
 
     // Read files
-    let source_data = fs::read(&src).map_err(|e| LupinError::SourceFileRead {
-        path: src,
-        source: e,
-    })?;
-    let payload_data = fs::read(&payload).map_err(|e| LupinError::PayloadFileRead {
-        path: payload,
-        source: e,
-    })?;
+    let (source_data, payload_data, is_tree) = read_inputs(src, &payload)?;
+    options = with_payload_name(options, &payload, is_tree, no_name);
+
+    // Pin the engine, so the operation uses the one the hint below checks for
+    let router = EngineRouter::new();
+    let engine = router.select_engine(&source_data, options.format.as_deref())?;
+    let text = is_text(engine);
+    options.format = Some(engine.format_name().to_string());
 
     // Process
-    let (embedded_data, result) = operations::embed(&source_data, &payload_data, mode)?;
+    let embedded = if replace {
+        operations::replace(&source_data, &payload_data, &options)
+    } else {
+        operations::embed_with_options(&source_data, &payload_data, &options)
+    };
+    let (embedded_data, result) = embedded.map_err(|e| {
+        if let LupinError::PayloadTooLarge {
+            capacity,
+            requested,
+        } = e
+        {
+            if text && options.mode == EmbedMode::Stealth {
+                info!("{}", text_stealth_shortfall(capacity, requested));
+            }
+        }
+        e
+    })?;
 
     // Write output
     fs::write(&output, &embedded_data).map_err(|e| LupinError::OutputFileWrite {
@@ -160,20 +529,62 @@ fn handle_embed(src: PathBuf, payload: PathBuf, output: PathBuf, mode: EmbedMode
 
     // Display results
     debug!("Using {} engine", result.engine);
+    if result.compression != Compression::None {
+        info!(
+            "Compressed payload with {}: {} → {} ({:.0}%)",
+            result.compression.name(),
+            format_size(result.payload_size),
+            format_size(result.compressed_size),
+            result.compression_ratio() * 100.0
+        );
+    } else {
+        debug!("Payload stored uncompressed");
+    }
+    if result.encrypted {
+        debug!("Payload was encrypted before embedding");
+    }
+    if let Some(key) = &options.signing_key {
+        debug!("Signed the payload with {}", key.verifying_key());
+    }
+    if let Some(parity) = options.fec {
+        debug!(
+            "Added error correction with {} parity bytes per codeword",
+            parity
+        );
+    }
+    if result.replaced {
+        info!("Replaced the payload already hidden in the source");
+    }
+    // Stealth mode re-encodes some formats, so the output can also be smaller. The sign
+    // comes from the sizes, as a change that rounds to 0% still has a direction.
+    let sign = match result.output_size.cmp(&result.source_size) {
+        Ordering::Greater => "+",
+        Ordering::Less => "-",
+        Ordering::Equal => "",
+    };
+    let change =
+        result.output_size.abs_diff(result.source_size) as f64 / result.source_size as f64 * 100.0;
     info!(
-        "Embedded payload into {} source → {} output (+{:.0}%)",
+        "Embedded payload into {} source → {} output ({}{:.0}%)",
         format_size(result.source_size),
         format_size(result.output_size),
-        ((result.output_size as f64 / result.source_size as f64 - 1.0) * 100.0).round()
+        sign,
+        change
     );
 
     Ok(())
 }
 
 /// Handle extract command
-fn handle_extract(src: PathBuf, output: PathBuf) -> Result<()> {
+fn handle_extract(src: PathBuf, output: Option<PathBuf>, options: ExtractOptions) -> Result<()> {
     debug!("Running command: extract");
-    debug!("Source: {}, Output: {}", src.display(), output.display());
+    debug!(
+        "Source: {}, Output: {}",
+        src.display(),
+        output
+            .as_ref()
+            .map_or("<stored filename>".into(), |o| o.display().to_string())
+    );
 
     // Read file
     let source_data = fs::read(&src).map_err(|e| LupinError::SourceFileRead {
@@ -182,30 +593,795 @@ fn handle_extract(src: PathBuf, output: PathBuf) -> Result<()> {
     })?;
 
     // Process
-    let (payload_data, result) = operations::extract(&source_data)?;
+    let (payload_data, result) = operations::extract_with_options(&source_data, &options)?;
+    write_extracted(output, &payload_data, &result)?;
 
-    // Write output
-    let written_to_stdout = output.as_os_str() == "-";
-    if written_to_stdout {
-        io::stdout()
-            .write_all(&payload_data)
-            .map_err(|e| LupinError::StdoutWrite { source: e })?;
-    } else {
-        fs::write(&output, &payload_data).map_err(|e| LupinError::OutputFileWrite {
-            path: output,
-            source: e,
-        })?;
+    info!("Successfully extracted payload from {}.", result.engine);
+    Ok(())
+}
+
+/// Writes an extracted payload to the output path, stdout or its stored name, and
+/// restores a hidden directory
+fn write_extracted(
+    output: Option<PathBuf>,
+    payload_data: &[u8],
+    result: &ExtractResult,
+) -> Result<()> {
+    let written_to_stdout = output.as_ref().is_some_and(|o| o.as_os_str() == "-");
+    let is_tree = result.content_type.as_deref() == Some(tree::CONTENT_TYPE);
+    match output {
+        _ if is_tree => {
+            if written_to_stdout {
+                return Err(LupinError::TreeToStdout);
+            }
+            // Check every path before anything is written
+            let entries = tree::unpack(payload_data)?;
+            let target = match output {
+                Some(output) => output,
+                None => {
+                    let path = PathBuf::from(stored_filename(result)?);
+                    fs::create_dir(&path).map_err(|e| LupinError::OutputFileWrite {
+                        path: path.clone(),
+                        source: e,
+                    })?;
+                    info!("Restored original directory name {}", path.display());
+                    path
+                }
+            };
+            restore_tree(&target, &entries).map_err(|e| LupinError::OutputFileWrite {
+                path: target.clone(),
+                source: e,
+            })?;
+            debug!(
+                "Restored {} entries into {}",
+                entries.len(),
+                target.display()
+            );
+        }
+        _ if written_to_stdout => io::stdout()
+            .write_all(payload_data)
+            .map_err(|e| LupinError::StdoutWrite { source: e })?,
+        Some(output) => {
+            fs::write(&output, payload_data).map_err(|e| LupinError::OutputFileWrite {
+                path: output,
+                source: e,
+            })?
+        }
+        None => {
+            let path = PathBuf::from(stored_filename(result)?);
+            fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+                .and_then(|mut f| f.write_all(payload_data))
+                .map_err(|e| LupinError::OutputFileWrite {
+                    path: path.clone(),
+                    source: e,
+                })?;
+            info!("Restored original filename {}", path.display());
+        }
     }
 
     // Display results
     debug!("Using {} engine", result.engine);
+    if result.encrypted {
+        debug!("Payload was decrypted after extraction");
+    }
+    if let Some(signer) = &result.signer {
+        info!("Payload is signed by {}", signer);
+    }
+    if result.corrected > 0 {
+        warn!(
+            "The hidden data was damaged; error correction repaired {} bytes",
+            result.corrected
+        );
+    }
+    match result.envelope_version {
+        Some(version) => debug!(
+            "Envelope v{}: filename {:?}, content type {:?}, compression {}, SHA-256 {}",
+            version,
+            result.filename,
+            result.content_type,
+            result.compression.unwrap_or_default().name(),
+            result
+                .sha256
+                .map(|hash| hash
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect::<String>())
+                .unwrap_or_default()
+        ),
+        None => debug!("Payload has no envelope (embedded by an older version)"),
+    }
     if written_to_stdout {
         debug!("Extracted {} to stdout", format_size(result.payload_size));
     } else {
         debug!("Extracted {} from source", format_size(result.payload_size));
     }
+    Ok(())
+}
+
+/// Handle split command
+fn handle_split(
+    payload: PathBuf,
+    sources: Vec<PathBuf>,
+    threshold: u8,
+    output_dir: PathBuf,
+    mut options: EmbedOptions,
+    no_name: bool,
+) -> Result<()> {
+    debug!("Running command: split");
+    debug!(
+        "Payload: {}, Sources: {}, Threshold: {}, Output directory: {}, Options: {:?}",
+        payload.display(),
+        sources.len(),
+        threshold,
+        output_dir.display(),
+        options
+    );
+
+    // Every output is named after its source, so the names must be distinct
+    let mut outputs: Vec<PathBuf> = Vec::with_capacity(sources.len());
+    for src in &sources {
+        let output = output_dir.join(src.file_name().unwrap_or(src.as_os_str()));
+        if outputs.contains(&output) {
+            return Err(LupinError::InvalidSplit {
+                reason: format!("two sources would be written to {}", output.display()),
+            });
+        }
+        outputs.push(output);
+    }
+    let count = u8::try_from(sources.len()).map_err(|_| LupinError::InvalidSplit {
+        reason: format!("at most 255 sources, got {}", sources.len()),
+    })?;
+
+    let (payload_data, is_tree) = read_payload(&payload)?;
+    options = with_payload_name(options, &payload, is_tree, no_name);
+    let source_data = sources
+        .into_iter()
+        .map(|src| {
+            fs::read(&src).map_err(|e| LupinError::SourceFileRead {
+                path: src,
+                source: e,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let (embedded, result) = operations::embed_split_with_options(
+        &source_data,
+        &payload_data,
+        threshold,
+        count,
+        &options,
+    )?;
+
+    fs::create_dir_all(&output_dir).map_err(|e| LupinError::OutputFileWrite {
+        path: output_dir.clone(),
+        source: e,
+    })?;
+    for ((output, embedded_data), carrier) in outputs.iter().zip(&embedded).zip(&result.carriers) {
+        fs::write(output, embedded_data).map_err(|e| LupinError::OutputFileWrite {
+            path: output.clone(),
+            source: e,
+        })?;
+        debug!(
+            "Wrote {} ({} engine): {} source → {} output",
+            output.display(),
+            carrier.engine,
+            format_size(carrier.source_size),
+            format_size(carrier.output_size)
+        );
+    }
+
+    info!(
+        "Split payload across {} files, any {} of which rebuild it ({} share in each)",
+        count,
+        result.threshold,
+        format_size(result.share_size)
+    );
+    Ok(())
+}
+
+/// Handle join command
+fn handle_join(
+    sources: Vec<PathBuf>,
+    output: Option<PathBuf>,
+    options: ExtractOptions,
+) -> Result<()> {
+    debug!("Running command: join");
+    debug!("Sources: {}", sources.len());
+
+    let source_data = sources
+        .into_iter()
+        .map(|src| {
+            fs::read(&src).map_err(|e| LupinError::SourceFileRead {
+                path: src,
+                source: e,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let (payload_data, result) = operations::extract_join_with_options(&source_data, &options)?;
+    write_extracted(output, &payload_data, &result)?;
+
+    info!(
+        "Successfully rebuilt payload from {} files ({}).",
+        result.shares, result.engine
+    );
+    Ok(())
+}
+
+/// The stored filename of an extracted payload
+///
+/// The name comes from the carrier, so never trust it with a path; callers must not
+/// overwrite an existing file with it either.
+fn stored_filename(result: &ExtractResult) -> Result<&str> {
+    let stored = result
+        .filename
+        .as_deref()
+        .ok_or(LupinError::NoStoredFilename)?;
+    envelope::safe_filename(stored).ok_or_else(|| LupinError::UnsafeFilename {
+        filename: stored.to_string(),
+    })
+}
+
+/// Stores the payload's name and type so `extract` can restore them; a directory is
+/// always marked as one, or it couldn't be restored
+fn with_payload_name(
+    mut options: EmbedOptions,
+    payload: &Path,
+    is_tree: bool,
+    no_name: bool,
+) -> EmbedOptions {
+    if let (Some(filename), false) = (payload_name(payload), no_name) {
+        if let Some(content_type) = envelope::guess_content_type(&filename) {
+            options = options.with_content_type(content_type);
+        }
+        options = options.with_filename(filename);
+    }
+    if is_tree {
+        options = options.with_content_type(tree::CONTENT_TYPE);
+    }
+    options
+}
+
+/// Name to store for a payload, resolving `.` and `..` for directories
+fn payload_name(payload: &Path) -> Option<String> {
+    let name = match payload.file_name() {
+        Some(name) => name.to_os_string(),
+        None => payload.canonicalize().ok()?.file_name()?.to_os_string(),
+    };
+    name.into_string().ok()
+}
+
+/// Reads the source and payload named on the command line
+fn read_inputs(src: PathBuf, payload: &Path) -> Result<(Vec<u8>, Vec<u8>, bool)> {
+    let source_data = fs::read(&src).map_err(|e| LupinError::SourceFileRead {
+        path: src,
+        source: e,
+    })?;
+    let (payload_data, is_tree) = read_payload(payload)?;
+    Ok((source_data, payload_data, is_tree))
+}
+
+/// Reads a payload file, or packs a payload directory into a tree, which the returned
+/// flag reports
+fn read_payload(payload: &Path) -> Result<(Vec<u8>, bool)> {
+    let read_error = |e| LupinError::PayloadFileRead {
+        path: payload.to_path_buf(),
+        source: e,
+    };
+    if !payload.is_dir() {
+        let payload_data = fs::read(payload).map_err(read_error)?;
+        return Ok((payload_data, false));
+    }
+
+    let mut entries = Vec::new();
+    read_tree(payload, "", &mut entries).map_err(read_error)?;
+    debug!(
+        "Packed {} entries from directory {}",
+        entries.len(),
+        payload.display()
+    );
+    Ok((tree::pack(&entries)?, true))
+}
+
+/// Walks a directory into tree entries, parents before children and siblings in name
+/// order; symlinks and special files are skipped
+fn read_tree(dir: &Path, prefix: &str, entries: &mut Vec<tree::Entry>) -> io::Result<()> {
+    let mut children = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    children.sort_by_key(|child| child.file_name());
+    for child in children {
+        let name = child.file_name().into_string().map_err(|name| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{:?} is not valid UTF-8", name),
+            )
+        })?;
+        let path = if prefix.is_empty() {
+            name
+        } else {
+            format!("{}/{}", prefix, name)
+        };
+
+        // symlink_metadata, so a link is never followed out of the directory
+        let metadata = fs::symlink_metadata(child.path())?;
+        let kind = if metadata.is_dir() {
+            tree::EntryKind::Directory
+        } else if metadata.is_file() {
+            tree::EntryKind::File(fs::read(child.path())?)
+        } else {
+            warn!(
+                "Skipping {}: not a regular file or directory",
+                child.path().display()
+            );
+            continue;
+        };
+        let mtime = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |since| since.as_secs());
+        entries.push(tree::Entry {
+            path: path.clone(),
+            mode: file_mode(&metadata),
+            mtime,
+            kind,
+        });
+        if metadata.is_dir() {
+            read_tree(&child.path(), &path, entries)?;
+        }
+    }
+    Ok(())
+}
+
+/// Mode bits kept in a packed tree: read, write and execute only, since setuid,
+/// setgid and sticky bits from an untrusted carrier must never reach the disk
+const PERMISSION_BITS: u32 = 0o777;
+
+/// Permission bits of a file, made up from the read-only flag off Unix
+#[cfg(unix)]
+fn file_mode(metadata: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & PERMISSION_BITS
+}
+
+#[cfg(not(unix))]
+fn file_mode(metadata: &fs::Metadata) -> u32 {
+    let mode = if metadata.is_dir() { 0o755 } else { 0o644 };
+    if metadata.permissions().readonly() {
+        mode & !0o222
+    } else {
+        mode
+    }
+}
+
+/// Restores a packed tree under the target directory, creating it if needed
+///
+/// Every path was checked by [`tree::unpack`], and nothing that already exists is
+/// overwritten. A symlink inside the target is never followed, so an existing target
+/// can't redirect the tree elsewhere. Directory permissions and times are applied
+/// last, so a read-only directory can still be filled and its mtime isn't bumped by
+/// its contents.
+fn restore_tree(target: &Path, entries: &[tree::Entry]) -> io::Result<()> {
+    fs::create_dir_all(target)?;
+    let mut directories = Vec::new();
+    for entry in entries {
+        let relative = tree::safe_path(&entry.path).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unsafe path in hidden directory",
+            )
+        })?;
+        create_parents(target, &relative)?;
+        let path = target.join(relative);
+        match &entry.kind {
+            tree::EntryKind::Directory => {
+                fs::create_dir(&path)?;
+                directories.push((path, entry));
+            }
+            tree::EntryKind::File(contents) => {
+                let mut file = fs::OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(&path)?;
+                file.write_all(contents)?;
+                drop(file);
+                set_mode(&path, entry.mode)?;
+                set_mtime(&path, entry.mtime)?;
+            }
+        }
+    }
+    for (path, entry) in directories.iter().rev() {
+        set_mtime(path, entry.mtime)?;
+        set_mode(path, entry.mode)?;
+    }
+    Ok(())
+}
+
+/// Creates the missing parent directories of `relative` under `target`, failing on any
+/// component that is a symlink or not a directory
+fn create_parents(target: &Path, relative: &Path) -> io::Result<()> {
+    let mut path = target.to_path_buf();
+    let Some(parent) = relative.parent() else {
+        return Ok(());
+    };
+    for component in parent.components() {
+        path.push(component);
+        match fs::symlink_metadata(&path) {
+            Ok(metadata) if metadata.is_dir() => {}
+            Ok(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} is not a directory", path.display()),
+                ))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => fs::create_dir(&path)?,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode & PERMISSION_BITS))
+}
+
+#[cfg(not(unix))]
+fn set_mode(path: &Path, mode: u32) -> io::Result<()> {
+    let mut permissions = fs::metadata(path)?.permissions();
+    permissions.set_readonly(mode & 0o222 == 0);
+    fs::set_permissions(path, permissions)
+}
+
+fn set_mtime(path: &Path, mtime: u64) -> io::Result<()> {
+    let mtime = FileTime::from_unix_time(i64::try_from(mtime).unwrap_or(i64::MAX), 0);
+    filetime::set_file_mtime(path, mtime)
+}
+
+/// Handle add command
+fn handle_add(
+    src: PathBuf,
+    payload: PathBuf,
+    output: PathBuf,
+    mut options: EmbedOptions,
+    unlock: ExtractOptions,
+) -> Result<()> {
+    debug!("Running command: add");
+    debug!(
+        "Source: {}, Payload: {}, Output: {}, Options: {:?}",
+        src.display(),
+        payload.display(),
+        output.display(),
+        options
+    );
+
+    let (source_data, payload_data, is_tree) = read_inputs(src, &payload)?;
+
+    // The entry is named after the payload, and keeps the existing payload's mode
+    // and, unless told otherwise, its error correction
+    options = with_payload_name(options, &payload, is_tree, false);
+    if let Some(existing) =
+        operations::detect_with_format(&source_data, options.format.as_deref())?.payload
+    {
+        options.mode = existing.mode;
+        options.fec = options.fec.or(existing.fec);
+    }
+
+    let (embedded_data, result) = operations::add(&source_data, &payload_data, &options, &unlock)?;
+
+    fs::write(&output, &embedded_data).map_err(|e| LupinError::OutputFileWrite {
+        path: output.clone(),
+        source: e,
+    })?;
+
+    debug!("Using {} engine", result.engine);
+    info!(
+        "Added {} ({}) to the hidden data: {} source → {} output",
+        options.filename.as_deref().unwrap_or_default(),
+        format_size(result.payload_size),
+        format_size(result.source_size),
+        format_size(result.output_size)
+    );
+
+    Ok(())
+}
+
+/// Handle rm command
+fn handle_rm(
+    src: PathBuf,
+    entry: String,
+    output: PathBuf,
+    mut options: EmbedOptions,
+    unlock: ExtractOptions,
+) -> Result<()> {
+    debug!("Running command: rm");
+    debug!(
+        "Source: {}, Entry: {}, Output: {}",
+        src.display(),
+        entry,
+        output.display()
+    );
+
+    let source_data = fs::read(&src).map_err(|e| LupinError::SourceFileRead {
+        path: src,
+        source: e,
+    })?;
+
+    // The remaining entries keep the payload's mode and error correction
+    if let Some(existing) =
+        operations::detect_with_format(&source_data, options.format.as_deref())?.payload
+    {
+        options.mode = existing.mode;
+        options.fec = existing.fec;
+    }
+
+    let (output_data, result) = operations::remove_entry(&source_data, &entry, &options, &unlock)?;
+
+    fs::write(&output, &output_data).map_err(|e| LupinError::OutputFileWrite {
+        path: output.clone(),
+        source: e,
+    })?;
+
+    debug!("Using {} engine", result.engine);
+    info!(
+        "Removed {} from the hidden data: {} source → {} output",
+        entry,
+        format_size(result.source_size),
+        format_size(result.output_size)
+    );
+
+    Ok(())
+}
+
+/// Handle ls command
+fn handle_ls(src: PathBuf, options: ExtractOptions) -> Result<()> {
+    debug!("Running command: ls");
+    debug!("Source: {}", src.display());
+
+    let source_data = fs::read(&src).map_err(|e| LupinError::SourceFileRead {
+        path: src,
+        source: e,
+    })?;
+
+    let entries = operations::list(&source_data, &options)?;
+
+    let mut stdout = io::stdout();
+    for entry in entries {
+        writeln!(
+            stdout,
+            "{:>10}  {:<24}  {}",
+            format_size(entry.size),
+            entry.content_type.as_deref().unwrap_or("-"),
+            entry.name.as_deref().unwrap_or("(unnamed)")
+        )
+        .map_err(|e| LupinError::StdoutWrite { source: e })?;
+    }
+
+    Ok(())
+}
+
+/// Handle strip command
+fn handle_strip(src: PathBuf, output: PathBuf, format: Option<&str>) -> Result<()> {
+    debug!("Running command: strip");
+    debug!("Source: {}, Output: {}", src.display(), output.display());
+
+    let source_data = fs::read(&src).map_err(|e| LupinError::SourceFileRead {
+        path: src,
+        source: e,
+    })?;
+
+    let (stripped_data, result) = operations::remove_with_format(&source_data, format)?;
+
+    fs::write(&output, &stripped_data).map_err(|e| LupinError::OutputFileWrite {
+        path: output.clone(),
+        source: e,
+    })?;
+
+    debug!("Using {} engine", result.engine);
+    if result.restored {
+        info!(
+            "Removed payload: {} source → {} output",
+            format_size(result.source_size),
+            format_size(result.output_size)
+        );
+    } else {
+        warn!(
+            "Cleared the stealth payload; {} is not byte-identical to the original file",
+            output.display()
+        );
+    }
+
+    Ok(())
+}
+
+/// Handle capacity command
+fn handle_capacity(src: PathBuf, format: Option<&str>) -> Result<()> {
+    debug!("Running command: capacity");
+    debug!("Source: {}", src.display());
+
+    let source_data = fs::read(&src).map_err(|e| LupinError::SourceFileRead {
+        path: src,
+        source: e,
+    })?;
+
+    // An unknown format fails every mode the same way
+    let router = EngineRouter::new();
+    let engine = router.select_engine(&source_data, format)?;
+    let text = is_text(engine);
+    let mut stdout = io::stdout();
+    for (name, mode) in [
+        ("capacity", EmbedMode::Capacity),
+        ("stealth", EmbedMode::Stealth),
+    ] {
+        let line = match operations::capacity_with_format(
+            &source_data,
+            mode,
+            Some(engine.format_name()),
+        ) {
+            Ok(Capacity::Unlimited) => "unlimited".to_string(),
+            Ok(Capacity::Exact(bytes)) if text && mode == EmbedMode::Stealth => format!(
+                "{} ({} bytes; one byte per line)",
+                format_size(bytes),
+                bytes
+            ),
+            Ok(Capacity::Exact(bytes)) => format!("{} ({} bytes)", format_size(bytes), bytes),
+            Ok(Capacity::Approximate(bytes)) => {
+                format!("~{} (about {} bytes)", format_size(bytes), bytes)
+            }
+            Ok(_) => "unknown".to_string(),
+            Err(e) => format!("unavailable: {}", e),
+        };
+        writeln!(stdout, "{:<9} {}", name, line)
+            .map_err(|e| LupinError::StdoutWrite { source: e })?;
+    }
+
+    Ok(())
+}
+
+/// Whether `engine` is the text engine
+fn is_text(engine: &dyn SteganographyEngine) -> bool {
+    engine.format_name() == TextEngine::new().format_name()
+}
+
+/// Hint for a payload too large for text stealth mode, given the sizes of the
+/// [`LupinError::PayloadTooLarge`] error
+///
+/// Each missing byte is a missing line. A capacity of zero may hide a text too short
+/// for the header and envelope as well, so the deficit is then only a lower bound.
+fn text_stealth_shortfall(capacity: usize, requested: usize) -> String {
+    let missing = requested.saturating_sub(capacity);
+    if capacity == 0 {
+        format!(
+            "Text stealth mode hides one byte per line: the text needs at least {} more lines",
+            missing
+        )
+    } else {
+        format!(
+            "Text stealth mode hides one byte per line: the text needs {} more lines",
+            missing
+        )
+    }
+}
+
+/// Handle probe command; returns whether a payload was found
+fn handle_probe(src: PathBuf, format: Option<&str>) -> Result<bool> {
+    debug!("Running command: probe");
+    debug!("Source: {}", src.display());
+
+    let source_data = fs::read(&src).map_err(|e| LupinError::SourceFileRead {
+        path: src.clone(),
+        source: e,
+    })?;
+
+    let result = operations::detect_with_format(&source_data, format)?;
+    let Some(payload) = result.payload else {
+        info!("No hidden payload found in {}.", src.display());
+        return Ok(false);
+    };
+
+    let mode = match payload.mode {
+        EmbedMode::Capacity => "capacity",
+        EmbedMode::Stealth => "stealth",
+        _ => "unknown",
+    };
+    // Error correction wraps everything else, so the rest can't be seen
+    let envelope = match payload.envelope_version {
+        Some(version) => format!("v{}", version),
+        None if payload.fec.is_some() => "unknown".to_string(),
+        None if payload.archive => "archive".to_string(),
+        None if payload.share => "share".to_string(),
+        None if payload.encrypted => "sealed".to_string(),
+        None => "none".to_string(),
+    };
+    let encrypted = match (payload.encrypted, payload.fec) {
+        (true, _) => "yes",
+        (false, Some(_)) => "unknown",
+        (false, None) => "no",
+    };
+    let fec = match payload.fec {
+        Some(parity) => format!("{} parity bytes per codeword", parity),
+        None => "none".to_string(),
+    };
+    let ranges = payload
+        .ranges
+        .iter()
+        .map(|range| format!("{}..{}", range.start, range.end))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let mut stdout = io::stdout();
+    let report = format!(
+        "format    {}\nmode      {}\nsize      {} ({} bytes)\nencrypted {}\nenvelope  {}\nfec       {}\nranges    {}\n",
+        result.engine,
+        mode,
+        format_size(payload.stored_size),
+        payload.stored_size,
+        encrypted,
+        envelope,
+        fec,
+        ranges
+    );
+    stdout
+        .write_all(report.as_bytes())
+        .map_err(|e| LupinError::StdoutWrite { source: e })?;
+
+    Ok(true)
+}
+
+/// Handle keygen command
+fn handle_keygen(output: Option<PathBuf>, sign: bool) -> Result<()> {
+    debug!("Running command: keygen");
+
+    let (kind, public_label, public_key, file) = if sign {
+        let key = SigningKey::generate();
+        let verifying_key = key.verifying_key();
+        let file = format!(
+            "# verifying key: {}\n{}\n",
+            verifying_key,
+            key.to_secret_string().as_str()
+        );
+        (
+            "signing key",
+            "Verifying key",
+            verifying_key.to_string(),
+            file,
+        )
+    } else {
+        let identity = Identity::generate();
+        let recipient = identity.recipient();
+        let file = format!(
+            "# public key: {}\n{}\n",
+            recipient,
+            identity.to_secret_string().as_str()
+        );
+        ("identity", "Public key", recipient.to_string(), file)
+    };
+
+    match output {
+        Some(path) => {
+            // Never clobber an existing key, and keep the new one private
+            let mut options = fs::OpenOptions::new();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+            options
+                .open(&path)
+                .and_then(|mut f| f.write_all(file.as_bytes()))
+                .map_err(|e| LupinError::OutputFileWrite {
+                    path: path.clone(),
+                    source: e,
+                })?;
+            info!("Wrote {} to {}", kind, path.display());
+            info!("{}: {}", public_label, public_key);
+        }
+        None => io::stdout()
+            .write_all(file.as_bytes())
+            .map_err(|e| LupinError::StdoutWrite { source: e })?,
+    }
 
-    info!("Successfully extracted payload from {}.", result.engine);
     Ok(())
 }
 
@@ -213,11 +1389,20 @@ fn main() -> ExitCode {
     let args = CliArgs::parse();
     let mut forced_quiet = false;
 
-    // check if extract was called with output as "-"
-    if let Command::Extract { output, .. } = &args.command {
-        if output.as_os_str() == "-" {
+    // check if extract was called with output as "-", or keygen without an output file
+    match &args.command {
+        Command::Extract {
+            output: Some(output),
+            ..
+        } if output.as_os_str() == "-" => {
             forced_quiet = true; // suppress normal output when writing to stdout
         }
+        Command::Join {
+            output: Some(output),
+            ..
+        } if output.as_os_str() == "-" => forced_quiet = true,
+        Command::Keygen { output: None, .. } => forced_quiet = true,
+        _ => {}
     }
 
     // Initialize logging based on verbosity flags
@@ -229,6 +1414,23 @@ fn main() -> ExitCode {
 
     debug!("Verbose mode enabled");
 
+    // `probe` is used in scripts like grep: 1 means "nothing found", so errors get 2
+    let error_code = match args.command {
+        Command::Probe { .. } => ExitCode::from(2),
+        _ => ExitCode::FAILURE,
+    };
+
+    // `--format` applies to every command, so it's added to the options here
+    let format = args.format;
+    let embed_format = |options: EmbedOptions| match &format {
+        Some(format) => options.with_format(format),
+        None => options,
+    };
+    let extract_format = |options: ExtractOptions| match &format {
+        Some(format) => options.with_format(format),
+        None => options,
+    };
+
     // Execute command and handle errors with pretty printing
     let result = match args.command {
         Command::Embed {
@@ -239,27 +1441,273 @@ fn main() -> ExitCode {
             // `--capacity` only exists to let users state the default explicitly and to
             // conflict with `--stealth`; capacity is selected whenever `--stealth` is absent.
             capacity: _,
+            password,
+            password_file,
+            recipients,
+            no_name,
+            compression,
+            fec,
+            sign_key,
+            replace,
         } => {
             let mode = if stealth {
                 EmbedMode::Stealth
             } else {
                 EmbedMode::Capacity
             };
-            handle_embed(src, payload, output, mode)
+            embed_options(
+                mode,
+                compression,
+                fec,
+                sign_key,
+                password,
+                password_file,
+                recipients,
+            )
+            .map(embed_format)
+            .and_then(|options| handle_embed(src, payload, output, options, no_name, replace))
+            .map(|()| ExitCode::SUCCESS)
+        }
+        Command::Extract {
+            src,
+            output,
+            password,
+            password_file,
+            identities,
+            entry,
+            verify_keys,
+        } => extract_options(entry, password, password_file, identities, verify_keys)
+            .map(extract_format)
+            .and_then(|options| handle_extract(src, output, options))
+            .map(|()| ExitCode::SUCCESS),
+        Command::Add {
+            src,
+            payload,
+            output,
+            password,
+            password_file,
+            recipients,
+            identities,
+            compression,
+            fec,
+            sign_key,
+        } => embed_options(
+            EmbedMode::Capacity,
+            compression,
+            fec,
+            sign_key,
+            password.clone(),
+            password_file.clone(),
+            recipients,
+        )
+        .map(embed_format)
+        .and_then(|options| {
+            let unlock = extract_options(None, password, password_file, identities, Vec::new())?;
+            handle_add(src, payload, output, options, extract_format(unlock))
+        })
+        .map(|()| ExitCode::SUCCESS),
+        Command::Rm {
+            src,
+            entry,
+            output,
+            password,
+            password_file,
+            recipients,
+            identities,
+        } => embed_options(
+            EmbedMode::Capacity,
+            CompressionArg::None,
+            None,
+            None,
+            password.clone(),
+            password_file.clone(),
+            recipients,
+        )
+        .map(embed_format)
+        .and_then(|options| {
+            let unlock = extract_options(None, password, password_file, identities, Vec::new())?;
+            handle_rm(src, entry, output, options, extract_format(unlock))
+        })
+        .map(|()| ExitCode::SUCCESS),
+        Command::Ls {
+            src,
+            password,
+            password_file,
+            identities,
+        } => extract_options(None, password, password_file, identities, Vec::new())
+            .map(extract_format)
+            .and_then(|options| handle_ls(src, options))
+            .map(|()| ExitCode::SUCCESS),
+        Command::Strip { src, output } => {
+            handle_strip(src, output, format.as_deref()).map(|()| ExitCode::SUCCESS)
+        }
+        Command::Capacity { src } => {
+            handle_capacity(src, format.as_deref()).map(|()| ExitCode::SUCCESS)
         }
-        Command::Extract { src, output } => handle_extract(src, output),
+        Command::Probe { src } => handle_probe(src, format.as_deref()).map(|found| {
+            if found {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            }
+        }),
+        Command::Split {
+            payload,
+            sources,
+            threshold,
+            output_dir,
+            stealth,
+            capacity: _,
+            password,
+            password_file,
+            recipients,
+            no_name,
+            compression,
+            fec,
+            sign_key,
+        } => {
+            let mode = if stealth {
+                EmbedMode::Stealth
+            } else {
+                EmbedMode::Capacity
+            };
+            embed_options(
+                mode,
+                compression,
+                fec,
+                sign_key,
+                password,
+                password_file,
+                recipients,
+            )
+            .map(embed_format)
+            .and_then(|options| {
+                handle_split(payload, sources, threshold, output_dir, options, no_name)
+            })
+            .map(|()| ExitCode::SUCCESS)
+        }
+        Command::Join {
+            sources,
+            output,
+            password,
+            password_file,
+            identities,
+            verify_keys,
+        } => extract_options(None, password, password_file, identities, verify_keys)
+            .map(extract_format)
+            .and_then(|options| handle_join(sources, output, options))
+            .map(|()| ExitCode::SUCCESS),
+        Command::Keygen { output, sign } => handle_keygen(output, sign).map(|()| ExitCode::SUCCESS),
     };
 
     // Handle errors with pretty printing using the log system
     match result {
-        Ok(()) => ExitCode::SUCCESS,
+        Ok(code) => code,
         Err(error) => {
             // print the user-friendly error message (from thiserror Display)
             error!("{}", error);
 
             // Log detailed debug information including source chain
             error!("{:?}", error);
-            ExitCode::FAILURE
+            error_code
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh, empty directory under the system temp directory
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lupin-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_text_stealth_shortfall() {
+        // Arrange - a stored filename adds to the envelope, as the CLI stores one
+        let lines = |count: usize| "line\n".repeat(count).into_bytes();
+        let options = EmbedOptions::new(EmbedMode::Stealth)
+            .with_filename("notes.txt")
+            .with_compression(Compression::None);
+        let payload = [0xA5; 60];
+
+        // Act
+        let Err(LupinError::PayloadTooLarge {
+            capacity,
+            requested,
+        }) = operations::embed_with_options(&lines(100), &payload, &options)
+        else {
+            panic!("expected PayloadTooLarge");
+        };
+        let missing = requested - capacity;
+        let enough = operations::embed_with_options(&lines(100 + missing), &payload, &options);
+        let short = operations::embed_with_options(&lines(99 + missing), &payload, &options);
+
+        // Assert - the reported deficit is exactly the lines the text lacks
+        assert!(capacity > 0);
+        assert_eq!(
+            text_stealth_shortfall(capacity, requested),
+            format!(
+                "Text stealth mode hides one byte per line: the text needs {} more lines",
+                missing
+            )
+        );
+        assert!(enough.is_ok());
+        assert!(matches!(short, Err(LupinError::PayloadTooLarge { .. })));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_restore_tree_drops_special_mode_bits() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // Arrange
+        let target = temp_dir("mode");
+        let entries = [tree::Entry {
+            path: "run.sh".to_string(),
+            mode: 0o4755,
+            mtime: 0,
+            kind: tree::EntryKind::File(b"#!/bin/sh\n".to_vec()),
+        }];
+
+        // Act
+        let result = restore_tree(&target, &entries);
+        let mode = fs::metadata(target.join("run.sh")).map(|m| m.permissions().mode());
+        fs::remove_dir_all(&target).unwrap();
+
+        // Assert
+        result.unwrap();
+        assert_eq!(mode.unwrap() & 0o7777, 0o755);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_restore_tree_refuses_symlinked_directories() {
+        // Arrange - the target already holds a link out of it, named like a directory
+        // in the tree
+        let target = temp_dir("link-target");
+        let outside = temp_dir("link-outside");
+        fs::create_dir_all(&target).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        std::os::unix::fs::symlink(&outside, target.join("docs")).unwrap();
+        let entries = [tree::Entry {
+            path: "docs/planted.txt".to_string(),
+            mode: 0o644,
+            mtime: 0,
+            kind: tree::EntryKind::File(b"planted".to_vec()),
+        }];
+
+        // Act
+        let result = restore_tree(&target, &entries);
+        let planted = outside.join("planted.txt").exists();
+        fs::remove_dir_all(&target).unwrap();
+        fs::remove_dir_all(&outside).unwrap();
+
+        // Assert
+        assert!(result.is_err());
+        assert!(!planted);
+    }
+}
@@ -14,8 +14,19 @@
 
 //! High-level operations for embedding and extracting steganographic data
 
+use crate::archive;
+use crate::compression::Compression;
+use crate::crypto::{self, Identity, Recipient, SealedKind};
+use crate::envelope::{self, Envelope};
 use crate::error::{LupinError, Result};
-use crate::{EmbedMode, EngineRouter};
+use crate::fec;
+use crate::shamir;
+use crate::signing::{SigningKey, VerifyingKey};
+use crate::{Capacity, EmbedMode, EngineRouter, SteganographyEngine};
+use log::debug;
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::Range;
 
 /// Result of an embed operation
 #[derive(Debug, Clone)]
@@ -23,6 +34,24 @@ pub struct EmbedResult {
     pub source_size: usize,
     pub output_size: usize,
     pub engine: String,
+    /// Whether the payload was encrypted before embedding
+    pub encrypted: bool,
+    /// Size of the payload before compression
+    pub payload_size: usize,
+    /// Size of the payload after compression (equal to `payload_size` if it wasn't)
+    pub compressed_size: usize,
+    /// Compression actually applied; [`Compression::None`] if it didn't help
+    pub compression: Compression,
+    /// Whether an existing payload was removed first (see [`replace`])
+    pub replaced: bool,
+}
+
+impl EmbedResult {
+    /// Compressed size as a fraction of the original payload size (below 1.0 means
+    /// compression saved space)
+    pub fn compression_ratio(&self) -> f64 {
+        self.compressed_size as f64 / self.payload_size as f64
+    }
 }
 
 /// Result of an extract operation  
@@ -31,6 +60,327 @@ pub struct ExtractResult {
     pub source_size: usize,
     pub payload_size: usize,
     pub engine: String,
+    /// Whether the hidden payload was encrypted
+    pub encrypted: bool,
+    /// Envelope format version, or `None` for a bare payload from before envelopes
+    pub envelope_version: Option<u8>,
+    /// How the payload was compressed inside the envelope
+    pub compression: Option<Compression>,
+    /// Original filename stored with the payload
+    pub filename: Option<String>,
+    /// MIME type stored with the payload
+    pub content_type: Option<String>,
+    /// CRC32 stored in the envelope
+    pub crc32: Option<u32>,
+    /// SHA-256 of the payload stored in the envelope
+    pub sha256: Option<[u8; 32]>,
+    /// Every entry of the hidden payload, in order; a single payload is one entry
+    pub entries: Vec<EntryInfo>,
+    /// Number of carriers the payload was rebuilt from; 1 unless it was split with
+    /// [`embed_split`]
+    pub shares: usize,
+    /// Number of damaged or missing bytes error correction repaired; 0 if the payload
+    /// was intact or embedded without error correction
+    pub corrected: usize,
+    /// Fingerprint of the key that signed the payload (see
+    /// [`crate::signing::VerifyingKey::fingerprint`]); `None` if it wasn't signed
+    pub signer: Option<String>,
+}
+
+/// An entry of the hidden payload, as listed by [`list`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryInfo {
+    /// The entry's stored filename; `None` for a payload stored without one
+    pub name: Option<String>,
+    /// MIME type stored with the entry
+    pub content_type: Option<String>,
+    /// Size of the entry's payload
+    pub size: usize,
+    /// Size of the payload as stored, after compression
+    pub stored_size: usize,
+    /// How the payload was compressed
+    pub compression: Compression,
+    /// SHA-256 of the payload; `None` for a bare payload from before envelopes
+    pub sha256: Option<[u8; 32]>,
+}
+
+impl EntryInfo {
+    fn new(envelope: &Envelope) -> Self {
+        Self {
+            name: envelope.filename.clone(),
+            content_type: envelope.content_type.clone(),
+            size: envelope.size,
+            stored_size: envelope.stored_size,
+            compression: envelope.compression,
+            sha256: Some(envelope.sha256),
+        }
+    }
+
+    fn bare(size: usize) -> Self {
+        Self {
+            name: None,
+            content_type: None,
+            size,
+            stored_size: size,
+            compression: Compression::None,
+            sha256: None,
+        }
+    }
+}
+
+/// Result of a detect operation
+#[derive(Debug, Clone)]
+pub struct DetectResult {
+    pub source_size: usize,
+    pub engine: String,
+    /// The hidden payload, or `None` if the file doesn't carry one
+    pub payload: Option<DetectedPayload>,
+}
+
+/// A hidden payload found by [`detect`]
+#[derive(Debug, Clone)]
+pub struct DetectedPayload {
+    /// Mode that embedded the payload
+    pub mode: EmbedMode,
+    /// Size of the payload as the engine stores it, including its envelope and any
+    /// encryption
+    pub stored_size: usize,
+    /// Byte ranges of the file that hold the payload
+    pub ranges: Vec<Range<usize>>,
+    /// Whether the payload is encrypted
+    pub encrypted: bool,
+    /// Envelope format version; `None` if the payload is encrypted (the envelope is
+    /// sealed inside), is an archive or was embedded before envelopes existed
+    pub envelope_version: Option<u8>,
+    /// Whether the payload is an archive of several entries; unknown, and `false`, if
+    /// it is encrypted
+    pub archive: bool,
+    /// Whether the payload is one share of a payload split with [`embed_split`]
+    pub share: bool,
+    /// Parity bytes per codeword if the payload carries error correction; everything
+    /// else about the payload is then unknown, as it is wrapped inside
+    pub fec: Option<u8>,
+}
+
+/// Result of a remove operation
+#[derive(Debug, Clone)]
+pub struct RemoveResult {
+    pub source_size: usize,
+    pub output_size: usize,
+    pub engine: String,
+    /// Mode of the removed payload; only a capacity-mode payload is removed
+    /// byte-for-byte, a stealth payload is cleared in place
+    pub mode: EmbedMode,
+    /// Whether the payload was removed byte-for-byte, which is also the case for a
+    /// stealth payload if the engine [restores](SteganographyEngine::restores_stealth) it
+    pub restored: bool,
+}
+
+/// Result of a split embed operation
+#[derive(Debug, Clone)]
+pub struct SplitResult {
+    /// Number of carriers needed to rebuild the payload
+    pub threshold: u8,
+    /// Size of the share hidden in each carrier, including its framing
+    pub share_size: usize,
+    /// One result per carrier, in the order the sources were given
+    pub carriers: Vec<EmbedResult>,
+}
+
+/// Options for [`embed_with_options`]
+///
+/// Marked `#[non_exhaustive]` so options can be added without breaking callers; build
+/// one with [`EmbedOptions::new`] or `Default` and the `with_*` methods.
+#[derive(Clone, Default)]
+#[non_exhaustive]
+pub struct EmbedOptions {
+    /// Embedding strategy passed to the engine
+    pub mode: EmbedMode,
+    /// Encrypts the payload with this password before embedding
+    pub password: Option<Vec<u8>>,
+    /// Encrypts the payload so that only these recipients can extract it
+    pub recipients: Vec<Recipient>,
+    /// Original filename to store alongside the payload
+    pub filename: Option<String>,
+    /// MIME type to store alongside the payload
+    pub content_type: Option<String>,
+    /// Compresses the payload before embedding, when that makes it smaller
+    pub compression: Compression,
+    /// Adds error correction with this many parity bytes per codeword
+    pub fec: Option<u8>,
+    /// Signs the payload with this key
+    pub signing_key: Option<SigningKey>,
+    /// Uses the engine for this format name or extension instead of detecting one
+    pub format: Option<String>,
+}
+
+impl EmbedOptions {
+    /// Creates options for the given mode with everything else at its default
+    pub fn new(mode: EmbedMode) -> Self {
+        Self {
+            mode,
+            ..Self::default()
+        }
+    }
+
+    /// Encrypts the payload with a key derived from `password`
+    pub fn with_password(mut self, password: impl Into<Vec<u8>>) -> Self {
+        self.password = Some(password.into());
+        self
+    }
+
+    /// Encrypts the payload for `recipient`; may be called once per recipient
+    pub fn with_recipient(mut self, recipient: Recipient) -> Self {
+        self.recipients.push(recipient);
+        self
+    }
+
+    /// Stores `filename` with the payload so extraction can restore it
+    pub fn with_filename(mut self, filename: impl Into<String>) -> Self {
+        self.filename = Some(filename.into());
+        self
+    }
+
+    /// Compresses the payload with `compression` (DEFLATE by default)
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Stores the payload's MIME type with it
+    pub fn with_content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_type = Some(content_type.into());
+        self
+    }
+
+    /// Adds Reed-Solomon error correction with `parity` bytes per codeword of up to
+    /// 255, so that up to `parity / 2` damaged bytes per codeword can be repaired (see
+    /// [`crate::fec`])
+    pub fn with_fec(mut self, parity: u8) -> Self {
+        self.fec = Some(parity);
+        self
+    }
+
+    /// Signs the payload with `key`, so extraction can check who embedded it (see
+    /// [`ExtractOptions::with_verify_key`])
+    pub fn with_signing_key(mut self, key: SigningKey) -> Self {
+        self.signing_key = Some(key);
+        self
+    }
+
+    /// Embeds with the engine for `format`, a format name or extension, instead of the
+    /// detected one (see [`EngineRouter::select_engine`])
+    pub fn with_format(mut self, format: impl Into<String>) -> Self {
+        self.format = Some(format.into());
+        self
+    }
+}
+
+// Hand-written so passwords never end up in debug logs
+impl fmt::Debug for EmbedOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EmbedOptions")
+            .field("mode", &self.mode)
+            .field("password", &self.password.as_ref().map(|_| "<redacted>"))
+            .field("recipients", &self.recipients)
+            .field("filename", &self.filename)
+            .field("content_type", &self.content_type)
+            .field("compression", &self.compression)
+            .field("fec", &self.fec)
+            .field("signing_key", &self.signing_key)
+            .field("format", &self.format)
+            .finish()
+    }
+}
+
+/// Options for [`extract_with_options`]
+///
+/// Marked `#[non_exhaustive]` so options can be added without breaking callers; build
+/// one with `Default` and the `with_*` methods.
+#[derive(Clone, Default)]
+#[non_exhaustive]
+pub struct ExtractOptions {
+    /// Password for an encrypted payload
+    pub password: Option<Vec<u8>>,
+    /// Identities to try on a payload encrypted to recipients
+    pub identities: Vec<Identity>,
+    /// Name of the entry to extract from a payload with several
+    pub entry: Option<String>,
+    /// Keys the payload must be signed by, one of them; an empty list checks nothing
+    pub verify_keys: Vec<VerifyingKey>,
+    /// Uses the engine for this format name or extension instead of detecting one
+    pub format: Option<String>,
+}
+
+impl ExtractOptions {
+    /// Decrypts the payload with a key derived from `password`
+    pub fn with_password(mut self, password: impl Into<Vec<u8>>) -> Self {
+        self.password = Some(password.into());
+        self
+    }
+
+    /// Adds an identity to try on a payload encrypted to recipients
+    pub fn with_identity(mut self, identity: Identity) -> Self {
+        self.identities.push(identity);
+        self
+    }
+
+    /// Extracts the entry named `name` (see [`list`])
+    pub fn with_entry(mut self, name: impl Into<String>) -> Self {
+        self.entry = Some(name.into());
+        self
+    }
+
+    /// Requires the payload to be signed by `key`; may be called once per trusted key
+    ///
+    /// Extraction then fails with [`LupinError::SignatureRequired`] if the payload
+    /// isn't signed, and [`LupinError::SignatureInvalid`] if it was signed by another
+    /// key. A signed payload's signature is always checked, trusted keys or not.
+    pub fn with_verify_key(mut self, key: VerifyingKey) -> Self {
+        self.verify_keys.push(key);
+        self
+    }
+
+    /// Extracts with the engine for `format`, a format name or extension, instead of
+    /// the detected one (see [`EngineRouter::select_engine`])
+    pub fn with_format(mut self, format: impl Into<String>) -> Self {
+        self.format = Some(format.into());
+        self
+    }
+}
+
+// Hand-written so passwords never end up in debug logs
+impl fmt::Debug for ExtractOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExtractOptions")
+            .field("password", &self.password.as_ref().map(|_| "<redacted>"))
+            .field("identities", &self.identities)
+            .field("entry", &self.entry)
+            .field("verify_keys", &self.verify_keys)
+            .field("format", &self.format)
+            .finish()
+    }
+}
+
+/// Returns how many payload bytes the source data can hide using `mode`
+///
+/// The figure is for the payload as stored, i.e. after compression. A stored filename
+/// or content type and encryption take room from it too.
+pub fn capacity(source_data: &[u8], mode: EmbedMode) -> Result<Capacity> {
+    capacity_with_format(source_data, mode, None)
+}
+
+/// Like [`capacity`], but uses the engine for `format` instead of detecting one when
+/// given (see [`EngineRouter::select_engine`])
+pub fn capacity_with_format(
+    source_data: &[u8],
+    mode: EmbedMode,
+    format: Option<&str>,
+) -> Result<Capacity> {
+    let router = EngineRouter::new();
+    let engine = router.select_engine(source_data, format)?;
+    let capacity = engine.capacity(source_data, mode)?;
+    Ok(capacity.saturating_sub(envelope::ENVELOPE_OVERHEAD))
 }
 
 /// Embeds payload data inside source data using the appropriate engine and mode
@@ -39,6 +389,17 @@ pub fn embed(
     source_data: &[u8],
     payload_data: &[u8],
     mode: EmbedMode,
+) -> Result<(Vec<u8>, EmbedResult)> {
+    embed_with_options(source_data, payload_data, &EmbedOptions::new(mode))
+}
+
+/// Embeds payload data inside source data, wrapped in an envelope with its metadata,
+/// compressed and optionally encrypted (with a password or for recipients, but not both)
+/// Returns the embedded data and operation metadata
+pub fn embed_with_options(
+    source_data: &[u8],
+    payload_data: &[u8],
+    options: &EmbedOptions,
 ) -> Result<(Vec<u8>, EmbedResult)> {
     // Reject empty payloads up front: there is nothing to hide, and some engines
     // (e.g. PDF) would otherwise emit a file indistinguishable from the source.
@@ -46,39 +407,694 @@ pub fn embed(
         return Err(LupinError::EmptyPayload);
     }
 
-    // Determine the correct engine based on magic bytes
+    // Determine the correct engine based on magic bytes, unless the caller named one
     let router = EngineRouter::new();
-    let engine = router.detect_engine(source_data)?;
+    let engine = router.select_engine(source_data, options.format.as_deref())?;
 
-    // Embed the payload data using the detected engine
-    let embedded_data = engine.embed(source_data, payload_data, mode)?;
+    // Wrap and seal the payload before it reaches the engine, so every engine gets the
+    // envelope and encryption; sealing the envelope keeps the filename private too
+    let (wrapped, stored) = wrap(payload_data, options)?;
+    let embedded_data = seal_and_embed(engine, source_data, &wrapped, stored.size, options)?;
 
     // Create the result metadata
     let result = EmbedResult {
         source_size: source_data.len(),
         output_size: embedded_data.len(),
         engine: engine.format_name().to_string(),
+        encrypted: options.password.is_some() || !options.recipients.is_empty(),
+        payload_size: payload_data.len(),
+        compressed_size: stored.size,
+        compression: stored.compression,
+        replaced: false,
+    };
+
+    Ok((embedded_data, result))
+}
+
+/// Wraps a payload in an envelope with the metadata `options` gives, compressing it and
+/// signing it as they ask
+fn wrap(payload_data: &[u8], options: &EmbedOptions) -> Result<(Vec<u8>, envelope::Stored)> {
+    let (wrapped, stored) = envelope::wrap(
+        payload_data,
+        options.filename.as_deref(),
+        options.content_type.as_deref(),
+        options.compression,
+    )?;
+    match &options.signing_key {
+        Some(key) => Ok((envelope::sign(&wrapped, key)?, stored)),
+        None => Ok((wrapped, stored)),
+    }
+}
+
+/// Encrypts a wrapped payload as `options` asks and embeds it with `engine`
+///
+/// Sizes in a [`LupinError::PayloadTooLarge`] error are for the `stored_size` bytes of
+/// `payload_data` that hold the payload itself; the rest counts as overhead.
+fn seal_and_embed(
+    engine: &dyn SteganographyEngine,
+    source_data: &[u8],
+    payload_data: &[u8],
+    stored_size: usize,
+    options: &EmbedOptions,
+) -> Result<Vec<u8>> {
+    let sealed = seal(payload_data, options)?;
+    embed_sealed(engine, source_data, &sealed, stored_size, options)
+}
+
+/// Encrypts a wrapped payload as `options` asks, or leaves it as it is
+fn seal<'a>(payload_data: &'a [u8], options: &EmbedOptions) -> Result<Cow<'a, [u8]>> {
+    match (&options.password, options.recipients.is_empty()) {
+        (Some(_), false) => Err(LupinError::EmbedFailed {
+            source: std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "encrypt with either a password or recipients, not both",
+            ),
+        }),
+        (Some(password), true) => {
+            crypto::seal_with_password(payload_data, password).map(Cow::Owned)
+        }
+        (None, false) => {
+            crypto::seal_for_recipients(payload_data, &options.recipients).map(Cow::Owned)
+        }
+        (None, true) => Ok(Cow::Borrowed(payload_data)),
+    }
+}
+
+/// Embeds an already sealed payload with `engine`, adding error correction first if
+/// `options` asks for it and checking that it fits
+///
+/// Error sizes are reported as for [`seal_and_embed`].
+fn embed_sealed(
+    engine: &dyn SteganographyEngine,
+    source_data: &[u8],
+    payload_data: &[u8],
+    stored_size: usize,
+    options: &EmbedOptions,
+) -> Result<Vec<u8>> {
+    // Error correction goes on last so that it covers everything the engine stores
+    let protected;
+    let payload_data = match options.fec {
+        Some(parity) => {
+            protected = fec::encode(payload_data, parity)?;
+            &protected
+        }
+        None => payload_data,
+    };
+    let mode = options.mode;
+
+    // Fail before the engine does any work if the payload can't fit. Sizes in the
+    // error are for the stored payload, so the envelope, encryption and error
+    // correction overhead is taken off the capacity instead of added to the request.
+    let overhead = payload_data.len() - stored_size;
+    let too_large = |capacity: usize| LupinError::PayloadTooLarge {
+        capacity: capacity.saturating_sub(overhead),
+        requested: stored_size,
+    };
+    let capacity = engine.capacity(source_data, mode)?;
+    if !capacity.fits(payload_data.len()) {
+        return Err(too_large(capacity.bytes().unwrap_or_default()));
+    }
+
+    // Embed the payload data using the detected engine
+    engine
+        .embed(source_data, payload_data, mode)
+        .map_err(|e| match e {
+            LupinError::PayloadTooLarge { capacity, .. } => too_large(capacity),
+            e => e,
+        })
+}
+
+/// Embeds payload data like [`embed_with_options`], but first removes any payload the
+/// source already carries instead of failing with [`LupinError::EmbedCollision`]
+///
+/// The old payload is only dropped if the new one is embedded: on error nothing has
+/// changed. Foreign data, such as other tools' APP13 segments, is left in place.
+/// Returns the embedded data and operation metadata
+pub fn replace(
+    source_data: &[u8],
+    payload_data: &[u8],
+    options: &EmbedOptions,
+) -> Result<(Vec<u8>, EmbedResult)> {
+    let router = EngineRouter::new();
+    let engine = router.select_engine(source_data, options.format.as_deref())?;
+
+    let stripped = match engine.probe(source_data)? {
+        Some(_) => Some(engine.remove(source_data)?),
+        None => None,
     };
+    let (embedded_data, mut result) = embed_with_options(
+        stripped.as_deref().unwrap_or(source_data),
+        payload_data,
+        options,
+    )?;
 
+    result.source_size = source_data.len();
+    result.replaced = stripped.is_some();
     Ok((embedded_data, result))
 }
 
+/// Reports whether source data carries a hidden payload, and where, without
+/// extracting, decrypting or verifying it
+pub fn detect(source_data: &[u8]) -> Result<DetectResult> {
+    detect_with_format(source_data, None)
+}
+
+/// Like [`detect`], but uses the engine for `format` instead of detecting one when
+/// given (see [`EngineRouter::select_engine`])
+pub fn detect_with_format(source_data: &[u8], format: Option<&str>) -> Result<DetectResult> {
+    let router = EngineRouter::new();
+    let engine = router.select_engine(source_data, format)?;
+
+    let payload = engine.probe(source_data)?.map(|probe| {
+        let encrypted = crypto::is_sealed(&probe.prefix);
+        let envelope_version = envelope::version(&probe.prefix);
+        DetectedPayload {
+            mode: probe.mode,
+            stored_size: probe.size,
+            ranges: probe.ranges,
+            encrypted,
+            envelope_version,
+            archive: archive::is_archive(&probe.prefix),
+            share: shamir::is_share(&probe.prefix),
+            fec: fec::peek_parity(&probe.prefix),
+        }
+    });
+
+    Ok(DetectResult {
+        source_size: source_data.len(),
+        engine: engine.format_name().to_string(),
+        payload,
+    })
+}
+
 /// Extracts hidden data from source data using the appropriate engine
 /// Returns the extracted payload and operation metadata
 pub fn extract(source_data: &[u8]) -> Result<(Vec<u8>, ExtractResult)> {
+    extract_with_options(source_data, &ExtractOptions::default())
+}
+
+/// Extracts hidden data from source data, decrypting it if it was encrypted and
+/// verifying its envelope
+///
+/// A payload with several entries needs [`ExtractOptions::with_entry`] to pick one,
+/// and fails with [`LupinError::EntryRequired`] otherwise. Payloads embedded before
+/// envelopes existed are returned as-is, with no metadata.
+/// Returns the extracted payload and operation metadata
+pub fn extract_with_options(
+    source_data: &[u8],
+    options: &ExtractOptions,
+) -> Result<(Vec<u8>, ExtractResult)> {
     let router = EngineRouter::new();
-    let engine = router.detect_engine(source_data)?;
-    let payload = engine.extract(source_data)?;
+    let engine = router.select_engine(source_data, options.format.as_deref())?;
+    let opened = open_payload(engine, source_data, options)?;
+
+    let (payload, mut result) = open_entry(
+        &opened.payload,
+        opened.encrypted,
+        options,
+        engine.format_name(),
+        source_data.len(),
+    )?;
+    result.corrected = opened.corrected;
+    Ok((payload, result))
+}
+
+/// Picks the entry `options` asks for out of a decrypted payload and unwraps it
+fn open_entry(
+    payload: &[u8],
+    encrypted: bool,
+    options: &ExtractOptions,
+    engine: &str,
+    source_size: usize,
+) -> Result<(Vec<u8>, ExtractResult)> {
+    let entries = split_entries(payload)?;
+    let infos = entries
+        .iter()
+        .map(|entry| entry_info(entry))
+        .collect::<Result<Vec<_>>>()?;
+    let index = match &options.entry {
+        Some(name) => infos
+            .iter()
+            .position(|info| info.name.as_ref() == Some(name))
+            .ok_or_else(|| LupinError::EntryNotFound { name: name.clone() })?,
+        None if entries.len() == 1 => 0,
+        None => {
+            return Err(LupinError::EntryRequired {
+                count: entries.len(),
+            })
+        }
+    };
+
+    let (payload, envelope) = if envelope::is_envelope(entries[index]) {
+        let mut envelope = envelope::unwrap(entries[index])?;
+        (std::mem::take(&mut envelope.payload), Some(envelope))
+    } else {
+        debug!("Payload has no envelope; treating it as a legacy bare payload");
+        (entries[index].to_vec(), None)
+    };
+
+    // The envelope has checked any signature; this checks it is one we trust
+    let signer = envelope.as_ref().and_then(|e| e.signer);
+    if !options.verify_keys.is_empty() {
+        match signer {
+            None => return Err(LupinError::SignatureRequired),
+            Some(signer) if !options.verify_keys.contains(&signer) => {
+                return Err(LupinError::SignatureInvalid)
+            }
+            Some(_) => {}
+        }
+    }
 
     let result = ExtractResult {
-        engine: engine.format_name().to_string(),
+        engine: engine.to_string(),
         payload_size: payload.len(),
+        source_size,
+        encrypted,
+        envelope_version: envelope.as_ref().map(|e| e.version),
+        compression: envelope.as_ref().map(|e| e.compression),
+        crc32: envelope.as_ref().map(|e| e.crc32),
+        sha256: envelope.as_ref().map(|e| e.sha256),
+        filename: envelope.as_ref().and_then(|e| e.filename.clone()),
+        content_type: envelope.and_then(|e| e.content_type),
+        entries: infos,
+        shares: 1,
+        corrected: 0,
+        signer: signer.map(|signer| signer.fingerprint()),
+    };
+
+    Ok((payload, result))
+}
+
+/// Lists the entries of the hidden payload, decrypting it if it was encrypted
+///
+/// A payload embedded with [`embed`] is a single entry; [`add`] adds more.
+pub fn list(source_data: &[u8], options: &ExtractOptions) -> Result<Vec<EntryInfo>> {
+    let router = EngineRouter::new();
+    let engine = router.select_engine(source_data, options.format.as_deref())?;
+    let opened = open_payload(engine, source_data, options)?;
+
+    split_entries(&opened.payload)?
+        .into_iter()
+        .map(entry_info)
+        .collect()
+}
+
+/// Adds payload data as a new entry next to the payload the source already carries
+///
+/// The entry is named by [`EmbedOptions::with_filename`], which is required, and the
+/// name must not be taken. `unlock` decrypts the existing payload; the combined
+/// payload is then encrypted and embedded as `options` asks, so an encrypted payload
+/// must be given a password or recipients again. A source without a payload gets a
+/// single entry, as with [`embed_with_options`].
+/// Returns the embedded data and operation metadata
+pub fn add(
+    source_data: &[u8],
+    payload_data: &[u8],
+    options: &EmbedOptions,
+    unlock: &ExtractOptions,
+) -> Result<(Vec<u8>, EmbedResult)> {
+    if payload_data.is_empty() {
+        return Err(LupinError::EmptyPayload);
+    }
+    let name = options
+        .filename
+        .as_deref()
+        .ok_or(LupinError::UnnamedEntry)?;
+
+    let router = EngineRouter::new();
+    let engine = router.select_engine(source_data, options.format.as_deref())?;
+    let existing = match engine.probe(source_data)? {
+        Some(_) => Some(open_payload(engine, source_data, unlock)?),
+        None => None,
+    };
+    let Opened {
+        payload, encrypted, ..
+    } = existing.unwrap_or_default();
+    if encrypted && options.password.is_none() && options.recipients.is_empty() {
+        return Err(LupinError::EncryptionRequired);
+    }
+
+    // A legacy bare payload gets an envelope of its own, without a name. An empty
+    // payload means there was none, since embedding one is refused
+    let mut entries = Vec::new();
+    if !payload.is_empty() {
+        for entry in split_entries(&payload)? {
+            if !envelope::is_envelope(entry) {
+                let (wrapped, _) = envelope::wrap(entry, None, None, Compression::None)?;
+                entries.push(Cow::Owned(wrapped));
+                continue;
+            }
+            if envelope::peek(entry)?.filename.as_deref() == Some(name) {
+                return Err(LupinError::DuplicateEntry {
+                    name: name.to_string(),
+                });
+            }
+            entries.push(Cow::Borrowed(entry));
+        }
+    }
+
+    let (wrapped, stored) = wrap(payload_data, options)?;
+    let replaced = !entries.is_empty();
+    let packed = if replaced {
+        entries.push(Cow::Owned(wrapped));
+        archive::pack(&entries)
+    } else {
+        wrapped
+    };
+
+    let stripped = if replaced {
+        Cow::Owned(engine.remove(source_data)?)
+    } else {
+        Cow::Borrowed(source_data)
+    };
+    // Sizes in a PayloadTooLarge error cover the whole archive
+    let embedded_data = seal_and_embed(engine, &stripped, &packed, packed.len(), options)?;
+
+    let result = EmbedResult {
+        source_size: source_data.len(),
+        output_size: embedded_data.len(),
+        engine: engine.format_name().to_string(),
+        encrypted: options.password.is_some() || !options.recipients.is_empty(),
+        payload_size: payload_data.len(),
+        compressed_size: stored.size,
+        compression: stored.compression,
+        replaced,
+    };
+
+    Ok((embedded_data, result))
+}
+
+/// Removes the entry named `name` from the hidden payload
+///
+/// `unlock` decrypts the payload and the remaining entries are embedded again as
+/// `options` asks, so an encrypted payload must be given a password or recipients
+/// again. Removing the last entry removes the payload altogether, like [`remove`].
+/// Returns the new data and operation metadata
+pub fn remove_entry(
+    source_data: &[u8],
+    name: &str,
+    options: &EmbedOptions,
+    unlock: &ExtractOptions,
+) -> Result<(Vec<u8>, RemoveResult)> {
+    let router = EngineRouter::new();
+    let engine = router.select_engine(source_data, options.format.as_deref())?;
+    let mode = engine.probe(source_data)?.map(|probe| probe.mode);
+    let Opened {
+        payload, encrypted, ..
+    } = open_payload(engine, source_data, unlock)?;
+
+    let mut entries = split_entries(&payload)?;
+    let index = entries
+        .iter()
+        .position(|entry| {
+            envelope::is_envelope(entry)
+                && envelope::peek(entry).is_ok_and(|e| e.filename.as_deref() == Some(name))
+        })
+        .ok_or_else(|| LupinError::EntryNotFound {
+            name: name.to_string(),
+        })?;
+    entries.remove(index);
+
+    let stripped = engine.remove(source_data)?;
+    let output = match entries.as_slice() {
+        [] => stripped,
+        _ if encrypted && options.password.is_none() && options.recipients.is_empty() => {
+            return Err(LupinError::EncryptionRequired)
+        }
+        // A single entry goes back to being a plain envelope
+        [entry] => seal_and_embed(engine, &stripped, entry, entry.len(), options)?,
+        _ => {
+            let packed = archive::pack(&entries);
+            seal_and_embed(engine, &stripped, &packed, packed.len(), options)?
+        }
+    };
+
+    let result = RemoveResult {
         source_size: source_data.len(),
+        output_size: output.len(),
+        engine: engine.format_name().to_string(),
+        mode: mode.unwrap_or_default(),
+        restored: mode != Some(EmbedMode::Stealth) || engine.restores_stealth(),
+    };
+
+    Ok((output, result))
+}
+
+/// Splits a payload across `n` carriers so that any `k` of them rebuild it
+///
+/// See [`embed_split_with_options`].
+pub fn embed_split<S: AsRef<[u8]>>(
+    sources: &[S],
+    payload_data: &[u8],
+    k: u8,
+    n: u8,
+) -> Result<(Vec<Vec<u8>>, SplitResult)> {
+    embed_split_with_options(sources, payload_data, k, n, &EmbedOptions::default())
+}
+
+/// Splits a payload across `n` carriers so that any `k` of them rebuild it, and no
+/// fewer reveal anything about it
+///
+/// The payload is wrapped, compressed and encrypted as [`embed_with_options`] would,
+/// then cut into Shamir shares (see [`crate::shamir`]), one per source, which each
+/// engine embeds like any other payload. Every share is as large as the payload.
+/// Nothing is returned unless every carrier takes its share; `sources.len()` must be
+/// `n` and `2 <= k <= n`, or this fails with [`LupinError::InvalidSplit`].
+/// Returns the embedded carriers, in the order of `sources`, and operation metadata
+pub fn embed_split_with_options<S: AsRef<[u8]>>(
+    sources: &[S],
+    payload_data: &[u8],
+    k: u8,
+    n: u8,
+    options: &EmbedOptions,
+) -> Result<(Vec<Vec<u8>>, SplitResult)> {
+    if payload_data.is_empty() {
+        return Err(LupinError::EmptyPayload);
+    }
+    if sources.len() != n as usize {
+        return Err(LupinError::InvalidSplit {
+            reason: format!("{} shares need {} carriers, got {}", n, n, sources.len()),
+        });
+    }
+
+    let router = EngineRouter::new();
+    let engines = sources
+        .iter()
+        .map(|source| router.select_engine(source.as_ref(), options.format.as_deref()))
+        .collect::<Result<Vec<_>>>()?;
+
+    let (wrapped, stored) = wrap(payload_data, options)?;
+    let sealed = seal(&wrapped, options)?;
+    let shares = shamir::split(&sealed, k, n)?;
+    debug!("Split the payload into {} shares, {} needed", n, k);
+
+    let mut outputs = Vec::with_capacity(shares.len());
+    let mut carriers = Vec::with_capacity(shares.len());
+    for ((engine, source), share) in engines.iter().zip(sources).zip(&shares) {
+        let source_data = source.as_ref();
+        let embedded_data = embed_sealed(*engine, source_data, share, stored.size, options)?;
+        carriers.push(EmbedResult {
+            source_size: source_data.len(),
+            output_size: embedded_data.len(),
+            engine: engine.format_name().to_string(),
+            encrypted: options.password.is_some() || !options.recipients.is_empty(),
+            payload_size: payload_data.len(),
+            compressed_size: stored.size,
+            compression: stored.compression,
+            replaced: false,
+        });
+        outputs.push(embedded_data);
+    }
+
+    let result = SplitResult {
+        threshold: k,
+        share_size: shares[0].len(),
+        carriers,
     };
+    Ok((outputs, result))
+}
+
+/// Rebuilds a payload split with [`embed_split`] from any `k` of its carriers
+///
+/// See [`extract_join_with_options`].
+pub fn extract_join<S: AsRef<[u8]>>(sources: &[S]) -> Result<(Vec<u8>, ExtractResult)> {
+    extract_join_with_options(sources, &ExtractOptions::default())
+}
 
+/// Rebuilds a payload split with [`embed_split_with_options`] from any `k` of its
+/// carriers, then decrypts and unwraps it like [`extract_with_options`]
+///
+/// Extra carriers beyond `k` are fine. Fails with [`LupinError::NotEnoughShares`]
+/// if fewer than `k` are given, [`LupinError::ShareMismatch`] if they hold shares of
+/// different payloads and [`LupinError::NotAShare`] if one holds a whole payload.
+/// Returns the extracted payload and operation metadata; the engines are listed
+/// together and the source size is that of all carriers
+pub fn extract_join_with_options<S: AsRef<[u8]>>(
+    sources: &[S],
+    options: &ExtractOptions,
+) -> Result<(Vec<u8>, ExtractResult)> {
+    let router = EngineRouter::new();
+    let mut shares = Vec::with_capacity(sources.len());
+    let mut engines: Vec<&str> = Vec::new();
+    let mut corrected = 0;
+    for source in sources {
+        let engine = router.select_engine(source.as_ref(), options.format.as_deref())?;
+        let (payload, repaired) = repair(engine.extract(source.as_ref())?)?;
+        corrected += repaired;
+        if !shamir::is_share(&payload) {
+            return Err(LupinError::NotAShare);
+        }
+        shares.push(shamir::parse(&payload)?);
+        if !engines.contains(&engine.format_name()) {
+            engines.push(engine.format_name());
+        }
+    }
+
+    let joined = shamir::combine(&shares)?;
+    let (payload, encrypted) = unseal(joined, options)?;
+    // A split payload always has an envelope, so anything else was rebuilt wrongly
+    if !envelope::is_envelope(&payload) {
+        return Err(LupinError::ChecksumMismatch);
+    }
+    let source_size = sources.iter().map(|source| source.as_ref().len()).sum();
+    let (payload, mut result) = open_entry(
+        &payload,
+        encrypted,
+        options,
+        &engines.join(", "),
+        source_size,
+    )?;
+    result.shares = shares.len();
+    result.corrected = corrected;
     Ok((payload, result))
 }
 
+/// A hidden payload as [`open_payload`] returns it
+#[derive(Default)]
+struct Opened {
+    /// The decrypted payload
+    payload: Vec<u8>,
+    /// Whether it was encrypted
+    encrypted: bool,
+    /// Number of bytes error correction repaired
+    corrected: usize,
+}
+
+/// Extracts the hidden payload with `engine`, repairs it if it carries error
+/// correction and decrypts it if it was encrypted
+fn open_payload(
+    engine: &dyn SteganographyEngine,
+    source_data: &[u8],
+    options: &ExtractOptions,
+) -> Result<Opened> {
+    let (payload, corrected) = repair(engine.extract(source_data)?)?;
+    if shamir::is_share(&payload) {
+        return Err(LupinError::NotEnoughShares {
+            found: 1,
+            needed: shamir::parse(&payload)?.threshold,
+        });
+    }
+    let (payload, encrypted) = unseal(payload, options)?;
+    Ok(Opened {
+        payload,
+        encrypted,
+        corrected,
+    })
+}
+
+/// Strips error correction from an extracted payload, repairing it, and reports how
+/// many bytes were repaired
+fn repair(payload: Vec<u8>) -> Result<(Vec<u8>, usize)> {
+    if !fec::is_fec(&payload) {
+        return Ok((payload, 0));
+    }
+    let (payload, corrected) = fec::decode(&payload)?;
+    if corrected > 0 {
+        debug!("Error correction repaired {} bytes", corrected);
+    }
+    Ok((payload, corrected))
+}
+
+/// Decrypts a payload if it was encrypted, reporting whether it was
+fn unseal(payload: Vec<u8>, options: &ExtractOptions) -> Result<(Vec<u8>, bool)> {
+    let encrypted = crypto::is_sealed(&payload);
+    let payload = if encrypted {
+        match crypto::sealed_kind(&payload) {
+            Some(SealedKind::Password) => match &options.password {
+                Some(password) => crypto::open_with_password(&payload, password)?,
+                None => return Err(LupinError::PasswordRequired),
+            },
+            Some(SealedKind::Recipients) if options.identities.is_empty() => {
+                return Err(LupinError::IdentityRequired)
+            }
+            Some(SealedKind::Recipients) => {
+                crypto::open_with_identities(&payload, &options.identities)?
+            }
+            // Sealed by a newer version of the format
+            None => return Err(LupinError::AuthenticationFailed),
+        }
+    } else {
+        if options.password.is_some() || !options.identities.is_empty() {
+            debug!("Payload is not encrypted; ignoring the password/identities");
+        }
+        payload
+    };
+
+    Ok((payload, encrypted))
+}
+
+/// Splits a decrypted payload into its entries: the envelopes of an archive, or the
+/// payload itself
+fn split_entries(payload: &[u8]) -> Result<Vec<&[u8]>> {
+    if archive::is_archive(payload) {
+        archive::unpack(payload)
+    } else {
+        Ok(vec![payload])
+    }
+}
+
+/// Reads an entry's metadata without unwrapping its payload
+fn entry_info(entry: &[u8]) -> Result<EntryInfo> {
+    if envelope::is_envelope(entry) {
+        envelope::peek(entry).map(|envelope| EntryInfo::new(&envelope))
+    } else {
+        Ok(EntryInfo::bare(entry.len()))
+    }
+}
+
+/// Removes the hidden payload from source data using the appropriate engine
+///
+/// A capacity-mode payload is cut out, leaving the file as it was before embedding;
+/// a stealth payload is cleared (see [`crate::SteganographyEngine::remove`]).
+/// Returns the stripped data and operation metadata
+pub fn remove(source_data: &[u8]) -> Result<(Vec<u8>, RemoveResult)> {
+    remove_with_format(source_data, None)
+}
+
+/// Like [`remove`], but uses the engine for `format` instead of detecting one when
+/// given (see [`EngineRouter::select_engine`])
+pub fn remove_with_format(
+    source_data: &[u8],
+    format: Option<&str>,
+) -> Result<(Vec<u8>, RemoveResult)> {
+    let router = EngineRouter::new();
+    let engine = router.select_engine(source_data, format)?;
+
+    // Probe first: once the payload is gone there is no telling which mode it used
+    let mode = engine.probe(source_data)?.map(|probe| probe.mode);
+    let stripped = engine.remove(source_data)?;
+
+    let result = RemoveResult {
+        source_size: source_data.len(),
+        output_size: stripped.len(),
+        engine: engine.format_name().to_string(),
+        mode: mode.unwrap_or_default(),
+        restored: mode != Some(EmbedMode::Stealth) || engine.restores_stealth(),
+    };
+
+    Ok((stripped, result))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -108,7 +1124,7 @@ mod tests {
         // Verify the metadata is correct
         assert_eq!(metadata.engine, "PDF"); // Should use PDF engine
         assert_eq!(metadata.source_size, 125); // Known size of minimal PDF
-        assert_eq!(metadata.output_size, 141); // Length of the PDF plus "test message" base64 encoded
+        assert_eq!(metadata.output_size, 213); // PDF plus base64 of the 66-byte envelope around "test message"
     }
 
     #[test]
@@ -148,20 +1164,864 @@ mod tests {
         assert_eq!(metadata.engine, "PDF"); // Should use PDF engine
         assert_eq!(metadata.source_size, embedded_data.len()); // Should match input size
         assert_eq!(metadata.payload_size, 11); // Length of "secret data"
+        assert_eq!(metadata.envelope_version, Some(envelope::ENVELOPE_VERSION));
     }
 
     #[test]
-    fn test_embed_stealth_mode_threads_through_to_engine() {
-        // No engine implements stealth mode yet, so the request must surface the engine's
-        // StealthNotSupported error through the public API (proving the mode is wired all
-        // the way down, not silently dropped or coerced to capacity).
-        let source = create_minimal_pdf();
+    fn test_extract_restores_metadata() {
+        // Arrange
+        let options = EmbedOptions::new(EmbedMode::Capacity)
+            .with_filename("plan.txt")
+            .with_content_type("text/plain");
+        let (embedded, _) =
+            embed_with_options(&create_minimal_pdf(), b"meet at dawn", &options).unwrap();
 
-        let result = embed(&source, b"stealthy secret", EmbedMode::Stealth);
+        // Act
+        let (payload, result) = extract(&embedded).unwrap();
 
-        assert!(matches!(
-            result,
-            Err(LupinError::StealthNotSupported { format: "PDF" })
-        ));
+        // Assert
+        assert_eq!(payload, b"meet at dawn");
+        assert_eq!(result.filename.as_deref(), Some("plan.txt"));
+        assert_eq!(result.content_type.as_deref(), Some("text/plain"));
+        assert!(result.sha256.is_some());
+        assert!(result.crc32.is_some());
+    }
+
+    #[test]
+    fn test_embed_compresses_payload() {
+        // Arrange
+        let payload = b"to be or not to be, that is the question. ".repeat(30);
+
+        // Act
+        let (embedded, embed_result) =
+            embed(&create_minimal_pdf(), &payload, EmbedMode::Capacity).unwrap();
+        let (extracted, extract_result) = extract(&embedded).unwrap();
+
+        // Assert
+        assert_eq!(embed_result.compression, Compression::Deflate);
+        assert_eq!(embed_result.payload_size, payload.len());
+        assert!(embed_result.compression_ratio() < 0.2);
+        assert_eq!(extract_result.compression, Some(Compression::Deflate));
+        assert_eq!(extracted, payload);
+    }
+
+    #[test]
+    fn test_embed_without_compression() {
+        // Arrange
+        let payload = b"to be or not to be, that is the question. ".repeat(30);
+        let options = EmbedOptions::new(EmbedMode::Capacity).with_compression(Compression::None);
+
+        // Act
+        let (embedded, result) =
+            embed_with_options(&create_minimal_pdf(), &payload, &options).unwrap();
+
+        // Assert
+        assert_eq!(result.compression, Compression::None);
+        assert_eq!(result.compressed_size, payload.len());
+        assert_eq!(result.compression_ratio(), 1.0);
+        assert_eq!(extract(&embedded).unwrap().0, payload);
+    }
+
+    /// An 8-bit RGB PNG of mid-grey pixels, big enough for a small stealth payload
+    fn create_rgb_png(width: u32, height: u32) -> Vec<u8> {
+        use flate2::write::ZlibEncoder;
+        use std::io::Write;
+
+        let chunk = |png: &mut Vec<u8>, kind: &[u8], data: &[u8]| {
+            png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            png.extend_from_slice(kind);
+            png.extend_from_slice(data);
+            let crc = crc32fast::hash(&[kind, data].concat());
+            png.extend_from_slice(&crc.to_be_bytes());
+        };
+
+        let mut ihdr = Vec::new();
+        ihdr.extend_from_slice(&width.to_be_bytes());
+        ihdr.extend_from_slice(&height.to_be_bytes());
+        ihdr.extend_from_slice(&[8, 2, 0, 0, 0]); // 8-bit RGB, no interlacing
+        let scanline = [vec![0u8], vec![0x80; width as usize * 3]].concat();
+        let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder
+            .write_all(&scanline.repeat(height as usize))
+            .unwrap();
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        chunk(&mut png, b"IHDR", &ihdr);
+        chunk(&mut png, b"IDAT", &encoder.finish().unwrap());
+        chunk(&mut png, b"IEND", &[]);
+        png
+    }
+
+    #[test]
+    fn test_capacity() {
+        // Arrange - 32x32 RGB holds 384 bytes, minus the engine and envelope headers
+        let png = create_rgb_png(32, 32);
+
+        // Act
+        let capacity_mode = capacity(&create_minimal_pdf(), EmbedMode::Capacity).unwrap();
+        let stealth = capacity(&png, EmbedMode::Stealth).unwrap();
+
+        // Assert
+        assert_eq!(capacity_mode, Capacity::Unlimited);
+        assert_eq!(
+            stealth,
+            Capacity::Exact(384 - 16 - envelope::ENVELOPE_OVERHEAD)
+        );
+    }
+
+    #[test]
+    fn test_embed_payload_too_large() {
+        // Arrange - random-looking bytes that DEFLATE can't shrink
+        let png = create_rgb_png(32, 32);
+        let bytes = capacity(&png, EmbedMode::Stealth).unwrap().bytes().unwrap();
+        let payload: Vec<u8> = (0..=bytes as u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8)
+            .collect();
+
+        // Act
+        let fits = embed(&png, &payload[..bytes], EmbedMode::Stealth);
+        let too_large = embed(&png, &payload, EmbedMode::Stealth);
+
+        // Assert - sizes are reported for the payload, not the envelope around it
+        assert!(fits.is_ok());
+        match too_large {
+            Err(LupinError::PayloadTooLarge {
+                capacity,
+                requested,
+            }) => {
+                assert_eq!(capacity, bytes);
+                assert_eq!(requested, bytes + 1);
+            }
+            other => panic!("expected PayloadTooLarge, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_detect() {
+        // Arrange
+        let source = create_minimal_pdf();
+        let (embedded, _) = embed(&source, b"find me", EmbedMode::Capacity).unwrap();
+        let options = EmbedOptions::new(EmbedMode::Capacity).with_password("hunter2");
+        let (encrypted, _) = embed_with_options(&source, b"find me", &options).unwrap();
+        let (stealth, _) = embed(&create_rgb_png(32, 32), b"find me", EmbedMode::Stealth).unwrap();
+
+        // Act
+        let clean = detect(&source).unwrap();
+        let plain = detect(&embedded).unwrap().payload.unwrap();
+        let sealed = detect(&encrypted).unwrap().payload.unwrap();
+        let pixels = detect(&stealth).unwrap().payload.unwrap();
+
+        // Assert
+        assert!(clean.payload.is_none());
+        assert_eq!(clean.engine, "PDF");
+        assert_eq!(plain.mode, EmbedMode::Capacity);
+        assert_eq!(plain.envelope_version, Some(envelope::ENVELOPE_VERSION));
+        assert!(!plain.encrypted);
+        assert_eq!(plain.stored_size, envelope::ENVELOPE_OVERHEAD + 7);
+        assert_eq!(plain.ranges.len(), 1);
+        assert_eq!(plain.ranges[0], source.len()..embedded.len());
+        assert!(sealed.encrypted);
+        assert_eq!(sealed.envelope_version, None);
+        assert_eq!(pixels.mode, EmbedMode::Stealth);
+        assert_eq!(pixels.envelope_version, Some(envelope::ENVELOPE_VERSION));
+    }
+
+    #[test]
+    fn test_remove() {
+        // Arrange
+        let source = create_minimal_pdf();
+        let (embedded, _) = embed(&source, b"strip me", EmbedMode::Capacity).unwrap();
+        let png = create_rgb_png(32, 32);
+        let (stealth, _) = embed(&png, b"strip me", EmbedMode::Stealth).unwrap();
+
+        // Act
+        let (stripped, result) = remove(&embedded).unwrap();
+        let (cleared, stealth_result) = remove(&stealth).unwrap();
+
+        // Assert
+        assert_eq!(stripped, source);
+        assert_eq!(result.engine, "PDF");
+        assert_eq!(result.mode, EmbedMode::Capacity);
+        assert_eq!(result.source_size, embedded.len());
+        assert_eq!(result.output_size, source.len());
+        assert!(result.restored);
+        assert_eq!(stealth_result.mode, EmbedMode::Stealth);
+        assert!(!stealth_result.restored);
+        assert!(detect(&cleared).unwrap().payload.is_none());
+        assert!(matches!(remove(&source), Err(LupinError::PdfNoHiddenData)));
+    }
+
+    #[test]
+    fn test_remove_restored_stealth() {
+        // Arrange - text stealth mode only appends whitespace, so it comes out exactly
+        let source: Vec<u8> = (0..200)
+            .flat_map(|i| format!("line {i}\n").into_bytes())
+            .collect();
+        let (embedded, _) = embed(&source, b"strip me", EmbedMode::Stealth).unwrap();
+
+        // Act
+        let (stripped, result) = remove(&embedded).unwrap();
+
+        // Assert
+        assert_eq!(stripped, source);
+        assert_eq!(result.engine, "Text");
+        assert_eq!(result.mode, EmbedMode::Stealth);
+        assert!(result.restored);
+    }
+
+    #[test]
+    fn test_format_overrides_detection() {
+        // Arrange - an SVG file is detected as SVG, but is text all the same
+        let source = b"<svg xmlns=\"http://www.w3.org/2000/svg\">\n<rect/>\n</svg>\n";
+        let options = EmbedOptions::new(EmbedMode::Capacity).with_format("txt");
+        let unlock = ExtractOptions::default().with_format("Text");
+
+        // Act
+        let (embedded, result) = embed_with_options(source, b"as text", &options).unwrap();
+        let (payload, extracted) = extract_with_options(&embedded, &unlock).unwrap();
+
+        // Assert
+        assert_eq!(result.engine, "Text");
+        assert_eq!(payload, b"as text");
+        assert_eq!(extracted.engine, "Text");
+        assert_eq!(detect(&embedded).unwrap().engine, "SVG");
+        assert!(detect_with_format(&embedded, Some("text"))
+            .unwrap()
+            .payload
+            .is_some());
+        let (stripped, _) = remove_with_format(&embedded, Some(".txt")).unwrap();
+        assert_eq!(stripped, source);
+        assert!(matches!(
+            capacity_with_format(source, EmbedMode::Capacity, Some("avi")),
+            Err(LupinError::UnknownFormat { .. })
+        ));
+    }
+
+    #[test]
+    fn test_replace() {
+        // Arrange
+        let source = create_minimal_pdf();
+        let options = EmbedOptions::new(EmbedMode::Capacity);
+        let (embedded, _) = embed(&source, b"old secret", EmbedMode::Capacity).unwrap();
+        let (fresh, _) = embed(&source, b"new secret", EmbedMode::Capacity).unwrap();
+
+        // Act
+        let (replaced, result) = replace(&embedded, b"new secret", &options).unwrap();
+        let (first, first_result) = replace(&source, b"new secret", &options).unwrap();
+
+        // Assert
+        assert_eq!(replaced, fresh);
+        assert!(result.replaced);
+        assert_eq!(result.source_size, embedded.len());
+        assert_eq!(first, fresh);
+        assert!(!first_result.replaced);
+        assert!(matches!(
+            embed(&embedded, b"new secret", EmbedMode::Capacity),
+            Err(LupinError::EmbedCollision { .. })
+        ));
+    }
+
+    #[test]
+    fn test_replace_keeps_foreign_app13() {
+        // Arrange - SOI, a Photoshop APP13 segment, EOI
+        let foreign = b"Photoshop 3.0\x008BIM";
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xED, 0x00, 2 + foreign.len() as u8];
+        jpeg.extend_from_slice(foreign);
+        jpeg.extend_from_slice(&[0xFF, 0xD9]);
+        let (embedded, _) = embed(&jpeg, b"old secret", EmbedMode::Capacity).unwrap();
+
+        // Act
+        let options = EmbedOptions::new(EmbedMode::Capacity);
+        let (replaced, _) = replace(&embedded, b"new secret", &options).unwrap();
+
+        // Assert
+        assert_eq!(extract(&replaced).unwrap().0, b"new secret");
+        assert_eq!(remove(&replaced).unwrap().0, jpeg);
+    }
+
+    #[test]
+    fn test_replace_too_large() {
+        // Arrange
+        let (embedded, _) =
+            embed(&create_rgb_png(32, 32), b"old secret", EmbedMode::Stealth).unwrap();
+        let options = EmbedOptions::new(EmbedMode::Stealth).with_compression(Compression::None);
+
+        // Act
+        let result = replace(&embedded, &[0x5a; 1000], &options);
+
+        // Assert - the old payload is still there to extract
+        assert!(matches!(result, Err(LupinError::PayloadTooLarge { .. })));
+        assert_eq!(extract(&embedded).unwrap().0, b"old secret");
+    }
+
+    #[test]
+    fn test_extract_legacy_bare_payload() {
+        // Arrange - embed without an envelope, as versions before envelopes did
+        let router = EngineRouter::new();
+        let source = create_minimal_pdf();
+        let engine = router.detect_engine(&source).unwrap();
+        let embedded = engine
+            .embed(&source, b"old style", EmbedMode::Capacity)
+            .unwrap();
+
+        // Act
+        let (payload, result) = extract(&embedded).unwrap();
+        let detected = detect(&embedded).unwrap().payload.unwrap();
+
+        // Assert
+        assert_eq!(detected.envelope_version, None);
+        assert!(!detected.encrypted);
+        assert_eq!(payload, b"old style");
+        assert_eq!(result.envelope_version, None);
+        assert_eq!(result.filename, None);
+        assert_eq!(result.sha256, None);
+    }
+
+    fn named(name: &str) -> EmbedOptions {
+        EmbedOptions::new(EmbedMode::Capacity)
+            .with_compression(Compression::None)
+            .with_filename(name)
+    }
+
+    #[test]
+    fn test_add_and_extract_entries() {
+        // Arrange
+        let (embedded, _) =
+            embed_with_options(&create_minimal_pdf(), b"first", &named("a.txt")).unwrap();
+        let unlock = ExtractOptions::default();
+
+        // Act
+        let (added, result) = add(&embedded, b"second", &named("b.txt"), &unlock).unwrap();
+        let entries = list(&added, &unlock).unwrap();
+
+        // Assert
+        assert_eq!(result.payload_size, 6);
+        assert!(detect(&added).unwrap().payload.unwrap().archive);
+        let names: Vec<_> = entries.iter().map(|e| e.name.as_deref()).collect();
+        assert_eq!(names, [Some("a.txt"), Some("b.txt")]);
+        assert!(matches!(
+            extract(&added),
+            Err(LupinError::EntryRequired { count: 2 })
+        ));
+        let (payload, extracted) =
+            extract_with_options(&added, &unlock.clone().with_entry("b.txt")).unwrap();
+        assert_eq!(payload, b"second");
+        assert_eq!(extracted.filename.as_deref(), Some("b.txt"));
+        assert_eq!(extracted.entries, entries);
+        assert!(matches!(
+            extract_with_options(&added, &unlock.with_entry("c.txt")),
+            Err(LupinError::EntryNotFound { name }) if name == "c.txt"
+        ));
+    }
+
+    #[test]
+    fn test_add_rejects_bad_entries() {
+        // Arrange
+        let (embedded, _) =
+            embed_with_options(&create_minimal_pdf(), b"first", &named("a.txt")).unwrap();
+        let unlock = ExtractOptions::default();
+
+        // Act & Assert
+        assert!(matches!(
+            add(&embedded, b"again", &named("a.txt"), &unlock),
+            Err(LupinError::DuplicateEntry { name }) if name == "a.txt"
+        ));
+        assert!(matches!(
+            add(&embedded, b"anonymous", &EmbedOptions::default(), &unlock),
+            Err(LupinError::UnnamedEntry)
+        ));
+        assert!(matches!(
+            add(&embedded, b"", &named("b.txt"), &unlock),
+            Err(LupinError::EmptyPayload)
+        ));
+    }
+
+    #[test]
+    fn test_add_to_encrypted_payload() {
+        // Arrange
+        let sealed = named("a.txt").with_password("hunter2");
+        let (embedded, _) = embed_with_options(&create_minimal_pdf(), b"first", &sealed).unwrap();
+        let unlock = ExtractOptions::default().with_password("hunter2");
+
+        // Act
+        let unsealed = add(&embedded, b"second", &named("b.txt"), &unlock);
+        let (added, _) = add(
+            &embedded,
+            b"second",
+            &named("b.txt").with_password("hunter2"),
+            &unlock,
+        )
+        .unwrap();
+
+        // Assert - the archive is encrypted again rather than left in the clear
+        assert!(matches!(unsealed, Err(LupinError::EncryptionRequired)));
+        assert!(detect(&added).unwrap().payload.unwrap().encrypted);
+        assert_eq!(list(&added, &unlock).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_add_to_legacy_bare_payload() {
+        // Arrange
+        let router = EngineRouter::new();
+        let source = create_minimal_pdf();
+        let engine = router.detect_engine(&source).unwrap();
+        let embedded = engine
+            .embed(&source, b"old style", EmbedMode::Capacity)
+            .unwrap();
+        let unlock = ExtractOptions::default();
+
+        // Act
+        let (added, _) = add(&embedded, b"new style", &named("b.txt"), &unlock).unwrap();
+        let entries = list(&added, &unlock).unwrap();
+
+        // Assert - the bare payload becomes an unnamed entry
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].name, None);
+        assert_eq!(entries[0].size, 9);
+        assert_eq!(entries[1].name.as_deref(), Some("b.txt"));
+    }
+
+    #[test]
+    fn test_remove_entry() {
+        // Arrange
+        let source = create_minimal_pdf();
+        let (embedded, _) = embed_with_options(&source, b"first", &named("a.txt")).unwrap();
+        let unlock = ExtractOptions::default();
+        let (added, _) = add(&embedded, b"second", &named("b.txt"), &unlock).unwrap();
+        let options = EmbedOptions::default();
+
+        // Act
+        let (one_left, _) = remove_entry(&added, "a.txt", &options, &unlock).unwrap();
+        let (none_left, result) = remove_entry(&one_left, "b.txt", &options, &unlock).unwrap();
+
+        // Assert - a single remaining entry is a plain envelope again
+        let detected = detect(&one_left).unwrap().payload.unwrap();
+        assert!(!detected.archive);
+        assert_eq!(detected.envelope_version, Some(envelope::ENVELOPE_VERSION));
+        assert_eq!(extract(&one_left).unwrap().0, b"second");
+        assert_eq!(none_left, source);
+        assert_eq!(result.output_size, source.len());
+        assert!(matches!(
+            remove_entry(&one_left, "a.txt", &options, &unlock),
+            Err(LupinError::EntryNotFound { .. })
+        ));
+    }
+
+    fn split_sources() -> Vec<Vec<u8>> {
+        let jpeg = vec![0xFF, 0xD8, 0xFF, 0xD9];
+        vec![create_minimal_pdf(), create_rgb_png(8, 8), jpeg]
+    }
+
+    #[test]
+    fn test_embed_split_and_join() {
+        // Arrange
+        let sources = split_sources();
+        let options = named("plan.txt");
+
+        // Act
+        let (carriers, result) =
+            embed_split_with_options(&sources, b"meet at dawn", 2, 3, &options).unwrap();
+
+        // Assert - any two carriers rebuild the payload, in any order
+        assert_eq!(result.threshold, 2);
+        assert_eq!(result.carriers.len(), 3);
+        assert_eq!(result.carriers[2].engine, "JPEG");
+        assert!(detect(&carriers[1]).unwrap().payload.unwrap().share);
+        for pair in [[0, 1], [2, 0], [1, 2]] {
+            let subset = [&carriers[pair[0]], &carriers[pair[1]]];
+            let (payload, joined) = extract_join(&subset).unwrap();
+            assert_eq!(payload, b"meet at dawn");
+            assert_eq!(joined.filename.as_deref(), Some("plan.txt"));
+            assert_eq!(joined.shares, 2);
+        }
+        assert_eq!(extract_join(&carriers).unwrap().1.shares, 3);
+    }
+
+    #[test]
+    fn test_join_needs_enough_shares() {
+        // Arrange
+        let (carriers, _) = embed_split(&split_sources(), b"meet at dawn", 3, 3).unwrap();
+
+        // Act & Assert - one carrier on its own reveals nothing
+        assert!(matches!(
+            extract(&carriers[0]),
+            Err(LupinError::NotEnoughShares {
+                found: 1,
+                needed: 3
+            })
+        ));
+        assert!(matches!(
+            extract_join(&carriers[..2]),
+            Err(LupinError::NotEnoughShares {
+                found: 2,
+                needed: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn test_join_rejects_foreign_carriers() {
+        // Arrange
+        let sources = split_sources();
+        let (first, _) = embed_split(&sources, b"meet at dawn", 2, 3).unwrap();
+        let (second, _) = embed_split(&sources, b"meet at dusk", 2, 3).unwrap();
+        let (whole, _) = embed(&sources[0], b"meet at noon", EmbedMode::Capacity).unwrap();
+
+        // Act & Assert
+        assert!(matches!(
+            extract_join(&[&first[0], &second[1]]),
+            Err(LupinError::ShareMismatch)
+        ));
+        assert!(matches!(
+            extract_join(&[&first[1], &whole]),
+            Err(LupinError::NotAShare)
+        ));
+    }
+
+    /// Flips bytes of the hidden payload in place, `gap` bytes apart from its middle,
+    /// well clear of the repeated error correction header
+    fn damage(carrier: &[u8], count: usize, gap: usize) -> Vec<u8> {
+        let range = detect(carrier).unwrap().payload.unwrap().ranges[0].clone();
+        let mut damaged = carrier.to_vec();
+        for i in 0..count {
+            let position = range.start + range.len() / 2 + i * gap;
+            assert!(position < range.end);
+            damaged[position] ^= 0x02;
+        }
+        damaged
+    }
+
+    #[test]
+    fn test_fec_repairs_damaged_carriers() {
+        for source in split_sources() {
+            // Arrange
+            let options = named("plan.txt").with_fec(16);
+            let (embedded, _) =
+                embed_with_options(&source, b"meet at dawn by the old mill", &options).unwrap();
+            let damaged = damage(&embedded, 3, 20);
+
+            // Act
+            let (payload, result) = extract(&damaged).unwrap();
+
+            // Assert
+            assert_eq!(payload, b"meet at dawn by the old mill");
+            assert!(result.corrected > 0);
+            assert_eq!(extract(&embedded).unwrap().1.corrected, 0);
+            assert_eq!(detect(&embedded).unwrap().payload.unwrap().fec, Some(16));
+        }
+    }
+
+    #[test]
+    fn test_fec_reports_unrepairable_damage() {
+        // Arrange
+        let source = create_minimal_pdf();
+        let options = EmbedOptions::default().with_fec(4);
+        let (embedded, _) = embed_with_options(&source, b"meet at dawn", &options).unwrap();
+        let without_fec = embed(&source, b"meet at dawn", EmbedMode::Capacity)
+            .unwrap()
+            .0;
+
+        // Act & Assert
+        assert!(matches!(
+            extract(&damage(&embedded, 6, 8)),
+            Err(LupinError::TooManyErrors)
+        ));
+        assert!(matches!(
+            extract(&damage(&without_fec, 1, 0)),
+            Err(LupinError::PdfCorruptedData | LupinError::ChecksumMismatch)
+        ));
+        assert!(matches!(
+            embed_with_options(&source, b"payload", &EmbedOptions::default().with_fec(1)),
+            Err(LupinError::InvalidRedundancy { parity: 1 })
+        ));
+    }
+
+    #[test]
+    fn test_fec_with_split() {
+        // Arrange
+        let options = EmbedOptions::default().with_fec(8);
+        let (carriers, _) =
+            embed_split_with_options(&split_sources(), b"meet at dawn", 2, 3, &options).unwrap();
+        let damaged = [damage(&carriers[0], 2, 10), damage(&carriers[1], 1, 0)];
+
+        // Act
+        let (payload, result) = extract_join(&damaged).unwrap();
+
+        // Assert
+        assert_eq!(payload, b"meet at dawn");
+        assert_eq!(result.shares, 2);
+        // A damaged base64 character garbles one or two bytes
+        assert!(result.corrected >= 3);
+    }
+
+    #[test]
+    fn test_embed_split_with_password() {
+        // Arrange
+        let options = EmbedOptions::default().with_password("hunter2");
+        let (carriers, result) =
+            embed_split_with_options(&split_sources(), b"meet at dawn", 2, 3, &options).unwrap();
+
+        // Act
+        let locked = extract_join(&carriers[..2]);
+        let unlock = ExtractOptions::default().with_password("hunter2");
+        let (payload, joined) = extract_join_with_options(&carriers[1..], &unlock).unwrap();
+
+        // Assert
+        assert!(result.carriers.iter().all(|carrier| carrier.encrypted));
+        assert!(matches!(locked, Err(LupinError::PasswordRequired)));
+        assert_eq!(payload, b"meet at dawn");
+        assert!(joined.encrypted);
+    }
+
+    #[test]
+    fn test_embed_split_rejects_bad_counts() {
+        // Arrange
+        let sources = split_sources();
+
+        // Act & Assert
+        assert!(matches!(
+            embed_split(&sources, b"payload", 2, 2),
+            Err(LupinError::InvalidSplit { .. })
+        ));
+        assert!(matches!(
+            embed_split(&sources, b"payload", 1, 3),
+            Err(LupinError::InvalidSplit { .. })
+        ));
+        assert!(matches!(
+            embed_split(&sources, b"", 2, 3),
+            Err(LupinError::EmptyPayload)
+        ));
+    }
+
+    #[test]
+    fn test_extract_truncated_payload() {
+        // Arrange - drop the last base64 quantum (3 payload bytes) from the PDF
+        let source = create_minimal_pdf();
+        let (mut embedded, _) = embed(&source, b"cut me short", EmbedMode::Capacity).unwrap();
+        embedded.truncate(embedded.len() - 4);
+
+        // Act
+        let result = extract(&embedded);
+
+        // Assert
+        assert!(matches!(result, Err(LupinError::PayloadTruncated { .. })));
+    }
+
+    #[test]
+    fn test_embed_stealth_mode_threads_through_to_engine() {
+        // Capacity mode happily appends to the minimal PDF, but stealth mode needs a
+        // usable xref table, so the engine's PDF-specific error must surface through the
+        // public API (proving the mode is wired all the way down, not silently dropped or
+        // coerced to capacity).
+        let source = create_minimal_pdf();
+
+        let result = embed(&source, b"stealthy secret", EmbedMode::Stealth);
+
+        assert!(matches!(result, Err(LupinError::PdfInvalidFormat { .. })));
+    }
+
+    #[test]
+    fn test_embed_and_extract_with_password() {
+        // Arrange
+        let source = create_minimal_pdf();
+        let options = EmbedOptions::new(EmbedMode::Capacity).with_password("hunter2");
+
+        // Act
+        let (embedded, embed_result) =
+            embed_with_options(&source, b"launch codes", &options).unwrap();
+        let (payload, extract_result) = extract_with_options(
+            &embedded,
+            &ExtractOptions::default().with_password("hunter2"),
+        )
+        .unwrap();
+
+        // Assert
+        assert_eq!(payload, b"launch codes");
+        assert!(embed_result.encrypted);
+        assert!(extract_result.encrypted);
+        assert_eq!(extract_result.payload_size, 12); // Size of the decrypted payload
+    }
+
+    #[test]
+    fn test_extract_encrypted_payload_errors() {
+        // Arrange
+        let source = create_minimal_pdf();
+        let options = EmbedOptions::new(EmbedMode::Capacity).with_password("hunter2");
+        let (embedded, _) = embed_with_options(&source, b"launch codes", &options).unwrap();
+
+        // Act
+        let without_password = extract(&embedded);
+        let wrong_password = extract_with_options(
+            &embedded,
+            &ExtractOptions::default().with_password("hunter3"),
+        );
+
+        // Assert - both are distinct from the engine's corrupted-data errors
+        assert!(matches!(
+            without_password,
+            Err(LupinError::PasswordRequired)
+        ));
+        assert!(matches!(wrong_password, Err(LupinError::WrongPassword)));
+    }
+
+    #[test]
+    fn test_extract_plain_payload_ignores_password() {
+        // Arrange
+        let source = create_minimal_pdf();
+        let (embedded, _) = embed(&source, b"not secret", EmbedMode::Capacity).unwrap();
+
+        // Act
+        let (payload, result) = extract_with_options(
+            &embedded,
+            &ExtractOptions::default().with_password("unused"),
+        )
+        .unwrap();
+
+        // Assert
+        assert_eq!(payload, b"not secret");
+        assert!(!result.encrypted);
+    }
+
+    #[test]
+    fn test_options_debug_redacts_password() {
+        // Arrange
+        let options = EmbedOptions::default().with_password("hunter2");
+
+        // Act
+        let debug = format!("{:?}", options);
+
+        // Assert
+        assert!(!debug.contains("hunter2"));
+        assert!(debug.contains("<redacted>"));
+    }
+
+    #[test]
+    fn test_embed_and_extract_for_recipients() {
+        // Arrange
+        let identity = Identity::generate();
+        let options = EmbedOptions::new(EmbedMode::Capacity).with_recipient(identity.recipient());
+        let source = create_minimal_pdf();
+        let (embedded, embed_result) =
+            embed_with_options(&source, b"for your eyes", &options).unwrap();
+
+        // Act
+        let without_identity = extract(&embedded);
+        let wrong_identity = extract_with_options(
+            &embedded,
+            &ExtractOptions::default().with_identity(Identity::generate()),
+        );
+        let (payload, extract_result) = extract_with_options(
+            &embedded,
+            &ExtractOptions::default().with_identity(identity),
+        )
+        .unwrap();
+
+        // Assert
+        assert!(embed_result.encrypted);
+        assert!(extract_result.encrypted);
+        assert_eq!(payload, b"for your eyes");
+        assert!(matches!(
+            without_identity,
+            Err(LupinError::IdentityRequired)
+        ));
+        assert!(matches!(
+            wrong_identity,
+            Err(LupinError::NoMatchingIdentity)
+        ));
+    }
+
+    #[test]
+    fn test_embed_rejects_password_and_recipients() {
+        // Arrange
+        let options = EmbedOptions::new(EmbedMode::Capacity)
+            .with_password("hunter2")
+            .with_recipient(Identity::generate().recipient());
+
+        // Act
+        let result = embed_with_options(&create_minimal_pdf(), b"payload", &options);
+
+        // Assert
+        assert!(matches!(result, Err(LupinError::EmbedFailed { .. })));
+    }
+
+    #[test]
+    fn test_embed_signed_and_verify() {
+        // Arrange
+        let key = SigningKey::generate();
+        let options = named("orders.txt")
+            .with_password("hunter2")
+            .with_signing_key(key.clone());
+        let (embedded, _) =
+            embed_with_options(&create_minimal_pdf(), b"hold the line", &options).unwrap();
+        let unlock = ExtractOptions::default().with_password("hunter2");
+
+        // Act
+        let (payload, result) = extract_with_options(
+            &embedded,
+            &unlock.clone().with_verify_key(key.verifying_key()),
+        )
+        .unwrap();
+        let unverified = extract_with_options(&embedded, &unlock).unwrap().1;
+        let other_key = extract_with_options(
+            &embedded,
+            &unlock
+                .clone()
+                .with_verify_key(SigningKey::generate().verifying_key()),
+        );
+
+        // Assert
+        assert_eq!(payload, b"hold the line");
+        assert_eq!(result.signer, Some(key.verifying_key().fingerprint()));
+        assert_eq!(unverified.signer, result.signer);
+        assert!(matches!(other_key, Err(LupinError::SignatureInvalid)));
+    }
+
+    #[test]
+    fn test_verify_requires_signature() {
+        // Arrange
+        let key = SigningKey::generate();
+        let (embedded, _) =
+            embed_with_options(&create_minimal_pdf(), b"unsigned", &named("a.txt")).unwrap();
+        let (signed, _) = add(
+            &embedded,
+            b"signed",
+            &named("b.txt").with_signing_key(key.clone()),
+            &ExtractOptions::default(),
+        )
+        .unwrap();
+        let verify = ExtractOptions::default().with_verify_key(key.verifying_key());
+
+        // Act & Assert - each entry is signed, or not, on its own
+        assert!(matches!(
+            extract_with_options(&embedded, &verify),
+            Err(LupinError::SignatureRequired)
+        ));
+        assert!(matches!(
+            extract_with_options(&signed, &verify.clone().with_entry("a.txt")),
+            Err(LupinError::SignatureRequired)
+        ));
+        let (payload, result) = extract_with_options(&signed, &verify.with_entry("b.txt")).unwrap();
+        assert_eq!(payload, b"signed");
+        assert!(result.signer.is_some());
+    }
+
+    #[test]
+    fn test_split_signed() {
+        // Arrange
+        let key = SigningKey::generate();
+        let options = named("plan.txt").with_signing_key(key.clone());
+        let (carriers, _) =
+            embed_split_with_options(&split_sources(), b"meet at dawn", 2, 3, &options).unwrap();
+
+        // Act
+        let (payload, result) = extract_join_with_options(
+            &carriers[1..],
+            &ExtractOptions::default().with_verify_key(key.verifying_key()),
+        )
+        .unwrap();
+
+        // Assert
+        assert_eq!(payload, b"meet at dawn");
+        assert_eq!(result.signer, Some(key.verifying_key().fingerprint()));
     }
 }
@@ -0,0 +1,319 @@
+// Copyright 2025 Niclas Hedam
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Shamir secret sharing of a payload across several carriers
+//!
+//! A payload split `k` of `n` ways becomes `n` shares, one per carrier. Any `k` of
+//! them rebuild it; fewer reveal nothing about it, not even in part. Every byte of the
+//! payload is the constant term of its own random polynomial of degree `k - 1` over
+//! GF(2^8), and share `x` holds each polynomial evaluated at `x`, so a share is as
+//! long as the payload. Each share is framed so that shares of different payloads
+//! are never combined, and a damaged share is caught on its own:
+//!
+//! ```text
+//! [4 bytes: "LpSh"][1 byte: Version = 1][1 byte: Threshold][1 byte: Index]
+//! [8 bytes: Split ID][N bytes: Share][4 bytes: CRC32]
+//! ```
+//!
+//! The split ID is random and shared by all shares of one split; the CRC32 covers
+//! everything before it.
+
+use crate::error::{LupinError, Result};
+use crate::gf256;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+
+/// Marks a share of a split payload
+pub const SHARE_MAGIC: &[u8] = b"LpSh";
+
+/// Current version of the share format
+pub const SHARE_VERSION: u8 = 1;
+
+/// Bytes a share adds to the payload
+pub const SHARE_OVERHEAD: usize = HEADER_LEN + 4;
+
+/// Magic, version, threshold, index and split ID
+const HEADER_LEN: usize = 4 + 1 + 1 + 1 + ID_LEN;
+
+const ID_LEN: usize = 8;
+
+/// A parsed share
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Share {
+    /// Number of shares needed to rebuild the payload
+    pub threshold: u8,
+    /// The share's x coordinate, from 1 to the number of shares
+    pub index: u8,
+    /// Random ID shared by all shares of one split
+    pub id: [u8; ID_LEN],
+    /// The payload's polynomials evaluated at `index`
+    pub data: Vec<u8>,
+}
+
+/// Returns true if the data starts like a share
+pub fn is_share(data: &[u8]) -> bool {
+    data.starts_with(SHARE_MAGIC)
+}
+
+/// Splits a payload into `count` framed shares, any `threshold` of which rebuild it
+///
+/// Fails with [`LupinError::InvalidSplit`] unless `2 <= threshold <= count`: a
+/// threshold of one would put the whole payload in every share.
+pub fn split(payload: &[u8], threshold: u8, count: u8) -> Result<Vec<Vec<u8>>> {
+    if threshold < 2 || threshold > count {
+        return Err(LupinError::InvalidSplit {
+            reason: format!(
+                "need at least 2 shares to rebuild, and no more than the {} made; got {}",
+                count, threshold
+            ),
+        });
+    }
+
+    let mut id = [0u8; ID_LEN];
+    OsRng.fill_bytes(&mut id);
+    // One row of random coefficients per payload byte; the constant term is the byte
+    let degree = threshold as usize - 1;
+    let mut coefficients = vec![0u8; payload.len() * degree];
+    OsRng.fill_bytes(&mut coefficients);
+
+    let shares = (1..=count)
+        .map(|index| {
+            let mut out = Vec::with_capacity(SHARE_OVERHEAD + payload.len());
+            out.extend_from_slice(SHARE_MAGIC);
+            out.push(SHARE_VERSION);
+            out.push(threshold);
+            out.push(index);
+            out.extend_from_slice(&id);
+            for (byte, row) in payload.iter().zip(coefficients.chunks_exact(degree)) {
+                // Horner's rule, highest coefficient first
+                let y = row
+                    .iter()
+                    .rev()
+                    .fold(0, |acc, &c| gf256::mul(acc, index) ^ c);
+                out.push(gf256::mul(y, index) ^ byte);
+            }
+            let crc32 = crc32fast::hash(&out);
+            out.extend_from_slice(&crc32.to_be_bytes());
+            out
+        })
+        .collect();
+    Ok(shares)
+}
+
+/// Parses a framed share, checking its CRC32
+///
+/// Fails with [`LupinError::PayloadTruncated`] if the data is too short,
+/// [`LupinError::UnsupportedEnvelope`] if it was written by a newer version and
+/// [`LupinError::ChecksumMismatch`] if it was damaged.
+pub fn parse(data: &[u8]) -> Result<Share> {
+    if data.len() < SHARE_OVERHEAD {
+        return Err(LupinError::PayloadTruncated {
+            expected: SHARE_OVERHEAD,
+            actual: data.len(),
+        });
+    }
+    if !is_share(data) {
+        return Err(LupinError::InvalidEnvelope {
+            reason: "missing share magic".to_string(),
+        });
+    }
+    if data[4] != SHARE_VERSION {
+        return Err(LupinError::UnsupportedEnvelope {
+            reason: format!("share version {}", data[4]),
+        });
+    }
+
+    let (body, crc32) = data.split_at(data.len() - 4);
+    if crc32fast::hash(body).to_be_bytes() != crc32 {
+        return Err(LupinError::ChecksumMismatch);
+    }
+    let (threshold, index) = (data[5], data[6]);
+    if threshold < 2 || index == 0 {
+        return Err(LupinError::InvalidEnvelope {
+            reason: format!("share {} of threshold {}", index, threshold),
+        });
+    }
+    Ok(Share {
+        threshold,
+        index,
+        id: data[7..HEADER_LEN].try_into().unwrap(),
+        data: body[HEADER_LEN..].to_vec(),
+    })
+}
+
+/// Rebuilds a payload from its shares
+///
+/// Repeated shares count once. Fails with [`LupinError::ShareMismatch`] if the
+/// shares come from different splits, and [`LupinError::NotEnoughShares`] if fewer
+/// than the threshold are given.
+pub fn combine(shares: &[Share]) -> Result<Vec<u8>> {
+    let first = shares.first().ok_or(LupinError::NotEnoughShares {
+        found: 0,
+        needed: 2,
+    })?;
+    if shares.iter().any(|share| {
+        share.id != first.id
+            || share.threshold != first.threshold
+            || share.data.len() != first.data.len()
+    }) {
+        return Err(LupinError::ShareMismatch);
+    }
+
+    let mut unique: Vec<&Share> = Vec::new();
+    for share in shares {
+        if !unique.iter().any(|seen| seen.index == share.index) {
+            unique.push(share);
+        }
+    }
+    let needed = first.threshold as usize;
+    if unique.len() < needed {
+        return Err(LupinError::NotEnoughShares {
+            found: unique.len(),
+            needed: first.threshold,
+        });
+    }
+    let unique = &unique[..needed];
+
+    // Lagrange basis polynomials evaluated at zero; subtraction is XOR in GF(2^8)
+    let weights: Vec<u8> = unique
+        .iter()
+        .map(|share| {
+            unique
+                .iter()
+                .filter(|other| other.index != share.index)
+                .fold(1, |acc, other| {
+                    gf256::mul(acc, gf256::div(other.index, other.index ^ share.index))
+                })
+        })
+        .collect();
+
+    Ok((0..first.data.len())
+        .map(|i| {
+            unique
+                .iter()
+                .zip(&weights)
+                .fold(0, |acc, (share, &weight)| {
+                    acc ^ gf256::mul(share.data[i], weight)
+                })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_all(shares: &[Vec<u8>]) -> Vec<Share> {
+        shares.iter().map(|share| parse(share).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_any_threshold_subset_rebuilds() {
+        // Arrange
+        let payload = b"the launch codes are 0000".to_vec();
+        let shares = parse_all(&split(&payload, 3, 5).unwrap());
+
+        // Act & Assert - every 3 of the 5 shares work
+        for a in 0..5 {
+            for b in a + 1..5 {
+                for c in b + 1..5 {
+                    let subset = [shares[a].clone(), shares[b].clone(), shares[c].clone()];
+                    assert_eq!(combine(&subset).unwrap(), payload);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_split_framing() {
+        // Arrange
+        let payload = [0x5a; 40];
+
+        // Act
+        let shares = split(&payload, 2, 3).unwrap();
+        let parsed = parse_all(&shares);
+
+        // Assert
+        assert_eq!(shares.len(), 3);
+        assert!(shares.iter().all(|share| is_share(share)));
+        assert!(shares
+            .iter()
+            .all(|share| share.len() == SHARE_OVERHEAD + payload.len()));
+        assert_eq!(
+            parsed.iter().map(|share| share.index).collect::<Vec<_>>(),
+            [1, 2, 3]
+        );
+        assert!(parsed.iter().all(|share| share.id == parsed[0].id));
+        // A single share gives nothing away
+        assert!(parsed.iter().all(|share| share.data != payload));
+    }
+
+    #[test]
+    fn test_combine_too_few_shares() {
+        // Arrange
+        let shares = parse_all(&split(b"secret", 3, 4).unwrap());
+        let repeated = [shares[0].clone(), shares[1].clone(), shares[1].clone()];
+
+        // Act
+        let result = combine(&repeated);
+
+        // Assert - a repeated share doesn't count twice
+        assert!(matches!(
+            result,
+            Err(LupinError::NotEnoughShares {
+                found: 2,
+                needed: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn test_combine_rejects_mixed_splits() {
+        // Arrange
+        let first = parse_all(&split(b"secret", 2, 2).unwrap());
+        let second = parse_all(&split(b"secret", 2, 2).unwrap());
+
+        // Act
+        let result = combine(&[first[0].clone(), second[1].clone()]);
+
+        // Assert
+        assert!(matches!(result, Err(LupinError::ShareMismatch)));
+    }
+
+    #[test]
+    fn test_parse_detects_damage() {
+        // Arrange
+        let mut share = split(b"secret", 2, 2).unwrap().remove(0);
+        share[HEADER_LEN] ^= 1;
+
+        // Act & Assert
+        assert!(matches!(parse(&share), Err(LupinError::ChecksumMismatch)));
+        assert!(matches!(
+            parse(&share[..SHARE_OVERHEAD - 1]),
+            Err(LupinError::PayloadTruncated { .. })
+        ));
+    }
+
+    #[test]
+    fn test_split_rejects_bad_threshold() {
+        assert!(matches!(
+            split(b"secret", 1, 3),
+            Err(LupinError::InvalidSplit { .. })
+        ));
+        assert!(matches!(
+            split(b"secret", 4, 3),
+            Err(LupinError::InvalidSplit { .. })
+        ));
+    }
+}
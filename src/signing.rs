@@ -0,0 +1,259 @@
+// Copyright 2025 Niclas Hedam
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Ed25519 keys for signing payloads
+//!
+//! A payload signed with a [`SigningKey`] proves who embedded it: the signature is
+//! stored in the payload's envelope (see [`crate::envelope::sign`]) together with the
+//! signer's [`VerifyingKey`], and anyone holding that public key can check that the
+//! payload is the one its owner embedded. Encryption alone can't prove this: anyone
+//! who knows the password, or a recipient's public key, can embed a payload too.
+//!
+//! Keys are encoded as text like [`crate::crypto`]'s, with their own prefixes so the
+//! two kinds can't be mixed up: `lupin-vk-` for a verifying key and `LUPIN-SIGN-` for a
+//! signing key, each followed by the unpadded URL-safe base64 key.
+
+use crate::crypto::decode_key;
+use crate::error::{LupinError, Result};
+use base64::{
+    engine::general_purpose::{STANDARD_NO_PAD, URL_SAFE_NO_PAD},
+    Engine as _,
+};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use ed25519_dalek::Signer;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::str::FromStr;
+use zeroize::Zeroizing;
+
+/// Length of an Ed25519 public key
+pub const PUBLIC_KEY_LEN: usize = 32;
+
+/// Length of an Ed25519 signature
+pub const SIGNATURE_LEN: usize = 64;
+
+/// Text prefix of an encoded [`VerifyingKey`]
+const VERIFYING_PREFIX: &str = "lupin-vk-";
+
+/// Text prefix of an encoded [`SigningKey`]
+const SIGNING_PREFIX: &str = "LUPIN-SIGN-";
+
+/// An Ed25519 public key that checks payloads signed by its [`SigningKey`]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct VerifyingKey(ed25519_dalek::VerifyingKey);
+
+impl VerifyingKey {
+    /// Parses a raw 32-byte public key
+    ///
+    /// Fails with [`LupinError::InvalidKey`] if the bytes aren't a valid curve point.
+    pub fn from_bytes(bytes: &[u8; PUBLIC_KEY_LEN]) -> Result<Self> {
+        ed25519_dalek::VerifyingKey::from_bytes(bytes)
+            .map(Self)
+            .map_err(|_| LupinError::InvalidKey {
+                reason: "verifying key is not a valid Ed25519 key".to_string(),
+            })
+    }
+
+    /// Returns the raw 32-byte public key
+    pub fn as_bytes(&self) -> &[u8; PUBLIC_KEY_LEN] {
+        self.0.as_bytes()
+    }
+
+    /// Returns the key's fingerprint: `SHA256:` followed by the unpadded base64
+    /// SHA-256 of the raw key, as OpenSSH shows keys
+    pub fn fingerprint(&self) -> String {
+        format!(
+            "SHA256:{}",
+            STANDARD_NO_PAD.encode(Sha256::digest(self.as_bytes()))
+        )
+    }
+
+    /// Checks a signature over `message`
+    ///
+    /// Fails with [`LupinError::SignatureInvalid`] if it doesn't match.
+    pub fn verify(&self, message: &[u8], signature: &[u8; SIGNATURE_LEN]) -> Result<()> {
+        self.0
+            .verify_strict(message, &ed25519_dalek::Signature::from_bytes(signature))
+            .map_err(|_| LupinError::SignatureInvalid)
+    }
+}
+
+impl fmt::Display for VerifyingKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            VERIFYING_PREFIX,
+            URL_SAFE_NO_PAD.encode(self.as_bytes())
+        )
+    }
+}
+
+impl fmt::Debug for VerifyingKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "VerifyingKey({})", self)
+    }
+}
+
+impl FromStr for VerifyingKey {
+    type Err = LupinError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::from_bytes(&decode_key(s, VERIFYING_PREFIX, "verifying key")?)
+    }
+}
+
+/// An Ed25519 private key that signs payloads
+///
+/// The key is wiped from memory on drop and never shown by `Debug`.
+#[derive(Clone)]
+pub struct SigningKey(ed25519_dalek::SigningKey);
+
+impl SigningKey {
+    /// Generates a new random signing key
+    pub fn generate() -> Self {
+        let mut secret = Zeroizing::new([0u8; 32]);
+        OsRng.fill_bytes(secret.as_mut());
+        Self(ed25519_dalek::SigningKey::from_bytes(&secret))
+    }
+
+    /// Returns the public key that checks this key's signatures
+    pub fn verifying_key(&self) -> VerifyingKey {
+        VerifyingKey(self.0.verifying_key())
+    }
+
+    /// Signs `message`
+    pub fn sign(&self, message: &[u8]) -> [u8; SIGNATURE_LEN] {
+        self.0.sign(message).to_bytes()
+    }
+
+    /// Encodes the private key as text, for writing to a key file
+    pub fn to_secret_string(&self) -> Zeroizing<String> {
+        Zeroizing::new(format!(
+            "{}{}",
+            SIGNING_PREFIX,
+            URL_SAFE_NO_PAD.encode(self.0.as_bytes())
+        ))
+    }
+}
+
+impl fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "SigningKey(<redacted>, verifying key: {})",
+            self.verifying_key()
+        )
+    }
+}
+
+impl FromStr for SigningKey {
+    type Err = LupinError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let secret = Zeroizing::new(decode_key(s, SIGNING_PREFIX, "signing key")?);
+        Ok(Self(ed25519_dalek::SigningKey::from_bytes(&secret)))
+    }
+}
+
+/// Parses the signing key in a key file, ignoring blank lines and `#` comments
+///
+/// Fails with [`LupinError::InvalidKey`] unless the file holds exactly one key.
+pub fn parse_signing_key(text: &str) -> Result<SigningKey> {
+    let mut keys = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+    match (keys.next(), keys.next()) {
+        (Some(key), None) => key.parse(),
+        _ => Err(LupinError::InvalidKey {
+            reason: "a signing key file must hold exactly one key".to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_and_verify() {
+        // Arrange
+        let key = SigningKey::generate();
+        let signature = key.sign(b"payload");
+
+        // Act & Assert
+        assert!(key.verifying_key().verify(b"payload", &signature).is_ok());
+        assert!(matches!(
+            key.verifying_key().verify(b"payloaD", &signature),
+            Err(LupinError::SignatureInvalid)
+        ));
+        assert!(matches!(
+            SigningKey::generate()
+                .verifying_key()
+                .verify(b"payload", &signature),
+            Err(LupinError::SignatureInvalid)
+        ));
+    }
+
+    #[test]
+    fn test_key_text_round_trip() {
+        // Arrange
+        let key = SigningKey::generate();
+        let verifying_key = key.verifying_key();
+
+        // Act
+        let parsed_verifying: VerifyingKey = verifying_key.to_string().parse().unwrap();
+        let parsed_signing: SigningKey = key.to_secret_string().parse().unwrap();
+
+        // Assert
+        assert!(verifying_key.to_string().starts_with("lupin-vk-"));
+        assert_eq!(parsed_verifying, verifying_key);
+        assert_eq!(parsed_signing.verifying_key(), verifying_key);
+        assert!(!format!("{:?}", key).contains(key.to_secret_string().as_str()));
+        assert!(verifying_key.fingerprint().starts_with("SHA256:"));
+        assert_eq!(verifying_key.fingerprint().len(), 7 + 43);
+    }
+
+    #[test]
+    fn test_parse_signing_key_file() {
+        // Arrange
+        let key = SigningKey::generate();
+        let file = format!(
+            "# verifying key: {}\n\n{}\n",
+            key.verifying_key(),
+            key.to_secret_string().as_str()
+        );
+
+        // Act & Assert
+        assert_eq!(
+            parse_signing_key(&file).unwrap().verifying_key(),
+            key.verifying_key()
+        );
+        assert!(matches!(
+            parse_signing_key("# nothing here\n"),
+            Err(LupinError::InvalidKey { .. })
+        ));
+        // An encryption identity is not a signing key
+        assert!(matches!(
+            parse_signing_key(
+                crate::crypto::Identity::generate()
+                    .to_secret_string()
+                    .as_str()
+            ),
+            Err(LupinError::InvalidKey { .. })
+        ));
+    }
+}
@@ -0,0 +1,340 @@
+// Copyright 2025 Niclas Hedam
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A directory tree packed into a single payload
+//!
+//! A packed tree is an ordinary payload: it is wrapped in an envelope with
+//! [`CONTENT_TYPE`], so extraction knows to restore it as a directory. Walking and
+//! restoring the directory is left to the caller; this module only deals in bytes:
+//!
+//! ```text
+//! [4 bytes: "LpTr"][1 byte: Version = 1][4 bytes: Entry Count]
+//! per entry:
+//! [1 byte: Kind (0 directory, 1 file)][2 bytes: Path Length][Path (UTF-8)]
+//! [4 bytes: Permissions][8 bytes: Modified, seconds since the Unix epoch]
+//! [8 bytes: File Length][N bytes: File Contents] ... files only
+//! ```
+//!
+//! All integers are big-endian. Paths are relative to the packed directory and use
+//! `/` as the separator; a directory comes before anything inside it. The paths come
+//! from the carrier, so [`unpack`] refuses any that could escape the target
+//! directory (see [`safe_path`]).
+
+use crate::envelope;
+use crate::error::{LupinError, Result};
+use std::path::PathBuf;
+
+/// Marks a packed directory tree
+pub const TREE_MAGIC: &[u8] = b"LpTr";
+
+/// Current version of the tree format
+pub const TREE_VERSION: u8 = 1;
+
+/// Content type stored in the envelope of a packed directory tree
+pub const CONTENT_TYPE: &str = "application/x-lupin-tree";
+
+/// Magic, version and entry count
+const HEADER_LEN: usize = 4 + 1 + 4;
+
+const KIND_DIRECTORY: u8 = 0;
+const KIND_FILE: u8 = 1;
+
+/// A file or directory in a packed tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// Path relative to the packed directory, `/`-separated
+    pub path: String,
+    /// Permission bits, as in a Unix file mode
+    pub mode: u32,
+    /// Modification time in seconds since the Unix epoch
+    pub mtime: u64,
+    /// What the entry is; a file carries its contents
+    pub kind: EntryKind,
+}
+
+/// What a tree entry is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryKind {
+    /// A directory; its files are entries of their own
+    Directory,
+    /// A regular file and its contents
+    File(Vec<u8>),
+}
+
+/// Returns true if the data starts like a packed tree
+pub fn is_tree(data: &[u8]) -> bool {
+    data.starts_with(TREE_MAGIC)
+}
+
+/// Packs tree entries, in order, into a payload
+///
+/// Fails with [`LupinError::InvalidTree`] if a path is longer than the format allows.
+pub fn pack(entries: &[Entry]) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    out.extend_from_slice(TREE_MAGIC);
+    out.push(TREE_VERSION);
+    out.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+    for entry in entries {
+        let kind = match entry.kind {
+            EntryKind::Directory => KIND_DIRECTORY,
+            EntryKind::File(_) => KIND_FILE,
+        };
+        let path_len = u16::try_from(entry.path.len()).map_err(|_| {
+            invalid(&format!(
+                "path {:?} is longer than {} bytes",
+                entry.path,
+                u16::MAX
+            ))
+        })?;
+        out.push(kind);
+        out.extend_from_slice(&path_len.to_be_bytes());
+        out.extend_from_slice(entry.path.as_bytes());
+        out.extend_from_slice(&entry.mode.to_be_bytes());
+        out.extend_from_slice(&entry.mtime.to_be_bytes());
+        if let EntryKind::File(contents) = &entry.kind {
+            out.extend_from_slice(&(contents.len() as u64).to_be_bytes());
+            out.extend_from_slice(contents);
+        }
+    }
+    Ok(out)
+}
+
+/// Unpacks a packed tree into its entries, checking every path
+///
+/// Fails with [`LupinError::PayloadTruncated`] if the data ends early,
+/// [`LupinError::UnsafeTreePath`] if a path could escape the target directory and
+/// [`LupinError::InvalidTree`] if the data is malformed.
+pub fn unpack(data: &[u8]) -> Result<Vec<Entry>> {
+    let mut reader = Reader { data, pos: 0 };
+    let header = reader.take(HEADER_LEN)?;
+    if !is_tree(header) {
+        return Err(invalid("missing tree magic"));
+    }
+    if header[4] != TREE_VERSION {
+        return Err(LupinError::UnsupportedEnvelope {
+            reason: format!("tree version {}", header[4]),
+        });
+    }
+
+    let count = u32::from_be_bytes(header[5..9].try_into().unwrap());
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        let kind = reader.take(1)?[0];
+        let path_len = u16::from_be_bytes(reader.take(2)?.try_into().unwrap()) as usize;
+        let path = std::str::from_utf8(reader.take(path_len)?)
+            .map_err(|_| invalid("path is not valid UTF-8"))?
+            .to_string();
+        if safe_path(&path).is_none() {
+            return Err(LupinError::UnsafeTreePath { path });
+        }
+        let mode = u32::from_be_bytes(reader.take(4)?.try_into().unwrap());
+        let mtime = u64::from_be_bytes(reader.take(8)?.try_into().unwrap());
+        let kind = match kind {
+            KIND_DIRECTORY => EntryKind::Directory,
+            KIND_FILE => {
+                let len = u64::from_be_bytes(reader.take(8)?.try_into().unwrap());
+                let len = usize::try_from(len).unwrap_or(usize::MAX);
+                EntryKind::File(reader.take(len)?.to_vec())
+            }
+            other => return Err(invalid(&format!("unknown entry kind {}", other))),
+        };
+        entries.push(Entry {
+            path,
+            mode,
+            mtime,
+            kind,
+        });
+    }
+
+    if reader.pos != data.len() {
+        return Err(invalid(&format!(
+            "{} unexpected bytes after the tree",
+            data.len() - reader.pos
+        )));
+    }
+    Ok(entries)
+}
+
+/// Returns a stored path as a relative path if it is safe to create under the target
+/// directory
+///
+/// The path comes from the carrier, so it is untrusted: it must be relative, and
+/// every `/`-separated component must be a safe filename (see
+/// [`envelope::safe_filename`]), which rules out `.`, `..`, backslashes, drive
+/// prefixes and control characters.
+pub fn safe_path(path: &str) -> Option<PathBuf> {
+    if path.is_empty() {
+        return None;
+    }
+    path.split('/')
+        .map(|component| envelope::safe_filename(component).map(PathBuf::from))
+        .collect()
+}
+
+fn invalid(reason: &str) -> LupinError {
+    LupinError::InvalidTree {
+        reason: reason.to_string(),
+    }
+}
+
+/// Reads a packed tree front to back, reporting truncation
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self.pos.saturating_add(len);
+        if end > self.data.len() {
+            return Err(LupinError::PayloadTruncated {
+                expected: end,
+                actual: self.data.len(),
+            });
+        }
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Vec<Entry> {
+        vec![
+            Entry {
+                path: "config".to_string(),
+                mode: 0o755,
+                mtime: 1_700_000_000,
+                kind: EntryKind::Directory,
+            },
+            Entry {
+                path: "config/app.toml".to_string(),
+                mode: 0o644,
+                mtime: 1_700_000_100,
+                kind: EntryKind::File(b"port = 8080\n".to_vec()),
+            },
+            Entry {
+                path: "id_ed25519".to_string(),
+                mode: 0o600,
+                mtime: 1_700_000_200,
+                kind: EntryKind::File(b"private key".to_vec()),
+            },
+        ]
+    }
+
+    fn packed_path(path: &str) -> Vec<u8> {
+        pack(&[Entry {
+            path: path.to_string(),
+            mode: 0o644,
+            mtime: 0,
+            kind: EntryKind::File(b"owned".to_vec()),
+        }])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_pack_unpack_round_trip() {
+        // Arrange
+        let entries = sample();
+
+        // Act
+        let packed = pack(&entries).unwrap();
+        let unpacked = unpack(&packed).unwrap();
+
+        // Assert
+        assert!(is_tree(&packed));
+        assert_eq!(unpacked, entries);
+    }
+
+    #[test]
+    fn test_unpack_truncated() {
+        // Arrange
+        let packed = pack(&sample()).unwrap();
+
+        // Act
+        let result = unpack(&packed[..packed.len() - 1]);
+
+        // Assert
+        assert!(matches!(
+            result,
+            Err(LupinError::PayloadTruncated { expected, actual })
+                if expected == packed.len() && actual == packed.len() - 1
+        ));
+    }
+
+    #[test]
+    fn test_unpack_rejects_path_traversal() {
+        for path in [
+            "../escape",
+            "config/../../escape",
+            "/etc/passwd",
+            "./config",
+            "config//app.toml",
+            "config/",
+            "..\\escape",
+            "C:\\Windows",
+            "bad\nname",
+            "",
+        ] {
+            // Act
+            let result = unpack(&packed_path(path));
+
+            // Assert
+            assert!(
+                matches!(&result, Err(LupinError::UnsafeTreePath { path: p }) if p == path),
+                "{:?} was not rejected",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn test_unpack_rejects_malformed() {
+        // Arrange
+        let packed = pack(&sample()).unwrap();
+        let mut newer = packed.clone();
+        newer[4] = TREE_VERSION + 1;
+        let mut trailing = packed.clone();
+        trailing.push(0);
+        let mut unknown_kind = packed.clone();
+        unknown_kind[HEADER_LEN] = 7;
+
+        // Act & Assert
+        assert!(matches!(
+            unpack(&newer),
+            Err(LupinError::UnsupportedEnvelope { .. })
+        ));
+        assert!(matches!(
+            unpack(&trailing),
+            Err(LupinError::InvalidTree { .. })
+        ));
+        assert!(matches!(
+            unpack(&unknown_kind),
+            Err(LupinError::InvalidTree { .. })
+        ));
+    }
+
+    #[test]
+    fn test_safe_path() {
+        assert_eq!(
+            safe_path("config/app.toml"),
+            Some(PathBuf::from("config").join("app.toml"))
+        );
+        assert_eq!(safe_path(".hidden"), Some(PathBuf::from(".hidden")));
+        assert_eq!(safe_path("a/../b"), None);
+    }
+}